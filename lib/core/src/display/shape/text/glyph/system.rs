@@ -15,6 +15,7 @@ use crate::system::gpu::types::*;
 
 use nalgebra::Vector2;
 use nalgebra::Vector4;
+use std::ops::Range;
 use crate::display;
 
 // =============
@@ -109,6 +110,15 @@ impl Line {
         }
     }
 
+    /// Set the color of glyphs in given range. The glyphs are indexed from the beginning of
+    /// currently visible text.
+    pub fn set_color(&mut self, range:Range<usize>, color:Vector4<f32>) {
+        let length = range.end.saturating_sub(range.start);
+        for glyph in self.glyphs.iter_mut().skip(range.start).take(length) {
+            glyph.color().set(color);
+        }
+    }
+
     /// Set the baseline start point for this line.
     pub fn set_baseline_start(&mut self, new_start:Vector2<f32>) {
         let offset = new_start - self.baseline_start;
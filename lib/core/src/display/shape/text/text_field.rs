@@ -1,36 +1,69 @@
 //! A module defining TextField. TextField is a basegl component displaying editable block of text.
 
+pub mod bracket;
 pub mod content;
 pub mod cursor;
+pub mod editing;
+pub mod folding;
 pub mod frp;
+pub mod gutter;
+pub mod history;
 pub mod location;
 pub mod render;
+pub mod search;
 pub mod word_occurrence;
 
 use crate::prelude::*;
 
 use crate::display;
+use crate::display::shape::text::text_field::bracket::AutoClosedBrackets;
+use crate::display::shape::text::text_field::bracket::BracketMatch;
+use crate::display::shape::text::text_field::bracket::BracketMatcher;
+use crate::display::shape::text::text_field::bracket::BracketPair;
+use crate::display::shape::text::text_field::bracket::TypedCharAction;
 use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::content::line::ColorSpan;
 use crate::display::shape::text::text_field::cursor::Cursors;
 use crate::display::shape::text::text_field::cursor::Cursor;
 use crate::display::shape::text::text_field::cursor::CursorId;
 use crate::display::shape::text::text_field::cursor::Step;
 use crate::display::shape::text::text_field::cursor::CursorNavigation;
+use crate::display::shape::text::text_field::editing::EditingRules;
+use crate::display::shape::text::text_field::editing::PlainTextRules;
+use crate::display::shape::text::text_field::editing::backspace_dedent_column;
+use crate::display::shape::text::text_field::editing::dedent_length;
+use crate::display::shape::text::text_field::folding::FoldingRegion;
+use crate::display::shape::text::text_field::folding::indentation_folding_regions;
+use crate::display::shape::text::text_field::gutter::LineDecoration;
+use crate::display::shape::text::text_field::gutter::LineDecorations;
 use crate::display::shape::text::text_field::location::TextLocationChange;
+use crate::display::shape::text::text_field::location::moved_by_change;
 use crate::display::shape::text::text_field::frp::TextFieldFrp;
+use crate::display::shape::text::text_field::history::Edit;
+use crate::display::shape::text::text_field::history::EditKind;
+use crate::display::shape::text::text_field::history::History;
+use crate::display::shape::text::text_field::history::RecordedChange;
 use crate::display::shape::text::text_field::word_occurrence::WordOccurrences;
+use crate::display::shape::text::text_field::word_occurrence::WordRange;
 use crate::display::shape::text::glyph::font::FontHandle;
 use crate::display::shape::text::glyph::font::FontRegistry;
 use crate::display::shape::text::text_field::render::TextFieldSprites;
 use crate::display::shape::text::text_field::render::assignment::GlyphLinesAssignmentUpdate;
+use crate::display::shape::text::text_field::render::gutter::GutterSprites;
+use crate::display::shape::text::text_field::search::Search;
 use crate::display::world::World;
 
 use data::text::TextChange;
 use data::text::TextChangedNotification;
 use data::text::TextLocation;
+use enso_frp::CommandHandler;
+use enso_frp::Keyboard;
+use enso_frp::Keymap;
 use nalgebra::Vector2;
 use nalgebra::Vector3;
 use nalgebra::Vector4;
+use std::collections::BTreeSet;
+use std::ops::Range;
 
 
 
@@ -84,8 +117,19 @@ shared! { TextField
         display_object   : display::object::Node,
         frp              : Option<TextFieldFrp>,
         word_occurrences : Option<WordOccurrences>,
+        history          : History,
+        editing_rules    : Box<dyn EditingRules>,
+        folding_regions  : Option<Vec<FoldingRegion>>,
+        composition      : Option<Edit>,
+        decorations      : LineDecorations,
+        auto_closed      : AutoClosedBrackets,
+        gutter           : GutterSprites,
         #[derivative(Debug="ignore")]
-        text_change_callback : Option<Box<dyn FnMut(&TextChangedNotification)>>
+        text_change_callback   : Option<Box<dyn FnMut(&TextChangedNotification)>>,
+        #[derivative(Debug="ignore")]
+        edit_finished_callback : Option<Box<dyn FnMut()>>,
+        #[derivative(Debug="ignore")]
+        cursors_moved_callback : Option<Box<dyn FnMut()>>,
     }
 
     impl {
@@ -104,8 +148,11 @@ shared! { TextField
             self.properties.size
         }
 
-        /// Scroll text by given offset in pixels.
+        /// Scroll text by given offset in pixels. When the soft wrap is enabled, the text is
+        /// scrolled only vertically.
         pub fn scroll(&mut self, offset:Vector2<f32>) {
+            let is_wrapped      = self.content.wrap_width().is_some();
+            let offset          = if is_wrapped { Vector2::new(0.0,offset.y) } else { offset };
             let position_change = -Vector3::new(offset.x,offset.y,0.0);
             self.rendered.display_object.mod_position(|pos| *pos += position_change );
             let mut update = self.assignment_update();
@@ -116,6 +163,7 @@ shared! { TextField
                 update.update_line_assignment();
             }
             self.rendered.update_glyphs(&mut self.content);
+            self.update_gutter();
         }
 
         /// Get current scroll position.
@@ -123,6 +171,132 @@ shared! { TextField
             self.rendered.display_object.position().xy()
         }
 
+        /// Enable or disable the soft wrap. When enabled, the lines longer than TextField's width
+        /// are broken at word boundaries and displayed in many visual rows, and the horizontal
+        /// scroll is reset.
+        pub fn set_soft_wrap(&mut self, enabled:bool) {
+            let wrap_width = if enabled { Some(self.properties.size.x) } else { None };
+            if enabled {
+                let scroll_x = self.scroll_position().x;
+                self.rendered.display_object.mod_position(|pos| pos.x -= scroll_x);
+            }
+            self.content.set_wrap_width(wrap_width);
+            self.assignment_update().update_after_text_edit();
+            self.rendered.update_glyphs(&mut self.content);
+            self.update_gutter();
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Check if the soft wrap is enabled.
+        pub fn is_soft_wrapped(&self) -> bool {
+            self.content.wrap_width().is_some()
+        }
+
+        /// Show or hide the gutter with line numbers and decorations, displayed left to the
+        /// TextField's text.
+        pub fn set_gutter_visible(&mut self, visible:bool) {
+            self.gutter.visible = visible;
+            self.update_gutter();
+        }
+
+        /// Check if the gutter is displayed.
+        pub fn is_gutter_visible(&self) -> bool {
+            self.gutter.visible
+        }
+
+        /// Set the decoration displayed in gutter next to the line number. The decoration moves
+        /// with its line when text is edited, and is dropped when the line is removed.
+        pub fn set_line_decoration(&mut self, line:usize, decoration:LineDecoration) {
+            self.decorations.set(line,decoration);
+            self.update_gutter();
+        }
+
+        /// Remove the decoration of given line.
+        pub fn remove_line_decoration(&mut self, line:usize) {
+            self.decorations.remove(line);
+            self.update_gutter();
+        }
+
+        /// Remove decorations of all lines.
+        pub fn clear_line_decorations(&mut self) {
+            self.decorations.clear();
+            self.update_gutter();
+        }
+
+        /// Get the decoration of given line.
+        pub fn line_decoration(&self, line:usize) -> Option<LineDecoration> {
+            self.decorations.get(line).cloned()
+        }
+
+        /// Get the decoration displayed in gutter at given point on the screen, e.g. to show its
+        /// tooltip when the mouse hovers over it.
+        pub fn decoration_at_point(&self, point:Vector2<f32>) -> Option<LineDecoration> {
+            let gutter_position = self.gutter.display_object.global_position().xy();
+            let point_on_gutter = point - gutter_position;
+            let decorations     = &self.decorations;
+            self.gutter.decoration_at_point(&self.content,decorations,point_on_gutter).cloned()
+        }
+
+        /// Set the folding regions of content, e.g. computed from the structure of code. Like the
+        /// highlighting, they should be updated after each edit. Until they are set, the regions
+        /// are computed from the lines' indentation.
+        pub fn set_folding_regions(&mut self, regions:Vec<FoldingRegion>) {
+            self.folding_regions = Some(regions);
+        }
+
+        /// The folding regions of content, see `set_folding_regions`.
+        pub fn folding_regions(&self) -> Vec<FoldingRegion> {
+            let content = &self.content;
+            self.folding_regions.clone().unwrap_or_else(|| indentation_folding_regions(content))
+        }
+
+        /// Fold the region, hiding its lines behind a placeholder. The cursors in hidden lines
+        /// are moved to the end of region's header.
+        pub fn fold(&mut self, region:FoldingRegion) {
+            self.content.fold(region);
+            self.update_after_folding();
+        }
+
+        /// Unfold the region folded at given header line.
+        pub fn unfold(&mut self, header:usize) {
+            if self.content.unfold(header) {
+                self.update_after_folding();
+            }
+        }
+
+        /// Fold the innermost region containing the active cursor which is not folded yet.
+        pub fn fold_at_cursor(&mut self) {
+            let line    = self.cursors.active_cursor().position.line;
+            let content = &self.content;
+            let regions = self.folding_regions().into_iter();
+            let mut at_cursor = regions.filter(|region| {
+                region.contains_line(line) && !content.is_folded(region.header)
+            });
+            if let Some(region) = at_cursor.max_by_key(|region| region.header) {
+                self.fold(region);
+            }
+        }
+
+        /// Unfold the region folded at the active cursor's line.
+        pub fn unfold_at_cursor(&mut self) {
+            let line = self.cursors.active_cursor().position.line;
+            self.unfold(line);
+        }
+
+        /// Fold all the folding regions.
+        pub fn fold_all(&mut self) {
+            for region in self.folding_regions() {
+                self.content.fold(region);
+            }
+            self.update_after_folding();
+        }
+
+        /// Unfold all the folded regions.
+        pub fn unfold_all(&mut self) {
+            self.content.unfold_all();
+            self.update_after_folding();
+        }
+
         /// Clear word occurrences.
         pub fn clear_word_occurrences(&mut self) {
             self.word_occurrences = None;
@@ -151,6 +325,67 @@ shared! { TextField
             self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
         }
 
+        /// Replace all cursors with a column (rectangular) selection between two points on the
+        /// screen. See `Cursors::select_columns`.
+        pub fn select_columns(&mut self, from:Vector2<f32>, to:Vector2<f32>) {
+            self.clear_word_occurrences();
+            let text_position = self.rendered.display_object.global_position().xy();
+            let from_on_text  = from - text_position;
+            let to_on_text    = to   - text_position;
+            self.cursors.select_columns(&mut self.content,from_on_text,to_on_text);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Add a new cursor one visual row above the active one.
+        pub fn add_cursor_above(&mut self) {
+            self.clear_word_occurrences();
+            let content        = &mut self.content;
+            let selecting      = false;
+            let mut navigation = CursorNavigation {content,selecting};
+            self.cursors.add_cursor_above(&mut navigation);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Add a new cursor one visual row below the active one.
+        pub fn add_cursor_below(&mut self) {
+            self.clear_word_occurrences();
+            let content        = &mut self.content;
+            let selecting      = false;
+            let mut navigation = CursorNavigation {content,selecting};
+            self.cursors.add_cursor_below(&mut navigation);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Split each selection of many lines into one selection per line.
+        pub fn split_selection_into_lines(&mut self) {
+            self.clear_word_occurrences();
+            self.cursors.split_selections_into_lines(&self.content);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Replace all cursors with a single one selecting given range. The cursor is placed at
+        /// the range's end.
+        pub fn select_range(&mut self, range:Range<TextLocation>) {
+            self.clear_word_occurrences();
+            let mut cursor = Cursor::new(range.end);
+            cursor.select_range(&range);
+            self.cursors.replace_cursors(vec![cursor]);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Get the position of active cursor in text.
+        pub fn active_cursor_location(&self) -> TextLocation {
+            self.cursors.active_cursor().position
+        }
+
+        /// Get the point where the active cursor is displayed, relative to TextField's position.
+        /// See `Cursor::render_position`.
+        pub fn active_cursor_point(&mut self) -> Vector2<f32> {
+            let location = self.cursors.active_cursor().position;
+            let on_text  = Cursor::render_position(&location,&mut self.content);
+            on_text + self.rendered.display_object.position().xy()
+        }
+
         /// Move all cursors by given step.
         pub fn navigate_cursors(&mut self, step:Step, selecting:bool) {
             if !selecting {
@@ -166,20 +401,23 @@ shared! { TextField
         /// Whenever possible, tries to maintain cursor positions.
         pub fn set_content(&mut self, text:&str) {
             self.clear_word_occurrences();
+            self.history.clear();
+            self.composition = None;
+            self.auto_closed.clear();
             self.content.set_content(text);
+            self.decorations.truncate(self.content.lines().len());
             self.cursors.recalculate_positions(&self.content);
             self.assignment_update().update_after_text_edit();
             self.rendered.update_glyphs(&mut self.content);
+            self.update_gutter();
             self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
         }
 
-        /// Make change in text content.
-        ///
-        /// As an opposite to `edit` function, here we don't care about cursors, nor call any
-        /// "text changed" callback, just do the change described in `TextChange` structure.
-        pub fn apply_change(&mut self, change:TextChange) {
-            self.content.apply_change(change);
-            self.assignment_update().update_after_text_edit();
+        /// Set the colors of text fragments, given as list of spans for each line. The lines
+        /// without given spans are displayed in the base color. The highlighting of a line is
+        /// dropped once the line is edited.
+        pub fn set_highlighting(&mut self, lines:Vec<Vec<ColorSpan>>) {
+            self.content.set_highlighting(lines);
             self.rendered.update_glyphs(&mut self.content);
         }
 
@@ -189,10 +427,15 @@ shared! { TextField
             line_strings.join("\n")
         }
 
-        /// Get the selected text.
+        /// Get the selected text. The selections of many cursors are joined with new lines in
+        /// order of their position in text.
         pub fn get_selected_text(&self) -> String {
-            let cursor_select  = |c:&Cursor| self.content.copy_fragment(c.selection_range());
-            let mut selections = self.cursors.cursors.iter().map(cursor_select);
+            let cursor_select  = |CursorId(id)| {
+                let cursor:&Cursor = &self.cursors.cursors[id];
+                self.content.copy_fragment(cursor.selection_range())
+            };
+            let sorted_ids     = self.cursors.sorted_cursor_indices();
+            let mut selections = sorted_ids.into_iter().map(cursor_select);
             selections.join("\n")
         }
 
@@ -224,6 +467,71 @@ shared! { TextField
             }
         }
 
+        /// Select all occurrences of the word at the active cursor, or of the text it selects, each
+        /// with a separate cursor. For definition of word check `word_occurrence` module doc.
+        pub fn select_all_word_occurrences(&mut self) {
+            let cursor = *self.cursors.active_cursor();
+            if let Some(occurrences) = WordOccurrences::new(&self.content,&cursor) {
+                let position     = cursor.position;
+                let is_at_cursor = |word:&&WordRange| {
+                    word.start <= position && position <= word.end
+                };
+                let (at_cursor,others):(Vec<_>,Vec<_>) = occurrences.iter().partition(is_at_cursor);
+                // The occurrence at the active cursor stays active.
+                let cursors = others.into_iter().chain(at_cursor).map(|word| {
+                    let mut cursor = Cursor::new(word.start);
+                    cursor.select_range(&word.word_range);
+                    cursor
+                }).collect();
+                self.clear_word_occurrences();
+                self.cursors.replace_cursors(cursors);
+                self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+            }
+        }
+
+        /// The brackets at cursors with their partners, for highlighting. See
+        /// `BracketMatcher::find_match`.
+        pub fn matching_brackets(&self) -> Vec<BracketMatch> {
+            let matcher = BracketMatcher::new(self.editing_rules.bracket_pairs());
+            let content = &self.content;
+            let cursors = self.cursors.cursors.iter();
+            cursors.filter_map(|cursor| matcher.find_match(content,cursor.position)).collect()
+        }
+
+        /// Move each cursor at a bracket to its partner. The cursors not being at any bracket stay
+        /// in place.
+        pub fn jump_to_matching_bracket(&mut self) {
+            let matcher = BracketMatcher::new(self.editing_rules.bracket_pairs());
+            let content = &self.content;
+            let cursors = self.cursors.cursors.iter().map(|cursor| {
+                let found = matcher.find_match(content,cursor.position);
+                found.map_or(*cursor, |found| Cursor::new(found.partner))
+            }).collect();
+            self.cursors.replace_cursors(cursors);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
+        /// Select the text inside the innermost brackets enclosing each cursor's selection. If
+        /// the text inside is already selected, the brackets are selected as well, so the next
+        /// use extends the selection to the outer brackets.
+        pub fn select_inside_brackets(&mut self) {
+            let matcher = BracketMatcher::new(self.editing_rules.bracket_pairs());
+            let content = &self.content;
+            let cursors = self.cursors.cursors.iter().map(|cursor| {
+                let range     = cursor.selection_range();
+                let enclosing = matcher.enclosing(content,&range);
+                enclosing.map_or(*cursor, |brackets| {
+                    let inner      = brackets.inner_range();
+                    let selected   = if inner == range { brackets.outer_range() } else { inner };
+                    let mut cursor = Cursor::new(selected.end);
+                    cursor.select_range(&selected);
+                    cursor
+                })
+            }).collect();
+            self.cursors.replace_cursors(cursors);
+            self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+        }
+
         /// Update underlying Display Object.
         pub fn update(&self) {
             self.display_object.update()
@@ -238,6 +546,43 @@ shared! { TextField
             x_range.contains(&point.x) && y_range.contains(&point.y)
         }
 
+        /// Replace the keymap of TextField commands. The user overrides should be applied to the
+        /// keymap returned by `TextFieldKeyboardFrp::default_keymap`.
+        pub fn set_keymap(&mut self, keymap:Keymap) {
+            if let Some(frp) = &mut self.frp {
+                frp.keyboard.keymap.set_keymap(keymap);
+            }
+        }
+
+        /// Activate or deactivate the context of TextField's keymap. The bindings of active
+        /// context take precedence over the default ones, so e.g. a popup displayed over the text
+        /// may take over the arrow keys.
+        pub fn set_keymap_context_active(&self, context:&str, active:bool) {
+            if let Some(frp) = &self.frp {
+                frp.keyboard.keymap.set_context_active(context,active);
+            }
+        }
+
+        /// The keyboard FRP graph of TextField. It is fed by the js keyboard events, but may be fed
+        /// by other sources as well, e.g. the input scripts replayed in tests.
+        pub fn keyboard(&self) -> Option<Keyboard> {
+            self.frp.as_ref().map(|frp| frp.keyboard.keyboard.clone())
+        }
+
+        /// Set handler run when the command is triggered. This way the keymap set by `set_keymap`
+        /// may contain commands handled outside of TextField.
+        pub fn set_command_handler<F:CommandHandler>(&mut self, command:&str, handler:F) {
+            if let Some(frp) = &mut self.frp {
+                frp.keyboard.keymap.set_handler(command,handler);
+            }
+        }
+
+        /// Set the rules of language-aware editing, like auto-indentation. By default the
+        /// `PlainTextRules` are used.
+        pub fn set_editing_rules<Rules:EditingRules + 'static>(&mut self, rules:Rules) {
+            self.editing_rules = Box::new(rules);
+        }
+
         /// Set text edit callback.
         ///
         /// This callback will be called once per `write` function call and all functions using it.
@@ -246,6 +591,28 @@ shared! { TextField
         (&mut self, callback:Callback) {
             self.text_change_callback = Some(Box::new(callback))
         }
+
+        /// Set the callback called once the edit is finished, after the text edit callback was
+        /// called for all its changes (e.g. one per cursor), and the cursors are placed after it.
+        pub fn set_edit_finished_callback<Callback:FnMut() + 'static>
+        (&mut self, callback:Callback) {
+            self.edit_finished_callback = Some(Box::new(callback))
+        }
+
+        /// Set the callback called whenever the user moves the cursors without editing text, e.g.
+        /// by clicking or navigating with keyboard.
+        pub fn set_cursors_moved_callback<Callback:FnMut() + 'static>
+        (&mut self, callback:Callback) {
+            self.cursors_moved_callback = Some(Box::new(callback))
+        }
+
+        /// Set the callback called whenever TextField loses the keyboard focus, e.g. when the
+        /// browser window is deactivated.
+        pub fn set_focus_lost_callback<Callback:FnMut() + 'static>(&mut self, callback:Callback) {
+            if let Some(frp) = &mut self.frp {
+                frp.set_focus_lost_handler(callback);
+            }
+        }
     }
 }
 
@@ -277,30 +644,60 @@ impl TextField {
     /// Edit text.
     ///
     /// All the currently selected text will be removed, and the given string will be inserted
-    /// by each cursor.
+    /// by each cursor. The edit is recorded in undo history, the single typed characters are
+    /// undone together with the rest of typed word.
+    ///
+    /// The single opening bracket or quote of the editing rules is auto-closed or wraps the
+    /// selected text, and the auto-closed closing one is typed over if it is still next to the
+    /// cursors. See `BracketMatcher::typed_char_action`.
     pub fn write(&self, text:&str) {
-        let trimmed    = text.trim_end_matches('\n');
-        let cursor_ids = self.with_borrowed(|this| this.cursors.sorted_cursor_indices());
-        // When we insert (e.g. paste) many lines in multicursor mode, under some circumnstances
-        // we insert one line per cursor, instead of having all cursors inserting the whole
-        // content. Such situation we call here Line Per Cursor Edit.
-        let is_line_per_cursor_edit = trimmed.contains('\n') && cursor_ids.len() > 1;
+        let cursors_before = self.cursors_snapshot();
+        let mut chars      = text.chars();
+        let typed          = chars.next().filter(|_| chars.next().is_none());
+        let action         = typed.map_or(TypedCharAction::Insert, |typed| {
+            self.with_borrowed(|this| this.typed_char_action(typed))
+        });
+        match action {
+            TypedCharAction::Insert          => self.write_and_record(text,cursors_before),
+            TypedCharAction::AutoClose(pair) => self.write_bracket_pair(pair,cursors_before),
+            TypedCharAction::Wrap(pair)      => self.write_bracket_pair(pair,cursors_before),
+            TypedCharAction::TypeOver        => self.with_borrowed(|this| {
+                for cursor in &this.cursors.cursors {
+                    this.auto_closed.remove(cursor.position);
+                }
+                this.navigate_cursors(Step::Right,false);
+            }),
+        }
+    }
 
-        if is_line_per_cursor_edit {
-            let cursor_with_line = cursor_ids.iter().cloned().zip(trimmed.split('\n'));
-            self.write_per_cursor(cursor_with_line);
-        } else {
-            let cursor_with_line = cursor_ids.iter().map(|cursor_id| (*cursor_id,text));
-            self.write_per_cursor(cursor_with_line);
-        };
-        self.with_borrowed(|this| {
-            this.clear_word_occurrences();
-            // TODO[ao] updates should be done only in one place and only once per frame
-            // see https://github.com/luna/ide/issues/178
-            this.assignment_update().update_after_text_edit();
-            this.rendered.update_glyphs(&mut this.content);
-            this.rendered.update_cursor_sprites(&this.cursors, &mut this.content);
+    /// Insert a new line at each cursor, replacing the selected text. The new line is indented
+    /// according to the editing rules.
+    pub fn new_line(&self) {
+        let cursors_before  = self.cursors_snapshot();
+        let text_per_cursor = self.with_borrowed(|this| {
+            this.cursors.sorted_cursor_indices().into_iter().map(|cursor_id| {
+                let CursorId(id) = cursor_id;
+                let start        = this.cursors.cursors[id].selection_range().start;
+                let line         = &this.content.lines()[start.line].chars()[..start.column];
+                let indent       = this.editing_rules.new_line_indent(line);
+                (cursor_id,format!("\n{}",indent))
+            }).collect_vec()
         });
+        let text_per_cursor = text_per_cursor.iter().map(|(id,text)| (*id,text.as_str()));
+        let changes         = self.write_per_cursor(text_per_cursor);
+        self.finish_edit(EditKind::Other,changes,cursors_before);
+    }
+
+    /// Indent every line having a cursor or touched by a selection by one indentation unit of
+    /// the editing rules. The empty lines are skipped.
+    pub fn indent_selected_lines(&self) {
+        self.change_selected_lines_indentation(true);
+    }
+
+    /// Remove one indentation unit of the editing rules from every line having a cursor or touched
+    /// by a selection. See `editing::dedent_length`.
+    pub fn dedent_selected_lines(&self) {
+        self.change_selected_lines_indentation(false);
     }
 
     /// Remove all text selected by all cursors.
@@ -311,16 +708,302 @@ impl TextField {
     /// Do delete operation on text.
     ///
     /// For cursors with selection it will just remove the selected text. For the rest, it will
-    /// remove all content covered by `step`.
+    /// remove all content covered by `step`. If the editing rules say so, the step left in the
+    /// line's leading whitespace removes the whole indentation unit.
     pub fn do_delete_operation(&self, step:Step) {
+        let cursors_before = self.cursors_snapshot();
         self.with_borrowed(|this| {
+            if step == Step::Left && this.editing_rules.removes_whole_indent_unit() {
+                this.select_indentation_before_cursors();
+            }
             let content           = &mut this.content;
             let selecting         = true;
             let mut navigation    = CursorNavigation {content,selecting};
             let without_selection = |c:&Cursor| !c.has_selection();
             this.cursors.navigate_cursors(&mut navigation,step,without_selection);
         });
-        self.remove_selection();
+        self.write_and_record("",cursors_before);
+    }
+
+    /// Display the text being composed by an input method at each cursor, replacing the selected
+    /// text and the previously composed one. The composed text is provisional: it is not recorded
+    /// in undo history nor reported to the "text changed" callback until it's committed with
+    /// `commit_composition`. See `system::web::text_input::composition` module.
+    pub fn set_composition(&self, text:&str) {
+        let cursors_before = self.revert_composition().unwrap_or_else(|| self.cursors_snapshot());
+        if !text.is_empty() {
+            let cursor_ids = self.with_borrowed(|this| this.cursors.sorted_cursor_indices());
+            let changes    = self.without_notifications(|| {
+                self.write_per_cursor(cursor_ids.into_iter().map(|id| (id,text)))
+            });
+            self.with_borrowed(|this| {
+                this.highlight_composition(&changes);
+                this.update_after_edit();
+                let kind          = EditKind::Other;
+                let cursors_after = this.cursors.cursors.clone();
+                this.composition  = Some(Edit {kind,changes,cursors_before,cursors_after});
+            });
+        }
+    }
+
+    /// Replace the composed text with the final one, committing it as a single edit.
+    pub fn commit_composition(&self, text:&str) {
+        let cursors_before = self.revert_composition().unwrap_or_else(|| self.cursors_snapshot());
+        if !text.is_empty() {
+            self.write_and_record(text,cursors_before);
+        }
+    }
+
+    /// Remove the composed text, restoring the text and cursors from before the composition.
+    pub fn cancel_composition(&self) {
+        self.revert_composition();
+    }
+
+    /// Make change in text content.
+    ///
+    /// As an opposite to `edit` function, here we don't call any "text changed" callback, just do
+    /// the change described in `TextChange` structure, e.g. made by another view of the same
+    /// file. The cursors are moved to keep their place in the text. Such change cannot be undone,
+    /// so the undo history keeps only the edits it does not affect (see
+    /// `History::update_after_change`). The composition in progress is reverted first, as the
+    /// change could overlap the composed text; the input method's next update inserts it again.
+    pub fn apply_change(&self, change:TextChange) {
+        self.revert_composition();
+        self.with_borrowed(|this| this.apply_change(change));
+    }
+
+    /// Undo the last edit, restoring the cursors from before it. The "text changed" callback is
+    /// called for each reverted change.
+    pub fn undo(&self) {
+        if let Some(edit) = self.with_borrowed(|this| this.history.undo()) {
+            self.apply_changes_notifying(edit.undo_changes().into_iter());
+            self.restore_cursors(edit.cursors_before);
+            self.call_callback(|this| &mut this.edit_finished_callback);
+        }
+    }
+
+    /// Redo the last undone edit, restoring the cursors from after it. The "text changed"
+    /// callback is called for each repeated change.
+    pub fn redo(&self) {
+        if let Some(edit) = self.with_borrowed(|this| this.history.redo()) {
+            self.apply_changes_notifying(edit.redo_changes().into_iter());
+            self.restore_cursors(edit.cursors_after);
+            self.call_callback(|this| &mut this.edit_finished_callback);
+        }
+    }
+}
+
+
+// === Private Editing ===
+
+impl TextField {
+    fn write_and_record(&self, text:&str, cursors_before:Vec<Cursor>) {
+        let trimmed    = text.trim_end_matches('\n');
+        let cursor_ids = self.with_borrowed(|this| this.cursors.sorted_cursor_indices());
+        // When we insert (e.g. paste) many lines in multicursor mode, and the lines count is
+        // equal to cursors count, we insert one line per cursor, instead of having all cursors
+        // inserting the whole content. Such situation we call here Line Per Cursor Edit.
+        let lines_count             = trimmed.split('\n').count();
+        let is_line_per_cursor_edit = cursor_ids.len() > 1 && lines_count == cursor_ids.len();
+
+        let changes = if is_line_per_cursor_edit {
+            let cursor_with_line = cursor_ids.iter().cloned().zip(trimmed.split('\n'));
+            self.write_per_cursor(cursor_with_line)
+        } else {
+            let cursor_with_line = cursor_ids.iter().map(|cursor_id| (*cursor_id,text));
+            self.write_per_cursor(cursor_with_line)
+        };
+        let is_typing = text.chars().count() == 1 && text != "\n";
+        let kind      = if is_typing { EditKind::Typing } else { EditKind::Other };
+        self.finish_edit(kind,changes,cursors_before);
+    }
+
+    /// Write the pair's characters around the text selected by each cursor, which stays selected.
+    /// The closing characters inserted by the cursors without selection are remembered as
+    /// auto-closed.
+    fn write_bracket_pair(&self, pair:BracketPair, cursors_before:Vec<Cursor>) {
+        let cursor_ids = self.with_borrowed(|this| this.cursors.sorted_cursor_indices());
+        let texts      = self.with_borrowed(|this| cursor_ids.iter().map(|CursorId(id)| {
+            let selected = this.content.copy_fragment(this.cursors.cursors[*id].selection_range());
+            format!("{}{}{}",pair.open,selected,pair.close)
+        }).collect_vec());
+        let text_per_cursor = cursor_ids.iter().cloned().zip(texts.iter().map(String::as_str));
+        let changes         = self.write_per_cursor(text_per_cursor);
+        self.with_borrowed(|this| {
+            // The changes are made in the text order, so the later ones do not move the earlier.
+            for (CursorId(id),change) in cursor_ids.iter().zip(&changes) {
+                let inserted = change.change.inserted_text_range();
+                let start    = TextLocation {column:inserted.start.column + 1, ..inserted.start};
+                let end      = TextLocation {column:inserted.end.column - 1, ..inserted.end};
+                this.cursors.cursors[*id] = Cursor {position:end, selected_to:start};
+                if start == end {
+                    this.auto_closed.insert(end);
+                }
+            }
+        });
+        self.finish_edit(EditKind::Typing,changes,cursors_before);
+    }
+
+    /// Update the rendered text and cursors after edit done by `write_per_cursor`, and record it
+    /// in undo history.
+    fn finish_edit(&self, kind:EditKind, changes:Vec<RecordedChange>, cursors_before:Vec<Cursor>) {
+        self.with_borrowed(|this| this.update_after_edit());
+        self.record_edit(kind,changes,cursors_before);
+    }
+
+    /// Remove the provisional text of composition, restoring the cursors from before it. Returns
+    /// the restored cursors, or `None` if there was no composition in progress.
+    fn revert_composition(&self) -> Option<Vec<Cursor>> {
+        let edit    = self.with_borrowed(|this| this.composition.take())?;
+        let changes = edit.undo_changes().into_iter();
+        self.without_notifications(|| self.apply_changes_notifying(changes));
+        self.restore_cursors(edit.cursors_before.clone());
+        Some(edit.cursors_before)
+    }
+
+    /// Run `f` without calling the "text changed" callback.
+    fn without_notifications<R>(&self, f:impl FnOnce() -> R) -> R {
+        let callback = self.with_borrowed(|this| this.text_change_callback.take());
+        let result   = f();
+        self.with_borrowed(|this| this.text_change_callback = callback);
+        result
+    }
+
+    fn change_selected_lines_indentation(&self, indent:bool) {
+        let cursors_before = self.cursors_snapshot();
+        let line_changes   = self.with_borrowed(|this| {
+            let unit = this.editing_rules.indent_unit().to_string();
+            this.lines_under_cursors().into_iter().filter_map(|line_index| {
+                let line = this.content.lines()[line_index].chars();
+                if indent {
+                    (!line.is_empty()).as_some_from(|| (line_index,0,unit.clone()))
+                } else {
+                    let removed = dedent_length(line,&unit);
+                    (removed > 0).as_some((line_index,removed,String::new()))
+                }
+            }).collect_vec()
+        });
+        if !line_changes.is_empty() {
+            // The changes are applied from the last line, so the locations of the remaining ones
+            // stay valid.
+            let changes = line_changes.iter().rev().map(|(line,removed,inserted)| {
+                let start = TextLocation {line:*line, column:0};
+                let end   = TextLocation {line:*line, column:*removed};
+                TextChange::replace(start..end,inserted)
+            });
+            let changes = self.apply_changes_notifying(changes);
+            let moved   = |location:TextLocation| {
+                let change = line_changes.iter().find(|(line,_,_)| *line == location.line);
+                change.map_or(location, |(_,removed,inserted)| {
+                    let inserted = inserted.chars().count();
+                    let column   = location.column.saturating_sub(*removed) + inserted;
+                    TextLocation {column,..location}
+                })
+            };
+            let cursors_after = cursors_before.iter().map(|cursor| Cursor {
+                position    : moved(cursor.position),
+                selected_to : moved(cursor.selected_to),
+            }).collect();
+            self.restore_cursors(cursors_after);
+            self.record_edit(EditKind::Other,changes,cursors_before);
+        }
+    }
+
+    fn cursors_snapshot(&self) -> Vec<Cursor> {
+        self.with_borrowed(|this| this.cursors.cursors.clone())
+    }
+
+    fn restore_cursors(&self, cursors:Vec<Cursor>) {
+        self.with_borrowed(|this| {
+            this.cursors.cursors = cursors;
+            this.cursors.recalculate_positions(&this.content);
+            this.rendered.update_cursor_sprites(&this.cursors, &mut this.content);
+        });
+    }
+
+    /// Record the edit in undo history and call the "edit finished" callback. The cursors after
+    /// edit are the current ones.
+    fn record_edit(&self, kind:EditKind, changes:Vec<RecordedChange>, cursors_before:Vec<Cursor>) {
+        self.with_borrowed(|this| {
+            let cursors_after = this.cursors.cursors.clone();
+            this.history.record(Edit {kind,changes,cursors_before,cursors_after});
+        });
+        self.call_callback(|this| &mut this.edit_finished_callback);
+    }
+
+    /// Call the "cursors moved" callback. Used by FRP handlers of the cursor-moving events. The
+    /// auto-closed characters are not typed over anymore once the user moves the cursors.
+    fn notify_cursors_moved(&self) {
+        self.with_borrowed(|this| this.auto_closed.clear());
+        self.call_callback(|this| &mut this.cursors_moved_callback);
+    }
+
+    /// Call the callback stored in the field returned by `field`. The data is not borrowed while
+    /// the callback runs, so it can use this TextField.
+    fn call_callback(&self, field:impl Fn(&mut TextFieldData) -> &mut Option<Box<dyn FnMut()>>) {
+        if let Some(mut callback) = self.with_borrowed(|this| field(this).take()) {
+            callback();
+            self.with_borrowed(|this| {
+                let field = field(this);
+                if field.is_none() {
+                    *field = Some(callback);
+                }
+            });
+        }
+    }
+}
+
+
+// === Searching ===
+
+impl TextField {
+    /// Find all matches of the search in the content.
+    pub fn find_all(&self, search:&Search) -> Vec<Range<TextLocation>> {
+        self.with_borrowed(|this| search.find_all(&this.content))
+    }
+
+    /// Replace the first match starting at the active cursor or after it (wrapping around the end
+    /// of content). The cursor is placed after the inserted text.
+    ///
+    /// Returns the range of the inserted text, or `None` if there was no match.
+    pub fn replace_next(&self, search:&Search, replacement:&str) -> Option<Range<TextLocation>> {
+        let next = self.with_borrowed(|this| {
+            let cursor       = this.cursors.active_cursor();
+            let start        = cursor.selection_range().start;
+            let replacements = search.replacements(&this.content,replacement).into_iter();
+            let mut after    = replacements.clone().filter(|r| r.range.start >= start);
+            after.next().or_else(|| replacements.clone().next())
+        });
+        next.map(|next| {
+            let cursors_before = self.cursors_snapshot();
+            let change         = TextChange::replace(next.range,&next.text);
+            let inserted       = change.inserted_text_range();
+            let changes        = self.apply_changes_notifying(std::iter::once(change));
+            self.with_borrowed(|this| {
+                this.cursors.remove_additional_cursors();
+                *this.cursors.active_cursor_mut() = Cursor::new(inserted.end);
+                this.rendered.update_cursor_sprites(&this.cursors, &mut this.content);
+            });
+            self.record_edit(EditKind::Other,changes,cursors_before);
+            inserted
+        })
+    }
+
+    /// Replace all matches of the search in the content.
+    ///
+    /// Returns the number of replaced matches.
+    pub fn replace_all(&self, search:&Search, replacement:&str) -> usize {
+        let replacements = self.with_borrowed(|this| {
+            search.replacements(&this.content,replacement)
+        });
+        let count          = replacements.len();
+        let cursors_before = self.cursors_snapshot();
+        // The replacements are applied from the last one, so the locations of the remaining ones
+        // stay valid.
+        let changes = replacements.into_iter().rev().map(|r| TextChange::replace(r.range,&r.text));
+        let changes = self.apply_changes_notifying(changes);
+        self.record_edit(EditKind::Other,changes,cursors_before);
+        count
     }
 }
 
@@ -329,23 +1012,56 @@ impl TextField {
 
 impl TextField {
 
-    fn write_per_cursor<'a,It>(&self, text_per_cursor:It)
+    /// Apply the changes one by one, calling the "text changed" callback for each of them.
+    ///
+    /// Returns the applied changes, which are not recorded in undo history by this function.
+    fn apply_changes_notifying(&self, changes:impl Iterator<Item=TextChange>)
+    -> Vec<RecordedChange> {
+        let mut opt_callback = self.with_borrowed(|this| this.text_change_callback.take());
+        let mut recorded     = Vec::new();
+        for change in changes {
+            let (notification,change) = self.with_borrowed(|this| {
+                this.apply_recorded_change(change)
+            });
+            if let Some(callback) = opt_callback.as_mut() {
+                callback(&notification);
+            }
+            recorded.push(change);
+        }
+        self.with_borrowed(|this| {
+            if this.text_change_callback.is_none() {
+                this.text_change_callback = opt_callback
+            }
+            this.clear_word_occurrences();
+            this.assignment_update().update_after_text_edit();
+            this.rendered.update_glyphs(&mut this.content);
+            this.update_gutter();
+            this.cursors.recalculate_positions(&this.content);
+            this.rendered.update_cursor_sprites(&this.cursors, &mut this.content);
+        });
+        recorded
+    }
+
+    fn write_per_cursor<'a,It>(&self, text_per_cursor:It) -> Vec<RecordedChange>
         where It : Iterator<Item=(CursorId,&'a str)> {
         let mut location_change = TextLocationChange::default();
         let mut opt_callback    = self.with_borrowed(|this| std::mem::take(&mut this.text_change_callback));
+        let mut recorded        = Vec::new();
         for (cursor_id,to_insert) in text_per_cursor {
-            let notification = self.with_borrowed(|this| {
+            let (notification,change) = self.with_borrowed(|this| {
                 this.apply_one_cursor_change(&mut location_change,cursor_id,to_insert)
             });
             if let Some(callback) = opt_callback.as_mut() {
                 callback(&notification);
             }
+            recorded.push(change);
         }
         self.with_borrowed(|this| {
             if this.text_change_callback.is_none() {
                 this.text_change_callback = opt_callback
             }
         });
+        recorded
     }
 }
 
@@ -358,16 +1074,29 @@ impl TextFieldData {
         let rendered             = TextFieldSprites::new(world,&properties);
         let frp                  = None;
         let word_occurrences     = None;
-        let text_change_callback = None;
+        let history              = default();
+        let editing_rules        = Box::new(PlainTextRules);
+        let folding_regions      = None;
+        let composition          = None;
+        let decorations          = default();
+        let auto_closed          = default();
+        let gutter               = GutterSprites::new(world,&properties);
+        let text_change_callback   = None;
+        let edit_finished_callback = None;
+        let cursors_moved_callback = None;
         display_object.add_child(rendered.display_object.clone_ref());
+        display_object.add_child(gutter.display_object.clone_ref());
 
-        Self {properties,content,cursors,rendered,display_object,frp,word_occurrences,
-              text_change_callback}.initialize()
+        let this = Self {properties,content,cursors,rendered,display_object,frp,word_occurrences,
+            history,editing_rules,folding_regions,composition,decorations,auto_closed,gutter,
+            text_change_callback,edit_finished_callback,cursors_moved_callback};
+        this.initialize()
     }
 
     fn initialize(mut self) -> Self {
         self.assignment_update().update_line_assignment();
         self.rendered.update_glyphs(&mut self.content);
+        self.update_gutter();
         self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
         self
     }
@@ -381,18 +1110,128 @@ impl TextFieldData {
         }
     }
 
+    /// Update the rendered text and cursors after edit.
+    fn update_after_edit(&mut self) {
+        self.clear_word_occurrences();
+        // TODO[ao] updates should be done only in one place and only once per frame
+        // see https://github.com/luna/ide/issues/178
+        self.assignment_update().update_after_text_edit();
+        self.rendered.update_glyphs(&mut self.content);
+        self.update_gutter();
+        self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+    }
+
+    /// Display the text inserted by composition changes in a distinct color.
+    fn highlight_composition(&mut self, changes:&[RecordedChange]) {
+        let color = Vector4::new(0.5,0.5,1.0,1.0);
+        for range in changes.iter().map(|change| change.change.inserted_text_range()) {
+            for line in range.start.line..=range.end.line {
+                let lines    = self.content.lines_mut();
+                let is_first = line == range.start.line;
+                let is_last  = line == range.end.line;
+                let start    = if is_first { range.start.column } else { 0 };
+                let end      = if is_last  { range.end.column   } else { lines[line].len() };
+                let span     = ColorSpan {columns:start..end, color};
+                lines.modify(line, |line| {
+                    let mut spans = line.highlighting().to_vec();
+                    spans.push(span);
+                    line.set_highlighting(spans);
+                });
+                self.content.dirty_lines.add_single_line(line);
+            }
+        }
+    }
+
+    fn update_gutter(&mut self) {
+        let scroll_offset = -self.rendered.display_object.position().xy();
+        let view_height   = self.properties.size.y;
+        self.gutter.update(&self.content,&self.decorations,scroll_offset,view_height);
+    }
+
+    /// Move the cursors out of hidden lines and update the rendered content after folding or
+    /// unfolding regions.
+    fn update_after_folding(&mut self) {
+        let content = &self.content;
+        let visible = |location:TextLocation| {
+            let line = content.displayed_line(location.line);
+            if line == location.line { location }
+            else                     { TextLocation {line, column:content.lines()[line].len()} }
+        };
+        for cursor in &mut self.cursors.cursors {
+            cursor.position    = visible(cursor.position);
+            cursor.selected_to = visible(cursor.selected_to);
+        }
+        self.cursors.recalculate_positions(&self.content);
+        self.assignment_update().update_after_text_edit();
+        self.rendered.update_glyphs(&mut self.content);
+        self.update_gutter();
+        self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+    }
+
+    /// Indices of lines having a cursor or touched by a selection, in ascending order. See
+    /// `Cursor::selected_lines`.
+    fn lines_under_cursors(&self) -> Vec<usize> {
+        let lines = self.cursors.cursors.iter().flat_map(|cursor| cursor.selected_lines());
+        lines.collect::<BTreeSet<_>>().into_iter().collect()
+    }
+
+    /// Select the text removed by Backspace in the line's leading whitespace for cursors without
+    /// selection. See `editing::backspace_dedent_column`.
+    fn select_indentation_before_cursors(&mut self) {
+        let unit = self.editing_rules.indent_unit();
+        for cursor in self.cursors.cursors.iter_mut().filter(|c| !c.has_selection()) {
+            let position = cursor.position;
+            let line     = &self.content.lines()[position.line].chars()[..position.column];
+            if let Some(column) = backspace_dedent_column(line,unit) {
+                cursor.selected_to = TextLocation {column,..position};
+            }
+        }
+    }
+
+    fn typed_char_action(&self, typed:char) -> TypedCharAction {
+        let matcher = BracketMatcher::new(self.editing_rules.bracket_pairs());
+        matcher.typed_char_action(&self.content,&self.cursors.cursors,&self.auto_closed,typed)
+    }
+
     fn apply_one_cursor_change
     (&mut self, location_change:&mut TextLocationChange, cursor_id:CursorId, to_insert:&str)
-    -> TextChangedNotification {
+    -> (TextChangedNotification,RecordedChange) {
         let CursorId(id)   = cursor_id;
         let cursor         = &mut self.cursors.cursors[id];
         let replaced       = location_change.apply_to_range(cursor.selection_range());
-        let replaced_chars = self.content.convert_location_range_to_char_index(&replaced);
         let change         = TextChange::replace(replaced,to_insert);
         location_change.add_change(&change);
         *cursor = Cursor::new(change.inserted_text_range().end);
+        self.apply_recorded_change(change)
+    }
+
+    fn apply_change(&mut self, change:TextChange) {
+        self.history.update_after_change(&change);
+        self.decorations.update_after_change(&change);
+        self.auto_closed.update_after_change(&change);
+        for cursor in &mut self.cursors.cursors {
+            cursor.position    = moved_by_change(cursor.position,&change);
+            cursor.selected_to = moved_by_change(cursor.selected_to,&change);
+        }
+        self.content.apply_change(change);
+        self.clear_word_occurrences();
+        self.assignment_update().update_after_text_edit();
+        self.rendered.update_glyphs(&mut self.content);
+        self.update_gutter();
+        self.cursors.recalculate_positions(&self.content);
+        self.rendered.update_cursor_sprites(&self.cursors, &mut self.content);
+    }
+
+    /// Apply change to the content, returning the notification about it and the change recorded
+    /// for undo history.
+    fn apply_recorded_change(&mut self, change:TextChange)
+    -> (TextChangedNotification,RecordedChange) {
+        let replaced_chars = self.content.convert_location_range_to_char_index(&change.replaced);
+        let recorded       = RecordedChange::new(change.clone(),&self.content);
+        self.decorations.update_after_change(&change);
+        self.auto_closed.update_after_change(&change);
         self.content.apply_change(change.clone());
-        TextChangedNotification {change,replaced_chars}
+        (TextChangedNotification {change,replaced_chars},recorded)
     }
 }
 
@@ -0,0 +1,460 @@
+//! Matching brackets and quotes in TextField content.
+//!
+//! The brackets are matched over the whole content, taking nesting into account. The quotes, being
+//! pairs with the same opening and closing character, are matched within a single line only: the
+//! quote is an opening one if it is preceded by an even number of unescaped quotes in its line.
+
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::cursor::Cursor;
+use crate::display::shape::text::text_field::location::TextLocationChange;
+
+use data::text::TextChange;
+use data::text::TextLocation;
+use std::ops::Range;
+
+
+
+// ===================
+// === BracketPair ===
+// ===================
+
+/// A pair of characters opening and closing a fragment of text.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct BracketPair {
+    /// The opening character.
+    pub open  : char,
+    /// The closing character.
+    pub close : char,
+}
+
+impl BracketPair {
+    /// Create pair of different opening and closing brackets.
+    pub const fn new(open:char, close:char) -> Self {
+        BracketPair {open,close}
+    }
+
+    /// Create pair of quotes, i.e. with the same opening and closing character.
+    pub const fn quote(quote:char) -> Self {
+        BracketPair {open:quote, close:quote}
+    }
+
+    /// Check if this pair is a quote.
+    pub fn is_quote(&self) -> bool {
+        self.open == self.close
+    }
+}
+
+/// The brackets matched in every text.
+pub const DEFAULT_BRACKETS:&[BracketPair] = &
+    [ BracketPair::new('(',')')
+    , BracketPair::new('[',']')
+    , BracketPair::new('{','}')
+    ];
+
+
+
+// ====================
+// === BracketMatch ===
+// ====================
+
+/// The bracket at some location and its partner.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub struct BracketMatch {
+    /// The location of the bracket.
+    pub bracket : TextLocation,
+    /// The location of the bracket's partner.
+    pub partner : TextLocation,
+}
+
+impl BracketMatch {
+    /// The locations of both brackets, the earlier one first.
+    pub fn sorted(&self) -> (TextLocation,TextLocation) {
+        if self.bracket <= self.partner { (self.bracket,self.partner) }
+        else                            { (self.partner,self.bracket) }
+    }
+
+    /// The ranges of both brackets' characters, e.g. for highlighting.
+    pub fn ranges(&self) -> Vec<Range<TextLocation>> {
+        let (first,second) = self.sorted();
+        vec![char_range(first),char_range(second)]
+    }
+
+    /// The range of text between the brackets.
+    pub fn inner_range(&self) -> Range<TextLocation> {
+        let (first,second) = self.sorted();
+        char_range(first).end..second
+    }
+
+    /// The range of text between the brackets, including the brackets themselves.
+    pub fn outer_range(&self) -> Range<TextLocation> {
+        let (first,second) = self.sorted();
+        first..char_range(second).end
+    }
+}
+
+fn char_range(location:TextLocation) -> Range<TextLocation> {
+    let next = TextLocation {column:location.column + 1, ..location};
+    location..next
+}
+
+
+
+// =======================
+// === TypedCharAction ===
+// =======================
+
+/// The way of handling a single character typed by all the cursors.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum TypedCharAction {
+    /// Insert the character as usual.
+    Insert,
+    /// Insert the pair's both characters, placing the cursors between them.
+    AutoClose(BracketPair),
+    /// Insert the pair's characters around the text selected by each cursor, keeping it selected.
+    /// The cursors without selection get the pair inserted as by `AutoClose`.
+    Wrap(BracketPair),
+    /// Do not insert anything, just move the cursors over the typed closing character which was
+    /// auto-closed before.
+    TypeOver,
+}
+
+
+
+// ==========================
+// === AutoClosedBrackets ===
+// ==========================
+
+/// The locations of the closing characters inserted by auto-closing, which may be typed over. The
+/// closing characters typed by the user or loaded with the content are never typed over.
+#[derive(Clone,Debug,Default)]
+pub struct AutoClosedBrackets {
+    locations : Vec<TextLocation>,
+}
+
+impl AutoClosedBrackets {
+    /// Remember the closing character auto-inserted at given location.
+    pub fn insert(&mut self, location:TextLocation) {
+        self.locations.push(location);
+    }
+
+    /// Forget the closing character at given location, e.g. once it was typed over.
+    pub fn remove(&mut self, location:TextLocation) {
+        self.locations.retain(|l| *l != location);
+    }
+
+    /// Forget all the closing characters.
+    pub fn clear(&mut self) {
+        self.locations.clear();
+    }
+
+    /// Check if the closing character at given location was auto-inserted.
+    pub fn contains(&self, location:TextLocation) -> bool {
+        self.locations.contains(&location)
+    }
+
+    /// Move the locations, so they point at the same characters after the change. The characters
+    /// removed by the change are forgotten.
+    pub fn update_after_change(&mut self, change:&TextChange) {
+        let replaced = &change.replaced;
+        self.locations.retain(|l| *l < replaced.start || *l >= replaced.end);
+        let mut location_change = TextLocationChange::default();
+        location_change.add_change(change);
+        for location in self.locations.iter_mut().filter(|l| **l >= replaced.end) {
+            *location = location_change.apply_to(*location);
+        }
+    }
+}
+
+
+
+// ======================
+// === BracketMatcher ===
+// ======================
+
+/// Finds the bracket partners in the TextField content and decides about auto-closing the typed
+/// brackets.
+#[derive(Clone,Copy,Debug)]
+pub struct BracketMatcher<'a> {
+    /// The matched pairs.
+    pub pairs : &'a [BracketPair],
+}
+
+impl<'a> BracketMatcher<'a> {
+    /// Create matcher of given pairs.
+    pub fn new(pairs:&'a [BracketPair]) -> Self {
+        BracketMatcher {pairs}
+    }
+
+    /// Find the partner of the bracket at the cursor located at `location`. The bracket after the
+    /// cursor is checked first, then the one before it.
+    pub fn find_match(&self, content:&TextFieldContent, location:TextLocation)
+    -> Option<BracketMatch> {
+        let before = location.column.checked_sub(1).map(|column| TextLocation {column,..location});
+        let mut candidates = std::iter::once(location).chain(before);
+        candidates.find_map(|bracket| {
+            self.partner_of(content,bracket).map(|partner| BracketMatch {bracket,partner})
+        })
+    }
+
+    /// Find the partner of the bracket or quote character at given location.
+    pub fn partner_of(&self, content:&TextFieldContent, location:TextLocation)
+    -> Option<TextLocation> {
+        let character = char_at(content,location)?;
+        let pair      = self.pairs.iter().find(|p| p.open == character || p.close == character)?;
+        if pair.is_quote() {
+            Self::quote_partner(content,location,pair.open)
+        } else if pair.open == character {
+            Self::bracket_partner(chars_after(content,location),*pair)
+        } else {
+            let flipped = BracketPair::new(pair.close,pair.open);
+            Self::bracket_partner(chars_before(content,location),flipped)
+        }
+    }
+
+    /// Find the innermost brackets enclosing the given range. Quotes are not taken into account.
+    ///
+    /// The returned match has the opening bracket as `bracket`.
+    pub fn enclosing(&self, content:&TextFieldContent, range:&Range<TextLocation>)
+    -> Option<BracketMatch> {
+        let brackets      = self.pairs.iter().filter(|pair| !pair.is_quote());
+        let brackets      = brackets.collect_vec();
+        let mut unmatched = Vec::new();
+        for (location,character) in chars_before(content,range.start) {
+            if let Some(pair) = brackets.iter().find(|pair| pair.close == character) {
+                unmatched.push(pair.open);
+            } else if brackets.iter().any(|pair| pair.open == character) {
+                if unmatched.is_empty() {
+                    let partner = self.partner_of(content,location);
+                    let matched = partner.filter(|partner| *partner >= range.end);
+                    if let Some(partner) = matched {
+                        return Some(BracketMatch {bracket:location, partner});
+                    }
+                } else if unmatched.last() == Some(&character) {
+                    unmatched.pop();
+                }
+            }
+        }
+        None
+    }
+
+    /// Decide how the character typed by all the cursors should be handled.
+    ///
+    /// The closing characters are typed over if every cursor is just before the same character
+    /// which was `auto_closed`, and does not select anything. The opening characters wrap the
+    /// selected text if any cursor has a selection. Otherwise they are auto-closed if every cursor
+    /// is followed by whitespace, closing bracket or line end; the quotes additionally must not be
+    /// preceded by an alphanumeric character.
+    pub fn typed_char_action
+    (&self, content:&TextFieldContent, cursors:&[Cursor], auto_closed:&AutoClosedBrackets
+    , typed:char) -> TypedCharAction {
+        let closes      = self.pairs.iter().any(|pair| pair.close == typed);
+        let opened_pair = self.pairs.iter().find(|pair| pair.open == typed);
+        let wraps       = cursors.iter().any(Cursor::has_selection);
+        let types_over  = closes && cursors.iter().all(|cursor| {
+            let at_closing = char_at(content,cursor.position) == Some(typed);
+            !cursor.has_selection() && at_closing && auto_closed.contains(cursor.position)
+        });
+        let auto_closes = |pair:&BracketPair| cursors.iter().all(|cursor| {
+            let range   = cursor.selection_range();
+            let next    = char_at(content,range.end);
+            let prev    = chars_before(content,range.start).next().map(|(_,c)| c);
+            let is_free = |c:char| c.is_whitespace() || self.pairs.iter().any(|p| p.close == c);
+            let prev_ok = !pair.is_quote() || prev.map_or(true, |c| !c.is_alphanumeric());
+            next.map_or(true,is_free) && prev_ok
+        });
+        if types_over {
+            TypedCharAction::TypeOver
+        } else {
+            match opened_pair {
+                Some(pair) if wraps             => TypedCharAction::Wrap(*pair),
+                Some(pair) if auto_closes(pair) => TypedCharAction::AutoClose(*pair),
+                _                               => TypedCharAction::Insert,
+            }
+        }
+    }
+}
+
+
+// === Private ===
+
+impl<'a> BracketMatcher<'a> {
+    /// Find the partner of a bracket in the characters following it in the search direction. The
+    /// `pair` is given in the search direction, i.e. flipped when searching backwards.
+    fn bracket_partner
+    (chars:impl Iterator<Item=(TextLocation,char)>, pair:BracketPair) -> Option<TextLocation> {
+        let mut depth = 0;
+        for (location,character) in chars {
+            if character == pair.open {
+                depth += 1;
+            } else if character == pair.close {
+                if depth == 0 { return Some(location) }
+                depth -= 1;
+            }
+        }
+        None
+    }
+
+    fn quote_partner(content:&TextFieldContent, location:TextLocation, quote:char)
+    -> Option<TextLocation> {
+        let line     = content.lines()[location.line].chars();
+        let is_quote = |column:usize| line[column] == quote && !is_escaped(line,column);
+        is_quote(location.column).and_option_from(|| {
+            let quotes     = (0..location.column).filter(|column| is_quote(*column)).count();
+            let is_opening = quotes % 2 == 0;
+            let column     = if is_opening {
+                (location.column+1..line.len()).find(|column| is_quote(*column))
+            } else {
+                (0..location.column).rev().find(|column| is_quote(*column))
+            };
+            column.map(|column| TextLocation {column,..location})
+        })
+    }
+}
+
+fn is_escaped(line:&[char], column:usize) -> bool {
+    let backslashes = line[..column].iter().rev().take_while(|c| **c == '\\').count();
+    backslashes % 2 == 1
+}
+
+fn char_at(content:&TextFieldContent, location:TextLocation) -> Option<char> {
+    let line = content.lines().get(location.line)?;
+    line.chars().get(location.column).cloned()
+}
+
+/// The characters after given location, with their locations, in the text order.
+fn chars_after(content:&TextFieldContent, location:TextLocation)
+-> impl Iterator<Item=(TextLocation,char)> + '_ {
+    let lines = content.lines();
+    (location.line..lines.len()).flat_map(move |line| {
+        let chars = lines[line].chars();
+        let skip  = if line == location.line { location.column + 1 } else { 0 };
+        chars.iter().enumerate().skip(skip).map(move |(column,c)| (TextLocation {line,column},*c))
+    })
+}
+
+/// The characters before given location, with their locations, in the reversed text order.
+fn chars_before(content:&TextFieldContent, location:TextLocation)
+-> impl Iterator<Item=(TextLocation,char)> + '_ {
+    let lines     = content.lines();
+    let last_line = location.line.min(lines.len().saturating_sub(1));
+    (0..=last_line).rev().flat_map(move |line| {
+        let chars = lines[line].chars();
+        let end   = if line == location.line { location.column.min(chars.len()) }
+                    else                     { chars.len() };
+        let chars = chars[..end].iter().enumerate().rev();
+        chars.map(move |(column,c)| (TextLocation {line,column},*c))
+    })
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::shape::text::text_field::content::test::mock_properties;
+
+    use basegl_core_msdf_sys as msdf_sys;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const PAIRS:&[BracketPair] = &
+        [ BracketPair::new('(',')')
+        , BracketPair::new('[',']')
+        , BracketPair::quote('"')
+        ];
+
+    fn location(line:usize, column:usize) -> TextLocation {
+        TextLocation {line,column}
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn matching_brackets() {
+        msdf_sys::initialized().await;
+        let content = TextFieldContent::new("f (a [b]\n  (c)) \"x\\\"y\" z",&mock_properties());
+        let matcher = BracketMatcher::new(PAIRS);
+        let partner = |line,column| matcher.partner_of(&content,location(line,column));
+        assert_eq!(Some(location(1,5)), partner(0,2));
+        assert_eq!(Some(location(0,2)), partner(1,5));
+        assert_eq!(Some(location(0,7)), partner(0,5));
+        assert_eq!(Some(location(1,4)), partner(1,2));
+        assert_eq!(Some(location(1,12)), partner(1,7));
+        assert_eq!(Some(location(1,7)), partner(1,12));
+        assert_eq!(None, partner(0,0));
+        assert_eq!(None, partner(1,10));
+
+        let found = matcher.find_match(&content,location(0,8));
+        let found = found.map(|m| (m.bracket,m.partner));
+        assert_eq!(Some((location(0,7),location(0,5))), found);
+        let found = matcher.find_match(&content,location(0,5));
+        let found = found.map(|m| (m.bracket,m.partner));
+        assert_eq!(Some((location(0,5),location(0,7))), found);
+        assert_eq!(None, matcher.find_match(&content,location(0,1)));
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn finding_enclosing_brackets() {
+        msdf_sys::initialized().await;
+        let content   = TextFieldContent::new("f (a [b]\n  (c)) [d",&mock_properties());
+        let matcher   = BracketMatcher::new(PAIRS);
+        let enclosing = |start:TextLocation, end:TextLocation| {
+            matcher.enclosing(&content,&(start..end)).map(|m| m.inner_range())
+        };
+        assert_eq!(Some(location(0,6)..location(0,7)), enclosing(location(0,6),location(0,6)));
+        assert_eq!(Some(location(0,3)..location(1,5)), enclosing(location(0,6),location(1,0)));
+        assert_eq!(Some(location(0,3)..location(1,5)), enclosing(location(0,5),location(0,8)));
+        assert_eq!(Some(location(1,3)..location(1,4)), enclosing(location(1,4),location(1,4)));
+        assert_eq!(Some(location(0,3)..location(1,5)), enclosing(location(1,5),location(1,5)));
+        assert_eq!(None, enclosing(location(0,1),location(0,1)));
+        assert_eq!(None, enclosing(location(1,8),location(1,8)));
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn deciding_about_auto_closing() {
+        msdf_sys::initialized().await;
+        let content         = TextFieldContent::new("a) b\"c  x)",&mock_properties());
+        let matcher         = BracketMatcher::new(PAIRS);
+        let mut auto_closed = AutoClosedBrackets::default();
+        auto_closed.insert(location(0,1));
+        auto_closed.insert(location(0,4));
+        let action = |columns:&[usize], typed:char| {
+            let cursors = columns.iter().map(|c| Cursor::new(location(0,*c))).collect_vec();
+            matcher.typed_char_action(&content,&cursors,&auto_closed,typed)
+        };
+        let brackets = BracketPair::new('(',')');
+        let quotes   = BracketPair::quote('"');
+        assert_eq!(TypedCharAction::TypeOver             , action(&[1]  ,')'));
+        assert_eq!(TypedCharAction::Insert               , action(&[1,4],')'));
+        assert_eq!(TypedCharAction::Insert               , action(&[9]  ,')'));
+        assert_eq!(TypedCharAction::TypeOver             , action(&[4]  ,'"'));
+        assert_eq!(TypedCharAction::AutoClose(brackets)  , action(&[1,2],'('));
+        assert_eq!(TypedCharAction::AutoClose(brackets)  , action(&[9]  ,'('));
+        assert_eq!(TypedCharAction::Insert               , action(&[0]  ,'('));
+        assert_eq!(TypedCharAction::AutoClose(quotes)    , action(&[7]  ,'"'));
+        assert_eq!(TypedCharAction::Insert               , action(&[6]  ,'"'));
+        assert_eq!(TypedCharAction::Insert               , action(&[9]  ,'"'));
+        assert_eq!(TypedCharAction::Insert               , action(&[9]  ,'x'));
+
+        let selection = Cursor {position:location(0,0), selected_to:location(0,1)};
+        let cursors   = vec![selection,Cursor::new(location(0,9))];
+        let action    = matcher.typed_char_action(&content,&cursors,&auto_closed,'"');
+        assert_eq!(TypedCharAction::Wrap(quotes), action);
+    }
+
+    #[test]
+    fn moving_auto_closed_brackets() {
+        let mut auto_closed = AutoClosedBrackets::default();
+        auto_closed.insert(location(0,2));
+        auto_closed.insert(location(1,0));
+        auto_closed.update_after_change(&TextChange::insert(location(0,2),"ab\nc"));
+        assert!(auto_closed.contains(location(1,1)));
+        assert!(auto_closed.contains(location(2,0)));
+        auto_closed.update_after_change(&TextChange::delete(location(1,0)..location(2,0)));
+        assert!(auto_closed.contains(location(1,0)));
+        assert_eq!(auto_closed.locations.len(), 1);
+    }
+}
@@ -1,11 +1,17 @@
 //! Module with all structures describing the content of the TextField.
 pub mod line;
+pub mod rope;
+pub mod wrap;
 
 use crate::prelude::*;
 
 use crate::display::shape::text::glyph::font::FontHandle;
+use crate::display::shape::text::text_field::content::line::ColorSpan;
 use crate::display::shape::text::text_field::content::line::Line;
 use crate::display::shape::text::text_field::content::line::LineFullInfo;
+use crate::display::shape::text::text_field::content::rope::LineRope;
+use crate::display::shape::text::text_field::content::wrap::wrap_line;
+use crate::display::shape::text::text_field::folding::FoldingRegion;
 use crate::display::shape::text::text_field::TextFieldProperties;
 
 use data::text::ChangeType;
@@ -84,6 +90,13 @@ impl DirtyLines {
 // ============================
 
 /// The content of text component - namely lines of text.
+///
+/// The lines are kept in a `LineRope`, so getting line and converting between locations and char
+/// indices take logarithmic time, and edits are linear only in the length of edited lines.
+///
+/// When the soft wrap is enabled, the lines are displayed in many _visual rows_ (see `wrap`
+/// module), and all the positions on the screen are computed for rows instead of lines. The lines
+/// hidden in folded regions (see `folding` module) have no rows at all.
 #[derive(Debug)]
 pub struct TextFieldContent {
     /// A struct which describe which lines are dirty (were modified after last rendering).
@@ -93,9 +106,9 @@ pub struct TextFieldContent {
     /// Line height in pixels, being a distance between baselines of consecutive lines.
     pub line_height: f32,
     /// Lines being the actual content.
-    lines: Vec<Line>,
-    /// This field caches the position of beginning of each line.
-    line_offsets: Vec<usize>,
+    lines: LineRope,
+    /// The maximum width of visual row in pixels, or `None` if lines are not wrapped.
+    wrap_width: Option<f32>,
 }
 
 impl TextFieldContent {
@@ -108,7 +121,7 @@ impl TextFieldContent {
             lines        : split_to_lines(text).map(Line::new).collect(),
             dirty_lines  : DirtyLines::default(),
             font         : properties.font.clone_ref(),
-            line_offsets : Vec::new(),
+            wrap_width   : None,
         }
     }
 
@@ -131,7 +144,7 @@ impl TextFieldContent {
             output.extend(first_line.chars()[fragment.start.column..].iter().cloned());
             output.extend(std::iter::once('\n'));
             let whole_lines = (fragment.start.line+1)..fragment.end.line;
-            for line in &self.lines[whole_lines] {
+            for line in self.lines.range(whole_lines) {
                 output.extend(line.chars().iter().cloned());
                 output.extend(std::iter::once('\n'));
             }
@@ -144,23 +157,38 @@ impl TextFieldContent {
     ///
     /// It's a little bit quicker than getting specific line by `line` method, because it don't
     /// need to copy any additional data.
-    pub fn lines(&self) -> &[Line] {
-        self.lines.as_slice()
+    pub fn lines(&self) -> &LineRope {
+        &self.lines
     }
 
-    /// A mutablie `lines` accessor.
+    /// A mutable `lines` accessor. The lines should be edited with `LineRope::modify`, which
+    /// keeps the rope's cached lengths and rows up to date.
     ///
-    /// Please be noted, that this invalidate some cached data.
-    pub fn lines_mut(&mut self) -> &mut Vec<Line> {
-        self.line_offsets.clear();
+    /// Please be noted, that the dirty lines are not marked by changes made this way.
+    pub(crate) fn lines_mut(&mut self) -> &mut LineRope {
         &mut self.lines
     }
 
+    /// Set the highlighting of lines, starting from the first one. The lines without given spans
+    /// are displayed in the base color.
+    pub fn set_highlighting(&mut self, lines:Vec<Vec<ColorSpan>>) {
+        let mut spans = lines.into_iter();
+        for index in 0..self.lines.len() {
+            let line_spans = spans.next().unwrap_or_default();
+            self.lines.modify(index, |line| line.set_highlighting(line_spans));
+        }
+        self.dirty_lines.add_lines_range_from(0..);
+    }
+
     /// Get a handy wrapper for line under index.
+    ///
+    /// The returned wrapper must not be used to change the line's characters, use `apply_change`
+    /// instead.
     pub fn line(&mut self, index:usize) -> LineFullInfo {
-        LineFullInfo {
+        let first_row = self.lines.line_first_row(index);
+        LineFullInfo {first_row,
             height  : self.line_height,
-            line    : &mut self.lines[index],
+            line    : self.lines.get_mut(index).expect("Line index out of bounds."),
             line_id : index,
             font    : self.font.clone_ref(),
         }
@@ -169,54 +197,91 @@ impl TextFieldContent {
     /// Replaces content with a new text. This marks all lines as dirty.
     pub fn set_content(&mut self, text:&str) {
         self.lines = split_to_lines(text).map(Line::new).collect();
+        self.wrap_lines(0..self.lines.len());
+        self.dirty_lines.add_lines_range_from(0..);
+    }
+
+    /// The maximum width of visual row in pixels, or `None` if lines are not wrapped.
+    pub fn wrap_width(&self) -> Option<f32> {
+        self.wrap_width
+    }
+
+    /// Enable soft wrap of lines to visual rows of given width in pixels, or disable it by passing
+    /// `None`. This marks all lines as dirty.
+    pub fn set_wrap_width(&mut self, wrap_width:Option<f32>) {
+        self.wrap_width = wrap_width;
+        if wrap_width.is_some() {
+            self.wrap_lines(0..self.lines.len());
+        } else {
+            for index in 0..self.lines.len() {
+                self.lines.modify(index, |line| line.set_rows(default()));
+            }
+        }
         self.dirty_lines.add_lines_range_from(0..);
     }
 
+    /// Number of visual rows of all lines. Without soft wrap it is equal to number of lines.
+    pub fn rows_count(&self) -> usize {
+        self.lines.row_count()
+    }
+
+    /// Get the line index and the row within that line of given visual row. Rows past the end are
+    /// clamped to the last row.
+    pub fn row_to_line_and_row(&self, row:usize) -> (usize,usize) {
+        self.lines.row_to_line_and_row(row)
+    }
+
+    /// Get the index of visual row where the given location is displayed.
+    pub fn row_of_location(&self, location:TextLocation) -> usize {
+        let first_row = self.lines.line_first_row(location.line);
+        first_row + self.lines[location.line].row_of_column(location.column)
+    }
+
     /// Get the nearest text location from the point on the screen.
     pub fn location_at_point(&mut self, point:Vector2<f32>) -> TextLocation {
-        let line_opt = self.line_at_y_position(point.y);
-        let mut line = match line_opt {
-            Some(line)             => line,
-            None if point.y >= 0.0 => self.line(0),
-            None                   => self.line(self.lines.len()-1),
+        let row_opt = self.row_at_y_position(point.y);
+        let row     = match row_opt {
+            Some(row)              => row,
+            None if point.y >= 0.0 => 0,
+            None                   => self.rows_count()-1,
         };
-        let column_opt = line.find_char_at_x_position(point.x);
+        let (line_index,row) = self.row_to_line_and_row(row);
+        let mut line         = self.line(line_index);
+        let row_range        = line.row_range(row);
+        let row_x_offset     = line.row_x_offset(row);
+        let column_opt       = line.find_char_at_x_position(point.x + row_x_offset);
         let column = match column_opt {
-            Some(column)           => column,
-            None if point.x <= 0.0 => 0,
-            None                   => line.len(),
+            Some(column)           => column.max(row_range.start).min(line.row_end_column(row)),
+            None if point.x <= 0.0 => row_range.start,
+            None                   => line.row_end_column(row),
         };
+        let column = line.snap_to_grapheme_boundary(column);
         TextLocation{line:line.line_id, column}
     }
 
-    /// Get the index of line which is displayed at given y screen coordinate.
-    pub fn line_at_y_position(&mut self, y:f32) -> Option<LineFullInfo> {
+    /// Get the index of visual row which is displayed at given y screen coordinate.
+    pub fn row_at_y_position(&self, y:f32) -> Option<usize> {
         let index    = -(y / self.line_height).ceil();
-        let is_valid = index >= 0.0 && index < self.lines.len() as f32;
-        let index    = is_valid.and_option_from(|| Some(index as usize));
-        index.map(move |i| self.line(i))
+        let is_valid = index >= 0.0 && index < self.rows_count() as f32;
+        is_valid.and_option_from(|| Some(index as usize))
     }
 
     /// Converts location in this text represented by `row:column` pair to absolute char's position
-    /// from document begin. Panics if location is invalid for current content.
-    pub fn convert_location_to_char_index(&mut self, location:TextLocation) -> usize {
-        if self.line_offsets.is_empty() {
-            self.line_offsets.push(0);
-        }
-        if self.line_offsets.len() <= location.line {
-            let required_len           = location.line + 1;
-            let mut current_char_index = *self.line_offsets.last().unwrap();
-            for line in self.lines.iter().take(required_len).skip(self.line_offsets.len()-1) {
-                current_char_index += line.len() + 1;
-                self.line_offsets.push(current_char_index);
-            }
-        }
-        self.line_offsets[location.line] + location.column
+    /// from document begin.
+    pub fn convert_location_to_char_index(&self, location:TextLocation) -> usize {
+        self.lines.line_start_char_index(location.line) + location.column
+    }
+
+    /// Converts absolute char's position from document begin to location in this text represented
+    /// by `row:column` pair. Positions past the end of the text are clamped.
+    pub fn convert_char_index_to_location(&self, index:usize) -> TextLocation {
+        let (line,column) = self.lines.char_index_to_line_and_column(index);
+        TextLocation {line,column}
     }
 
     /// Converts range of locations in this text represented by `row:column` pair to absolute
     /// char's position from document begin.
-    pub fn convert_location_range_to_char_index(&mut self, range:&Range<TextLocation>)
+    pub fn convert_location_range_to_char_index(&self, range:&Range<TextLocation>)
     -> Range<usize> {
         let start = self.convert_location_to_char_index(range.start);
         let end   = self.convert_location_to_char_index(range.end);
@@ -225,19 +290,93 @@ impl TextFieldContent {
 }
 
 
+// === Folding ===
+
+impl TextFieldContent {
+    /// Fold the region, hiding its lines behind a placeholder displayed at the header's end.
+    ///
+    /// The hidden lines have no visual rows, so they are skipped when rendering, navigating cursors
+    /// and finding locations at points on the screen. The text of content is not affected.
+    pub fn fold(&mut self, region:FoldingRegion) {
+        let hidden = region.hidden_lines();
+        for index in hidden.start..hidden.end.min(self.lines.len()) {
+            self.lines.modify(index, |line| line.set_hidden(true));
+        }
+        self.dirty_lines.add_lines_range_from(region.header..);
+    }
+
+    /// Unfold the region with given header, revealing the hidden lines directly following it. The
+    /// regions folded inside are unfolded as well.
+    ///
+    /// Returns `false` if there was no folded region at the header.
+    pub fn unfold(&mut self, header:usize) -> bool {
+        let lines  = header+1..self.lines.len();
+        let hidden = lines.take_while(|index| self.lines[*index].is_hidden()).collect_vec();
+        for index in &hidden {
+            self.lines.modify(*index, |line| line.set_hidden(false));
+        }
+        if !hidden.is_empty() {
+            self.dirty_lines.add_lines_range_from(header..);
+        }
+        !hidden.is_empty()
+    }
+
+    /// Unfold all the folded regions.
+    pub fn unfold_all(&mut self) {
+        for index in 0..self.lines.len() {
+            if self.lines[index].is_hidden() {
+                self.lines.modify(index, |line| line.set_hidden(false));
+            }
+        }
+        self.dirty_lines.add_lines_range_from(0..);
+    }
+
+    /// Check if the line is a header of folded region.
+    pub fn is_folded(&self, header:usize) -> bool {
+        self.lines.get(header+1).map_or(false, |line| line.is_hidden())
+    }
+
+    /// The first visible line after the given one.
+    pub fn next_visible_line(&self, line:usize) -> Option<usize> {
+        let row = self.lines.line_first_row(line+1);
+        (row < self.rows_count()).as_some_from(|| self.row_to_line_and_row(row).0)
+    }
+
+    /// The last visible line before the given one.
+    pub fn prev_visible_line(&self, line:usize) -> Option<usize> {
+        let row = self.lines.line_first_row(line).checked_sub(1);
+        row.map(|row| self.row_to_line_and_row(row).0)
+    }
+
+    /// The line displayed in place of the given one: the line itself if it is visible, or the
+    /// header of folded region otherwise.
+    pub fn displayed_line(&self, line:usize) -> usize {
+        if self.lines[line].is_hidden() { self.prev_visible_line(line).unwrap_or(0) }
+        else                            { line }
+    }
+
+    /// Unfold the regions containing the lines in the range, so the edited text is visible.
+    fn unfold_lines(&mut self, range:&Range<TextLocation>) {
+        for index in range.start.line..=range.end.line {
+            if self.lines.get(index).map_or(false, |line| line.is_hidden()) {
+                let header = self.displayed_line(index);
+                self.unfold(header);
+            }
+        }
+    }
+}
+
+
 // === Implementing Changes ===
 
 impl TextFieldContent {
-    /// Apply change to content.
+    /// Apply change to content. The folded regions containing the replaced text are unfolded.
     pub fn apply_change(&mut self, change:TextChange) {
-        let first_modified_line = change.replaced.start.line;
+        self.unfold_lines(&change.replaced);
         match change.change_type() {
             ChangeType::SingleLine => self.make_simple_change(change),
             ChangeType::MultiLine  => self.make_multiline_change(change),
         }
-        // Invalidate lines offsets
-        let first_offset_invalidated = first_modified_line + 1;
-        self.line_offsets.resize(first_offset_invalidated,default());
     }
 
     /// Apply many changes to content.
@@ -251,8 +390,16 @@ impl TextFieldContent {
         let line_index  = change.replaced.start.line;
         let new_content = change.lines.first().unwrap();
         let range       = change.replaced.start.column..change.replaced.end.column;
-        self.lines[line_index].modify().splice(range,new_content.iter().cloned());
-        self.dirty_lines.add_single_line(line_index);
+        let rows_count  = self.lines[line_index].rows_count();
+        self.lines.modify(line_index, |line| {
+            line.modify().splice(range,new_content.iter().cloned());
+        });
+        self.wrap_lines(line_index..line_index+1);
+        if self.lines[line_index].rows_count() != rows_count {
+            self.dirty_lines.add_lines_range_from(line_index..);
+        } else {
+            self.dirty_lines.add_single_line(line_index);
+        }
     }
 
     fn make_multiline_change(&mut self, mut change:TextChange) {
@@ -261,15 +408,36 @@ impl TextFieldContent {
         let end_line             = change.replaced.end.line;
         let replaced_lines_count = end_line - start_line + 1;
         let inserted_lines_count = change.lines.len();
+        let replaced_lines       = start_line..end_line+1;
+        let replaced_rows_count  = self.rows_in_lines(replaced_lines.clone());
         let inserted_lines       = change.lines.drain(0..change.lines.len()).map(Line::new_raw);
-        self.lines.splice(start_line..=end_line,inserted_lines);
-        if replaced_lines_count != inserted_lines_count {
+        self.lines.splice(replaced_lines,inserted_lines);
+        let new_lines            = start_line..start_line+inserted_lines_count;
+        self.wrap_lines(new_lines.clone());
+        let inserted_rows_count  = self.rows_in_lines(new_lines);
+        let rows_moved           = replaced_rows_count != inserted_rows_count;
+        if replaced_lines_count != inserted_lines_count || rows_moved {
             self.dirty_lines.add_lines_range_from(start_line..);
         } else {
             self.dirty_lines.add_lines_range(start_line..=end_line);
         }
     }
 
+    /// Compute visual rows of lines in given range, if soft wrap is enabled.
+    fn wrap_lines(&mut self, lines:Range<usize>) {
+        if let Some(max_width) = self.wrap_width {
+            for index in lines {
+                let rows = wrap_line(&mut self.line(index),max_width);
+                self.lines.modify(index, |line| line.set_rows(rows));
+            }
+        }
+    }
+
+    /// Number of visual rows of lines in given range.
+    fn rows_in_lines(&self, lines:Range<usize>) -> usize {
+        self.lines.line_first_row(lines.end) - self.lines.line_first_row(lines.start)
+    }
+
     /// Mix the unchanged parts of modified lines into change.
     ///
     /// This is for convenience of making multiline content changes. After mixing existing content
@@ -501,10 +669,104 @@ pub(crate) mod test {
         assert_eq!(16, content.convert_location_to_char_index(TextLocation{line:2, column:5}));
     }
 
+    #[wasm_bindgen_test(async)]
+    async fn converting_offset_to_location() {
+        msdf_sys::initialized().await;
+        let text    = "First\nSecond\nThird";
+        let content = TextFieldContent::new(text,&mock_properties());
+
+        assert_eq!(TextLocation{line:0, column:0}, content.convert_char_index_to_location(0));
+        assert_eq!(TextLocation{line:0, column:5}, content.convert_char_index_to_location(5));
+        assert_eq!(TextLocation{line:1, column:0}, content.convert_char_index_to_location(6));
+        assert_eq!(TextLocation{line:2, column:5}, content.convert_char_index_to_location(18));
+        assert_eq!(TextLocation{line:2, column:5}, content.convert_char_index_to_location(30));
+    }
+
     fn get_lines_as_strings(content:&TextFieldContent) -> Vec<String> {
         content.lines.iter().map(|l| l.chars().iter().collect()).collect()
     }
 
+    #[wasm_bindgen_test(async)]
+    async fn soft_wrapping_lines() {
+        msdf_sys::initialized().await;
+        let properties  = mock_monospace_properties("ab ",10.0);
+        let location    = |line,column| TextLocation {line,column};
+        let mut content = TextFieldContent::new("ab ab ab\nab",&properties);
+        content.set_wrap_width(Some(40.0));
+        assert_eq!(content.rows_count(), 4);
+        assert_eq!(content.row_of_location(location(0,4)), 1);
+        assert_eq!(content.row_of_location(location(1,1)), 3);
+        assert_eq!(content.row_to_line_and_row(2), (0,2));
+        assert_eq!(content.location_at_point(Vector2::new(15.0,-15.0)), location(0,4));
+        assert_eq!(content.location_at_point(Vector2::new(35.0,-15.0)), location(0,5));
+        assert_eq!(content.location_at_point(Vector2::new(15.0,-35.0)), location(1,1));
+
+        content.dirty_lines = default();
+        content.apply_change(TextChange::delete(location(0,0)..location(0,3)));
+        assert_eq!(content.rows_count(), 3);
+        assert!(content.dirty_lines.is_dirty(1));
+
+        content.set_wrap_width(None);
+        assert_eq!(content.rows_count(), 2);
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn folding_lines() {
+        msdf_sys::initialized().await;
+        let location    = |line,column| TextLocation {line,column};
+        let text        = "a\n  b\n  c\nd";
+        let mut content = TextFieldContent::new(text,&mock_properties());
+        content.fold(FoldingRegion::new(0,3));
+        assert!(content.is_folded(0));
+        assert!(!content.is_folded(3));
+        assert_eq!(content.rows_count(), 2);
+        assert_eq!(content.row_to_line_and_row(1), (3,0));
+        assert_eq!(content.row_of_location(location(3,1)), 1);
+        assert_eq!(content.next_visible_line(0), Some(3));
+        assert_eq!(content.prev_visible_line(3), Some(0));
+        assert_eq!(content.displayed_line(2), 0);
+        assert_eq!(content.copy_fragment(location(0,0)..location(3,1)), text);
+
+        content.apply_change(TextChange::insert(location(0,1),"x"));
+        assert!(content.is_folded(0));
+        content.apply_change(TextChange::delete(location(2,0)..location(2,2)));
+        assert!(!content.is_folded(0));
+        assert_eq!(content.rows_count(), 4);
+
+        content.fold(FoldingRegion::new(0,3));
+        assert!(content.unfold(0));
+        assert!(!content.unfold(0));
+        assert_eq!(content.rows_count(), 4);
+    }
+
+    /// A mock font having the chars of given advances, without kerning.
+    pub(crate) fn mock_font(advances:&[(char,f32)]) -> FontHandle {
+        let font   = FontRenderInfo::mock_font("Test font".to_string());
+        let scale  = Vector2::new(1.0, 1.0);
+        let offset = Vector2::new(0.0, 0.0);
+        for (left,advance) in advances {
+            font.mock_char_info(*left,scale,offset,*advance);
+            advances.iter().for_each(|(right,_)| font.mock_kerning_info(*left,*right,0.0));
+        }
+        FontHandle::new(font)
+    }
+
+    /// A mock font where each of `chars` has advance 1.0 and there is no kerning.
+    pub(crate) fn mock_monospace_font(chars:&str) -> FontHandle {
+        mock_font(&chars.chars().map(|c| (c,1.0)).collect_vec())
+    }
+
+    /// Properties with a mock font, where each of `chars` has the width of `text_size` and there is
+    /// no kerning.
+    pub(crate) fn mock_monospace_properties(chars:&str, text_size:f32) -> TextFieldProperties {
+        TextFieldProperties {
+            font       : mock_monospace_font(chars),
+            text_size,
+            base_color : Vector4::new(1.0, 1.0, 1.0, 1.0),
+            size       : Vector2::new(100.0, 100.0)
+        }
+    }
+
     pub(crate) fn mock_properties()->  TextFieldProperties {
         TextFieldProperties {
             font       : FontHandle::new(FontRenderInfo::mock_font("Test font".to_string())),
@@ -5,7 +5,24 @@ use crate::display::shape::text::glyph::font::FontHandle;
 use crate::display::shape::text::glyph::pen::PenIterator;
 
 use nalgebra::Vector2;
+use nalgebra::Vector4;
 use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+
+
+// =================
+// === ColorSpan ===
+// =================
+
+/// A fragment of line displayed in a color other than the TextField's base color.
+#[derive(Clone,Debug,PartialEq)]
+pub struct ColorSpan {
+    /// The range of columns in the line.
+    pub columns : Range<usize>,
+    /// The color of characters.
+    pub color   : Vector4<f32>,
+}
 
 
 
@@ -20,10 +37,23 @@ use std::ops::Range;
 /// position of the char in a _text space_ (where value of 1.0 is equal to lines height). The cache
 /// is initially empty and is load on demand - so the `char_x_position` vector will be often shorter
 /// than number of characters in line.
+///
+/// The line may also have highlighting: the list of fragments having its own color. Highlighting
+/// is dropped when the line is modified.
+///
+/// When the TextField is soft-wrapped, the line is displayed in many _visual rows_. Their column
+/// ranges are computed by the `wrap` module and kept here. The rows are dropped when the line is
+/// modified, and a line without rows is displayed in a single row.
+///
+/// The line hidden in a folded region (see `text_field::folding` module) is not displayed at all,
+/// so it has no visual rows.
 #[derive(Clone,Debug)]
 pub struct Line {
     chars            : Vec<char>,
     char_x_positions : Vec<f32>,
+    highlighting     : Vec<ColorSpan>,
+    rows             : Vec<Range<usize>>,
+    hidden           : bool,
 }
 
 impl Line {
@@ -36,6 +66,9 @@ impl Line {
     pub fn new_raw(chars:Vec<char>) -> Self {
         Line {chars,
             char_x_positions : Vec::new(),
+            highlighting     : Vec::new(),
+            rows             : Vec::new(),
+            hidden           : false,
         }
     }
 
@@ -44,6 +77,9 @@ impl Line {
         Line{
             chars            : Vec::new(),
             char_x_positions : Vec::new(),
+            highlighting     : Vec::new(),
+            rows             : Vec::new(),
+            hidden           : false,
         }
     }
 
@@ -63,11 +99,105 @@ impl Line {
     }
 
     /// Get the mutable reference to characters. Because we're allowing for modifications here,
-    /// the `chars_x_position` cache is cleared.
+    /// the `chars_x_position` cache, highlighting and visual rows are cleared.
     pub fn modify(&mut self) -> &mut Vec<char> {
         self.char_x_positions.clear();
+        self.highlighting.clear();
+        self.rows.clear();
         &mut self.chars
     }
+
+    /// The fragments of line with non-default color.
+    pub fn highlighting(&self) -> &[ColorSpan] {
+        &self.highlighting
+    }
+
+    /// Set the fragments of line with non-default color.
+    pub fn set_highlighting(&mut self, spans:Vec<ColorSpan>) {
+        self.highlighting = spans;
+    }
+
+    /// Set the column ranges of visual rows. The ranges must cover the whole line in order.
+    pub fn set_rows(&mut self, rows:Vec<Range<usize>>) {
+        self.rows = rows;
+    }
+
+    /// Number of visual rows this line is displayed in. The hidden line has no rows.
+    pub fn rows_count(&self) -> usize {
+        if self.hidden { 0 } else { self.rows.len().max(1) }
+    }
+
+    /// Check if the line is hidden in a folded region.
+    pub fn is_hidden(&self) -> bool {
+        self.hidden
+    }
+
+    /// Hide or reveal the line. The hidden line keeps its visual rows, which are used again once
+    /// it is revealed.
+    pub fn set_hidden(&mut self, hidden:bool) {
+        self.hidden = hidden;
+    }
+
+    /// The range of columns displayed in given visual row.
+    pub fn row_range(&self, row:usize) -> Range<usize> {
+        self.rows.get(row).cloned().unwrap_or(0..self.len())
+    }
+
+    /// The visual row where the cursor at given column is displayed. The column at the boundary of
+    /// two rows is displayed at the beginning of the latter.
+    pub fn row_of_column(&self, column:usize) -> usize {
+        self.rows.iter().rposition(|row| row.start <= column).unwrap_or(0)
+    }
+
+    /// The last column in given visual row where the cursor can be put. For all rows but the last
+    /// one it is before the row's last char, because the column after it is displayed at the
+    /// beginning of the next row.
+    pub fn row_end_column(&self, row:usize) -> usize {
+        let range   = self.row_range(row);
+        let is_last = row + 1 >= self.rows_count();
+        if is_last || range.start == range.end { range.end } else { range.end - 1 }
+    }
+}
+
+
+
+// === Graphemes ===
+
+impl Line {
+    /// The column following the next grapheme cluster after `column`, or `None` if `column` is at
+    /// the line's end.
+    pub fn next_grapheme_boundary(&self, column:usize) -> Option<usize> {
+        grapheme_boundaries(&self.chars).into_iter().find(|boundary| *boundary > column)
+    }
+
+    /// The column preceding the grapheme cluster before `column`, or `None` if `column` is at
+    /// the line's beginning.
+    pub fn prev_grapheme_boundary(&self, column:usize) -> Option<usize> {
+        grapheme_boundaries(&self.chars).into_iter().rev().find(|boundary| *boundary < column)
+    }
+
+    /// The nearest column not greater than `column` which is not inside a grapheme cluster.
+    pub fn snap_to_grapheme_boundary(&self, column:usize) -> usize {
+        let boundaries = grapheme_boundaries(&self.chars).into_iter().rev();
+        boundaries.skip_while(|boundary| *boundary > column).next().unwrap_or(0)
+    }
+}
+
+/// The char indices between extended grapheme clusters of given chars, including `0` and the
+/// number of chars.
+///
+/// The grapheme cluster is what the user perceives as a single character, e.g. a letter with
+/// combining accent or an emoji composed of many code points. The cursor should never be put
+/// inside one.
+pub fn grapheme_boundaries(chars:&[char]) -> Vec<usize> {
+    let string         = String::from_iter(chars);
+    let mut column     = 0;
+    let mut boundaries = vec![0];
+    for grapheme in string.graphemes(true) {
+        column += grapheme.chars().count();
+        boundaries.push(column);
+    }
+    boundaries
 }
 
 
@@ -92,10 +222,11 @@ impl ToString for Line {
 #[allow(missing_docs)]
 pub struct LineFullInfo<'a> {
     #[shrinkwrap(main_field)]
-    pub line    : &'a mut Line,
-    pub line_id : usize,
-    pub font    : FontHandle,
-    pub height  : f32,
+    pub line      : &'a mut Line,
+    pub line_id   : usize,
+    pub first_row : usize,
+    pub font      : FontHandle,
+    pub height    : f32,
 }
 
 impl<'a> LineFullInfo<'a> {
@@ -103,7 +234,19 @@ impl<'a> LineFullInfo<'a> {
     /// term, for details see [freetype documentation]
     /// (https://www.freetype.org/freetype2/docs/glyphs/glyphs-3.html#section-1)).
     pub fn baseline_start(&self) -> Vector2<f32> {
-        Vector2::new(0.0, (-(self.line_id as f32) - 0.85) * self.height)
+        Vector2::new(0.0, (-(self.first_row as f32) - 0.85) * self.height)
+    }
+
+    /// Get the point where a _baseline_ of given visual row of this line begins.
+    pub fn row_baseline_start(&self, row:usize) -> Vector2<f32> {
+        self.baseline_start() - Vector2::new(0.0, row as f32 * self.height)
+    }
+
+    /// Get the x position of given visual row's beginning in _text space_. The x positions of
+    /// chars in the row displayed on the screen are shifted left by this value.
+    pub fn row_x_offset(&mut self, row:usize) -> f32 {
+        let start = self.row_range(row).start;
+        if start == 0 { self.baseline_start().x } else { self.get_char_x_position(start) }
     }
 
     /// Get x position of character with given index. The position is in _text space_.
@@ -183,7 +326,7 @@ impl<'a> LineFullInfo<'a> {
 mod test {
     use super::*;
 
-    use crate::display::shape::text::glyph::font::FontRenderInfo;
+    use crate::display::shape::text::text_field::content::test::mock_font;
 
     use wasm_bindgen_test::wasm_bindgen_test;
 
@@ -192,10 +335,11 @@ mod test {
         basegl_core_msdf_sys::initialized().await;
         let mut line     = Line::new("ABA");
         let mut line_ref = LineFullInfo {
-            line    : &mut line,
-            font    : prepare_font_with_ab(),
-            line_id : 0,
-            height  : 1.0,
+            line      : &mut line,
+            font      : prepare_font_with_ab(),
+            line_id   : 0,
+            first_row : 0,
+            height    : 1.0,
         };
 
         assert_eq!(0, line_ref.char_x_positions.len());
@@ -213,10 +357,11 @@ mod test {
         basegl_core_msdf_sys::initialized().await;
         let mut line     = Line::new("ABBA");
         let mut line_ref = LineFullInfo {
-            line    : &mut line,
-            font    : prepare_font_with_ab(),
-            line_id : 0,
-            height  : 1.0,
+            line      : &mut line,
+            font      : prepare_font_with_ab(),
+            line_id   : 0,
+            first_row : 0,
+            height    : 1.0,
         };
 
         let before_first       = line_ref.find_char_at_x_position(-0.1);
@@ -248,10 +393,11 @@ mod test {
         basegl_core_msdf_sys::initialized().await;
         let mut line     = Line::new("");
         let mut line_ref = LineFullInfo {
-            line    : &mut line,
-            font    : prepare_font_with_ab(),
-            line_id : 0,
-            height  : 1.0,
+            line      : &mut line,
+            font      : prepare_font_with_ab(),
+            line_id   : 0,
+            first_row : 0,
+            height    : 1.0,
         };
         let below_0  = line_ref.find_char_at_x_position(-0.1);
         let above_0  = line_ref.find_char_at_x_position( 0.1);
@@ -264,10 +410,11 @@ mod test {
         basegl_core_msdf_sys::initialized().await;
         let mut line     = Line::new("AB");
         let mut line_ref = LineFullInfo {
-            line    : &mut line,
-            font    : prepare_font_with_ab(),
-            line_id : 0,
-            height  : 1.0,
+            line      : &mut line,
+            font      : prepare_font_with_ab(),
+            line_id   : 0,
+            first_row : 0,
+            height    : 1.0,
         };
         let before_edit = line_ref.get_char_x_position(1);
         assert_eq!(2, line_ref.char_x_positions.len());
@@ -279,16 +426,52 @@ mod test {
         assert_eq!(1.5, after_edit);
     }
 
+    #[test]
+    fn finding_rows_of_columns() {
+        let mut line = Line::new("AB AB AB");
+        assert_eq!(line.rows_count(), 1);
+        assert_eq!(line.row_range(0), 0..8);
+        assert_eq!(line.row_end_column(0), 8);
+
+        line.set_rows(vec![0..3,3..6,6..8]);
+        assert_eq!(line.rows_count(), 3);
+        assert_eq!(line.row_of_column(0), 0);
+        assert_eq!(line.row_of_column(3), 1);
+        assert_eq!(line.row_of_column(8), 2);
+        assert_eq!(line.row_end_column(1), 5);
+        assert_eq!(line.row_end_column(2), 8);
+
+        line.modify().push('A');
+        assert_eq!(line.rows_count(), 1);
+    }
+
+    #[test]
+    fn finding_grapheme_boundaries() {
+        let boundaries = |text:&str| grapheme_boundaries(&text.chars().collect_vec());
+        let family     = "\u{1F468}\u{200D}\u{1F469}\u{200D}\u{1F467}!";
+        let flags      = "\u{1F1F5}\u{1F1F1}\u{1F1EF}\u{1F1F5}";
+        let hangul     = "\u{1100}\u{1161}\u{11A8}ą";
+        let hindi      = "\u{928}\u{92E}\u{938}\u{94D}\u{924}\u{947}";
+        assert_eq!(boundaries("")            , vec![0]);
+        assert_eq!(boundaries("abc")         , vec![0,1,2,3]);
+        assert_eq!(boundaries("cafe\u{301}") , vec![0,1,2,3,5]);
+        assert_eq!(boundaries(family)        , vec![0,5,6]);
+        assert_eq!(boundaries(flags)         , vec![0,2,4]);
+        assert_eq!(boundaries(hindi)         , vec![0,1,2,4,6]);
+        assert_eq!(boundaries(hangul)        , vec![0,3,4]);
+
+        let line = Line::new("ae\u{301}\u{1F1F5}\u{1F1F1}");
+        assert_eq!(line.next_grapheme_boundary(1)   , Some(3));
+        assert_eq!(line.next_grapheme_boundary(5)   , None);
+        assert_eq!(line.prev_grapheme_boundary(5)   , Some(3));
+        assert_eq!(line.prev_grapheme_boundary(3)   , Some(1));
+        assert_eq!(line.prev_grapheme_boundary(0)   , None);
+        assert_eq!(line.snap_to_grapheme_boundary(2), 1);
+        assert_eq!(line.snap_to_grapheme_boundary(4), 3);
+        assert_eq!(line.snap_to_grapheme_boundary(5), 5);
+    }
+
     fn prepare_font_with_ab() -> FontHandle {
-        let font   = FontRenderInfo::mock_font("Test font".to_string());
-        let scale  = Vector2::new(1.0, 1.0);
-        let offset = Vector2::new(0.0, 0.0);
-        font.mock_char_info('A',scale,offset,1.0);
-        font.mock_char_info('B',scale,offset,1.5);
-        font.mock_kerning_info('A', 'B', 0.0);
-        font.mock_kerning_info('B', 'A', 0.0);
-        font.mock_kerning_info('A', 'A', 0.0);
-        font.mock_kerning_info('B', 'B', 0.0);
-        FontHandle::new(font)
+        mock_font(&[('A',1.0),('B',1.5)])
     }
 }
\ No newline at end of file
@@ -0,0 +1,515 @@
+//! A rope of lines, being the storage of TextField content.
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::content::line::Line;
+
+use std::cmp::min;
+use std::ops::Index;
+use std::ops::Range;
+
+
+
+// ============
+// === Node ===
+// ============
+
+type Link = Option<Box<Node>>;
+
+/// A node of the treap keeping lines. The tree is ordered by line indices, which are implicit:
+/// the index of line is the number of lines before it in the in-order traversal. The heap
+/// property on random priorities keeps the tree balanced with high probability.
+#[derive(Clone,Debug)]
+struct Node {
+    line     : Line,
+    priority : u32,
+    /// Number of lines in the subtree.
+    lines    : usize,
+    /// Number of chars in the subtree, counting also the line separators after each line.
+    chars    : usize,
+    /// Number of visual rows in the subtree, see `Line::rows_count`.
+    rows     : usize,
+    left     : Link,
+    right    : Link,
+}
+
+impl Node {
+    fn new(line:Line, priority:u32) -> Box<Self> {
+        let lines = 1;
+        let chars = line.len() + 1;
+        let rows  = line.rows_count();
+        let left  = None;
+        let right = None;
+        Box::new(Node {line,priority,lines,chars,rows,left,right})
+    }
+
+    /// Recalculate the cached values after the line or children changed.
+    fn update(&mut self) {
+        self.lines = lines_in(&self.left) + 1                   + lines_in(&self.right);
+        self.chars = chars_in(&self.left) + self.line.len() + 1 + chars_in(&self.right);
+        self.rows  = rows_in(&self.left) + self.line.rows_count() + rows_in(&self.right);
+    }
+}
+
+fn lines_in(link:&Link) -> usize {
+    link.as_ref().map_or(0,|node| node.lines)
+}
+
+fn chars_in(link:&Link) -> usize {
+    link.as_ref().map_or(0,|node| node.chars)
+}
+
+fn rows_in(link:&Link) -> usize {
+    link.as_ref().map_or(0,|node| node.rows)
+}
+
+/// Concatenate two trees.
+fn merge(left:Link, right:Link) -> Link {
+    match (left,right) {
+        (None,right) => right,
+        (left,None)  => left,
+        (Some(mut left),Some(mut right)) => {
+            if left.priority > right.priority {
+                left.right = merge(left.right.take(),Some(right));
+                left.update();
+                Some(left)
+            } else {
+                right.left = merge(Some(left),right.left.take());
+                right.update();
+                Some(right)
+            }
+        }
+    }
+}
+
+/// Split tree into the one with first `count` lines and the one with the rest.
+fn split(link:Link, count:usize) -> (Link,Link) {
+    match link {
+        None           => (None,None),
+        Some(mut node) => {
+            let left_lines = lines_in(&node.left);
+            if count <= left_lines {
+                let (left,right) = split(node.left.take(),count);
+                node.left = right;
+                node.update();
+                (left,Some(node))
+            } else {
+                let (left,right) = split(node.right.take(),count - left_lines - 1);
+                node.right = left;
+                node.update();
+                (Some(node),right)
+            }
+        }
+    }
+}
+
+/// Find the node with line of given index.
+fn node_mut(link:&mut Link, index:usize) -> Option<&mut Node> {
+    let node       = link.as_mut()?;
+    let left_lines = lines_in(&node.left);
+    if index < left_lines {
+        node_mut(&mut node.left,index)
+    } else if index == left_lines {
+        Some(node)
+    } else {
+        node_mut(&mut node.right,index - left_lines - 1)
+    }
+}
+
+/// Modify line of given index and update the cached values on the path to it.
+fn modify<R>(link:&mut Link, index:usize, f:impl FnOnce(&mut Line) -> R) -> R {
+    let node       = link.as_mut().expect("Line index out of bounds.");
+    let left_lines = lines_in(&node.left);
+    let result     = if index < left_lines {
+        modify(&mut node.left,index,f)
+    } else if index == left_lines {
+        f(&mut node.line)
+    } else {
+        modify(&mut node.right,index - left_lines - 1,f)
+    };
+    node.update();
+    result
+}
+
+
+
+// ================
+// === LineRope ===
+// ================
+
+/// A sequence of lines stored in a balanced tree.
+///
+/// Getting the line by index, converting between line and char indices, and inserting or removing
+/// lines all take O(log n) time, where n is the number of lines. The char indices count also the
+/// line separators, so they match indices in text with lines joined by `'\n'`. The same holds for
+/// converting between line and visual row indices, when lines are soft-wrapped.
+#[derive(Clone,Debug)]
+pub struct LineRope {
+    root : Link,
+    /// State of the pseudo-random generator of node priorities.
+    seed : u32,
+}
+
+impl Default for LineRope {
+    fn default() -> Self {
+        let root = None;
+        let seed = 0x9E37_79B9;
+        LineRope {root,seed}
+    }
+}
+
+impl LineRope {
+    /// Create an empty rope.
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Number of lines.
+    pub fn len(&self) -> usize {
+        lines_in(&self.root)
+    }
+
+    /// Check if there are no lines.
+    pub fn is_empty(&self) -> bool {
+        self.root.is_none()
+    }
+
+    /// Number of chars in the lines joined by `'\n'`.
+    pub fn char_count(&self) -> usize {
+        chars_in(&self.root).saturating_sub(1)
+    }
+
+    /// Number of visual rows of all lines.
+    pub fn row_count(&self) -> usize {
+        rows_in(&self.root)
+    }
+
+    /// Get line by index.
+    pub fn get(&self, index:usize) -> Option<&Line> {
+        let mut link  = &self.root;
+        let mut index = index;
+        while let Some(node) = link {
+            let left_lines = lines_in(&node.left);
+            if index < left_lines {
+                link = &node.left;
+            } else if index == left_lines {
+                return Some(&node.line);
+            } else {
+                index -= left_lines + 1;
+                link   = &node.right;
+            }
+        }
+        None
+    }
+
+    /// Get mutable reference to the line by index, allowing to fill the line's cache of char
+    /// positions (see `LineFullInfo`).
+    ///
+    /// The length and rows of the line must not be changed through the returned reference, as
+    /// the rope would not notice it. Use `modify` to edit the line.
+    pub(super) fn get_mut(&mut self, index:usize) -> Option<&mut Line> {
+        node_mut(&mut self.root,index).map(|node| &mut node.line)
+    }
+
+    /// Modify the line with given index. Panics if index is out of bounds.
+    pub fn modify<R>(&mut self, index:usize, f:impl FnOnce(&mut Line) -> R) -> R {
+        modify(&mut self.root,index,f)
+    }
+
+    /// Get the first line.
+    pub fn first(&self) -> Option<&Line> {
+        self.get(0)
+    }
+
+    /// Get the last line.
+    pub fn last(&self) -> Option<&Line> {
+        self.len().checked_sub(1).and_then(|index| self.get(index))
+    }
+
+    /// Iterate over all lines.
+    pub fn iter(&self) -> Iter {
+        self.iter_from(0)
+    }
+
+    /// Iterate over lines starting from the given index.
+    pub fn iter_from(&self, index:usize) -> Iter {
+        let mut stack = Vec::new();
+        let mut link  = &self.root;
+        let mut index = index;
+        while let Some(node) = link {
+            let left_lines = lines_in(&node.left);
+            if index <= left_lines {
+                stack.push(node.as_ref());
+                link = &node.left;
+            } else {
+                index -= left_lines + 1;
+                link   = &node.right;
+            }
+        }
+        Iter {stack}
+    }
+
+    /// Iterate over the lines in the range.
+    pub fn range(&self, range:Range<usize>) -> impl Iterator<Item=&Line> {
+        let count = range.end.saturating_sub(range.start);
+        self.iter_from(range.start).take(count)
+    }
+
+    /// Get the index of the first char of the line. For index equal to the number of lines,
+    /// returns the index after the last char, counting the separator of the last line.
+    pub fn line_start_char_index(&self, line_index:usize) -> usize {
+        let mut link   = &self.root;
+        let mut index  = line_index;
+        let mut result = 0;
+        while let Some(node) = link {
+            let left_lines = lines_in(&node.left);
+            if index <= left_lines {
+                link = &node.left;
+            } else {
+                index  -= left_lines + 1;
+                result += chars_in(&node.left) + node.line.len() + 1;
+                link    = &node.right;
+            }
+        }
+        result
+    }
+
+    /// Get the line index and the column of given char index. Indices past the end are clamped to
+    /// the end of the last line.
+    pub fn char_index_to_line_and_column(&self, char_index:usize) -> (usize,usize) {
+        let mut link  = &self.root;
+        let mut index = min(char_index,self.char_count());
+        let mut line  = 0;
+        while let Some(node) = link {
+            let left_chars = chars_in(&node.left);
+            let line_chars = node.line.len() + 1;
+            if index < left_chars {
+                link = &node.left;
+            } else if index < left_chars + line_chars {
+                return (line + lines_in(&node.left), index - left_chars);
+            } else {
+                index -= left_chars + line_chars;
+                line  += lines_in(&node.left) + 1;
+                link   = &node.right;
+            }
+        }
+        (0,0)
+    }
+
+    /// Get the index of the first visual row of the line. For index equal to the number of lines,
+    /// returns the number of rows.
+    pub fn line_first_row(&self, line_index:usize) -> usize {
+        let mut link   = &self.root;
+        let mut index  = line_index;
+        let mut result = 0;
+        while let Some(node) = link {
+            let left_lines = lines_in(&node.left);
+            if index <= left_lines {
+                link = &node.left;
+            } else {
+                index  -= left_lines + 1;
+                result += rows_in(&node.left) + node.line.rows_count();
+                link    = &node.right;
+            }
+        }
+        result
+    }
+
+    /// Get the line index and the row within that line of given visual row. Rows past the end are
+    /// clamped to the last row of the last line.
+    pub fn row_to_line_and_row(&self, row:usize) -> (usize,usize) {
+        let mut link  = &self.root;
+        let mut index = min(row,self.row_count().saturating_sub(1));
+        let mut line  = 0;
+        while let Some(node) = link {
+            let left_rows = rows_in(&node.left);
+            let line_rows = node.line.rows_count();
+            if index < left_rows {
+                link = &node.left;
+            } else if index < left_rows + line_rows {
+                return (line + lines_in(&node.left), index - left_rows);
+            } else {
+                index -= left_rows + line_rows;
+                line  += lines_in(&node.left) + 1;
+                link   = &node.right;
+            }
+        }
+        (0,0)
+    }
+
+    /// Replace the lines in range with the given ones.
+    pub fn splice<Lines>(&mut self, range:Range<usize>, lines:Lines)
+    where Lines : IntoIterator<Item=Line> {
+        let (before,rest)  = split(self.root.take(),range.start);
+        let removed_count  = range.end.saturating_sub(range.start);
+        let (_,after)      = split(rest,removed_count);
+        let mut inserted   = None;
+        for line in lines {
+            inserted = merge(inserted,Some(Node::new(line,self.next_priority())));
+        }
+        self.root = merge(merge(before,inserted),after);
+    }
+
+    /// Insert line at given index.
+    pub fn insert(&mut self, index:usize, line:Line) {
+        self.splice(index..index,std::iter::once(line))
+    }
+
+    /// Remove line with given index.
+    pub fn remove(&mut self, index:usize) -> Line {
+        let (before,rest)   = split(self.root.take(),index);
+        let (removed,after) = split(rest,1);
+        self.root = merge(before,after);
+        removed.expect("Line index out of bounds.").line
+    }
+
+    /// Append line at the end.
+    pub fn push(&mut self, line:Line) {
+        self.insert(self.len(),line)
+    }
+
+    /// Remove the last line.
+    pub fn pop(&mut self) -> Option<Line> {
+        self.len().checked_sub(1).map(|index| self.remove(index))
+    }
+
+    /// Get the next pseudo-random priority (xorshift).
+    fn next_priority(&mut self) -> u32 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed
+    }
+}
+
+impl Index<usize> for LineRope {
+    type Output = Line;
+    fn index(&self, index:usize) -> &Line {
+        self.get(index).expect("Line index out of bounds.")
+    }
+}
+
+impl FromIterator<Line> for LineRope {
+    fn from_iter<I:IntoIterator<Item=Line>>(lines:I) -> Self {
+        let mut rope = LineRope::new();
+        rope.splice(0..0,lines);
+        rope
+    }
+}
+
+impl<'a> IntoIterator for &'a LineRope {
+    type Item     = &'a Line;
+    type IntoIter = Iter<'a>;
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+
+// === Iter ===
+
+/// Iterator over lines in the rope.
+#[derive(Clone,Debug)]
+pub struct Iter<'a> {
+    /// Nodes which lines and right subtrees are to be visited, the next one on top.
+    stack : Vec<&'a Node>,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = &'a Line;
+    fn next(&mut self) -> Option<&'a Line> {
+        let node     = self.stack.pop()?;
+        let mut link = &node.right;
+        while let Some(child) = link {
+            self.stack.push(child.as_ref());
+            link = &child.left;
+        }
+        Some(&node.line)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::cmp::max;
+
+    fn rope_of(lines:&[&str]) -> LineRope {
+        lines.iter().map(|line| Line::new(*line)).collect()
+    }
+
+    fn strings(rope:&LineRope) -> Vec<String> {
+        rope.iter().map(|line| line.to_string()).collect()
+    }
+
+    #[test]
+    fn splicing_lines() {
+        let mut rope = rope_of(&["a","b","c","d"]);
+        rope.splice(1..3,vec![Line::new("x"),Line::new("y"),Line::new("z")]);
+        assert_eq!(strings(&rope), vec!["a","x","y","z","d"]);
+        rope.remove(0);
+        rope.push(Line::new("e"));
+        rope.insert(2,Line::new("w"));
+        assert_eq!(strings(&rope), vec!["x","y","w","z","d","e"]);
+        assert_eq!(rope.pop().map(|line| line.to_string()), Some("e".to_string()));
+        assert_eq!(rope.range(1..3).map(|line| line.to_string()).collect_vec(), vec!["y","w"]);
+        assert_eq!(rope.last().map(|line| line.to_string()), Some("d".to_string()));
+    }
+
+    #[test]
+    fn converting_char_indices() {
+        let mut rope = rope_of(&["First","Second","","Third"]);
+        assert_eq!(rope.char_count(), 19);
+        assert_eq!(rope.line_start_char_index(0), 0);
+        assert_eq!(rope.line_start_char_index(1), 6);
+        assert_eq!(rope.line_start_char_index(2), 13);
+        assert_eq!(rope.line_start_char_index(3), 14);
+        assert_eq!(rope.char_index_to_line_and_column(0) , (0,0));
+        assert_eq!(rope.char_index_to_line_and_column(5) , (0,5));
+        assert_eq!(rope.char_index_to_line_and_column(6) , (1,0));
+        assert_eq!(rope.char_index_to_line_and_column(13), (2,0));
+        assert_eq!(rope.char_index_to_line_and_column(19), (3,5));
+        assert_eq!(rope.char_index_to_line_and_column(99), (3,5));
+
+        rope.modify(1,|line| line.modify().truncate(3));
+        assert_eq!(rope.line_start_char_index(3), 11);
+        assert_eq!(rope.char_index_to_line_and_column(11), (3,0));
+    }
+
+    #[test]
+    fn converting_row_indices() {
+        let mut rope = rope_of(&["First","Second","Third"]);
+        rope.modify(1,|line| line.set_rows(vec![0..2,2..4,4..6]));
+        assert_eq!(rope.row_count(), 5);
+        assert_eq!(rope.line_first_row(1), 1);
+        assert_eq!(rope.line_first_row(2), 4);
+        assert_eq!(rope.line_first_row(3), 5);
+        assert_eq!(rope.row_to_line_and_row(0) , (0,0));
+        assert_eq!(rope.row_to_line_and_row(2) , (1,1));
+        assert_eq!(rope.row_to_line_and_row(4) , (2,0));
+        assert_eq!(rope.row_to_line_and_row(99), (2,0));
+
+        rope.modify(1,|line| line.modify().truncate(3));
+        assert_eq!(rope.row_count(), 3);
+        assert_eq!(rope.row_to_line_and_row(2), (2,0));
+    }
+
+    #[test]
+    fn staying_balanced() {
+        let mut rope = LineRope::new();
+        for i in 0..10000 {
+            rope.push(Line::new(i.to_string()));
+        }
+        fn depth(link:&Link) -> usize {
+            link.as_ref().map_or(0,|node| 1 + max(depth(&node.left),depth(&node.right)))
+        }
+        assert!(depth(&rope.root) < 60);
+        assert_eq!(rope[1234].to_string(), "1234");
+        assert_eq!(rope.line_start_char_index(10), 20);
+    }
+}
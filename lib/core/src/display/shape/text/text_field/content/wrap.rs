@@ -0,0 +1,97 @@
+//! Soft wrapping of TextField lines into visual rows.
+//!
+//! The line is broken at word boundaries, so that each visual row fits in the given width. The
+//! words are separated by whitespaces, which are kept at the end of the row they follow, even if
+//! they don't fit in it. A word wider than the whole row is broken at the last grapheme cluster
+//! which fits.
+
+use crate::display::shape::text::text_field::content::line::LineFullInfo;
+use crate::display::shape::text::text_field::content::line::grapheme_boundaries;
+
+use std::ops::Range;
+
+
+
+// ============
+// === Wrap ===
+// ============
+
+/// Compute the column ranges of visual rows the line should be displayed in, so each row fits in
+/// `max_width` pixels. The widths are measured with glyph advances and kernings of line's font.
+///
+/// The returned ranges cover the whole line in order. An empty line has a single, empty row.
+pub fn wrap_line(line:&mut LineFullInfo, max_width:f32) -> Vec<Range<usize>> {
+    let boundaries     = grapheme_boundaries(line.chars());
+    let mut rows       = Vec::new();
+    let mut row_start  = 0;
+    let mut word_start : Option<usize> = None;
+    for index in 0..line.len() {
+        let is_space      = line.chars()[index].is_whitespace();
+        let is_word_start = index > 0 && !is_space && line.chars()[index-1].is_whitespace();
+        if is_word_start {
+            word_start = Some(index);
+        }
+        if !is_space {
+            while index > row_start && width(line,row_start..index+1) > max_width {
+                let in_row   = |column:&usize| *column > row_start && *column <= index;
+                let grapheme = boundaries.iter().rev().cloned().find(in_row);
+                let word     = word_start.filter(|start| *start > row_start);
+                let break_at = word.or(grapheme).unwrap_or(index);
+                rows.push(row_start..break_at);
+                row_start  = break_at;
+                word_start = None;
+            }
+        }
+    }
+    rows.push(row_start..line.len());
+    rows
+}
+
+/// The width in pixels of the line's fragment.
+fn width(line:&mut LineFullInfo, columns:Range<usize>) -> f32 {
+    let start = line.get_char_x_position(columns.start);
+    let end   = line.get_char_x_range(columns.end - 1).end;
+    end - start
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::display::shape::text::text_field::content::line::Line;
+    use crate::display::shape::text::text_field::content::test::mock_monospace_font;
+
+    use basegl_core_msdf_sys as msdf_sys;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn wrap(text:&str, max_width:f32) -> Vec<Range<usize>> {
+        let mut line     = Line::new(text);
+        let mut line_ref = LineFullInfo {
+            line      : &mut line,
+            line_id   : 0,
+            first_row : 0,
+            font      : mock_monospace_font("ab \u{301}"),
+            height    : 1.0,
+        };
+        wrap_line(&mut line_ref,max_width)
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn wrapping_at_word_boundaries() {
+        msdf_sys::initialized().await;
+        assert_eq!(wrap(""              ,4.0), vec![0..0]);
+        assert_eq!(wrap("ab ab"         ,5.0), vec![0..5]);
+        assert_eq!(wrap("ab ab"         ,4.0), vec![0..3,3..5]);
+        assert_eq!(wrap("ab ab ab"      ,4.0), vec![0..3,3..6,6..8]);
+        assert_eq!(wrap("ab ab ab"      ,5.0), vec![0..6,6..8]);
+        assert_eq!(wrap("ab     ab"     ,4.0), vec![0..7,7..9]);
+        assert_eq!(wrap("a bbbbbbbbb a" ,4.0), vec![0..2,2..6,6..10,10..13]);
+        assert_eq!(wrap("abab\u{301}"   ,4.0), vec![0..3,3..5]);
+    }
+}
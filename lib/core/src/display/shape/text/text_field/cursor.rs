@@ -10,6 +10,7 @@ use nalgebra::Vector2;
 use nalgebra::min;
 use std::cmp::Ordering;
 use std::ops::Range;
+use std::ops::RangeInclusive;
 
 
 // ==============
@@ -32,16 +33,19 @@ impl Cursor {
         Cursor {position,selected_to}
     }
 
-    /// Recalculate cursor position adjusting itself to new content.
+    /// Recalculate cursor position adjusting itself to new content. The cursor is also moved out
+    /// of any grapheme cluster it would end up inside.
     pub fn recalculate_position(&mut self, content:&TextFieldContent) {
         let lines               = content.lines();
         let max_line_index      = lines.len() - 1;
         self.position.line      = min(self.position.line,max_line_index);
         self.selected_to.line   = min(self.selected_to.line,max_line_index);
-        let max_column_index    = lines[self.position.line].len();
-        self.position.column    = min(self.position.column,max_column_index);
-        let max_column_index    = lines[self.selected_to.line].len();
-        self.selected_to.column = min(self.selected_to.column,max_column_index);
+        let position_line       = &lines[self.position.line];
+        let column              = min(self.position.column,position_line.len());
+        self.position.column    = position_line.snap_to_grapheme_boundary(column);
+        let selected_to_line    = &lines[self.selected_to.line];
+        let column              = min(self.selected_to.column,selected_to_line.len());
+        self.selected_to.column = selected_to_line.snap_to_grapheme_boundary(column);
     }
 
     /// Returns true if some selection is bound to this cursor.
@@ -49,6 +53,15 @@ impl Cursor {
         self.position != self.selected_to
     }
 
+    /// The indices of lines touched by the selection, or the line of cursor without selection. The
+    /// line where a many-lines selection ends at the first column is not included.
+    pub fn selected_lines(&self) -> RangeInclusive<usize> {
+        let range              = self.selection_range();
+        let ends_at_line_begin = range.end.column == 0 && range.end.line > range.start.line;
+        let last_line          = range.end.line - if ends_at_line_begin {1} else {0};
+        range.start.line..=last_line
+    }
+
     /// Select text range.
     pub fn select_range(&mut self, range:&Range<TextLocation>) {
         self.position    = range.end;
@@ -95,18 +108,22 @@ impl Cursor {
     pub fn render_position(position:&TextLocation, content:&mut TextFieldContent) -> Vector2<f32> {
         let line_height = content.line_height;
         let mut line    = content.line(position.line);
+        let row         = line.row_of_column(position.column);
         // TODO[ao] this value should be read from font information, but msdf_sys library does
         // not provide it yet.
-        let descender = line.baseline_start().y - 0.15 * line_height;
+        let descender = line.row_baseline_start(row).y - 0.15 * line_height;
         let x         = Self::x_position_of_cursor_at(position.column,&mut line);
         let y         = descender + line_height / 2.0;
         Vector2::new(x,y)
     }
 
+    /// The x position of cursor at given column, relative to the beginning of its visual row.
     fn x_position_of_cursor_at(column:usize, line:&mut LineFullInfo) -> f32 {
-        if column > 0 {
+        let row       = line.row_of_column(column);
+        let row_start = line.row_range(row).start;
+        if column > row_start {
             let char_index = column - 1;
-            line.get_char_x_range(char_index).end
+            line.get_char_x_range(char_index).end - line.row_x_offset(row)
         } else {
             line.baseline_start().x
         }
@@ -121,6 +138,10 @@ impl Cursor {
 
 /// An enum representing cursor moving step. The steps are based of possible keystrokes (arrows,
 /// Home, End, Ctrl+Home, etc.)
+///
+/// When the lines are soft-wrapped, the `Up`, `Down`, `LineBegin` and `LineEnd` steps move
+/// the cursor within visual rows rather than lines. The lines hidden in folded regions are skipped
+/// by all steps.
 #[derive(Copy,Clone,Debug,Eq,Hash,PartialEq)]
 #[allow(missing_docs)]
 pub enum Step {Left,Right,Up,Down,LineBegin,LineEnd,DocBegin,DocEnd}
@@ -164,20 +185,22 @@ impl<'a> CursorNavigation<'a> {
         }
     }
 
-    /// Get cursor position at end of whole content
+    /// Get cursor position at end of whole content. If the last line is folded, it is the end of
+    /// the folded region's header.
     pub fn content_end_position(&self) -> TextLocation {
-        TextLocation {
-            column : self.content.lines().last().unwrap().len(),
-            line   : self.content.lines().len() - 1,
-        }
+        let last_line = self.content.displayed_line(self.content.lines().len() - 1);
+        self.line_end_position(last_line)
     }
 
     /// Get cursor position for the next char from given position. Returns none if at end of
     /// whole document.
+    ///
+    /// The char here is an extended grapheme cluster, so the cursor is never put e.g. between
+    /// letter and its combining accent. The lines hidden in folded regions are skipped.
     pub fn next_char_position(&self, position:&TextLocation) -> Option<TextLocation> {
         let current_line = &self.content.lines()[position.line];
-        let next_column  = Some(position.column + 1).filter(|c| *c <= current_line.len());
-        let next_line    = Some(position.line + 1)  .filter(|l| *l < self.content.lines().len());
+        let next_column  = current_line.next_grapheme_boundary(position.column);
+        let next_line    = self.content.next_visible_line(position.line);
         match (next_column,next_line) {
             (None         , None      ) => None,
             (None         , Some(line)) => Some(TextLocation::at_line_begin(line)),
@@ -187,9 +210,13 @@ impl<'a> CursorNavigation<'a> {
 
     /// Get cursor position for the previous char from given position. Returns none if at begin of
     /// whole document.
+    ///
+    /// The char here is an extended grapheme cluster, see `next_char_position`. The lines hidden
+    /// in folded regions are skipped.
     pub fn prev_char_position(&self, position:&TextLocation) -> Option<TextLocation> {
-        let prev_column = position.column.checked_sub(1);
-        let prev_line   = position.line.checked_sub(1);
+        let current_line = &self.content.lines()[position.line];
+        let prev_column  = current_line.prev_grapheme_boundary(position.column);
+        let prev_line    = self.content.prev_visible_line(position.line);
         match (prev_column,prev_line) {
             (None         , None      ) => None,
             (None         , Some(line)) => Some(self.line_end_position(line)),
@@ -197,18 +224,34 @@ impl<'a> CursorNavigation<'a> {
         }
     }
 
-    /// Get cursor position one line above the given position, such the new x coordinate of
+    /// Get cursor position one visual row above the given position, such the new x coordinate of
     /// displayed cursor on the screen will be nearest the current value.
     pub fn line_up_position(&mut self, position:&TextLocation) -> Option<TextLocation> {
-        let prev_line = position.line.checked_sub(1);
-        prev_line.map(|line| self.near_same_x_in_another_line(position,line))
+        let row      = self.content.row_of_location(*position);
+        let prev_row = row.checked_sub(1);
+        prev_row.map(|row| self.near_same_x_in_another_row(position,row))
     }
 
-    /// Get cursor position one line behind the given position, such the new x coordinate of
-    /// displayed cursor on the screen will be nearest the current value.
+    /// Get cursor position one visual row behind the given position, such the new x coordinate
+    /// of displayed cursor on the screen will be nearest the current value.
     pub fn line_down_position(&mut self, position:&TextLocation) -> Option<TextLocation> {
-        let next_line = Some(position.line + 1).filter(|l| *l < self.content.lines().len());
-        next_line.map(|line| self.near_same_x_in_another_line(position,line))
+        let row      = self.content.row_of_location(*position);
+        let next_row = Some(row + 1).filter(|r| *r < self.content.rows_count());
+        next_row.map(|row| self.near_same_x_in_another_row(position,row))
+    }
+
+    /// Get cursor position at the beginning of the visual row of given position.
+    pub fn row_begin_position(&self, position:&TextLocation) -> TextLocation {
+        let line = &self.content.lines()[position.line];
+        let row  = line.row_of_column(position.column);
+        TextLocation {line:position.line, column:line.row_range(row).start}
+    }
+
+    /// Get cursor position at the end of the visual row of given position.
+    pub fn row_end_position(&self, position:&TextLocation) -> TextLocation {
+        let line = &self.content.lines()[position.line];
+        let row  = line.row_of_column(position.column);
+        TextLocation {line:position.line, column:line.row_end_column(row)}
     }
 
     /// New position of cursor at `position` after applying `step`.
@@ -218,37 +261,41 @@ impl<'a> CursorNavigation<'a> {
             Step::Right     => self.next_char_position(&position).unwrap_or(position),
             Step::Up        => self.line_up_position(&position).unwrap_or(position),
             Step::Down      => self.line_down_position(&position).unwrap_or(position),
-            Step::LineBegin => TextLocation::at_line_begin(position.line),
-            Step::LineEnd   => self.line_end_position(position.line),
+            Step::LineBegin => self.row_begin_position(&position),
+            Step::LineEnd   => self.row_end_position(&position),
             Step::DocBegin  => TextLocation::at_document_begin(),
             Step::DocEnd    => self.content_end_position(),
         }
     }
 
-    /// Get the cursor position on another line, such that the new x coordinate of
+    /// Get the cursor position in another visual row, such that the new x coordinate of
     /// displayed cursor on the screen will be nearest the current value.
-    fn near_same_x_in_another_line(&mut self, position:&TextLocation, line_index:usize)
+    fn near_same_x_in_another_row(&mut self, position:&TextLocation, row:usize)
     -> TextLocation {
-        let mut line   = self.content.line(position.line);
-        let x_position = Cursor::x_position_of_cursor_at(position.column,&mut line);
-        let column     = self.column_near_x(line_index,x_position);
+        let mut line         = self.content.line(position.line);
+        let x_position       = Cursor::x_position_of_cursor_at(position.column,&mut line);
+        let (line_index,row) = self.content.row_to_line_and_row(row);
+        let column           = self.column_near_x(line_index,row,x_position);
         TextLocation {line:line_index, column}
     }
 
-    /// Get the column number in given line, so the cursor will be as near as possible the
-    /// `x_position` in _text space_. See `display::shape::text::content::line::Line`
-    /// documentation for details about _text space_.
-    fn column_near_x(&mut self, line_index:usize, x_position:f32) -> usize {
+    /// Get the column number in given visual row of line, so the cursor will be as near as
+    /// possible the `x_position` in _text space_ relative to the row beginning. See
+    /// `display::shape::text::content::line::Line` documentation for details about _text space_.
+    fn column_near_x(&mut self, line_index:usize, row:usize, x_position:f32) -> usize {
         let mut line                = self.content.line(line_index);
-        let x                       = x_position;
+        let row_range               = line.row_range(row);
+        let row_end                 = line.row_end_column(row);
+        let x                       = x_position + line.row_x_offset(row);
         let char_at_x               = line.find_char_at_x_position(x);
         let nearer_to_end           = |range:Range<f32>| range.end - x < x - range.start;
         let mut nearer_to_chars_end = |index| nearer_to_end(line.get_char_x_range(index));
-        match char_at_x {
+        let column = match char_at_x {
             Some(index) if nearer_to_chars_end(index) => index + 1,
             Some(index)                               => index,
             None                                      => line.len()
-        }
+        };
+        line.snap_to_grapheme_boundary(column.max(row_range.start).min(row_end))
     }
 }
 
@@ -319,6 +366,78 @@ impl Cursors {
         self.merge_overlapping_cursors();
     }
 
+    /// Replace all cursors with the given ones, the last becoming the active one. The overlapping
+    /// cursors are merged. Does nothing if `cursors` is empty, as there is always at least one
+    /// cursor.
+    pub fn replace_cursors(&mut self, cursors:Vec<Cursor>) {
+        if !cursors.is_empty() {
+            self.cursors = cursors;
+            self.merge_overlapping_cursors();
+        }
+    }
+
+    /// Add a new cursor one visual row above the active one, as near as possible its x coordinate.
+    /// The new cursor becomes active.
+    pub fn add_cursor_above(&mut self, navigation:&mut CursorNavigation) {
+        let position = self.active_cursor().position;
+        if let Some(new_position) = navigation.line_up_position(&position) {
+            self.add_cursor(new_position);
+        }
+    }
+
+    /// Add a new cursor one visual row below the active one, as near as possible its x coordinate.
+    /// The new cursor becomes active.
+    pub fn add_cursor_below(&mut self, navigation:&mut CursorNavigation) {
+        let position = self.active_cursor().position;
+        if let Some(new_position) = navigation.line_down_position(&position) {
+            self.add_cursor(new_position);
+        }
+    }
+
+    /// Replace each cursor selecting many lines with cursors selecting the parts of those lines
+    /// covered by the original selection, one cursor per line. See `Cursor::selected_lines`.
+    pub fn split_selections_into_lines(&mut self, content:&TextFieldContent) {
+        let lines = content.lines();
+        let split = self.cursors.iter().flat_map(|cursor| {
+            let range = cursor.selection_range();
+            cursor.selected_lines().map(move |line| {
+                let is_first = line == range.start.line;
+                let is_last  = line == range.end.line;
+                let start    = if is_first { range.start.column } else { 0 };
+                let end      = if is_last  { range.end.column   } else { lines[line].len() };
+                Cursor {
+                    position    : TextLocation {line, column:end},
+                    selected_to : TextLocation {line, column:start},
+                }
+            })
+        }).collect();
+        self.replace_cursors(split);
+    }
+
+    /// Replace all cursors with a column (rectangular) selection between two points in _text
+    /// space_. There is one cursor in each visual row between the points, selecting the text
+    /// between their x coordinates. The cursor in the row of `to` point becomes active.
+    pub fn select_columns
+    (&mut self, content:&mut TextFieldContent, from:Vector2<f32>, to:Vector2<f32>) {
+        let from_location = content.location_at_point(from);
+        let to_location   = content.location_at_point(to);
+        let from_row      = content.row_of_location(from_location);
+        let to_row        = content.row_of_location(to_location);
+        let rows          = if from_row <= to_row {
+            (from_row..=to_row).collect_vec()
+        } else {
+            (to_row..=from_row).rev().collect_vec()
+        };
+        let cursors = rows.into_iter().map(|row| {
+            let y = -(row as f32 + 0.5) * content.line_height;
+            Cursor {
+                position    : content.location_at_point(Vector2::new(to.x,y)),
+                selected_to : content.location_at_point(Vector2::new(from.x,y)),
+            }
+        }).collect();
+        self.replace_cursors(cursors);
+    }
+
     /// Do the navigation step of all cursors.
     ///
     /// If after this operation some of the cursors occupies the same position, or their selected
@@ -420,9 +539,12 @@ mod test {
 
     use crate::display::shape::text::glyph::font::FontRegistry;
     use crate::display::shape::text::text_field::content::TextFieldContent;
+    use crate::display::shape::text::text_field::content::test::mock_monospace_properties;
     use crate::display::shape::text::text_field::content::test::mock_properties;
+    use crate::display::shape::text::text_field::folding::FoldingRegion;
     use crate::display::shape::text::text_field::TextFieldProperties;
 
+    use data::text::TextChange;
     use wasm_bindgen_test::wasm_bindgen_test;
     use wasm_bindgen_test::wasm_bindgen_test_configure;
 
@@ -466,6 +588,33 @@ mod test {
         }
     }
 
+    #[wasm_bindgen_test(async)]
+    async fn moving_over_grapheme_clusters() {
+        msdf_sys::initialized().await;
+        let text        = "ae\u{301}\u{1F468}\u{200D}\u{1F469}\nb";
+        let mut content = TextFieldContent::new(text,&mock_properties());
+        let mut cursor  = Cursor::new(TextLocation::at_document_begin());
+        let moved       = |navigation:&mut CursorNavigation, cursor:&mut Cursor, step:Step| {
+            navigation.move_cursor(cursor,step);
+            (cursor.position.line,cursor.position.column)
+        };
+
+        let mut navigation = CursorNavigation {content:&mut content, selecting:false};
+        let right = (0..4).map(|_| moved(&mut navigation,&mut cursor,Right)).collect_vec();
+        assert_eq!(right, vec![(0,1),(0,3),(0,6),(1,0)]);
+        let left  = (0..4).map(|_| moved(&mut navigation,&mut cursor,Left)).collect_vec();
+        assert_eq!(left, vec![(0,6),(0,3),(0,1),(0,0)]);
+
+        let mut cursor     = Cursor::new(TextLocation {line:0, column:6});
+        let mut navigation = CursorNavigation {content:&mut content, selecting:true};
+        navigation.move_cursor(&mut cursor,Left);
+        let removed = cursor.selection_range();
+        assert_eq!(content.convert_location_range_to_char_index(&removed), 3..6);
+        content.apply_change(TextChange::delete(removed));
+        assert_eq!(content.lines()[0].to_string(), "ae\u{301}");
+        assert_eq!(content.convert_location_to_char_index(TextLocation::at_line_begin(1)), 4);
+    }
+
     #[wasm_bindgen_test(async)]
     async fn moving_without_select() {
         basegl_core_msdf_sys::initialized().await;
@@ -540,6 +689,90 @@ mod test {
         assert_eq!(expected_cursors, cursors.cursors);
     }
 
+    fn selection(start:(usize,usize), end:(usize,usize)) -> Cursor {
+        Cursor {
+            position    : TextLocation {line:end.0  , column:end.1  },
+            selected_to : TextLocation {line:start.0, column:start.1},
+        }
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn adding_cursors_in_adjacent_rows() {
+        msdf_sys::initialized().await;
+        let properties     = mock_monospace_properties("abcd",10.0);
+        let mut content    = TextFieldContent::new("abcd\nab\nabcd",&properties);
+        let mut navigation = CursorNavigation {content:&mut content, selecting:false};
+        let mut cursors    = Cursors::mock(vec![Cursor::new(TextLocation {line:0, column:3})]);
+        let positions      = |cursors:&Cursors| {
+            cursors.cursors.iter().map(|c| (c.position.line,c.position.column)).collect_vec()
+        };
+        cursors.add_cursor_below(&mut navigation);
+        cursors.add_cursor_below(&mut navigation);
+        assert_eq!(positions(&cursors), vec![(0,3),(1,2),(2,2)]);
+        cursors.add_cursor_below(&mut navigation);
+        cursors.add_cursor_above(&mut navigation);
+        assert_eq!(positions(&cursors), vec![(0,3),(1,2),(2,2)]);
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn splitting_selections_into_lines() {
+        msdf_sys::initialized().await;
+        let content     = TextFieldContent::new("abcd\nab\nabcd",&mock_properties());
+        let mut cursors = Cursors::mock(vec![selection((0,1),(2,2)), selection((0,0),(0,0))]);
+        cursors.split_selections_into_lines(&content);
+        let expected = vec!
+            [ selection((0,0),(0,0))
+            , selection((0,1),(0,4))
+            , selection((1,0),(1,2))
+            , selection((2,0),(2,2))
+            ];
+        assert_eq!(expected, cursors.cursors.iter().cloned().sorted_by_key(|c| c.position)
+            .collect_vec());
+
+        let mut cursors = Cursors::mock(vec![selection((1,0),(0,2))]);
+        cursors.split_selections_into_lines(&content);
+        assert_eq!(vec![selection((0,2),(0,4))], cursors.cursors);
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn selecting_columns() {
+        msdf_sys::initialized().await;
+        let properties  = mock_monospace_properties("abcd",10.0);
+        let mut content = TextFieldContent::new("abcd\nab\nabcd",&properties);
+        let mut cursors = Cursors::default();
+        cursors.select_columns(&mut content,Vector2::new(15.0,-5.0),Vector2::new(35.0,-25.0));
+        let expected = vec![selection((0,1),(0,3)), selection((1,1),(1,2)), selection((2,1),(2,3))];
+        assert_eq!(expected, cursors.cursors);
+
+        cursors.select_columns(&mut content,Vector2::new(35.0,-25.0),Vector2::new(15.0,-15.0));
+        let expected = vec![selection((2,3),(2,1)), selection((1,2),(1,1))];
+        assert_eq!(expected, cursors.cursors);
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn moving_over_folded_lines() {
+        msdf_sys::initialized().await;
+        let properties  = mock_monospace_properties("abc",10.0);
+        let mut content = TextFieldContent::new("ab\nc\nc\nab",&properties);
+        content.fold(FoldingRegion::new(0,3));
+        let moved = |content:&mut TextFieldContent, line, column, step| {
+            let mut navigation = CursorNavigation {content,selecting:false};
+            let mut cursor     = Cursor::new(TextLocation {line,column});
+            navigation.move_cursor(&mut cursor,step);
+            (cursor.position.line,cursor.position.column)
+        };
+        assert_eq!((3,0), moved(&mut content,0,2,Right));
+        assert_eq!((0,2), moved(&mut content,3,0,Left));
+        assert_eq!((3,1), moved(&mut content,0,1,Down));
+        assert_eq!((0,1), moved(&mut content,3,1,Up));
+        let point = Vector2::new(15.0,-15.0);
+        assert_eq!(TextLocation {line:3, column:1}, content.location_at_point(point));
+
+        let mut content = TextFieldContent::new("ab\nc",&properties);
+        content.fold(FoldingRegion::new(0,2));
+        assert_eq!((0,2), moved(&mut content,0,0,DocEnd));
+    }
+
     #[wasm_bindgen_test(async)]
     async fn recalculate_positions() {
         msdf_sys::initialized().await;
@@ -0,0 +1,221 @@
+//! Language-aware editing rules of TextField, describing how the indentation and brackets are
+//! handled.
+
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::bracket::BracketPair;
+use crate::display::shape::text::text_field::bracket::DEFAULT_BRACKETS;
+
+
+
+// ====================
+// === EditingRules ===
+// ====================
+
+/// Rules customizing the TextField's editing actions for a specific language.
+pub trait EditingRules : Debug {
+    /// The text of one indentation level, inserted when indenting lines and removed when
+    /// dedenting them.
+    fn indent_unit(&self) -> &str;
+
+    /// The indentation of a new line inserted after `line`, where `line` is the part of the
+    /// current line before the cursor.
+    fn new_line_indent(&self, line:&[char]) -> String;
+
+    /// Check if Backspace in the line's leading whitespace should remove the whole indentation
+    /// unit instead of a single character.
+    fn removes_whole_indent_unit(&self) -> bool;
+
+    /// The brackets and quotes which are matched and auto-closed. See `bracket` module.
+    fn bracket_pairs(&self) -> &[BracketPair];
+}
+
+
+// === PlainTextRules ===
+
+/// The default editing rules of plain text: new lines are not indented, Backspace removes single
+/// characters, and only the `DEFAULT_BRACKETS` are matched.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct PlainTextRules;
+
+impl EditingRules for PlainTextRules {
+    fn indent_unit(&self) -> &str {
+        DEFAULT_INDENT_UNIT
+    }
+
+    fn new_line_indent(&self, _line:&[char]) -> String {
+        default()
+    }
+
+    fn removes_whole_indent_unit(&self) -> bool {
+        false
+    }
+
+    fn bracket_pairs(&self) -> &[BracketPair] {
+        DEFAULT_BRACKETS
+    }
+}
+
+
+// === IndentationRules ===
+
+/// The indentation unit used by default.
+pub const DEFAULT_INDENT_UNIT:&str = "    ";
+
+/// The characters of operators. The block opener preceded by one of them is a part of another
+/// operator, like `=` in `==` or `>=`.
+const OPERATOR_CHARS:&[char] =
+    &['!','$','%','&','*','+','-','/','<','>','?','^','~','|',':','\\','=','.',','];
+
+/// Editing rules of indentation-sensitive languages. The new line keeps the indentation of the
+/// current one and is indented by one more unit after lines opening a block, i.e. ending with one
+/// of `block_openers` (ignoring trailing whitespace) which is not a part of a longer operator.
+#[derive(Clone,Debug)]
+pub struct IndentationRules {
+    /// The text of one indentation level. It should consist of spaces only.
+    pub indent_unit : String,
+    /// The line endings after which a new block is started.
+    pub block_openers : Vec<String>,
+    /// The matched brackets and quotes.
+    pub bracket_pairs : Vec<BracketPair>,
+}
+
+impl IndentationRules {
+    /// Create rules with given indentation unit and block openers. Only the `DEFAULT_BRACKETS`
+    /// are matched.
+    pub fn new(indent_unit:impl Str, block_openers:&[&str]) -> Self {
+        let indent_unit   = indent_unit.into();
+        let block_openers = block_openers.iter().map(|opener| opener.to_string()).collect();
+        let bracket_pairs = DEFAULT_BRACKETS.to_vec();
+        IndentationRules {indent_unit,block_openers,bracket_pairs}
+    }
+
+    /// Add the language's quotes to the matched pairs.
+    pub fn with_quotes(mut self, quotes:&[char]) -> Self {
+        self.bracket_pairs.extend(quotes.iter().map(|quote| BracketPair::quote(*quote)));
+        self
+    }
+
+    fn opens_block(&self, line:&[char]) -> bool {
+        let line : String = line.iter().collect();
+        let line          = line.trim_end();
+        self.block_openers.iter().any(|opener| {
+            let before = || &line[..line.len() - opener.len()];
+            line.ends_with(opener.as_str()) && !before().ends_with(OPERATOR_CHARS)
+        })
+    }
+}
+
+impl EditingRules for IndentationRules {
+    fn indent_unit(&self) -> &str {
+        &self.indent_unit
+    }
+
+    fn new_line_indent(&self, line:&[char]) -> String {
+        let indent       = line[..leading_whitespace(line)].iter().collect::<String>();
+        let extra_indent = if self.opens_block(line) { self.indent_unit.as_str() } else { "" };
+        indent + extra_indent
+    }
+
+    fn removes_whole_indent_unit(&self) -> bool {
+        true
+    }
+
+    fn bracket_pairs(&self) -> &[BracketPair] {
+        &self.bracket_pairs
+    }
+}
+
+
+
+// ===============
+// === Helpers ===
+// ===============
+
+/// The number of whitespace characters at the beginning of line.
+pub fn leading_whitespace(line:&[char]) -> usize {
+    line.iter().take_while(|c| c.is_whitespace()).count()
+}
+
+/// The number of characters removed from the line's beginning when dedenting it by one
+/// indentation unit. If the line does not start with the whole unit, only its leading whitespace
+/// is removed: the spaces up to the unit length, or a single tab.
+pub fn dedent_length(line:&[char], indent_unit:&str) -> usize {
+    let unit_length      = indent_unit.chars().count();
+    let starts_with_unit = line.iter().cloned().take(unit_length).eq(indent_unit.chars());
+    if starts_with_unit {
+        unit_length
+    } else if line.first() == Some(&'\t') {
+        1
+    } else {
+        line.iter().take(unit_length).take_while(|c| **c == ' ').count()
+    }
+}
+
+/// The column to which Backspace should remove text when the cursor is at the end of `line`,
+/// where `line` is the part of the current line before the cursor. Returns `Some` only if the
+/// cursor is in the leading whitespace consisting of spaces; the text is removed up to the
+/// previous multiple of the indentation unit length.
+pub fn backspace_dedent_column(line:&[char], indent_unit:&str) -> Option<usize> {
+    let unit_length = indent_unit.chars().count();
+    let all_spaces  = line.iter().all(|c| *c == ' ');
+    (all_spaces && !line.is_empty() && unit_length > 0).as_some_from(|| {
+        (line.len() - 1) / unit_length * unit_length
+    })
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn chars(text:&str) -> Vec<char> {
+        text.chars().collect()
+    }
+
+    #[test]
+    fn indenting_new_lines() {
+        let rules  = IndentationRules::new("    ",&["->","="]);
+        let indent = |line:&str| rules.new_line_indent(&chars(line));
+        assert_eq!(""        , indent(""));
+        assert_eq!(""        , indent("foo"));
+        assert_eq!("    "    , indent("main ="));
+        assert_eq!("    "    , indent("main =  "));
+        assert_eq!("  "      , indent("  foo"));
+        assert_eq!("      "  , indent("  x ->"));
+        assert_eq!("    "    , indent("f(x)="));
+        assert_eq!(""        , indent("a =="));
+        assert_eq!(""        , indent("a >="));
+        assert_eq!(""        , indent("a <="));
+        assert_eq!(""        , indent("a /="));
+        assert_eq!(""        , indent("a -->"));
+        assert_eq!("    "    , indent("    "));
+        assert_eq!(""        , PlainTextRules.new_line_indent(&chars("  main =")));
+    }
+
+    #[test]
+    fn dedenting_lines() {
+        assert_eq!(4, dedent_length(&chars("        foo"),"    "));
+        assert_eq!(2, dedent_length(&chars("  foo")      ,"    "));
+        assert_eq!(1, dedent_length(&chars("\t\tfoo")    ,"    "));
+        assert_eq!(0, dedent_length(&chars("foo")        ,"    "));
+        assert_eq!(1, dedent_length(&chars("\t\tfoo")    ,"\t"));
+        assert_eq!(0, dedent_length(&chars("")           ,"    "));
+    }
+
+    #[test]
+    fn removing_indentation_with_backspace() {
+        assert_eq!(Some(4), backspace_dedent_column(&chars("        ") ,"    "));
+        assert_eq!(Some(4), backspace_dedent_column(&chars("      ")   ,"    "));
+        assert_eq!(Some(0), backspace_dedent_column(&chars("    ")     ,"    "));
+        assert_eq!(Some(0), backspace_dedent_column(&chars(" ")        ,"    "));
+        assert_eq!(None   , backspace_dedent_column(&chars("")         ,"    "));
+        assert_eq!(None   , backspace_dedent_column(&chars("  a ")     ,"    "));
+        assert_eq!(None   , backspace_dedent_column(&chars("\t")       ,"    "));
+    }
+}
@@ -0,0 +1,119 @@
+//! Code folding in TextField.
+//!
+//! The _folding region_ is a fragment of content consisting of a _header_ line and the lines
+//! following it, which may be hidden behind a placeholder displayed at the header's end. The
+//! regions are usually computed from the structure of code (e.g. its blocks), and for plain text
+//! they are computed from indentation by `indentation_folding_regions`.
+
+use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::editing::leading_whitespace;
+
+use std::ops::Range;
+
+
+
+// =====================
+// === FoldingRegion ===
+// =====================
+
+/// The text displayed at the end of header line of a folded region.
+pub const FOLD_PLACEHOLDER:&str = " …";
+
+/// A fragment of content which may be folded.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct FoldingRegion {
+    /// The index of line which stays visible when the region is folded.
+    pub header : usize,
+    /// The index of line after the region's last one.
+    pub end    : usize,
+}
+
+impl FoldingRegion {
+    /// Create region of lines from `header` to `end` exclusive.
+    pub fn new(header:usize, end:usize) -> Self {
+        FoldingRegion {header,end}
+    }
+
+    /// The lines hidden when the region is folded.
+    pub fn hidden_lines(&self) -> Range<usize> {
+        self.header + 1 .. self.end
+    }
+
+    /// Check if the line belongs to this region.
+    pub fn contains_line(&self, line:usize) -> bool {
+        self.header <= line && line < self.end
+    }
+}
+
+
+
+// ====================================
+// === Regions Based on Indentation ===
+// ====================================
+
+/// Compute the folding regions from the lines' indentation. Each line followed by more indented
+/// lines is a header of region containing all these lines; the blank lines between them belong to
+/// the region, but the ones at its end do not. The regions are ordered by their headers.
+pub fn indentation_folding_regions(content:&TextFieldContent) -> Vec<FoldingRegion> {
+    let mut regions        = Vec::new();
+    let mut open_headers   = Vec::new();
+    let mut last_non_blank = 0;
+    for (index,line) in content.lines().iter().enumerate() {
+        let chars  = line.chars();
+        let indent = leading_whitespace(chars);
+        if indent < chars.len() {
+            close_regions(&mut regions,&mut open_headers,indent,last_non_blank + 1);
+            open_headers.push((index,indent));
+            last_non_blank = index;
+        }
+    }
+    close_regions(&mut regions,&mut open_headers,0,last_non_blank + 1);
+    regions.sort_by_key(|region| region.header);
+    regions
+}
+
+/// Finish the regions of `open_headers` indented at least by `indent` at the line `end`. The
+/// `open_headers` are the headers of not finished regions with their indentation, the most
+/// indented last.
+fn close_regions
+(regions:&mut Vec<FoldingRegion>, open_headers:&mut Vec<(usize,usize)>, indent:usize, end:usize) {
+    while let Some((header,_)) = open_headers.last().filter(|(_,i)| *i >= indent).cloned() {
+        open_headers.pop();
+        if end > header + 1 {
+            regions.push(FoldingRegion::new(header,end));
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::shape::text::text_field::content::test::mock_properties;
+
+    use basegl_core_msdf_sys as msdf_sys;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test(async)]
+    async fn computing_regions_from_indentation() {
+        msdf_sys::initialized().await;
+        let text     = "main =\n    a = 1\n    foo x ->\n        x\n\n    foo a\n\nbar\n  baz\n\n";
+        let content  = TextFieldContent::new(text,&mock_properties());
+        let regions  = indentation_folding_regions(&content);
+        let expected = vec!
+            [ FoldingRegion::new(0,6)
+            , FoldingRegion::new(2,4)
+            , FoldingRegion::new(7,9)
+            ];
+        assert_eq!(expected, regions);
+        assert_eq!(1..6, regions[0].hidden_lines());
+
+        let content = TextFieldContent::new("a\nb\n  \nc",&mock_properties());
+        assert!(indentation_folding_regions(&content).is_empty());
+    }
+}
@@ -10,6 +10,7 @@ use crate::display::shape::text::text_field::frp::keyboard::TextFieldKeyboardFrp
 use crate::display::shape::text::text_field::frp::mouse::TextFieldMouseFrp;
 use crate::display::shape::text::text_field::WeakTextField;
 use crate::display::world::World;
+use crate::system::web::text_input::BlurHandler;
 use crate::system::web::text_input::KeyboardBinding;
 
 
@@ -41,4 +42,9 @@ impl TextFieldFrp {
         let mouse_binding    = mouse.bind_frp_to_mouse(world);
         TextFieldFrp {keyboard,mouse,keyboard_binding,mouse_binding}
     }
+
+    /// Set the handler called whenever the TextField loses the keyboard focus.
+    pub fn set_focus_lost_handler<Handler:BlurHandler>(&mut self, handler:Handler) {
+        self.keyboard_binding.set_blur_handler(handler);
+    }
 }
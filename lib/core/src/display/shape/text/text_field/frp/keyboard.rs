@@ -6,12 +6,59 @@ use crate::display::shape::text::text_field::cursor::Step;
 use crate::display::shape::text::text_field::TextField;
 use crate::display::shape::text::text_field::WeakTextField;
 use crate::system::web::text_input::KeyboardBinding;
+use crate::system::web::text_input::composition::CompositionAction;
 use crate::system::web::text_input::bind_frp_to_js_keyboard_actions;
 
 use enso_frp::*;
 
 
 
+// =================
+// === Constants ===
+// =================
+
+/// The default keymap of TextField commands. See `enso_frp::io::keymap` for the format
+/// description.
+pub const DEFAULT_KEYMAP:&str = r#"
+    [ { "keys" : "Left"            , "command" : "text_field.cursor.left"                  }
+    , { "keys" : "Right"           , "command" : "text_field.cursor.right"                 }
+    , { "keys" : "Up"              , "command" : "text_field.cursor.up"                    }
+    , { "keys" : "Down"            , "command" : "text_field.cursor.down"                  }
+    , { "keys" : "Home"            , "command" : "text_field.cursor.line_begin"            }
+    , { "keys" : "End"             , "command" : "text_field.cursor.line_end"              }
+    , { "keys" : "Ctrl+Home"       , "command" : "text_field.cursor.doc_begin"             }
+    , { "keys" : "Ctrl+End"        , "command" : "text_field.cursor.doc_end"               }
+    , { "keys" : "Shift+Left"      , "command" : "text_field.select.left"                  }
+    , { "keys" : "Shift+Right"     , "command" : "text_field.select.right"                 }
+    , { "keys" : "Shift+Up"        , "command" : "text_field.select.up"                    }
+    , { "keys" : "Shift+Down"      , "command" : "text_field.select.down"                  }
+    , { "keys" : "Shift+Home"      , "command" : "text_field.select.line_begin"            }
+    , { "keys" : "Shift+End"       , "command" : "text_field.select.line_end"              }
+    , { "keys" : "Ctrl+Shift+Home" , "command" : "text_field.select.doc_begin"             }
+    , { "keys" : "Ctrl+Shift+End"  , "command" : "text_field.select.doc_end"               }
+    , { "keys" : "Alt+J"           , "command" : "text_field.select_next_word_occurrence"  }
+    , { "keys" : "Ctrl+Shift+L"    , "command" : "text_field.select_all_word_occurrences"  }
+    , { "keys" : "Ctrl+Alt+Up"     , "command" : "text_field.add_cursor_above"             }
+    , { "keys" : "Ctrl+Alt+Down"   , "command" : "text_field.add_cursor_below"             }
+    , { "keys" : "Alt+Shift+I"     , "command" : "text_field.split_selection_into_lines"   }
+    , { "keys" : "Ctrl+Shift+M"    , "command" : "text_field.jump_to_matching_bracket"     }
+    , { "keys" : "Alt+Shift+M"     , "command" : "text_field.select_inside_brackets"       }
+    , { "keys" : "Ctrl+Shift+["    , "command" : "text_field.fold"                         }
+    , { "keys" : "Ctrl+Shift+]"    , "command" : "text_field.unfold"                       }
+    , { "keys" : "Ctrl+K Ctrl+0"   , "command" : "text_field.fold_all"                     }
+    , { "keys" : "Ctrl+K Ctrl+J"   , "command" : "text_field.unfold_all"                   }
+    , { "keys" : "Enter"           , "command" : "text_field.new_line"                     }
+    , { "keys" : "Tab"             , "command" : "text_field.indent"                       }
+    , { "keys" : "Shift+Tab"       , "command" : "text_field.dedent"                       }
+    , { "keys" : "Delete"          , "command" : "text_field.delete_forward"               }
+    , { "keys" : "Backspace"       , "command" : "text_field.delete_backward"              }
+    , { "keys" : "Ctrl+Z"          , "command" : "text_field.undo"                         }
+    , { "keys" : "Ctrl+Shift+Z"    , "command" : "text_field.redo"                         }
+    , { "keys" : "Ctrl+Y"          , "command" : "text_field.redo"                         }
+    ]"#;
+
+
+
 // ====================
 // === TextFieldFrp ===
 // ====================
@@ -19,19 +66,21 @@ use enso_frp::*;
 /// This structure contains all nodes in FRP graph handling keyboards events of one TextField
 /// component.
 ///
-/// The most of TextField actions are covered by providing actions to KeyboardActions for specific
-/// key masks. However, there are special actions which must be done in a lower level:
+/// The most of TextField actions are covered by named commands, bound to keys by the keymap (see
+/// `DEFAULT_KEYMAP`). However, there are special actions which must be done in a lower level:
 ///  * *clipboard operations* - they are performed by reading text input js events directly from
 ///    text area component. See `system::web::text_input` crate.
 ///  * *text input operations* - here we want to handle all the keyboard mapping set by user, so
 ///    we connect this action directly to `key_press` node from `keyboard`.
+///  * *text composition* - the text composed by input methods or with dead keys is read from the
+///    text area component, like the clipboard operations.
 #[derive(Debug)]
 pub struct TextFieldKeyboardFrp {
     /// A "keyboard" part of graph derived from frp crate.
     pub keyboard: Keyboard,
-    /// Keyboard actions. Here we define shortcuts for all actions except letters input, copying
+    /// Keymap actions. Here we define commands for all actions except letters input, copying
     /// and pasting.
-    pub actions: KeyboardActions,
+    pub keymap: KeymapActions,
     /// Event sent once cut operation was requested.
     pub on_cut: Dynamic<()>,
     /// Event sent once copy operation was requested.
@@ -48,29 +97,41 @@ pub struct TextFieldKeyboardFrp {
     pub do_paste: Dynamic<()>,
     /// A lambda node performing character input operation.
     pub do_char_input: Dynamic<()>,
+    /// Event sent when the text composition requests an action on text.
+    pub on_composition: Dynamic<CompositionAction>,
+    /// A lambda node performing the text composition action.
+    pub do_composition: Dynamic<()>,
 }
 
 impl TextFieldKeyboardFrp {
     /// Create FRP graph operating on given TextField pointer.
     pub fn new(text_field:WeakTextField) -> Self {
         let keyboard    = Keyboard::default();
-        let mut actions = KeyboardActions::new(&keyboard);
+        let mut keymap  = KeymapActions::new(&keyboard,Self::default_keymap());
         let cut         = Self::copy_lambda(true, text_field.clone_ref());
         let copy        = Self::copy_lambda(false, text_field.clone_ref());
         let paste       = Self::paste_lambda(text_field.clone_ref());
         let insert_char = Self::char_typed_lambda(text_field.clone_ref());
+        let compose     = Self::composition_lambda(text_field.clone_ref());
         frp! {
-            text_field.on_cut        = source();
-            text_field.on_copy       = source();
-            text_field.on_paste      = source();
-            text_field.do_copy       = on_copy .map(move |()| copy());
-            text_field.do_cut        = on_cut  .map(move |()| cut());
-            text_field.do_paste      = on_paste.map(paste);
-            text_field.do_char_input = keyboard.on_pressed.map2(&keyboard.key_mask,insert_char);
+            text_field.on_cut         = source();
+            text_field.on_copy        = source();
+            text_field.on_paste       = source();
+            text_field.on_composition = source();
+            text_field.do_copy        = on_copy .map(move |()| copy());
+            text_field.do_cut         = on_cut  .map(move |()| cut());
+            text_field.do_paste       = on_paste.map(paste);
+            text_field.do_char_input  = keyboard.on_pressed.map2(&keyboard.key_mask,insert_char);
+            text_field.do_composition = on_composition.map(compose);
         }
-        Self::initialize_actions_map(&mut actions, text_field);
-        TextFieldKeyboardFrp {keyboard,actions,on_cut,on_copy,on_paste,do_cut,do_copy,do_paste,
-            do_char_input}
+        Self::initialize_command_handlers(&mut keymap, text_field);
+        TextFieldKeyboardFrp {keyboard,keymap,on_cut,on_copy,on_paste,do_cut,do_copy,do_paste,
+            do_char_input,on_composition,do_composition}
+    }
+
+    /// The keymap created from `DEFAULT_KEYMAP`.
+    pub fn default_keymap() -> Keymap {
+        Keymap::from_json(DEFAULT_KEYMAP).expect("The default TextField keymap should be valid.")
     }
 
     /// Bind this FRP graph to js events.
@@ -97,8 +158,12 @@ impl TextFieldKeyboardFrp {
         let paste_handler = enclose!((self.on_paste => on_paste) move |text_to_paste| {
             on_paste.event.emit(text_to_paste);
         });
+        let composition_handler = enclose!((self.on_composition => on_composition) move |action| {
+            on_composition.event.emit(action);
+        });
         binding.set_copy_handler(copy_handler);
         binding.set_paste_handler(paste_handler);
+        binding.set_composition_handler(composition_handler);
         binding
     }
 }
@@ -139,50 +204,96 @@ impl TextFieldKeyboardFrp {
         }
     }
 
-    fn initialize_actions_map
-    (actions:&mut KeyboardActions, text_field:WeakTextField) {
-        use Key::*;
-        let mut setter = TextFieldActionsSetter{actions,text_field};
-        setter.set_navigation_action(&[ArrowLeft],    Step::Left);
-        setter.set_navigation_action(&[ArrowRight],   Step::Right);
-        setter.set_navigation_action(&[ArrowUp],      Step::Up);
-        setter.set_navigation_action(&[ArrowDown],    Step::Down);
-        setter.set_navigation_action(&[Home],         Step::LineBegin);
-        setter.set_navigation_action(&[End],          Step::LineEnd);
-        setter.set_navigation_action(&[Control,Home], Step::DocBegin);
-        setter.set_navigation_action(&[Control,End],  Step::DocEnd);
-        setter.set_action(&[Alt, Character("j".into())], |t| t.select_next_word_occurrence());
-        setter.set_action(&[Enter],                      |t| t.write("\n"));
-        setter.set_action(&[Delete],                     |t| t.do_delete_operation(Step::Right));
-        setter.set_action(&[Backspace],                  |t| t.do_delete_operation(Step::Left));
+    fn composition_lambda(text_field:WeakTextField) -> impl Fn(&CompositionAction) {
+        move |action| {
+            text_field.upgrade().for_each(|text_field| match action {
+                CompositionAction::Update(text) => text_field.set_composition(text),
+                CompositionAction::Commit(text) => text_field.commit_composition(text),
+                CompositionAction::Cancel       => text_field.cancel_composition(),
+            })
+        }
+    }
+
+    fn initialize_command_handlers(keymap:&mut KeymapActions, text_field:WeakTextField) {
+        let mut setter = TextFieldCommandsSetter{keymap,text_field};
+        setter.set_navigation_command("left",       Step::Left);
+        setter.set_navigation_command("right",      Step::Right);
+        setter.set_navigation_command("up",         Step::Up);
+        setter.set_navigation_command("down",       Step::Down);
+        setter.set_navigation_command("line_begin", Step::LineBegin);
+        setter.set_navigation_command("line_end",   Step::LineEnd);
+        setter.set_navigation_command("doc_begin",  Step::DocBegin);
+        setter.set_navigation_command("doc_end",    Step::DocEnd);
+        setter.set_command("select_next_word_occurrence", |t| t.select_next_word_occurrence());
+        setter.set_command("select_all_word_occurrences", |t| t.select_all_word_occurrences());
+        setter.set_command("add_cursor_above",            |t| t.add_cursor_above());
+        setter.set_command("add_cursor_below",            |t| t.add_cursor_below());
+        setter.set_command("split_selection_into_lines",  |t| t.split_selection_into_lines());
+        setter.set_command("jump_to_matching_bracket",    |t| {
+            t.jump_to_matching_bracket();
+            t.notify_cursors_moved();
+        });
+        setter.set_command("select_inside_brackets",      |t| t.select_inside_brackets());
+        setter.set_command("fold",                        |t| t.fold_at_cursor());
+        setter.set_command("unfold",                      |t| t.unfold_at_cursor());
+        setter.set_command("fold_all",                    |t| t.fold_all());
+        setter.set_command("unfold_all",                  |t| t.unfold_all());
+        setter.set_command("new_line",                    |t| t.new_line());
+        setter.set_command("indent",                      |t| t.indent_selected_lines());
+        setter.set_command("dedent",                      |t| t.dedent_selected_lines());
+        setter.set_command("delete_forward",              |t| t.do_delete_operation(Step::Right));
+        setter.set_command("delete_backward",             |t| t.do_delete_operation(Step::Left));
+        setter.set_command("undo",                        |t| t.undo());
+        setter.set_command("redo",                        |t| t.redo());
     }
 }
 
 
 // === Private Utilities ===
 
-/// An utility struct for setting actions in text field. See `initialize_actions_map` function
-/// for its usage.
-struct TextFieldActionsSetter<'a> {
+/// An utility struct for setting command handlers in text field. See
+/// `initialize_command_handlers` function for its usage.
+struct TextFieldCommandsSetter<'a> {
     text_field : WeakTextField,
-    actions    : &'a mut KeyboardActions,
+    keymap     : &'a mut KeymapActions,
 }
 
-impl<'a> TextFieldActionsSetter<'a> {
-    fn set_action<F>(&mut self, keys:&[Key], action:F)
+impl<'a> TextFieldCommandsSetter<'a> {
+    fn set_command<F>(&mut self, name:&str, action:F)
     where F : Fn(&TextField) + 'static {
         let ptr = self.text_field.clone_ref();
-        self.actions.set_action(keys.into(), move |_| {
+        self.keymap.set_handler(format!("text_field.{}",name), move || {
             if let Some(text_field) = ptr.upgrade() {
                 action(&text_field);
             }
         });
     }
 
-    fn set_navigation_action(&mut self, base_keys:&[Key], step:Step) {
-        self.set_action(base_keys, move |t| t.navigate_cursors(step,false));
-        let base_keys_cloned = base_keys.iter().cloned();
-        let selecting_keys   = base_keys_cloned.chain(std::iter::once(Key::Shift)).collect_vec();
-        self.set_action(selecting_keys.as_ref(), move |t| t.navigate_cursors(step,true));
+    fn set_navigation_command(&mut self, name:&str, step:Step) {
+        self.set_command(&format!("cursor.{}",name), move |t| {
+            t.navigate_cursors(step,false);
+            t.notify_cursors_moved();
+        });
+        self.set_command(&format!("select.{}",name), move |t| {
+            t.navigate_cursors(step,true);
+            t.notify_cursors_moved();
+        });
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn default_keymap_has_no_conflicts() {
+        let keymap = TextFieldKeyboardFrp::default_keymap();
+        assert_eq!(Vec::<Conflict>::new(), keymap.conflicts());
     }
 }
@@ -23,9 +23,12 @@ pub struct TextFieldMouseFrp {
     pub selecting: Dynamic<bool>,
     /// Node giving `true` when using keyboard modifiers for multicursor edit.
     pub multicursor: Dynamic<bool>,
+    /// Node giving `true` when using keyboard modifiers for column (rectangular) selection.
+    pub column_selection: Dynamic<bool>,
     /// A node setting cursor after mouse click.
     pub set_cursor_action: Dynamic<()>,
-    /// A node modifying selection on mouse drag.
+    /// A node modifying selection on mouse drag. In column selection mode, the rectangle between
+    /// the click position and the current one is selected.
     pub select_action: Dynamic<()>,
 }
 
@@ -37,23 +40,27 @@ impl TextFieldMouseFrp {
         let mouse               = Mouse::default();
         let is_inside           = Self::is_inside_text_field_lambda(text_field_ptr.clone());
         let is_multicursor_mode = |mask:&KeyMask| mask == &[Alt,Shift].iter().collect();
+        let is_column_mode      = |mask:&KeyMask| mask == &[Alt].iter().collect();
+        let drag                = |position:&Position, start:&Position| (*start,*position);
         let set_cursor_action   = Self::set_cursor_lambda(text_field_ptr.clone());
         let select_action       = Self::select_lambda(text_field_ptr);
         frp! {
-            text_field.is_inside     = mouse.position.map(is_inside);
-            text_field.click_in      = mouse.on_down.gate(&is_inside);
-            text_field.click_in_bool = click_in.constant(true);
-            text_field.mouse_up_bool = mouse.on_up.constant(false);
-            text_field.selecting     = click_in_bool.merge(&mouse_up_bool);
-            text_field.multicursor   = keyboard.keyboard.key_mask.map(is_multicursor_mode);
+            text_field.is_inside        = mouse.position.map(is_inside);
+            text_field.click_in         = mouse.on_down.gate(&is_inside);
+            text_field.click_in_bool    = click_in.constant(true);
+            text_field.mouse_up_bool    = mouse.on_up.constant(false);
+            text_field.selecting        = click_in_bool.merge(&mouse_up_bool);
+            text_field.multicursor      = keyboard.keyboard.key_mask.map(is_multicursor_mode);
+            text_field.column_selection = keyboard.keyboard.key_mask.map(is_column_mode);
 
             text_field.click_in_pos = mouse.position.sample(&click_in);
             text_field.select_pos   = mouse.position.gate(&selecting);
+            text_field.drag         = select_pos.map2(&click_in_pos,drag);
 
             text_field.set_cursor_action = click_in_pos.map2(&multicursor,set_cursor_action);
-            text_field.select_action     = select_pos.map(select_action);
+            text_field.select_action     = drag.map2(&column_selection,select_action);
         }
-        Self {mouse,click_in,selecting,multicursor,set_cursor_action,select_action}
+        Self {mouse,click_in,selecting,multicursor,column_selection,set_cursor_action,select_action}
     }
 
     /// Bind this FRP graph to js events.
@@ -81,15 +88,22 @@ impl TextFieldMouseFrp {
                 } else {
                     text_field.set_cursor(position);
                 }
+                text_field.notify_cursors_moved();
             }
         }
     }
 
-    fn select_lambda(text_field:WeakTextField) -> impl Fn(&Position) {
-        move |position| {
+    fn select_lambda(text_field:WeakTextField) -> impl Fn(&(Position,Position),&bool) {
+        move |(start,position),column_mode| {
+            let start    = Vector2::new(start.x as f32,start.y as f32);
             let position = Vector2::new(position.x as f32,position.y as f32);
             if let Some(text_field) = text_field.upgrade() {
-                text_field.jump_cursor(position,true);
+                if *column_mode {
+                    text_field.select_columns(start,position);
+                } else {
+                    text_field.jump_cursor(position,true);
+                }
+                text_field.notify_cursors_moved();
             }
         }
     }
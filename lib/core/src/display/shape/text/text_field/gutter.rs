@@ -0,0 +1,254 @@
+//! The gutter of TextField: a column beside the text displaying line numbers and line decorations.
+//!
+//! The _line decoration_ is an icon displayed before the line number, marking e.g. a breakpoint,
+//! a parse error or a changed line, with a tooltip describing it. This module keeps the
+//! decorations and computes the gutter's layout; the sprites are managed by
+//! `render::gutter::GutterSprites`.
+
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::content::rope::LineRope;
+
+use data::text::TextChange;
+use nalgebra::Vector2;
+use nalgebra::Vector4;
+use std::collections::BTreeMap;
+use std::ops::Range;
+
+
+
+// ======================
+// === LineDecoration ===
+// ======================
+
+/// A mark displayed in gutter next to the line number.
+#[derive(Clone,Debug,PartialEq)]
+pub struct LineDecoration {
+    /// The character displayed before the line number.
+    pub icon    : char,
+    /// The color of icon.
+    pub color   : Vector4<f32>,
+    /// The description of decoration, e.g. the parse error message.
+    pub tooltip : String,
+}
+
+impl LineDecoration {
+    /// Constructor.
+    pub fn new(icon:char, color:Vector4<f32>, tooltip:impl Str) -> Self {
+        let tooltip = tooltip.into();
+        LineDecoration {icon,color,tooltip}
+    }
+}
+
+
+// === LineDecorations ===
+
+/// The decorations of TextField's lines, at most one per line.
+///
+/// The decorations stick to their lines when text is edited: the lines below the edit are moved
+/// by the number of added or removed lines, and the decorations of removed lines are dropped.
+#[derive(Clone,Debug,Default)]
+pub struct LineDecorations {
+    decorations : BTreeMap<usize,LineDecoration>,
+}
+
+impl LineDecorations {
+    /// Set the decoration of line, replacing the previous one.
+    pub fn set(&mut self, line:usize, decoration:LineDecoration) {
+        self.decorations.insert(line,decoration);
+    }
+
+    /// Remove the decoration of line, returning it.
+    pub fn remove(&mut self, line:usize) -> Option<LineDecoration> {
+        self.decorations.remove(&line)
+    }
+
+    /// Remove all decorations.
+    pub fn clear(&mut self) {
+        self.decorations.clear()
+    }
+
+    /// Get the decoration of line.
+    pub fn get(&self, line:usize) -> Option<&LineDecoration> {
+        self.decorations.get(&line)
+    }
+
+    /// Iterate over decorated lines in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item=(usize,&LineDecoration)> {
+        self.decorations.iter().map(|(line,decoration)| (*line,decoration))
+    }
+
+    /// Move the decorations after the change of text. The decoration of the line where the
+    /// replaced fragment starts stays in place, unless whole lines are inserted before the line's
+    /// content, e.g. by the new line typed at its beginning.
+    pub fn update_after_change(&mut self, change:&TextChange) {
+        let start          = change.replaced.start;
+        let removed_lines  = change.replaced.end.line - start.line;
+        let inserted_lines = change.lines.len().saturating_sub(1);
+        let ends_in_empty  = change.lines.last().map_or(false, Vec::is_empty);
+        let start_moves    = start.column == 0 && removed_lines == 0 && inserted_lines > 0
+            && ends_in_empty;
+        let first_moved    = if start_moves {start.line} else {change.replaced.end.line + 1};
+        let below          = self.decorations.split_off(&first_moved.min(start.line + 1));
+        let moved          = below.into_iter().filter(|(line,_)| *line >= first_moved);
+        let moved          = moved.map(|(line,dec)| (line + inserted_lines - removed_lines,dec));
+        self.decorations.extend(moved);
+    }
+
+    /// Drop the decorations of lines past the content's end.
+    pub fn truncate(&mut self, lines_count:usize) {
+        self.decorations.split_off(&lines_count);
+    }
+}
+
+
+
+// ==============
+// === Layout ===
+// ==============
+
+/// The maximum number of characters displayed in gutter line.
+pub const MAX_LABEL_LENGTH:usize = 12;
+
+/// The minimum number of digits reserved for line numbers.
+const MIN_DIGITS:usize = 2;
+
+/// The number of characters before the line number: the decoration's icon and a space.
+const ICON_LENGTH:usize = 2;
+
+/// A line number displayed in gutter.
+#[derive(Clone,Debug,PartialEq)]
+pub struct GutterEntry<'a> {
+    /// The index of line.
+    pub line       : usize,
+    /// The visual row where the line number is displayed, being the first row of line.
+    pub row        : usize,
+    /// The line's decoration.
+    pub decoration : Option<&'a LineDecoration>,
+}
+
+impl<'a> GutterEntry<'a> {
+    /// The text displayed in gutter: the decoration's icon (or space), a space and the line
+    /// number aligned right to `digits` characters. Lines are numbered from 1.
+    pub fn label(&self, digits:usize) -> String {
+        let icon = self.decoration.map_or(' ', |decoration| decoration.icon);
+        format!("{} {:>digits$}",icon,self.line + 1,digits=digits)
+    }
+}
+
+/// The gutter's layout, computing which line numbers are displayed and where.
+///
+/// Line numbers are displayed in the first visual row of each line, so the continuation rows of
+/// soft-wrapped lines and the lines hidden in folded regions have none.
+#[derive(Clone,Copy,Debug)]
+pub struct GutterLayout {
+    /// Line height in pixels, the same as TextField's.
+    pub line_height : f32,
+    /// The width of digit in pixels.
+    pub char_width  : f32,
+}
+
+impl GutterLayout {
+    /// The number of digits of the largest line number.
+    pub fn digits(lines_count:usize) -> usize {
+        lines_count.to_string().len().max(MIN_DIGITS)
+    }
+
+    /// The width of gutter in pixels, with a one-character margin separating it from the text.
+    pub fn width(&self, lines_count:usize) -> f32 {
+        let label_length = (ICON_LENGTH + Self::digits(lines_count)).min(MAX_LABEL_LENGTH);
+        (label_length + 1) as f32 * self.char_width
+    }
+
+    /// The range of visual rows displayed in the view of given height, where `scroll_offset_y`
+    /// is the y coordinate of view's top (being non-positive, as rows go down from 0).
+    pub fn visible_rows(&self, scroll_offset_y:f32, view_height:f32, rows_count:usize)
+    -> Range<usize> {
+        let first = (-scroll_offset_y / self.line_height).floor().max(0.0) as usize;
+        let end   = ((view_height - scroll_offset_y) / self.line_height).ceil().max(0.0) as usize;
+        first.min(rows_count)..end.min(rows_count)
+    }
+
+    /// The line numbers displayed in given visual rows.
+    pub fn entries<'a>
+    (&self, lines:&LineRope, rows:Range<usize>, decorations:&'a LineDecorations)
+    -> Vec<GutterEntry<'a>> {
+        rows.filter_map(|row| {
+            let (line,line_row) = lines.row_to_line_and_row(row);
+            let decoration      = decorations.get(line);
+            (line_row == 0).as_some(GutterEntry {line,row,decoration})
+        }).collect()
+    }
+
+    /// The baseline start of gutter label in given visual row, matching the text's baseline (see
+    /// `LineFullInfo::baseline_start`).
+    pub fn baseline_start(&self, row:usize) -> Vector2<f32> {
+        Vector2::new(0.0, (-(row as f32) - 0.85) * self.line_height)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::display::shape::text::text_field::content::line::Line;
+
+    use data::text::TextLocation;
+
+    fn decoration(icon:char) -> LineDecoration {
+        LineDecoration::new(icon,Vector4::new(1.0,0.0,0.0,1.0),"")
+    }
+
+    fn decorated_lines(decorations:&LineDecorations) -> Vec<(usize,char)> {
+        decorations.iter().map(|(line,decoration)| (line,decoration.icon)).collect()
+    }
+
+    #[test]
+    fn moving_decorations_after_edits() {
+        let mut decorations = LineDecorations::default();
+        decorations.set(1,decoration('a'));
+        decorations.set(3,decoration('b'));
+        decorations.set(5,decoration('c'));
+
+        let at = |line,column| TextLocation {line,column};
+        decorations.update_after_change(&TextChange::insert(at(0,2),"x\ny\n"));
+        assert_eq!(decorated_lines(&decorations), vec![(3,'a'),(5,'b'),(7,'c')]);
+        decorations.update_after_change(&TextChange::delete(at(3,1)..at(5,0)));
+        assert_eq!(decorated_lines(&decorations), vec![(3,'a'),(5,'c')]);
+        decorations.update_after_change(&TextChange::replace(at(3,0)..at(3,1),"z"));
+        assert_eq!(decorated_lines(&decorations), vec![(3,'a'),(5,'c')]);
+        decorations.update_after_change(&TextChange::insert(at(3,0),"\n"));
+        assert_eq!(decorated_lines(&decorations), vec![(4,'a'),(6,'c')]);
+        decorations.truncate(5);
+        assert_eq!(decorated_lines(&decorations), vec![(4,'a')]);
+    }
+
+    #[test]
+    fn laying_out_line_numbers() {
+        let layout = GutterLayout {line_height:10.0, char_width:2.0};
+        assert_eq!(layout.visible_rows(0.0,35.0,10)  , 0..4);
+        assert_eq!(layout.visible_rows(-15.0,35.0,10), 1..5);
+        assert_eq!(layout.visible_rows(-80.0,35.0,10), 8..10);
+        assert_eq!(GutterLayout::digits(9)   , 2);
+        assert_eq!(GutterLayout::digits(120) , 3);
+        assert_eq!(layout.width(120)         , 12.0);
+
+        let mut lines : LineRope = (0..5).map(|_| Line::new("abc")).collect();
+        lines.modify(1, |line| line.set_rows(vec![0..2,2..3]));
+        lines.modify(3, |line| line.set_hidden(true));
+        let mut decorations = LineDecorations::default();
+        decorations.set(2,decoration('!'));
+
+        let entries = layout.entries(&lines,0..5,&decorations);
+        let rows    = entries.iter().map(|entry| (entry.line,entry.row)).collect_vec();
+        let labels  = entries.iter().map(|entry| entry.label(2)).collect_vec();
+        assert_eq!(rows  , vec![(0,0),(1,1),(2,3),(4,4)]);
+        assert_eq!(labels, vec!["   1","   2","!  3","   5"]);
+    }
+}
@@ -0,0 +1,276 @@
+//! This module contains the history of TextField edits, allowing to undo and redo them.
+
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::cursor::Cursor;
+
+use data::text::TextChange;
+
+
+
+// ======================
+// === RecordedChange ===
+// ======================
+
+/// A change applied to the TextField content, together with the change reverting it.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct RecordedChange {
+    /// The applied change.
+    pub change  : TextChange,
+    /// The change reverting `change`.
+    pub inverse : TextChange,
+}
+
+impl RecordedChange {
+    /// Record the change which is about to be applied to the given content.
+    pub fn new(change:TextChange, content:&TextFieldContent) -> Self {
+        let removed = content.copy_fragment(change.replaced.clone());
+        let inverse = TextChange::replace(change.inserted_text_range(),&removed);
+        RecordedChange {change,inverse}
+    }
+
+    /// Check if the change does not modify the content.
+    pub fn is_noop(&self) -> bool {
+        self.change == self.inverse
+    }
+}
+
+
+
+// ============
+// === Edit ===
+// ============
+
+/// The kind of edit, used to decide which edits are undone together.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum EditKind {
+    /// Typing a single character by each cursor. The typed characters are coalesced into words.
+    Typing,
+    /// Any other edit.
+    Other,
+}
+
+/// A single unit of undo and redo: the changes made by one user action, with the cursors' state
+/// before and after it.
+#[derive(Clone,Debug)]
+pub struct Edit {
+    /// The kind of the edit.
+    pub kind           : EditKind,
+    /// The changes, in order of their application.
+    pub changes        : Vec<RecordedChange>,
+    /// The cursors before the edit.
+    pub cursors_before : Vec<Cursor>,
+    /// The cursors after the edit.
+    pub cursors_after  : Vec<Cursor>,
+}
+
+impl Edit {
+    /// The changes reverting this edit, in order they should be applied.
+    pub fn undo_changes(&self) -> Vec<TextChange> {
+        self.changes.iter().rev().map(|change| change.inverse.clone()).collect()
+    }
+
+    /// The changes repeating this edit, in order they should be applied.
+    pub fn redo_changes(&self) -> Vec<TextChange> {
+        self.changes.iter().map(|change| change.change.clone()).collect()
+    }
+
+    /// The character typed in this edit, if it's a typing edit.
+    fn typed_char(&self) -> Option<char> {
+        let is_typing = self.kind == EditKind::Typing;
+        let change    = self.changes.first().filter(|_| is_typing);
+        change.and_then(|change| change.change.lines.first()?.first().cloned())
+    }
+
+    /// Check if the `next` edit continues this one, so they should be undone together. This is
+    /// the case of typing characters of one word, where a word ends with any non-word characters
+    /// typed after it.
+    fn is_continued_by(&self, next:&Edit) -> bool {
+        let is_word_char = |c:char| c.is_alphanumeric() || c == '_';
+        let continues    = match (self.typed_char(),next.typed_char()) {
+            (Some(last),Some(next)) => is_word_char(last) || !is_word_char(next),
+            _                       => false,
+        };
+        continues && self.cursors_after == next.cursors_before
+    }
+}
+
+
+
+// ===============
+// === History ===
+// ===============
+
+/// The history of edits made in TextField.
+#[derive(Debug,Default)]
+pub struct History {
+    undo_stack : Vec<Edit>,
+    redo_stack : Vec<Edit>,
+}
+
+impl History {
+    /// Record a new edit. The edits which were undone cannot be redone anymore.
+    ///
+    /// Edits without any actual change are ignored. The typed characters are coalesced into
+    /// words, see `EditKind::Typing`.
+    pub fn record(&mut self, edit:Edit) {
+        if !edit.changes.iter().all(RecordedChange::is_noop) {
+            self.redo_stack.clear();
+            match self.undo_stack.last_mut() {
+                Some(last) if last.is_continued_by(&edit) => {
+                    last.changes.extend(edit.changes);
+                    last.cursors_after = edit.cursors_after;
+                },
+                _ => self.undo_stack.push(edit),
+            }
+        }
+    }
+
+    /// Take the last edit to be undone. It can be redone afterwards.
+    pub fn undo(&mut self) -> Option<Edit> {
+        let edit = self.undo_stack.pop()?;
+        self.redo_stack.push(edit.clone());
+        Some(edit)
+    }
+
+    /// Take the last undone edit to be redone.
+    pub fn redo(&mut self) -> Option<Edit> {
+        let edit = self.redo_stack.pop()?;
+        self.undo_stack.push(edit.clone());
+        Some(edit)
+    }
+
+    /// Check if there is any edit to be undone.
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    /// Check if there is any edit to be redone.
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    /// Forget all the edits.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+
+    /// Drop the edits which cannot be undone or redone anymore, because the `change` was applied
+    /// to the content outside of the history, e.g. by another view of the same file.
+    ///
+    /// The latest edits are kept as long as all their changes are in lines above the changed
+    /// fragment, so the change does not affect them.
+    pub fn update_after_change(&mut self, change:&TextChange) {
+        let first_changed = change.replaced.start.line;
+        keep_unaffected_edits(&mut self.undo_stack,first_changed,Edit::undo_changes);
+        keep_unaffected_edits(&mut self.redo_stack,first_changed,Edit::redo_changes);
+    }
+}
+
+/// Keep only the edits on the top of `stack` which do not touch the `first_changed` line nor any
+/// line below. The edits would be applied in order from the top, each by its `changes`.
+fn keep_unaffected_edits
+(stack:&mut Vec<Edit>, mut first_changed:usize, changes:impl Fn(&Edit) -> Vec<TextChange>) {
+    let unaffected = stack.iter().rev().position(|edit| changes(edit).iter().any(|change| {
+        let affected = change.replaced.end.line >= first_changed;
+        if !affected {
+            let removed_lines = change.replaced.end.line - change.replaced.start.line;
+            first_changed     = first_changed + change.lines.len() - 1 - removed_lines;
+        }
+        affected
+    }));
+    if let Some(unaffected) = unaffected {
+        stack.drain(..stack.len() - unaffected);
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::shape::text::text_field::content::test::mock_properties;
+
+    use basegl_core_msdf_sys as msdf_sys;
+    use data::text::TextLocation;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    fn content_string(content:&TextFieldContent) -> String {
+        content.lines().iter().map(|line| line.to_string()).join("\n")
+    }
+
+    /// Type the text character by character at the cursor, recording each character as a
+    /// separate edit.
+    fn type_text
+    (content:&mut TextFieldContent, history:&mut History, cursor:&mut Cursor, text:&str) {
+        for c in text.chars() {
+            let change         = TextChange::insert(cursor.position,&c.to_string());
+            let cursors_before = vec![*cursor];
+            *cursor            = Cursor::new(change.inserted_text_range().end);
+            let cursors_after  = vec![*cursor];
+            let changes        = vec![RecordedChange::new(change.clone(),content)];
+            content.apply_change(change);
+            let kind = EditKind::Typing;
+            history.record(Edit {kind,changes,cursors_before,cursors_after});
+        }
+    }
+
+    fn undo(content:&mut TextFieldContent, history:&mut History) -> Edit {
+        let edit = history.undo().unwrap();
+        edit.undo_changes().into_iter().for_each(|change| content.apply_change(change));
+        edit
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn undoing_typed_words() {
+        msdf_sys::initialized().await;
+        let mut content = TextFieldContent::new("",&mock_properties());
+        let mut history = History::default();
+        let mut cursor  = Cursor::new(TextLocation::at_document_begin());
+        type_text(&mut content,&mut history,&mut cursor,"foo bar baz");
+        assert_eq!(content_string(&content), "foo bar baz");
+
+        let edit = undo(&mut content,&mut history);
+        assert_eq!(content_string(&content), "foo bar ");
+        assert_eq!(edit.cursors_before, vec![Cursor::new(TextLocation {line:0, column:8})]);
+        undo(&mut content,&mut history);
+        assert_eq!(content_string(&content), "foo ");
+        undo(&mut content,&mut history);
+        assert_eq!(content_string(&content), "");
+        assert!(!history.can_undo());
+
+        let edit = history.redo().unwrap();
+        edit.redo_changes().into_iter().for_each(|change| content.apply_change(change));
+        assert_eq!(content_string(&content), "foo ");
+        assert!(history.can_redo());
+
+        cursor = edit.cursors_after[0];
+        type_text(&mut content,&mut history,&mut cursor,"x");
+        assert!(!history.can_redo());
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn keeping_edits_above_other_changes() {
+        msdf_sys::initialized().await;
+        let mut content = TextFieldContent::new("\n\n",&mock_properties());
+        let mut history = History::default();
+        let mut cursor  = Cursor::new(TextLocation {line:2, column:0});
+        type_text(&mut content,&mut history,&mut cursor,"bar");
+        let mut cursor  = Cursor::new(TextLocation::at_document_begin());
+        type_text(&mut content,&mut history,&mut cursor,"foo");
+
+        let change = TextChange::insert(TextLocation::at_line_begin(1),"new\n");
+        history.update_after_change(&change);
+        content.apply_change(change);
+        assert_eq!(content_string(&content), "foo\nnew\n\nbar");
+        undo(&mut content,&mut history);
+        assert_eq!(content_string(&content), "\nnew\n\nbar");
+        assert!(!history.can_undo());
+    }
+}
@@ -61,6 +61,22 @@ impl TextLocationChange {
 }
 
 
+/// Return the location marking the same place in text after the change. The locations inside the
+/// replaced fragment are moved to the end of the inserted text.
+pub fn moved_by_change(location:TextLocation, change:&TextChange) -> TextLocation {
+    let replaced = &change.replaced;
+    if location <= replaced.start {
+        location
+    } else if location < replaced.end {
+        change.inserted_text_range().end
+    } else {
+        let mut location_change = TextLocationChange::default();
+        location_change.add_change(change);
+        location_change.apply_to(location)
+    }
+}
+
+
 
 #[cfg(test)]
 mod test {
@@ -139,4 +155,15 @@ mod test {
         assert_eq!(5 , location_change.last_changed_line);
         assert_eq!(-2, location_change.column_offset);
     }
+
+    #[test]
+    fn moving_location_by_change() {
+        let at     = |line,column| TextLocation {line,column};
+        let change = TextChange::replace(at(1,2)..at(3,1),"x\nyz");
+        assert_eq!(at(0,5), moved_by_change(at(0,5),&change));
+        assert_eq!(at(1,2), moved_by_change(at(1,2),&change));
+        assert_eq!(at(2,2), moved_by_change(at(2,0),&change));
+        assert_eq!(at(2,4), moved_by_change(at(3,3),&change));
+        assert_eq!(at(3,3), moved_by_change(at(4,3),&change));
+    }
 }
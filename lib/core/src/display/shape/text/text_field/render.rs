@@ -1,6 +1,7 @@
 //! Rendering TextField.
 
 pub mod assignment;
+pub mod gutter;
 pub mod selection;
 
 use crate::prelude::*;
@@ -11,6 +12,7 @@ use crate::display::shape::text::glyph::system::GlyphSystem;
 use crate::display::shape::text::text_field::content::TextFieldContent;
 use crate::display::shape::text::text_field::cursor::Cursor;
 use crate::display::shape::text::text_field::cursor::Cursors;
+use crate::display::shape::text::text_field::folding::FOLD_PLACEHOLDER;
 use crate::display::shape::text::text_field::render::assignment::GlyphLinesAssignment;
 use crate::display::shape::text::text_field::render::assignment::LineFragment;
 use crate::display::shape::text::text_field::render::selection::SelectionSpritesGenerator;
@@ -22,6 +24,9 @@ use crate::display::world::World;
 
 use nalgebra::Vector2;
 use nalgebra::Vector3;
+use nalgebra::Vector4;
+use std::cmp::max;
+use std::cmp::min;
 
 
 
@@ -187,24 +192,44 @@ impl TextFieldSprites {
     (glyph_line:&mut GlyphLine, fragment:&LineFragment, content:&mut TextFieldContent) {
         let bsl_start     = Self::baseline_start_for_fragment(fragment,content);
         let line          = &content.lines()[fragment.line_index];
-        let chars         = &line.chars()[fragment.chars_range.clone()];
+        let chars_range   = fragment.chars_range.clone();
+        let chars         = &line.chars()[chars_range.clone()];
+        let is_line_end   = chars_range.end == line.len();
+        let placeholder   = if is_line_end && content.is_folded(fragment.line_index) {
+            FOLD_PLACEHOLDER
+        } else {
+            ""
+        };
         glyph_line.set_baseline_start(bsl_start);
-        glyph_line.replace_text(chars.iter().cloned());
+        glyph_line.replace_text(chars.iter().cloned().chain(placeholder.chars()));
+        for span in line.highlighting() {
+            let start = max(span.columns.start,chars_range.start) - chars_range.start;
+            let end   = min(span.columns.end,chars_range.end).saturating_sub(chars_range.start);
+            glyph_line.set_color(start..end,span.color);
+        }
+        if !placeholder.is_empty() {
+            let placeholder_color = Vector4::new(0.5,0.5,0.5,1.0);
+            let placeholder_start = chars.len();
+            let placeholder_end   = placeholder_start + placeholder.chars().count();
+            glyph_line.set_color(placeholder_start..placeholder_end,placeholder_color);
+        }
     }
 
     /// The baseline start for given line's fragment.
     ///
     /// Because we're not rendering the whole lines, but only visible fragment of it (with some
     /// margin), the baseline used for placing glyph don't start on the line begin, but at the
-    /// position of first char of fragment.
+    /// position of first char of fragment, relative to the beginning of fragment's visual row.
     fn baseline_start_for_fragment(fragment:&LineFragment, content:&mut TextFieldContent)
     -> Vector2<f32> {
         let mut line = content.line(fragment.line_index);
+        let row      = fragment.row - line.first_row;
+        let baseline = line.row_baseline_start(row);
         if fragment.chars_range.start >= line.chars().len() {
-            line.baseline_start()
+            baseline
         } else {
-            let x = line.get_char_x_position(fragment.chars_range.start);
-            let y = line.baseline_start().y;
+            let x = line.get_char_x_position(fragment.chars_range.start) - line.row_x_offset(row);
+            let y = baseline.y;
             Vector2::new(x,y)
         }
     }
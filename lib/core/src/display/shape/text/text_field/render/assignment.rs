@@ -17,10 +17,11 @@ use std::ops::RangeInclusive;
 /// === LineFragment ===
 /// =====================
 
-/// Struct describing specific one line's fragment.
+/// Struct describing specific one line's fragment, displayed in one visual row.
 #[derive(Clone,Debug,Eq,PartialEq)]
 #[allow(missing_docs)]
 pub struct LineFragment {
+    pub row         : usize,
     pub line_index  : usize,
     pub chars_range : Range<usize>,
 }
@@ -30,10 +31,14 @@ impl LineFragment {
     pub fn covers_displayed_range
     (&self, displayed_range:&RangeInclusive<f32>, content:&mut TextFieldContent) -> bool {
         let mut line       = content.line(self.line_index);
-        let front_rendered = self.chars_range.start == 0;
-        let back_rendered  = self.chars_range.end == line.len();
-        let x_range_start  = line.get_char_x_position(self.chars_range.start);
-        let x_range_end    = line.get_char_x_range(self.chars_range.end.saturating_sub(1)).end;
+        let row            = self.row - line.first_row;
+        let row_range      = line.row_range(row);
+        let row_x_offset   = line.row_x_offset(row);
+        let front_rendered = self.chars_range.start == row_range.start;
+        let back_rendered  = self.chars_range.end == row_range.end;
+        let last_char      = self.chars_range.end.saturating_sub(1);
+        let x_range_start  = line.get_char_x_position(self.chars_range.start) - row_x_offset;
+        let x_range_end    = line.get_char_x_range(last_char).end - row_x_offset;
 
         let from_left  = front_rendered || *displayed_range.start() >= x_range_start;
         let from_right = back_rendered  || *displayed_range.end()   <= x_range_end;
@@ -53,11 +58,14 @@ impl LineFragment {
 /// _glyph lines_ which are sets of sprites designed to render text. So, each _glyph line_ has
 /// assigned line of text which it should render. We don't render the whole text, additionally after
 /// scroll we try to reassign only some of _glyph lines_ to make a minimum gpu data update.
+///
+/// The _glyph lines_ are actually assigned to _visual rows_, which are the same as lines unless
+/// the lines are soft-wrapped.
 #[derive(Debug)]
 pub struct GlyphLinesAssignment {
     /// The assigned line fragments for specific _glyph line_.
     pub glyph_lines_fragments: Vec<Option<LineFragment>>,
-    /// The range of currently assigned _lines_.
+    /// The range of currently assigned _visual rows_.
     pub assigned_lines: RangeInclusive<usize>,
     /// List of dirty _glyph lines_ after updating assignments. Once those will be refreshed, the
     /// this set should be cleared.
@@ -110,9 +118,9 @@ pub struct GlyphLinesAssignmentUpdate<'a,'b> {
 }
 
 impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
-    /// Reassign _glyph line_ to currently displayed fragment of line.
-    pub fn reassign(&mut self, glyph_line_id:usize, line_id:usize) {
-        let fragment = self.displayed_fragment(line_id);
+    /// Reassign _glyph line_ to currently displayed fragment of visual row.
+    pub fn reassign(&mut self, glyph_line_id:usize, row:usize) {
+        let fragment = self.displayed_fragment(row);
         self.assignment.glyph_lines_fragments[glyph_line_id] = Some(fragment);
         self.assignment.dirty_glyph_lines.insert(glyph_line_id);
     }
@@ -149,9 +157,9 @@ impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
         let updated_count = updated_count.min(self.assignment.glyph_lines_count());
         for glyph_line_id in 0..self.assignment.glyph_lines_count() {
             if self.should_be_updated_after_x_scroll(glyph_line_id,updated_count) {
-                let fragment   = self.assignment.glyph_lines_fragments[glyph_line_id].as_ref();
-                let line_index = fragment.unwrap().line_index;
-                self.reassign(glyph_line_id,line_index);
+                let fragment = self.assignment.glyph_lines_fragments[glyph_line_id].as_ref();
+                let row      = fragment.unwrap().row;
+                self.reassign(glyph_line_id,row);
             }
         }
         self.assignment.increment_next_glyph_line_to_x_scroll_update(updated_count);
@@ -160,19 +168,22 @@ impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
     /// Update assigned fragments after text edit.
     ///
     /// Some new lines could be created after edit, and some lines can be longer, what should be
-    /// reflected in assigned fragments.
+    /// reflected in assigned fragments. When the edit changes the number of visual rows of some
+    /// line, all lines below are dirty, as they are displayed in other rows.
     pub fn update_after_text_edit(&mut self) {
         if self.content.dirty_lines.range.is_some() {
             self.update_line_assignment();
         }
         for i in 0..self.assignment.glyph_lines_fragments.len() {
             let assigned_fragment = &self.assignment.glyph_lines_fragments[i];
-            let assigned_line     = assigned_fragment.as_ref().map(|f| f.line_index);
-
-            match assigned_line {
-                Some(line) if line >= self.content.lines().len()      => self.unassign(i),
-                Some(line) if self.content.dirty_lines.is_dirty(line) => self.reassign(i,line),
-                _                                                     => {},
+            let assigned          = assigned_fragment.as_ref().map(|f| (f.row,f.line_index));
+            let rows_count        = self.content.rows_count();
+            let dirty_lines       = &self.content.dirty_lines;
+
+            match assigned {
+                Some((row,_))    if row >= rows_count          => self.unassign(i),
+                Some((row,line)) if dirty_lines.is_dirty(line) => self.reassign(i,row),
+                _                                              => {},
             }
         }
     }
@@ -182,12 +193,12 @@ impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
 // === Private functions ===
 
 impl GlyphLinesAssignment {
-    /// Check if given _glyph line_ could be reassigned to another line assuming some set
-    /// of visible lines.
-    fn can_be_reassigned(&self, glyph_line_id:usize, displayed_lines:&RangeInclusive<usize>)
+    /// Check if given _glyph line_ could be reassigned to another visual row assuming some set
+    /// of visible rows.
+    fn can_be_reassigned(&self, glyph_line_id:usize, displayed_rows:&RangeInclusive<usize>)
     -> bool {
         match &self.glyph_lines_fragments[glyph_line_id] {
-            Some(fragment) => !displayed_lines.contains(&fragment.line_index),
+            Some(fragment) => !displayed_rows.contains(&fragment.row),
             None           => true
         }
     }
@@ -202,21 +213,25 @@ impl GlyphLinesAssignment {
 }
 
 impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
-    /// Returns LineFragment of specific line which is currently visible.
-    fn displayed_fragment(&mut self, line_id:usize) -> LineFragment {
+    /// Returns LineFragment of specific visual row which is currently visible.
+    fn displayed_fragment(&mut self, row:usize) -> LineFragment {
+        let (line_id,line_row)   = self.content.row_to_line_and_row(row);
         let mut line             = self.content.line(line_id);
-        let max_index            = line.len().saturating_sub(self.assignment.max_glyphs_in_line);
+        let row_range            = line.row_range(line_row);
+        let row_x_offset         = line.row_x_offset(line_row);
+        let max_glyphs           = self.assignment.max_glyphs_in_line;
+        let max_index            = row_range.end.saturating_sub(max_glyphs).max(row_range.start);
         let displayed_from_x     = self.scroll_offset.x - self.assignment.x_margin;
-        let first_displayed      = line.find_char_at_x_position(displayed_from_x);
+        let first_displayed      = line.find_char_at_x_position(displayed_from_x + row_x_offset);
         let line_front_displayed = self.scroll_offset.x <= 0.0;
 
         let start = match first_displayed {
-            Some(index)                  => index.min(max_index),
-            None if line_front_displayed => 0,
+            Some(index)                  => index.max(row_range.start).min(max_index),
+            None if line_front_displayed => row_range.start,
             None                         => max_index
         };
-        let end = (start + self.assignment.max_glyphs_in_line).min(line.len());
-        LineFragment {
+        let end = (start + max_glyphs).min(row_range.end);
+        LineFragment {row,
             line_index: line_id,
             chars_range: start..end,
         }
@@ -227,7 +242,7 @@ impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
     fn new_assignment(&mut self) -> RangeInclusive<usize> {
         let visible_lines         = self.visible_lines_range();
         let assigned_lines        = &self.assignment.assigned_lines;
-        let max_line_id           = self.content.lines().len().saturating_sub(1);
+        let max_line_id           = self.content.rows_count().saturating_sub(1);
         let lines_count           = |r:&RangeInclusive<usize>| r.end() + 1 - r.start();
         let assigned_lines_count  = lines_count(assigned_lines);
         let displayed_lines_count = lines_count(&visible_lines);
@@ -243,16 +258,14 @@ impl<'a,'b> GlyphLinesAssignmentUpdate<'a,'b> {
         }
     }
 
-    /// Returns range of currently visible lines.
+    /// Returns range of currently visible rows.
     fn visible_lines_range(&mut self) -> RangeInclusive<usize> {
-        let lines_count              = self.content.lines().len();
-        let top                      = self.scroll_offset.y;
-        let bottom                   = self.scroll_offset.y - self.view_size.y;
-        let top_line_clipped         = self.content.line_at_y_position(top);
-        let first_line_index         = top_line_clipped.map_or(0, |l| l.line_id);
-        let bottom_line_clipped      = self.content.line_at_y_position(bottom);
-        let last_line_index          = bottom_line_clipped.map_or(lines_count-1,|l| l.line_id);
-        first_line_index..=last_line_index
+        let rows_count = self.content.rows_count();
+        let top        = self.scroll_offset.y;
+        let bottom     = self.scroll_offset.y - self.view_size.y;
+        let first_row  = self.content.row_at_y_position(top).unwrap_or(0);
+        let last_row   = self.content.row_at_y_position(bottom).unwrap_or(rows_count-1);
+        first_row..=last_row
     }
 
     /// Check if given _glyph line_ should be updated after x scroll.
@@ -331,10 +344,10 @@ mod tests {
         };
         update.update_line_assignment();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 0..4})
-            , Some(LineFragment{line_index:2, chars_range: 0..0})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..4})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 0..0})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         let expected_dirties : HashSet<usize> = [0,1,2,3].iter().cloned().collect();
 
@@ -350,10 +363,10 @@ mod tests {
 
         let mut assignment = GlyphLinesAssignment::new(4, 4, 10.0);
         assignment.glyph_lines_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 0..4})
-            , Some(LineFragment{line_index:2, chars_range: 0..0})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..4})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 0..0})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         let assigned_lines        = 0..=3;
         assignment.assigned_lines = assigned_lines;
@@ -369,10 +382,10 @@ mod tests {
         };
         update.update_line_assignment();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:4, chars_range: 0..1})
-            , Some(LineFragment{line_index:5, chars_range: 0..2})
-            , Some(LineFragment{line_index:2, chars_range: 0..0})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:4, line_index:4, chars_range: 0..1})
+            , Some(LineFragment{row:5, line_index:5, chars_range: 0..2})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 0..0})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         let expected_dirties : HashSet<usize> = [0,1].iter().cloned().collect();
         assert_eq!(expected_fragments, update.assignment.glyph_lines_fragments);
@@ -384,10 +397,10 @@ mod tests {
         update.scroll_offset = Vector2::new(22.0,-11.0);
         update.update_line_assignment();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:4, chars_range: 0..1})
-            , Some(LineFragment{line_index:1, chars_range: 0..4})
-            , Some(LineFragment{line_index:2, chars_range: 0..0})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:4, line_index:4, chars_range: 0..1})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..4})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 0..0})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         let expected_dirties : HashSet<usize> = [1].iter().cloned().collect();
         assert_eq!(expected_fragments, update.assignment.glyph_lines_fragments);
@@ -403,10 +416,10 @@ mod tests {
 
         let mut assignment = GlyphLinesAssignment::new(4, 4, 10.0);
         assignment.glyph_lines_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 0..4})
-            , Some(LineFragment{line_index:2, chars_range: 1..5})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..4})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 1..5})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         assignment.next_glyph_line_to_x_scroll_update = 3;
         let mut update     = GlyphLinesAssignmentUpdate {
@@ -417,10 +430,10 @@ mod tests {
         };
         update.update_after_x_scroll(15.0);
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 2..6})
-            , Some(LineFragment{line_index:1, chars_range: 2..6})
-            , Some(LineFragment{line_index:2, chars_range: 1..5})
-            , Some(LineFragment{line_index:3, chars_range: 0..1})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 2..6})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 2..6})
+            , Some(LineFragment{row:2, line_index:2, chars_range: 1..5})
+            , Some(LineFragment{row:3, line_index:3, chars_range: 0..1})
             ];
         assert_eq!(expected_fragments, update.assignment.glyph_lines_fragments);
         assert_eq!(1                 , update.assignment.next_glyph_line_to_x_scroll_update);
@@ -433,8 +446,8 @@ mod tests {
 
         let mut assignment = GlyphLinesAssignment::new(3, 4, 10.0);
         assignment.glyph_lines_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 0..4})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..4})
             , None
             ];
         assignment.assigned_lines = 0..=1;
@@ -450,8 +463,8 @@ mod tests {
         update.content.dirty_lines.add_single_line(1);
         update.update_after_text_edit();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 0..2})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 0..2})
             , None
             ];
         let expected_dirties:HashSet<usize> = [1].iter().cloned().collect();
@@ -464,7 +477,7 @@ mod tests {
         update.content.dirty_lines.add_lines_range_from(1..);
         update.update_after_text_edit();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
             , None
             , None
             ];
@@ -478,8 +491,8 @@ mod tests {
         update.content.dirty_lines.add_lines_range_from(1..);
         update.update_after_text_edit();
         let expected_fragments = vec!
-            [ Some(LineFragment{line_index:0, chars_range: 1..5})
-            , Some(LineFragment{line_index:1, chars_range: 1..5})
+            [ Some(LineFragment{row:0, line_index:0, chars_range: 1..5})
+            , Some(LineFragment{row:1, line_index:1, chars_range: 1..5})
             , None
             ];
         let expected_dirties : HashSet<usize> = [1].iter().cloned().collect();
@@ -0,0 +1,132 @@
+//! Rendering the TextField's gutter.
+
+use crate::prelude::*;
+
+use crate::display;
+use crate::display::shape::text::glyph::system::GlyphSystem;
+use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::gutter::GutterLayout;
+use crate::display::shape::text::text_field::gutter::LineDecoration;
+use crate::display::shape::text::text_field::gutter::LineDecorations;
+use crate::display::shape::text::text_field::gutter::MAX_LABEL_LENGTH;
+use crate::display::shape::text::text_field::TextFieldProperties;
+use crate::display::world::World;
+
+use nalgebra::Vector2;
+use nalgebra::Vector3;
+use nalgebra::Vector4;
+
+
+
+// =====================
+// === GutterSprites ===
+// =====================
+
+/// Alias for line of glyph sprites, see `render::GlyphLine`.
+type GlyphLine = crate::display::shape::text::glyph::system::Line;
+
+/// Structure with all data and sprites required for rendering the gutter of TextField.
+///
+/// The gutter is placed left to the TextField's text. It follows the text's vertical scroll, but
+/// not the horizontal one. Each _glyph line_ displays the label of one visible line (see
+/// `GutterEntry::label`), so there are as many of them as rows fitting in the TextField.
+#[derive(Debug)]
+pub struct GutterSprites {
+    /// System used for rendering glyphs.
+    pub glyph_system: GlyphSystem,
+    /// All drawn glyph lines.
+    pub glyph_lines: Vec<GlyphLine>,
+    /// The layout of line numbers.
+    pub layout: GutterLayout,
+    /// Color of line numbers.
+    pub color: Vector4<f32>,
+    /// Whether the gutter is displayed.
+    pub visible: bool,
+    /// Display object of the whole gutter.
+    pub display_object: display::object::Node,
+}
+
+impl GutterSprites {
+    /// Create hidden gutter for TextField with given properties.
+    pub fn new(world:&World, properties:&TextFieldProperties) -> Self {
+        // The same number of displayed lines as `TextFieldSprites` has, see
+        // `create_assignment_structure`.
+        const ADDITIONAL:usize = 2;
+        let font               = properties.font.clone_ref();
+        let line_height        = properties.text_size;
+        let char_width         = font.get_glyph_info('0').advance * line_height;
+        let layout             = GutterLayout {line_height,char_width};
+        let base_color         = properties.base_color;
+        let color              = Vector4::new(base_color.x,base_color.y,base_color.z,0.5);
+        let mut glyph_system   = GlyphSystem::new(world,font);
+        let display_object     = display::object::Node::new(Logger::new("Gutter"));
+        display_object.add_child(&glyph_system);
+
+        let lines_count = (properties.size.y / line_height).floor() as usize + ADDITIONAL;
+        let bsl_start   = Vector2::new(0.0,0.0);
+        let glyph_lines = (0..lines_count).map(|_| {
+            glyph_system.new_empty_line(bsl_start,line_height,MAX_LABEL_LENGTH,color)
+        }).collect();
+        let visible = false;
+        GutterSprites {glyph_system,glyph_lines,layout,color,visible,display_object}
+    }
+
+    /// The width of gutter in pixels, or 0 if it is hidden.
+    pub fn width(&self, content:&TextFieldContent) -> f32 {
+        if self.visible { self.layout.width(content.lines().len()) } else { 0.0 }
+    }
+
+    /// Update the displayed line numbers and decorations. The `scroll_offset` is the scroll
+    /// position of TextField's text, and `view_height` is the TextField's height.
+    pub fn update
+    ( &mut self
+    , content       : &TextFieldContent
+    , decorations   : &LineDecorations
+    , scroll_offset : Vector2<f32>
+    , view_height   : f32
+    ) {
+        let width = self.width(content);
+        self.display_object.set_position(Vector3::new(-width,-scroll_offset.y,0.0));
+        let entries = if self.visible {
+            let rows = self.layout.visible_rows(scroll_offset.y,view_height,content.rows_count());
+            self.layout.entries(content.lines(),rows,decorations)
+        } else {
+            default()
+        };
+        let digits = GutterLayout::digits(content.lines().len());
+        for (index,glyph_line) in self.glyph_lines.iter_mut().enumerate() {
+            match entries.get(index) {
+                Some(entry) => {
+                    let label = entry.label(digits);
+                    glyph_line.set_baseline_start(self.layout.baseline_start(entry.row));
+                    glyph_line.replace_text(label.chars());
+                    if let Some(decoration) = entry.decoration {
+                        glyph_line.set_color(0..1,decoration.color);
+                    }
+                }
+                None => glyph_line.replace_text("".chars()),
+            }
+        }
+        self.display_object.update();
+    }
+
+    /// Get the decoration displayed at given point, being relative to the gutter's position.
+    pub fn decoration_at_point<'a>
+    (&self, content:&TextFieldContent, decorations:&'a LineDecorations, point:Vector2<f32>)
+    -> Option<&'a LineDecoration> {
+        let width    = self.width(content);
+        let is_valid = self.visible && point.x >= 0.0 && point.x < width;
+        let row      = is_valid.and_option_from(|| content.row_at_y_position(point.y))?;
+        let (line,_) = content.row_to_line_and_row(row);
+        decorations.get(line)
+    }
+}
+
+
+// === DisplayObject ===
+
+impl From<&GutterSprites> for display::object::Node {
+    fn from(gutter:&GutterSprites) -> Self {
+        gutter.display_object.clone_ref()
+    }
+}
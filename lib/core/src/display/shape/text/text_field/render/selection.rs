@@ -27,14 +27,19 @@ pub struct SelectionSpritesGenerator<'a,'b> {
 
 impl<'a,'b> SelectionSpritesGenerator<'a,'b> {
     /// Generate sprites for given selection.
+    ///
+    /// The sprites cover visual rows, so when the lines are soft-wrapped a selection inside one
+    /// line may span many rows.
     pub fn generate(&mut self, selection : &Range<TextLocation>) -> Vec<Sprite> {
         let mut return_value = Vec::new();
-        if selection.start.line < selection.end.line {
+        let start_row        = self.content.row_of_location(selection.start);
+        let end_row          = self.content.row_of_location(selection.end);
+        if start_row < end_row {
             return_value.push(self.top_line_of_multiline_selection(&selection));
             return_value.push(self.bottom_line_of_multiline_selection(&selection));
-            if selection.end.line - selection.start.line > 1 {
-                let lines = (selection.start.line+1)..selection.end.line;
-                return_value.push(self.whole_line_selection_block(&lines));
+            if end_row - start_row > 1 {
+                let rows_count = end_row - start_row - 1;
+                return_value.push(self.whole_line_selection_block(&selection.start,rows_count));
             }
         } else if selection.start.column < selection.end.column {
             return_value.push(self.single_line_selection(&selection))
@@ -81,14 +86,13 @@ impl<'a,'b> SelectionSpritesGenerator<'a,'b> {
         sprite
     }
 
-    fn whole_line_selection_block(&mut self, lines:&Range<usize>) -> Sprite {
-        let lines_count = lines.end - lines.start;
+    /// The block covering `rows_count` whole visual rows below the row of `above` location.
+    fn whole_line_selection_block(&mut self, above:&TextLocation, rows_count:usize) -> Sprite {
         let width  = Self::FULL_LINE_WIDTH;
-        let height = (lines_count as f32) * self.line_height;
+        let height = (rows_count as f32) * self.line_height;
         let x      = width/2.0;
-        let bottom = TextLocation::at_line_begin(lines.end - 1);
-        let min_y  = Cursor::render_position(&bottom,self.content).y - self.line_height / 2.0;
-        let y      = min_y + height/2.0;
+        let max_y  = Cursor::render_position(above,self.content).y - self.line_height / 2.0;
+        let y      = max_y - height/2.0;
         let size   = Vector2::new(width,height);
         let sprite = self.system.new_instance();
         sprite.set_position(Vector3::new(x,y,-1.0));
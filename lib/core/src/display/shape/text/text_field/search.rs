@@ -0,0 +1,266 @@
+//! This module contains the search engine used for finding and replacing text in TextField.
+//!
+//! The query is described by `SearchQuery` and compiled to `Search`, which finds all matches in
+//! `TextFieldContent`. Literal, case-insensitive, whole-word and regular expression queries are
+//! supported, as all of them are compiled to a regular expression.
+
+use crate::prelude::*;
+
+use crate::display::shape::text::text_field::content::TextFieldContent;
+
+use data::text::TextLocation;
+use regex::Regex;
+use regex::RegexBuilder;
+use std::ops::Range;
+
+
+
+// ===================
+// === SearchQuery ===
+// ===================
+
+/// Error returned when the search query cannot be compiled.
+#[derive(Clone,Debug,Fail)]
+#[fail(display = "Invalid search query {:?}: {}", pattern, message)]
+pub struct InvalidQuery {
+    /// The pattern of the query.
+    pub pattern : String,
+    /// Description of the problem.
+    pub message : String,
+}
+
+/// Description of searched text.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct SearchQuery {
+    /// Searched text or regular expression.
+    pub pattern        : String,
+    /// If true, the `pattern` is a regular expression in syntax of the `regex` crate.
+    pub is_regex       : bool,
+    /// If false, the letters match regardless of their case.
+    pub case_sensitive : bool,
+    /// If true, only matches being whole words are found. Words are considered to be composed of
+    /// alphanumeric characters and underscores.
+    pub whole_word     : bool,
+}
+
+impl SearchQuery {
+    /// Query for the given literal text.
+    pub fn literal(pattern:impl Str) -> Self {
+        let pattern        = pattern.into();
+        let is_regex       = false;
+        let case_sensitive = true;
+        let whole_word     = false;
+        SearchQuery {pattern,is_regex,case_sensitive,whole_word}
+    }
+
+    /// Query for the given regular expression.
+    pub fn regex(pattern:impl Str) -> Self {
+        SearchQuery {is_regex:true, ..Self::literal(pattern)}
+    }
+
+    /// The same query, but matching letters regardless of their case.
+    pub fn case_insensitive(self) -> Self {
+        SearchQuery {case_sensitive:false, ..self}
+    }
+
+    /// The same query, but matching only whole words.
+    pub fn whole_word(self) -> Self {
+        SearchQuery {whole_word:true, ..self}
+    }
+
+    /// Compile query, so it can be used for searching.
+    pub fn compile(&self) -> Result<Search,InvalidQuery> {
+        let escaped = regex::escape(&self.pattern);
+        let pattern = if self.is_regex { self.pattern.clone() } else { escaped };
+        let pattern = if self.whole_word { format!(r"\b(?:{})\b",pattern) } else { pattern };
+        let builder = RegexBuilder::new(&pattern).case_insensitive(!self.case_sensitive).build();
+        let regex   = builder.map_err(|err| InvalidQuery {
+            pattern : self.pattern.clone(),
+            message : err.to_string(),
+        })?;
+        let query = self.clone();
+        Ok(Search {query,regex})
+    }
+}
+
+
+
+// ==============
+// === Search ===
+// ==============
+
+/// A single match of `Search`, with the text which should replace it.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Replacement {
+    /// The matched text fragment.
+    pub range : Range<TextLocation>,
+    /// The text to be put in place of the match.
+    pub text  : String,
+}
+
+/// Compiled `SearchQuery`.
+#[derive(Clone,Debug)]
+pub struct Search {
+    query : SearchQuery,
+    regex : Regex,
+}
+
+impl Search {
+    /// The query this search was compiled from.
+    pub fn query(&self) -> &SearchQuery {
+        &self.query
+    }
+
+    /// Find all non-empty matches in the content, in order of their appearance.
+    pub fn find_all(&self, content:&TextFieldContent) -> Vec<Range<TextLocation>> {
+        let text    = content_to_string(content);
+        let matches = self.regex.find_iter(&text).map(|m| m.range());
+        to_locations(content,&text,matches)
+    }
+
+    /// Find the first match starting at `location` or after it. If there is none, the search
+    /// wraps around and the first match in the content is returned.
+    pub fn find_next(&self, content:&TextFieldContent, location:TextLocation)
+    -> Option<Range<TextLocation>> {
+        let matches   = self.find_all(content);
+        let mut after = matches.iter().filter(|range| range.start >= location);
+        after.next().or_else(|| matches.first()).cloned()
+    }
+
+    /// Find all non-empty matches in the content with the text replacing them.
+    ///
+    /// For literal queries the `replacement` is used as is. For regular expressions it may
+    /// refer to capture groups, e.g. `$1` or `${name}`.
+    pub fn replacements(&self, content:&TextFieldContent, replacement:&str) -> Vec<Replacement> {
+        let text         = content_to_string(content);
+        let captures     = self.regex.captures_iter(&text).collect_vec();
+        let whole_ranges = captures.iter().map(|c| c.get(0).unwrap().range());
+        let ranges       = to_locations(content,&text,whole_ranges);
+        let non_empty    = captures.iter().filter(|c| !c.get(0).unwrap().as_str().is_empty());
+        let texts        = non_empty.map(|captures| {
+            if self.query.is_regex {
+                let mut text = String::new();
+                captures.expand(replacement,&mut text);
+                text
+            } else {
+                replacement.to_string()
+            }
+        });
+        ranges.into_iter().zip(texts).map(|(range,text)| Replacement {range,text}).collect()
+    }
+}
+
+
+
+// =============
+// === Utils ===
+// =============
+
+fn content_to_string(content:&TextFieldContent) -> String {
+    content.lines().iter().map(|line| line.to_string()).join("\n")
+}
+
+/// Converts ranges of byte indices in `text` to text locations, skipping the empty ones. The
+/// ranges must be sorted and not overlapping.
+fn to_locations
+(content:&TextFieldContent, text:&str, byte_ranges:impl Iterator<Item=Range<usize>>)
+-> Vec<Range<TextLocation>> {
+    let mut byte_index = 0;
+    let mut char_index = 0;
+    let mut to_char_index = |byte:usize| {
+        char_index += text[byte_index..byte].chars().count();
+        byte_index  = byte;
+        char_index
+    };
+    let non_empty = byte_ranges.filter(|range| range.start != range.end);
+    non_empty.map(|range| {
+        let start = content.convert_char_index_to_location(to_char_index(range.start));
+        let end   = content.convert_char_index_to_location(to_char_index(range.end));
+        start..end
+    }).collect()
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::display::shape::text::text_field::content::test::mock_properties;
+
+    use basegl_core_msdf_sys as msdf_sys;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    const TEXT:&str = "fn print_n(n:i32) {\n    Print_n(n);\n    print_n_1(n)\n}";
+
+    fn location(line:usize, column:usize) -> TextLocation {
+        TextLocation {line,column}
+    }
+
+    fn find_all(query:SearchQuery) -> Vec<Range<TextLocation>> {
+        let content = TextFieldContent::new(TEXT,&mock_properties());
+        query.compile().unwrap().find_all(&content)
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn finding_literal_text() {
+        msdf_sys::initialized().await;
+        let expected = vec![location(0,3)..location(0,10), location(2,4)..location(2,11)];
+        assert_eq!(find_all(SearchQuery::literal("print_n")), expected);
+
+        let expected = vec!
+            [ location(0,3)..location(0,10)
+            , location(1,4)..location(1,11)
+            , location(2,4)..location(2,11)
+            ];
+        assert_eq!(find_all(SearchQuery::literal("print_n").case_insensitive()), expected);
+
+        let expected = vec![location(0,3)..location(0,10), location(1,4)..location(1,11)];
+        let query    = SearchQuery::literal("print_n").case_insensitive().whole_word();
+        assert_eq!(find_all(query), expected);
+
+        let expected = vec![location(1,11)..location(1,14), location(2,13)..location(2,16)];
+        assert_eq!(find_all(SearchQuery::literal("(n)")), expected);
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn finding_regex() {
+        msdf_sys::initialized().await;
+        let expected = vec![location(0,18)..location(1,4), location(1,14)..location(2,4)];
+        assert_eq!(find_all(SearchQuery::regex(r"[{;]?\n +")), expected);
+        assert!(find_all(SearchQuery::regex("x*")).is_empty());
+        assert!(SearchQuery::regex("(unclosed").compile().is_err());
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn finding_next_match() {
+        msdf_sys::initialized().await;
+        let content = TextFieldContent::new(TEXT,&mock_properties());
+        let search  = SearchQuery::literal("(n").compile().unwrap();
+        let next    = search.find_next(&content,location(1,0));
+        assert_eq!(next, Some(location(1,11)..location(1,13)));
+        let next    = search.find_next(&content,location(3,0));
+        assert_eq!(next, Some(location(0,10)..location(0,12)));
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn computing_replacements() {
+        msdf_sys::initialized().await;
+        let content      = TextFieldContent::new("ąb = 1\nąc = 2",&mock_properties());
+        let search       = SearchQuery::regex(r"(\w+) = (\d)").compile().unwrap();
+        let replacements = search.replacements(&content,"$2 = $1");
+        let expected     = vec!
+            [ Replacement {range:location(0,0)..location(0,6), text:"1 = ąb".into()}
+            , Replacement {range:location(1,0)..location(1,6), text:"2 = ąc".into()}
+            ];
+        assert_eq!(replacements, expected);
+
+        let search       = SearchQuery::literal("ąc").compile().unwrap();
+        let replacements = search.replacements(&content,"$1");
+        let expected     = vec![Replacement {range:location(1,0)..location(1,2), text:"$1".into()}];
+        assert_eq!(replacements, expected);
+    }
+}
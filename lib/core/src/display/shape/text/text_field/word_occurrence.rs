@@ -1,11 +1,13 @@
 //! This module contains definitions for word occurrences in TextField.
-//! Words are considered to be composed with alphanumeric and underscores.
+//! Words are considered to be composed with alphanumeric and underscores. The combining marks and
+//! other parts of grapheme clusters starting with such character are also part of the word.
 
 use crate::prelude::*;
 
 use std::ops::Range;
 
 use crate::display::shape::text::text_field::content::TextFieldContent;
+use crate::display::shape::text::text_field::content::line::grapheme_boundaries;
 use crate::display::shape::text::text_field::cursor::Cursor;
 use data::text::TextLocation;
 
@@ -147,10 +149,20 @@ impl WordOccurrences {
 // =============
 
 fn get_words(content:&[char]) -> Vec<Vec<(usize,char)>> {
-    let indexed:Vec<(usize,char)> = content.iter().copied().enumerate().collect();
-    let words = indexed.split(|(_,character)| !character.is_alphanumeric() && *character != '_');
-    let words = words.filter(|word_in_context| !word_in_context.is_empty());
-    words.map(|c| c.to_vec()).collect()
+    let is_word_char = |character:char| character.is_alphanumeric() || character == '_';
+    let mut words    = Vec::new();
+    let mut current  = Vec::new();
+    for (start,end) in grapheme_boundaries(content).into_iter().tuple_windows() {
+        if is_word_char(content[start]) {
+            current.extend((start..end).map(|index| (index,content[index])));
+        } else if !current.is_empty() {
+            words.push(std::mem::take(&mut current));
+        }
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
 }
 
 fn get_index_range_of_word_at(content:&[char], index:usize) -> Option<Range<usize>> {
@@ -287,6 +299,17 @@ mod test {
         assert!(range.is_none());
     }
 
+    #[test]
+    fn index_range_of_word_with_graphemes() {
+        let content:Vec<char> = "la cafe\u{301}-na\u{303}o".chars().collect();
+        assert_eq!(get_index_range_of_word_at(&content,5) , Some(3..8));
+        assert_eq!(get_index_range_of_word_at(&content,10), Some(9..13));
+
+        let content:Vec<char> = "x \u{1F468}\u{200D}\u{1F469} y".chars().collect();
+        assert_eq!(get_index_range_of_word_at(&content,4) , None);
+        assert_eq!(get_index_range_of_word_at(&content,6) , Some(6..7));
+    }
+
     #[test]
     fn word_occurrences() {
         let content = String::from("_5abc6   _5abc6    def    ghi  _5abc6abc _5abc6");
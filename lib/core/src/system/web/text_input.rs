@@ -1,9 +1,17 @@
 //! This module contains Rust wrappers for keyboard event handling.
 //!
 //! These keyboard events are taken from created invisible textarea element in html document body.
-//! We do it this way, because this is the only way for handling clipboard operations.
+//! We do it this way, because this is the only way for handling clipboard operations and text
+//! composition by input methods (see `composition` module).
+
+pub mod composition;
+
 use crate::prelude::*;
 
+use crate::system::web::text_input::composition::Composition;
+use crate::system::web::text_input::composition::CompositionAction;
+use crate::system::web::text_input::composition::InputEvent;
+
 use enso_frp::*;
 use std::fmt::Debug;
 use std::fmt::Formatter;
@@ -38,6 +46,15 @@ mod js {
         #[wasm_bindgen(method)]
         pub fn set_paste_handler(this:&TextInputHandlers, handler:&Closure<dyn FnMut(String)>);
 
+        #[allow(unsafe_code)]
+        #[wasm_bindgen(method)]
+        pub fn set_blur_handler(this:&TextInputHandlers, handler:&Closure<dyn FnMut()>);
+
+        #[allow(unsafe_code)]
+        #[wasm_bindgen(method)]
+        pub fn set_input_handler
+        (this:&TextInputHandlers, handler:&Closure<dyn FnMut(String,String,String)>);
+
         #[allow(unsafe_code)]
         #[wasm_bindgen(method)]
         pub fn set_event_handler
@@ -65,6 +82,16 @@ pub trait PasteHandler = FnMut(String) + 'static;
 /// Keyboard event handler takes event as an argument.
 pub trait KeyboardEventHandler = FnMut(KeyboardEvent) + 'static;
 
+/// The blur handler is called when the textarea loses focus.
+pub trait BlurHandler = FnMut() + 'static;
+
+/// The composition handler takes the action on text requested by the text composition.
+pub trait CompositionHandler = FnMut(CompositionAction) + 'static;
+
+/// The js input handler takes the event type, the `inputType` of `beforeinput` events and the
+/// event's data.
+trait InputHandler = FnMut(String,String,String) + 'static;
+
 /// The keyboard event bindings.
 ///
 /// This structure wraps the javascript content handling events in a way describing in this module
@@ -75,6 +102,9 @@ pub struct KeyboardBinding {
     paste_handler    : Option<Closure<dyn PasteHandler>>,
     key_down_handler : Option<Closure<dyn KeyboardEventHandler>>,
     key_up_handler   : Option<Closure<dyn KeyboardEventHandler>>,
+    blur_handler     : Option<Closure<dyn BlurHandler>>,
+    input_handler    : Option<Closure<dyn InputHandler>>,
+    composition      : Rc<RefCell<Composition>>,
 }
 
 impl KeyboardBinding {
@@ -86,7 +116,10 @@ impl KeyboardBinding {
             copy_handler     : None,
             paste_handler    : None,
             key_down_handler : None,
-            key_up_handler   : None
+            key_up_handler   : None,
+            blur_handler     : None,
+            input_handler    : None,
+            composition      : default(),
         }
     }
 
@@ -111,6 +144,37 @@ impl KeyboardBinding {
         self.key_down_handler = Some(handler_js);
     }
 
+    /// Set blur handler, called whenever the textarea loses focus, e.g. when the browser window
+    /// is deactivated.
+    pub fn set_blur_handler<Handler:BlurHandler>(&mut self, handler:Handler) {
+        let handler_js : Closure<dyn BlurHandler> = Closure::wrap(Box::new(handler));
+        self.js_handlers.set_blur_handler(&handler_js);
+        self.blur_handler = Some(handler_js);
+    }
+
+    /// Set the handler of text composition. The composition events are translated to the actions
+    /// on text by the `Composition` state machine shared with the keydown handler (see
+    /// `composition` method).
+    pub fn set_composition_handler<Handler:CompositionHandler>(&mut self, mut handler:Handler) {
+        let composition = self.composition.clone_ref();
+        let input       = move |event_type:String, input_type:String, data:String| {
+            let event  = InputEvent::from_js(&event_type,&input_type,data);
+            let action = event.and_then(|event| composition.borrow_mut().handle(event));
+            if let Some(action) = action {
+                handler(action);
+            }
+        };
+        let handler_js : Closure<dyn InputHandler> = Closure::wrap(Box::new(input));
+        self.js_handlers.set_input_handler(&handler_js);
+        self.input_handler = Some(handler_js);
+    }
+
+    /// The state of text composition. The keydown events should not be handled as typed keys
+    /// during composition, see `Composition::forwards_key_down`.
+    pub fn composition(&self) -> Rc<RefCell<Composition>> {
+        self.composition.clone_ref()
+    }
+
     /// Set keyup handler.
     pub fn set_key_up_handler<Handler:KeyboardEventHandler>(&mut self, handler:Handler) {
         let handler_js : Closure<dyn KeyboardEventHandler> = Closure::wrap(Box::new(handler));
@@ -139,11 +203,15 @@ impl Debug for KeyboardBinding {
 /// Bind this FRP graph to js events.
 ///
 /// Until the returned `KeyboardBinding` structure lives, the js events will emit the proper
-/// source events in this graph.
+/// source events in this graph. The keys pressed during text composition are handled by the
+/// input method, so they are not emitted.
 pub fn bind_frp_to_js_keyboard_actions(frp:&Keyboard) -> KeyboardBinding {
     let mut binding     = KeyboardBinding::create();
+    let composition     = binding.composition();
     binding.set_key_down_handler(enclose!((frp.on_pressed => frp) move |event:KeyboardEvent| {
-        if let Ok(key) = event.key().parse::<Key>() {
+        let is_composing = event.is_composing();
+        let forwarded    = composition.borrow().forwards_key_down(event.key_code(),is_composing);
+        if let Some(key) = event.key().parse::<Key>().ok().filter(|_| forwarded) {
             frp.event.emit(key);
         }
     }));
@@ -0,0 +1,204 @@
+//! The state machine of text composition by input methods and dead keys.
+//!
+//! When the user types with an input method (e.g. for Japanese, Chinese or Korean) or uses dead
+//! keys for accents, the browser does not emit a key for each character. Instead, the text is
+//! _composed_ in the text area: the `compositionstart` event is followed by `compositionupdate`
+//! events with the text composed so far, and by `compositionend` with the final text. The
+//! `beforeinput` events describe the same changes, and additionally the text inserted without
+//! composition, e.g. an accent typed with a dead key followed by space.
+//!
+//! The `Composition` translates these events to actions done on the text: the composed text is
+//! displayed as provisional, and committed when the composition ends.
+
+use crate::prelude::*;
+
+
+
+// ==================
+// === InputEvent ===
+// ==================
+
+/// The `keyCode` of keydown events handled by the input method.
+pub const IME_KEY_CODE:u32 = 229;
+
+/// The text area event related to text composition.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub enum InputEvent {
+    /// The `compositionstart` event.
+    CompositionStart,
+    /// The `compositionupdate` event with the text composed so far.
+    CompositionUpdate(String),
+    /// The `compositionend` event with the final text, empty if the composition was cancelled.
+    CompositionEnd(String),
+    /// The `beforeinput` event of `insertCompositionText` type with the text composed so far.
+    InsertCompositionText(String),
+    /// The `beforeinput` event of `insertText` type with the inserted text.
+    InsertText(String),
+}
+
+impl InputEvent {
+    /// Create event from the js event's type, the `inputType` of `beforeinput` events and the
+    /// event's data. Returns `None` for events which are not related to composition.
+    pub fn from_js(event_type:&str, input_type:&str, data:String) -> Option<Self> {
+        use InputEvent::*;
+        match (event_type,input_type) {
+            ("compositionstart" ,_)                       => Some(CompositionStart),
+            ("compositionupdate",_)                       => Some(CompositionUpdate(data)),
+            ("compositionend"   ,_)                       => Some(CompositionEnd(data)),
+            ("beforeinput"      ,"insertCompositionText") => Some(InsertCompositionText(data)),
+            ("beforeinput"      ,"insertText")            => Some(InsertText(data)),
+            _                                             => None,
+        }
+    }
+}
+
+
+
+// =========================
+// === CompositionAction ===
+// =========================
+
+/// The action on text requested by the composition.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub enum CompositionAction {
+    /// Display the text composed so far as provisional, replacing the previously displayed one.
+    Update(String),
+    /// Replace the provisional text with the final one and commit it as a single edit.
+    Commit(String),
+    /// Remove the provisional text.
+    Cancel,
+}
+
+// The default value is required by FRP nodes.
+impl Default for CompositionAction {
+    fn default() -> Self {
+        CompositionAction::Cancel
+    }
+}
+
+
+
+// ===================
+// === Composition ===
+// ===================
+
+/// The state of text composition, see the module docs.
+#[derive(Clone,Debug,Default)]
+pub struct Composition {
+    /// The text composed so far, or `None` if there is no composition in progress.
+    composed : Option<String>,
+}
+
+impl Composition {
+    /// Check if there is a composition in progress.
+    pub fn is_composing(&self) -> bool {
+        self.composed.is_some()
+    }
+
+    /// Update the state after the event, returning the action which should be done on text.
+    pub fn handle(&mut self, event:InputEvent) -> Option<CompositionAction> {
+        match event {
+            InputEvent::CompositionStart => {
+                let interrupted = self.composed.replace(default());
+                interrupted.filter(|text| !text.is_empty()).map(|_| CompositionAction::Cancel)
+            }
+            InputEvent::CompositionUpdate(text)     => self.update(text),
+            InputEvent::InsertCompositionText(text) => self.update(text),
+            InputEvent::CompositionEnd(text) => {
+                let composed = self.composed.take();
+                if text.is_empty() {
+                    composed.filter(|text| !text.is_empty()).map(|_| CompositionAction::Cancel)
+                } else {
+                    Some(CompositionAction::Commit(text))
+                }
+            }
+            InputEvent::InsertText(text) => {
+                let is_inserted = !self.is_composing() && !text.is_empty();
+                is_inserted.as_some(CompositionAction::Commit(text))
+            }
+        }
+    }
+
+    /// Check if the keydown event should be handled as a typed key. The keys pressed during
+    /// composition are handled by the input method.
+    pub fn forwards_key_down(&self, key_code:u32, is_composing:bool) -> bool {
+        !self.is_composing() && !is_composing && key_code != IME_KEY_CODE
+    }
+
+    fn update(&mut self, text:String) -> Option<CompositionAction> {
+        let is_changed = self.composed.as_ref() != Some(&text);
+        self.composed  = Some(text.clone());
+        is_changed.as_some(CompositionAction::Update(text))
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use super::CompositionAction::*;
+
+    fn handle_all(composition:&mut Composition, events:Vec<InputEvent>)
+    -> Vec<CompositionAction> {
+        events.into_iter().filter_map(|event| composition.handle(event)).collect()
+    }
+
+    #[test]
+    fn composing_with_input_method() {
+        let mut composition = Composition::default();
+        let events = vec!
+            [ InputEvent::CompositionStart
+            , InputEvent::InsertCompositionText("か".into())
+            , InputEvent::CompositionUpdate("か".into())
+            , InputEvent::CompositionUpdate("かん".into())
+            , InputEvent::CompositionUpdate("漢".into())
+            ];
+        let expected = vec![Update("か".into()),Update("かん".into()),Update("漢".into())];
+        assert_eq!(handle_all(&mut composition,events), expected);
+        assert!(composition.is_composing());
+        assert!(!composition.forwards_key_down(13,false));
+
+        let actions = handle_all(&mut composition,vec![InputEvent::CompositionEnd("漢".into())]);
+        assert_eq!(actions, vec![Commit("漢".into())]);
+        assert!(!composition.is_composing());
+        assert!(composition.forwards_key_down(13,false));
+        assert!(!composition.forwards_key_down(IME_KEY_CODE,false));
+        assert!(!composition.forwards_key_down(13,true));
+    }
+
+    #[test]
+    fn composing_with_dead_keys() {
+        let mut composition = Composition::default();
+        let events = vec!
+            [ InputEvent::CompositionStart
+            , InputEvent::CompositionUpdate("´".into())
+            , InputEvent::CompositionEnd("é".into())
+            , InputEvent::InsertText("´".into())
+            ];
+        let expected = vec![Update("´".into()),Commit("é".into()),Commit("´".into())];
+        assert_eq!(handle_all(&mut composition,events), expected);
+    }
+
+    #[test]
+    fn cancelling_composition() {
+        let mut composition = Composition::default();
+        let events = vec!
+            [ InputEvent::CompositionStart
+            , InputEvent::CompositionUpdate("n".into())
+            , InputEvent::InsertText("n".into())
+            , InputEvent::CompositionEnd("".into())
+            , InputEvent::CompositionStart
+            , InputEvent::CompositionEnd("".into())
+            ];
+        assert_eq!(handle_all(&mut composition,events), vec![Update("n".into()),Cancel]);
+        let start  = InputEvent::from_js("compositionstart","","".into());
+        let delete = InputEvent::from_js("beforeinput","deleteContentBackward","".into());
+        assert_eq!(start , Some(InputEvent::CompositionStart));
+        assert_eq!(delete, None);
+    }
+}
@@ -1,6 +1,6 @@
 // Text input event handlers.
 //
-// The "text input event" is the normal keyup/keydown event, or cut/copy/paste event. The handling of these events is
+// The "text input event" is the normal keyup/keydown event, cut/copy/paste event, or text composition event. The handling of these events is
 // done by creating an invisible focused textarea html element and intercepting events emitted to this element.
 // We do it this way, because this is so far the only way a website may affect the clipboard which works on each
 // browser.
@@ -30,6 +30,18 @@ export class TextInputHandlers {
         this.paste_handler = handler
     }
 
+    // Set blur handler. The blur handler is called whenever the textarea loses focus, e.g. when the browser window is
+    // deactivated.
+    set_blur_handler(handler) {
+        this.blur_handler = handler
+    }
+
+    // Set input handler. The input handler is called for composition events and `beforeinput` events with three
+    // arguments: the event type, the `inputType` of `beforeinput` events (empty for other events) and the event's data.
+    set_input_handler(handler) {
+        this.input_handler = handler
+    }
+
     // Remove the textarea element and stop handling any events.
     stop_handling() {
         this.text_area.remove()
@@ -57,6 +69,9 @@ export class TextInputHandlers {
             e.preventDefault()
         });
         this.text_area.addEventListener('blur', e => {
+            if (typeof this.blur_handler !== 'undefined') {
+                this.blur_handler()
+            }
             this.text_area.focus()
         })
         this.text_area.addEventListener('keydown', e => {
@@ -65,6 +80,9 @@ export class TextInputHandlers {
             let is_cut   = code === 88 && (e.metaKey || e.ctrlKey)
             let is_copy  = code === 67 && (e.metaKey || e.ctrlKey)
             let is_paste = code === 86 && (e.metaKey || e.ctrlKey)
+            // The keys handled by input method (including dead keys) must reach the textarea, otherwise the text
+            // composition would not start.
+            let is_composing = e.isComposing || code === 229 || e.key === 'Dead'
             if (is_copy || is_cut) {
                 if (typeof this.copy_handler !== 'undefined') {
                     this.text_area.value = this.copy_handler(is_cut)
@@ -73,7 +91,7 @@ export class TextInputHandlers {
                 } else {
                     e.preventDefault()
                 }
-            } else if (!is_paste) {
+            } else if (!is_paste && !is_composing) {
                 e.preventDefault()
             }
 
@@ -81,6 +99,25 @@ export class TextInputHandlers {
                 this.event_handlers['keydown'](e)
             }
         })
+        let handle_input = (e, input_type) => {
+            if (typeof this.input_handler !== 'undefined') {
+                this.input_handler(e.type, input_type, e.data || "")
+            }
+        }
+        this.text_area.addEventListener('compositionstart', e => handle_input(e, ""))
+        this.text_area.addEventListener('compositionupdate', e => handle_input(e, ""))
+        this.text_area.addEventListener('compositionend', e => {
+            handle_input(e, "")
+            // Clear textarea in next frame (after composition).
+            setTimeout(_ => {this.text_area.value = "";}, 0)
+        })
+        this.text_area.addEventListener('beforeinput', e => {
+            // The text inserted without composition is handled by the input handler only.
+            if (e.inputType === 'insertText') {
+                e.preventDefault()
+            }
+            handle_input(e, e.inputType)
+        })
         this.text_area.addEventListener('keyup', e => {
             e.preventDefault()
             if (typeof this.event_handlers['keyup'] !== 'undefined') {
@@ -0,0 +1,50 @@
+//! Benchmarks of the TextField content storage on large texts.
+#![cfg(target_arch = "wasm32")]
+
+use web_test::web_configure;
+web_configure!(run_in_browser);
+
+#[cfg(test)]
+mod tests {
+    use basegl::display::shape::text::text_field::content::line::Line;
+    use basegl::display::shape::text::text_field::content::rope::LineRope;
+    use web_test::*;
+
+    const LINES_COUNT : usize = 10_000;
+
+    fn large_text() -> LineRope {
+        (0..LINES_COUNT).map(|i| Line::new(format!("line number {}", i))).collect()
+    }
+
+    #[web_bench]
+    fn inserting_lines_in_the_middle(b: &mut Bencher) {
+        let mut lines = large_text();
+        b.iter(move || {
+            let middle = lines.len() / 2;
+            lines.splice(middle..middle+1,vec![Line::new("foo"),Line::new("bar")]);
+            lines.remove(middle);
+        })
+    }
+
+    #[web_bench]
+    fn editing_line_in_the_middle(b: &mut Bencher) {
+        let mut lines = large_text();
+        b.iter(move || {
+            let middle = lines.len() / 2;
+            lines.modify(middle, |line| line.modify().insert(0,'a'));
+            lines.modify(middle, |line| line.modify().remove(0));
+        })
+    }
+
+    #[web_bench]
+    fn converting_char_indices(b: &mut Bencher) {
+        let lines = large_text();
+        let total = lines.char_count();
+        b.iter(move || {
+            for char_index in (0..total).step_by(total / 100) {
+                let (line,column) = lines.char_index_to_line_and_column(char_index);
+                assert_eq!(lines.line_start_char_index(line) + column, char_index);
+            }
+        })
+    }
+}
@@ -2,6 +2,7 @@
 
 use enso_prelude::*;
 
+use std::cmp::max;
 use std::ops::Add;
 use std::ops::AddAssign;
 use std::ops::Range;
@@ -189,7 +190,7 @@ pub enum ChangeType {
 }
 
 /// A structure describing a text operation in one place.
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,Eq,PartialEq)]
 pub struct TextChange {
     /// Text fragment to be replaced. If we don't mean to remove any text, this should be an empty
     /// range with start set at position there `lines` will be inserted (see `TextChange::insert`
@@ -255,6 +256,88 @@ impl TextChange {
         start..TextLocation{line:end_line, column:end_column}
     }
 
+    /// Creates a change turning the `old` text into the `new` one. The change replaces the
+    /// fragment between the texts' common prefix and suffix. Returns `None` if texts are equal.
+    pub fn from_diff(old:&str, new:&str) -> Option<Self> {
+        let old      = old.chars().collect_vec();
+        let new      = new.chars().collect_vec();
+        let prefix   = old.iter().zip(&new).take_while(|(a,b)| a == b).count();
+        let old_rest = old[prefix..].iter().rev();
+        let new_rest = new[prefix..].iter().rev();
+        let suffix   = old_rest.zip(new_rest).take_while(|(a,b)| a == b).count();
+        let replaced = prefix..old.len() - suffix;
+        let inserted = &new[prefix..new.len() - suffix];
+        (old != new).as_some_from(|| {
+            let start    = location_of_index(&old,replaced.start);
+            let end      = location_of_index(&old,replaced.end);
+            let inserted = inserted.iter().collect::<String>();
+            TextChange::replace(start..end,&inserted)
+        })
+    }
+
+    /// Creates the changes turning the `old` text into the `new` one line by line. Each change
+    /// replaces a run of whole lines which were removed, added or modified, so the edits made in
+    /// distinct places of the text yield distinct changes. The changes are ordered and expressed in
+    /// the `old` text coordinates, as expected by `merge`.
+    pub fn from_line_diff(old:&str, new:&str) -> Vec<Self> {
+        let old = split_to_lines(old).collect_vec();
+        let new = split_to_lines(new).collect_vec();
+        // The length of the longest common subsequence of `old[i..]` and `new[j..]` lines.
+        let mut common = vec![vec![0;new.len() + 1];old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                common[i][j] = if old[i] == new[j] { common[i+1][j+1] + 1 }
+                               else                { max(common[i+1][j],common[i][j+1]) };
+            }
+        }
+        let mut changes                   = Vec::new();
+        let (mut i,mut j)                 = (0,0);
+        let (mut old_start,mut new_start) = (0,0);
+        loop {
+            let is_end    = i == old.len() && j == new.len();
+            let is_common = i < old.len() && j < new.len() && old[i] == new[j];
+            if is_end || is_common {
+                if (old_start,new_start) != (i,j) {
+                    changes.push(Self::replace_lines(&old,old_start..i,&new[new_start..j]));
+                }
+                if is_end {
+                    break changes;
+                }
+                i += 1;
+                j += 1;
+                old_start = i;
+                new_start = j;
+            } else if j == new.len() || (i < old.len() && common[i+1][j] >= common[i][j+1]) {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+    }
+
+    /// Creates the change replacing the whole `replaced` lines of the `old` text with `inserted`
+    /// lines.
+    fn replace_lines(old:&[String], replaced:Range<usize>, inserted:&[String]) -> Self {
+        let line_end = |line:usize| TextLocation {line, column:old[line].chars().count()};
+        if replaced.end < old.len() {
+            let start = TextLocation::at_line_begin(replaced.start);
+            let end   = TextLocation::at_line_begin(replaced.end);
+            let text  = inserted.iter().map(|line| format!("{}\n",line)).join("");
+            TextChange::replace(start..end,&text)
+        } else if replaced.is_empty() {
+            // Lines appended after the last one. The text has always at least one line.
+            let text = inserted.iter().map(|line| format!("\n{}",line)).join("");
+            TextChange::insert(line_end(replaced.start - 1),&text)
+        } else if inserted.is_empty() {
+            // The last lines removed, together with the line break before them. They cannot be all
+            // the lines, as the new text has at least one line too.
+            TextChange::delete(line_end(replaced.start - 1)..line_end(replaced.end - 1))
+        } else {
+            let start = TextLocation::at_line_begin(replaced.start);
+            TextChange::replace(start..line_end(replaced.end - 1),&inserted.join("\n"))
+        }
+    }
+
     fn mk_lines_as_char_vector(text:&str) -> Vec<Vec<char>> {
         split_to_lines(text).map(|s| s.chars().collect_vec()).collect()
     }
@@ -278,6 +361,86 @@ pub struct TextChangedNotification {
     pub replaced_chars : Range<usize>,
 }
 
+impl TextChangedNotification {
+    /// Applies the change to the text it was made to.
+    pub fn apply(&self, text:&mut String) {
+        let byte_index = |index:usize| text.char_indices().nth(index).map_or(text.len(),|(i,_)| i);
+        let start      = byte_index(self.replaced_chars.start);
+        let end        = byte_index(self.replaced_chars.end);
+        text.replace_range(start..end,&self.inserted_string());
+    }
+}
+
+
+// ===============
+// === Merging ===
+// ===============
+
+/// Conflict detected during the three-way merge: both sides changed the same fragment of the base
+/// text in different ways.
+#[derive(Clone,Debug,Fail)]
+#[fail(display = "Conflicting changes: {:?} and {:?}.", ours, theirs)]
+pub struct MergeConflict {
+    /// The conflicting change from our side.
+    pub ours   : TextChange,
+    /// The conflicting change from their side.
+    pub theirs : TextChange,
+}
+
+/// Merges two sets of changes made independently to the same `base` text.
+///
+/// Each set must consist of non-overlapping changes, expressed in the `base` text coordinates.
+/// Returns the base text with the changes from both sets applied, unless a change from `ours`
+/// overlaps a change from `theirs`. Changes overlap if their replaced fragments intersect or if
+/// both insert text at the same location. Identical changes made by both sides are applied once.
+///
+/// The texts are expected to use `\n` line endings.
+pub fn merge(base:&str, ours:&[TextChange], theirs:&[TextChange]) -> Result<String,MergeConflict> {
+    let base        = base.chars().collect_vec();
+    let to_range    = |change:&TextChange| {
+        let start = index_of_location(&base,change.replaced.start);
+        let end   = index_of_location(&base,change.replaced.end);
+        start..end
+    };
+    let ours        = ours.iter().map(|change| (true,to_range(change),change));
+    let theirs      = theirs.iter().map(|change| (false,to_range(change),change));
+    let mut changes = ours.chain(theirs).collect_vec();
+    changes.sort_by_key(|(_,range,_)| (range.start,range.end));
+
+    // The change reaching the furthest in the base text so far.
+    let mut furthest : Option<(bool,Range<usize>,&TextChange)> = None;
+    let mut accepted = Vec::new();
+    for (is_ours,range,change) in changes {
+        if let Some((furthest_is_ours,furthest_range,furthest_change)) = &furthest {
+            let both_insert = range.start == furthest_range.start && range.is_empty()
+                && furthest_range.is_empty();
+            let overlaps    = range.start < furthest_range.end || both_insert;
+            if overlaps && *furthest_is_ours != is_ours {
+                if change == *furthest_change {
+                    continue;
+                }
+                let (ours,theirs) = if is_ours {(change,*furthest_change)}
+                                    else       {(*furthest_change,change)};
+                let ours   = ours.clone();
+                let theirs = theirs.clone();
+                return Err(MergeConflict {ours,theirs});
+            }
+        }
+        let reaches_further = furthest.as_ref().map_or(true,|(_,r,_)| range.end >= r.end);
+        if reaches_further {
+            furthest = Some((is_ours,range.clone(),change));
+        }
+        accepted.push((range,change));
+    }
+
+    let mut merged = base;
+    for (range,change) in accepted.into_iter().rev() {
+        merged.splice(range,change.inserted_string().chars());
+    }
+    Ok(merged.into_iter().collect())
+}
+
+
 
 // =================
 // === Utilities ===
@@ -295,4 +458,103 @@ fn cut_cr_at_end_of_line(from:&str) -> &str {
     } else {
         from
     }
-}
\ No newline at end of file
+}
+
+/// Converts the char index in the text into the location.
+fn location_of_index(text:&[char], index:usize) -> TextLocation {
+    let before = &text[..index];
+    let line   = before.iter().filter(|c| **c == '\n').count();
+    let column = before.iter().rev().take_while(|c| **c != '\n').count();
+    TextLocation {line,column}
+}
+
+/// Converts the location into the char index in the text. Locations past the end of the line or
+/// text are clamped.
+fn index_of_location(text:&[char], location:TextLocation) -> usize {
+    let line_start = if location.line == 0 { Some(0) } else {
+        let mut newlines = text.iter().positions(|c| *c == '\n');
+        newlines.nth(location.line - 1).map(|index| index + 1)
+    };
+    let line_start = line_start.unwrap_or_else(|| text.len());
+    let line_len   = text[line_start..].iter().take_while(|c| **c != '\n').count();
+    line_start + location.column.min(line_len)
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn change_from_diff() {
+        let change = TextChange::from_diff("foo\nbar\nbaz","foo\nbor\nbaz").unwrap();
+        assert_eq!(change.replaced, TextLocation{line:1,column:1}..TextLocation{line:1,column:2});
+        assert_eq!(change.inserted_string(), "o");
+        let change = TextChange::from_diff("ab","abc").unwrap();
+        assert_eq!(change.replaced, TextLocation{line:0,column:2}..TextLocation{line:0,column:2});
+        assert_eq!(change.inserted_string(), "c");
+        assert!(TextChange::from_diff("same","same").is_none());
+    }
+
+    #[test]
+    fn changes_from_line_diff() {
+        let apply = |old:&str, new:&str| {
+            let changes = TextChange::from_line_diff(old,new);
+            let merged  = merge(old,&changes,&[]).unwrap();
+            assert_eq!(merged, new);
+            changes.len()
+        };
+        assert_eq!(apply("a\nb\nc\nd","x\nb\nc\ny")  , 2);
+        assert_eq!(apply("a\nb\nc"   ,"a\nx\nb\nc\ny"), 2);
+        assert_eq!(apply("a\nb\nc"   ,"b")          , 2);
+        assert_eq!(apply("a\nb"      ,"a\nb\n")      , 1);
+        assert_eq!(apply(""          ,"a")          , 1);
+        assert_eq!(apply("same"      ,"same")       , 0);
+    }
+
+    #[test]
+    fn applying_change_notification() {
+        let mut text = "zażółć\ngęślą".to_string();
+        let replaced = TextLocation{line:0,column:2}..TextLocation{line:1,column:1};
+        let change   = TextChangedNotification {
+            change         : TextChange::replace(replaced,"X"),
+            replaced_chars : 2..8,
+        };
+        change.apply(&mut text);
+        assert_eq!(text, "zaXęślą");
+    }
+
+    #[test]
+    fn merging_non_overlapping_changes() {
+        let base   = "main =\n    a = 1\n    a + 1";
+        let ours   = "main =\n    a = 2\n    a + 1";
+        let theirs = "main =\n    a = 1\n    a + 1 + 2";
+        let ours   = TextChange::from_diff(base,ours).unwrap();
+        let theirs = TextChange::from_diff(base,theirs).unwrap();
+        let merged = merge(base,&[ours],&[theirs]).unwrap();
+        assert_eq!(merged, "main =\n    a = 2\n    a + 1 + 2");
+    }
+
+    #[test]
+    fn merging_identical_changes() {
+        let change = TextChange::insert(TextLocation{line:0,column:1},"x");
+        let merged = merge("ab",&[change.clone()],&[change]).unwrap();
+        assert_eq!(merged, "axb");
+    }
+
+    #[test]
+    fn merging_overlapping_changes() {
+        let location = |column| TextLocation{line:0,column};
+        let ours     = TextChange::replace(location(0)..location(3),"x");
+        let theirs   = TextChange::replace(location(2)..location(4),"y");
+        assert!(merge("abcd",&[ours],&[theirs]).is_err());
+        let ours     = TextChange::insert(location(1),"x");
+        let theirs   = TextChange::insert(location(1),"y");
+        assert!(merge("abcd",&[ours],&[theirs]).is_err());
+    }
+}
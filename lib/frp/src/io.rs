@@ -2,6 +2,10 @@
 
 pub mod mouse;
 pub mod keyboard;
+pub mod keymap;
+pub mod script;
 
 pub use mouse::*;
 pub use keyboard::*;
+pub use keymap::*;
+pub use script::*;
@@ -140,8 +140,8 @@ impl KeyState {
 // === Keyboard ===
 // ================
 
-/// A FRP graph for basic keyboard events.
-#[derive(Debug)]
+/// A FRP graph for basic keyboard events. The clones share the graph's nodes.
+#[derive(Clone,Debug)]
 pub struct Keyboard {
     /// The key pressed event.
     pub on_pressed: Dynamic<Key>,
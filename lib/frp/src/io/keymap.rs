@@ -0,0 +1,655 @@
+//! A configurable keymap, binding named commands to key chords.
+//!
+//! The keymap is described declaratively in JSON, as a list of bindings:
+//!
+//! ```text
+//! [ { "keys" : "Ctrl+Z"        , "command" : "text_field.undo"                             }
+//! , { "keys" : "Ctrl+K Ctrl+C" , "command" : "text_field.comment", "context" : "text_editor" }
+//! ]
+//! ```
+//!
+//! The `keys` field is a chord: a sequence of key combinations separated by whitespaces, where
+//! each combination is a list of keys joined with `+`. A binding with `context` is active only
+//! while its context is active (see `KeymapActions::set_context_active`). A binding without
+//! context is always active.
+//!
+//! The keymap is bound to the `Keyboard` FRP graph with `KeymapActions`, which tracks the chord
+//! being typed and runs handlers of the bound commands.
+
+use crate::prelude::*;
+
+use crate::*;
+
+use serde::Deserialize;
+use std::collections::hash_map::Entry;
+use std::str::FromStr;
+
+
+
+// ===============
+// === Command ===
+// ===============
+
+/// A name of command which may be bound to keys, e.g. `text_field.undo`.
+#[derive(Clone,Debug,Eq,Hash,PartialEq,Shrinkwrap)]
+pub struct Command(pub String);
+
+impl From<&str> for Command {
+    fn from(name:&str) -> Self {
+        Command(name.to_string())
+    }
+}
+
+impl From<String> for Command {
+    fn from(name:String) -> Self {
+        Command(name)
+    }
+}
+
+
+
+// ===============
+// === Context ===
+// ===============
+
+/// A name of context in which binding is active, e.g. `text_editor` or `graph`.
+///
+/// The contexts usually correspond to views which may be focused, so they are assumed to be
+/// mutually exclusive when looking for conflicts.
+#[derive(Clone,Debug,Eq,Hash,PartialEq,Shrinkwrap)]
+pub struct Context(pub String);
+
+impl From<&str> for Context {
+    fn from(name:&str) -> Self {
+        Context(name.to_string())
+    }
+}
+
+impl From<String> for Context {
+    fn from(name:String) -> Self {
+        Context(name)
+    }
+}
+
+
+
+// =============
+// === Chord ===
+// =============
+
+/// A sequence of key combinations which must be pressed one after another, e.g. `Ctrl+K Ctrl+C`.
+pub type Chord = Vec<KeyMask>;
+
+/// Errors which may occur when building keymap.
+#[derive(Clone,Debug,Fail)]
+pub enum KeymapError {
+    /// The keymap definition is not a valid JSON list of bindings.
+    #[fail(display = "Invalid keymap definition: {}.", _0)]
+    InvalidDefinition(String),
+    /// The key name was not recognized.
+    #[fail(display = "Unknown key \"{}\" in \"{}\".", key, keys)]
+    UnknownKey {
+        /// The unrecognized key name.
+        key : String,
+        /// The whole chord description.
+        keys : String,
+    },
+    /// The chord description does not contain any keys.
+    #[fail(display = "No keys given for command \"{}\".", _0)]
+    EmptyChord(String),
+}
+
+/// Parse the chord description, like `Ctrl+K Ctrl+C`.
+///
+/// The key names are case-sensitive and follow the `Key` variants names (e.g. `Enter`, `PageUp`,
+/// `F5`). Additionally the `Ctrl`, `Esc`, `Del`, `Left`, `Right`, `Up` and `Down` abbreviations are
+/// accepted, and `Space` and `Plus` stand for the space and `+` characters. Any other single
+/// character is a `Key::Character`.
+pub fn parse_chord(keys:&str) -> Result<Chord,KeymapError> {
+    keys.split_whitespace().map(|combination| {
+        let parsed_keys = combination.split('+').map(|name| parse_key(name,keys));
+        parsed_keys.collect::<Result<Vec<_>,_>>().map(KeyMask::from_vec)
+    }).collect()
+}
+
+pub(crate) fn parse_key(name:&str, keys:&str) -> Result<Key,KeymapError> {
+    let unknown_key = || KeymapError::UnknownKey {key:name.to_string(), keys:keys.to_string()};
+    let mut chars   = name.chars();
+    let is_single   = chars.next().is_some() && chars.next().is_none();
+    match name {
+        "Ctrl"           => Ok(Key::Control),
+        "Esc"            => Ok(Key::Escape),
+        "Del"            => Ok(Key::Delete),
+        "Left"           => Ok(Key::ArrowLeft),
+        "Right"          => Ok(Key::ArrowRight),
+        "Up"             => Ok(Key::ArrowUp),
+        "Down"           => Ok(Key::ArrowDown),
+        "Space"          => Ok(Key::Character(" ".to_string())),
+        "Plus"           => Ok(Key::Character("+".to_string())),
+        _ if is_single   => Ok(Key::Character(name.to_string())),
+        _                => match Key::from_str(name) {
+            Ok(Key::Character(_)) | Ok(Key::Unidentified) | Err(_) => Err(unknown_key()),
+            Ok(key)                                                  => Ok(key),
+        }
+    }
+}
+
+fn is_modifier(key:&Key) -> bool {
+    match key {
+        Key::Control | Key::Shift | Key::Alt | Key::Meta => true,
+        _                                                => false,
+    }
+}
+
+
+
+// ===============
+// === Binding ===
+// ===============
+
+/// A single keymap entry.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Binding {
+    /// The chord description, as it was given in keymap definition.
+    pub keys : String,
+    /// The parsed chord.
+    pub chord : Chord,
+    /// The command run once chord is typed.
+    pub command : Command,
+    /// The context in which binding is active. If `None`, the binding is always active.
+    pub context : Option<Context>,
+}
+
+impl Binding {
+    /// Create binding, parsing the chord description.
+    pub fn new(keys:&str, command:impl Into<Command>, context:Option<Context>)
+    -> Result<Self,KeymapError> {
+        let command = command.into();
+        let chord   = parse_chord(keys)?;
+        if chord.is_empty() {
+            Err(KeymapError::EmptyChord(command.0))
+        } else {
+            let keys = keys.split_whitespace().join(" ");
+            Ok(Binding {keys,chord,command,context})
+        }
+    }
+
+    /// Check if this and `other` binding may be active at the same time.
+    pub fn may_be_active_with(&self, other:&Binding) -> bool {
+        match (&self.context, &other.context) {
+            (Some(this), Some(other)) => this == other,
+            _                         => true,
+        }
+    }
+
+    fn is_active(&self, contexts:&HashSet<Context>) -> bool {
+        self.context.as_ref().map_or(true, |context| contexts.contains(context))
+    }
+}
+
+
+
+// ================
+// === Conflict ===
+// ================
+
+/// The way the two bindings conflict.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum ConflictKind {
+    /// Both bindings have the same chord, so the second one wins.
+    SameChord,
+    /// The first binding's chord is a prefix of the second one's. The first command will never be
+    /// run, because after typing its chord the keymap waits for the rest of the longer one.
+    Prefix,
+}
+
+/// Two bindings which may be active at the same time and are triggered by colliding chords.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Conflict {
+    /// The way bindings conflict.
+    pub kind : ConflictKind,
+    /// The binding which is shadowed.
+    pub first : Binding,
+    /// The binding which shadows the `first`.
+    pub second : Binding,
+}
+
+impl Display for Conflict {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        let Conflict {kind,first,second} = self;
+        match kind {
+            ConflictKind::SameChord => write!(f,"\"{}\" is bound to both \"{}\" and \"{}\"",
+                first.keys, first.command.0, second.command.0),
+            ConflictKind::Prefix    => write!(f,
+                "\"{}\" bound to \"{}\" is shadowed by \"{}\" bound to \"{}\"",
+                first.keys, first.command.0, second.keys, second.command.0),
+        }
+    }
+}
+
+
+
+// ==============
+// === Keymap ===
+// ==============
+
+/// A set of bindings between chords and commands.
+#[derive(Clone,Debug,Default)]
+pub struct Keymap {
+    bindings : Vec<Binding>,
+}
+
+/// A binding as it is written in JSON keymap definition.
+#[derive(Deserialize)]
+struct BindingDefinition {
+    keys    : String,
+    command : String,
+    #[serde(default)]
+    context : Option<String>,
+}
+
+/// The result of matching a typed chord against keymap.
+#[derive(Clone,Debug,Eq,PartialEq)]
+enum ChordMatch {
+    /// The chord triggers the command.
+    Complete(Command),
+    /// The chord is a beginning of some longer chords.
+    Pending,
+    /// No binding matches the chord.
+    None,
+}
+
+impl Keymap {
+    /// Create an empty keymap.
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Create keymap from the JSON definition. See module docs for the format description.
+    pub fn from_json(json:&str) -> Result<Self,KeymapError> {
+        let to_error    = |err:serde_json::Error| KeymapError::InvalidDefinition(err.to_string());
+        let definitions = serde_json::from_str::<Vec<BindingDefinition>>(json).map_err(to_error)?;
+        let mut keymap  = Self::new();
+        for BindingDefinition {keys,command,context} in definitions {
+            keymap.bind(&keys,command,context.map(Context))?;
+        }
+        Ok(keymap)
+    }
+
+    /// Bind the chord to the command in given context. If the chord is already bound in the same
+    /// context, the new binding wins.
+    pub fn bind(&mut self, keys:&str, command:impl Into<Command>, context:Option<Context>)
+    -> Result<(),KeymapError> {
+        self.bindings.push(Binding::new(keys,command,context)?);
+        Ok(())
+    }
+
+    /// All bindings, in order they were added.
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// The keymap with only those bindings of this keymap which satisfy the predicate.
+    pub fn filtered(&self, predicate:impl Fn(&Binding) -> bool) -> Keymap {
+        let bindings = self.bindings.iter().filter(|binding| predicate(binding)).cloned().collect();
+        Keymap {bindings}
+    }
+
+    /// Apply user overrides of this keymap. The commands bound in `overrides` lose all their
+    /// bindings in this keymap, as well as the chords rebound by `overrides` to other commands.
+    pub fn override_with(&mut self, overrides:Keymap) {
+        self.bindings.retain(|binding| !overrides.bindings.iter().any(|overriding| {
+            let same_command = overriding.command == binding.command;
+            let same_chord   = overriding.chord == binding.chord;
+            same_command || (same_chord && overriding.may_be_active_with(binding))
+        }));
+        self.bindings.extend(overrides.bindings);
+    }
+
+    /// Find all pairs of bindings which are triggered by colliding chords and may be active at the
+    /// same time.
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = Vec::new();
+        for (index,first) in self.bindings.iter().enumerate() {
+            for second in &self.bindings[index+1..] {
+                if first.may_be_active_with(second) {
+                    let first_shorter    = first.chord.len() <= second.chord.len();
+                    let (shorter,longer) = if first_shorter {(first,second)} else {(second,first)};
+                    if first.chord == second.chord {
+                        let kind = ConflictKind::SameChord;
+                        conflicts.push(Conflict {kind,first:first.clone(),second:second.clone()});
+                    } else if longer.chord.starts_with(&shorter.chord) {
+                        let kind = ConflictKind::Prefix;
+                        conflicts.push(Conflict {kind,first:shorter.clone(),second:longer.clone()});
+                    }
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// Match the typed chord against bindings active in given contexts. The longer chords take
+    /// precedence over shorter ones, and context-specific bindings over the global ones.
+    fn match_chord(&self, chord:&[KeyMask], contexts:&HashSet<Context>) -> ChordMatch {
+        let mut complete : Option<&Binding> = None;
+        for binding in self.bindings.iter().filter(|b| b.is_active(contexts)) {
+            if binding.chord.len() > chord.len() && binding.chord.starts_with(chord) {
+                return ChordMatch::Pending;
+            }
+            if binding.chord.as_slice() == chord {
+                let is_better = complete.map_or(true, |current| {
+                    current.context.is_none() || binding.context.is_some()
+                });
+                if is_better {
+                    complete = Some(binding);
+                }
+            }
+        }
+        complete.map_or(ChordMatch::None, |binding| ChordMatch::Complete(binding.command.clone()))
+    }
+}
+
+
+
+// =====================
+// === KeymapActions ===
+// =====================
+
+/// A handler run when the bound command is triggered.
+pub trait CommandHandler = FnMut() + 'static;
+
+/// The state of `KeymapActions`, shared with the FRP node.
+#[derive(Default)]
+struct KeymapState {
+    keymap   : Keymap,
+    contexts : HashSet<Context>,
+    pending  : Chord,
+    handlers : HashMap<Command,Box<dyn CommandHandler>>,
+}
+
+impl KeymapState {
+    /// Process the next key combination pressed, returning the command to be run, if any.
+    fn process_combination(&mut self, combination:KeyMask) -> Option<Command> {
+        let mut chord  = std::mem::take(&mut self.pending);
+        let is_started = !chord.is_empty();
+        chord.push(combination);
+        match self.keymap.match_chord(&chord,&self.contexts) {
+            ChordMatch::Complete(command) => Some(command),
+            ChordMatch::Pending           => { self.pending = chord; None }
+            ChordMatch::None if is_started => {
+                // The unfinished chord is abandoned, but the last combination may start a new one.
+                chord.pop().and_then(|combination| self.process_combination(combination))
+            }
+            ChordMatch::None => None,
+        }
+    }
+}
+
+/// A structure bound to Keyboard FRP graph, which runs handlers of commands once their chords are
+/// typed.
+///
+/// Each pressed key which is not a modifier completes a key combination of the currently pressed
+/// keys. If the combinations typed so far are the beginning of some longer chord, the structure
+/// waits for the next ones; any combination which does not continue the chord abandons it.
+pub struct KeymapActions {
+    state   : Rc<RefCell<KeymapState>>,
+    _action : Dynamic<()>,
+}
+
+impl KeymapActions {
+    /// Create structure without any handlers yet. It will be listening for events from passed
+    /// `Keyboard` structure.
+    pub fn new(keyboard:&Keyboard, keymap:Keymap) -> Self {
+        let state = Rc::new(RefCell::new(KeymapState {keymap, ..default()}));
+        frp! {
+            keyboard.keymap_action = keyboard.on_pressed.map2
+                (&keyboard.key_mask,Self::perform_command_lambda(state.clone()));
+        }
+        KeymapActions {state, _action:keymap_action}
+    }
+
+    fn perform_command_lambda(state:Rc<RefCell<KeymapState>>) -> impl Fn(&Key,&KeyMask) {
+        move |key,key_mask| {
+            if !is_modifier(key) {
+                let mut combination = key_mask.clone();
+                combination.set_key(key,true);
+                let command = state.borrow_mut().process_combination(combination);
+                if let Some(command) = command {
+                    Self::run_handler(&state,command);
+                }
+            }
+        }
+    }
+
+    fn run_handler(state:&Rc<RefCell<KeymapState>>, command:Command) {
+        let handler = state.borrow_mut().handlers.remove(&command);
+        if let Some(mut handler) = handler {
+            handler();
+            if let Entry::Vacant(entry) = state.borrow_mut().handlers.entry(command) {
+                entry.insert(handler);
+            }
+        }
+    }
+
+    /// Replace the keymap. Any unfinished chord is abandoned.
+    pub fn set_keymap(&mut self, keymap:Keymap) {
+        let mut state = self.state.borrow_mut();
+        state.keymap  = keymap;
+        state.pending = default();
+    }
+
+    /// Get the copy of the current keymap.
+    pub fn keymap(&self) -> Keymap {
+        self.state.borrow().keymap.clone()
+    }
+
+    /// Set handler run when the command is triggered.
+    pub fn set_handler<F:CommandHandler>(&mut self, command:impl Into<Command>, handler:F) {
+        self.state.borrow_mut().handlers.insert(command.into(),Box::new(handler));
+    }
+
+    /// Remove the handler of given command.
+    pub fn unset_handler(&mut self, command:&Command) {
+        self.state.borrow_mut().handlers.remove(command);
+    }
+
+    /// Activate or deactivate the context. Only the bindings without context or with active one
+    /// are triggered.
+    pub fn set_context_active(&self, context:impl Into<Context>, active:bool) {
+        let mut state = self.state.borrow_mut();
+        let context   = context.into();
+        if active {
+            state.contexts.insert(context);
+        } else {
+            state.contexts.remove(&context);
+        }
+        state.pending = default();
+    }
+
+    /// Check if the context is active.
+    pub fn is_context_active(&self, context:&Context) -> bool {
+        self.state.borrow().contexts.contains(context)
+    }
+
+    /// The beginning of chord typed so far, waiting for the next key combinations.
+    pub fn pending_chord(&self) -> Chord {
+        self.state.borrow().pending.clone()
+    }
+}
+
+impl Debug for KeymapActions {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<KeymapActions>")
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use keyboard_types::Key::*;
+
+    fn character(character:&str) -> Key {
+        Character(character.to_string())
+    }
+
+    fn press(keyboard:&Keyboard, keys:&[Key]) {
+        for key in keys {
+            keyboard.on_pressed.event.emit(key.clone());
+        }
+        for key in keys.iter().rev() {
+            keyboard.on_released.event.emit(key.clone());
+        }
+    }
+
+    fn run_counter(actions:&mut KeymapActions, command:&str) -> Rc<Cell<usize>> {
+        let counter = Rc::new(Cell::new(0));
+        let counter_clone = counter.clone();
+        actions.set_handler(command, move || counter_clone.set(counter_clone.get() + 1));
+        counter
+    }
+
+    #[test]
+    fn parsing_chords() {
+        let expected = vec!
+            [ KeyMask::from_vec(vec![Control,character("K")])
+            , KeyMask::from_vec(vec![Control,Shift,character("c")])
+            ];
+        assert_eq!(expected, parse_chord("Ctrl+K  Control+Shift+c").unwrap());
+        let expected = vec![KeyMask::from_vec(vec![Alt,ArrowLeft,character(" "),character("+")])];
+        assert_eq!(expected, parse_chord("Alt+Left+Space+Plus").unwrap());
+        assert_eq!(vec![KeyMask::from_vec(vec![PageUp])], parse_chord("PageUp").unwrap());
+        assert!(parse_chord("Ctrl+Foo").is_err());
+        assert!(parse_chord("Ctrl++").is_err());
+        assert!(Binding::new(" ","command",None).is_err());
+    }
+
+    #[test]
+    fn loading_keymap_from_json() {
+        let json = r#"
+            [ { "keys" : "Ctrl+Z"        , "command" : "undo"                           }
+            , { "keys" : "Ctrl+K Ctrl+C" , "command" : "comment", "context" : "editor" }
+            ]"#;
+        let keymap   = Keymap::from_json(json).unwrap();
+        let bindings = keymap.bindings();
+        assert_eq!(2, bindings.len());
+        assert_eq!(Command::from("undo"), bindings[0].command);
+        assert_eq!(None, bindings[0].context);
+        assert_eq!(2, bindings[1].chord.len());
+        assert_eq!(Some(Context::from("editor")), bindings[1].context);
+        assert!(Keymap::from_json(r#"[{"keys":"Ctrl+Z"}]"#).is_err());
+        assert!(Keymap::from_json(r#"[{"keys":"Ctrl+Foo","command":"undo"}]"#).is_err());
+    }
+
+    #[test]
+    fn reporting_conflicts() {
+        let mut keymap = Keymap::new();
+        keymap.bind("Ctrl+Z"       ,"undo"     ,None).unwrap();
+        keymap.bind("Ctrl+Z"       ,"zoom"     ,Some("graph".into())).unwrap();
+        keymap.bind("Ctrl+K Ctrl+C","comment"  ,Some("editor".into())).unwrap();
+        keymap.bind("Ctrl+K"       ,"kill"     ,Some("editor".into())).unwrap();
+        keymap.bind("Ctrl+K"       ,"collapse" ,Some("graph".into())).unwrap();
+        keymap.bind("Ctrl+Y"       ,"redo"     ,None).unwrap();
+        keymap.bind("Ctrl+Y"       ,"redo_all" ,None).unwrap();
+        let conflicts = keymap.conflicts().into_iter().map(|conflict| {
+            (conflict.kind,conflict.first.command.0,conflict.second.command.0)
+        }).collect_vec();
+        let expected = vec!
+            [ (ConflictKind::SameChord, "undo".to_string()   , "zoom".to_string())
+            , (ConflictKind::Prefix   , "kill".to_string()   , "comment".to_string())
+            , (ConflictKind::SameChord, "redo".to_string()   , "redo_all".to_string())
+            ];
+        assert_eq!(expected, conflicts);
+    }
+
+    #[test]
+    fn overriding_bindings() {
+        let mut keymap = Keymap::new();
+        keymap.bind("Ctrl+Z","undo",None).unwrap();
+        keymap.bind("Ctrl+Y","redo",None).unwrap();
+        keymap.bind("Ctrl+F","find",Some("editor".into())).unwrap();
+        let overrides = Keymap::from_json(r#"
+            [ { "keys" : "Ctrl+Shift+Z" , "command" : "redo" }
+            , { "keys" : "Ctrl+Y"       , "command" : "yank" }
+            , { "keys" : "Ctrl+F"       , "command" : "fold", "context" : "graph" }
+            ]"#).unwrap();
+        let redo_overrides = overrides.filtered(|binding| binding.command.0 == "redo");
+        assert_eq!(redo_overrides.bindings(), &overrides.bindings()[..1]);
+        keymap.override_with(overrides);
+        let bindings = keymap.bindings().iter().map(|b| (b.keys.as_str(),b.command.0.as_str()));
+        let expected = vec!
+            [ ("Ctrl+Z"       , "undo")
+            , ("Ctrl+F"       , "find")
+            , ("Ctrl+Shift+Z" , "redo")
+            , ("Ctrl+Y"       , "yank")
+            , ("Ctrl+F"       , "fold")
+            ];
+        assert_eq!(expected, bindings.collect_vec());
+        assert!(keymap.conflicts().is_empty());
+    }
+
+    #[test]
+    fn running_commands() {
+        let keymap = Keymap::from_json(r#"
+            [ { "keys" : "Ctrl+Z"        , "command" : "undo"                            }
+            , { "keys" : "Ctrl+Shift+Z"  , "command" : "redo"                            }
+            , { "keys" : "Ctrl+K Ctrl+C" , "command" : "comment" , "context" : "editor" }
+            , { "keys" : "Ctrl+K"        , "command" : "collapse", "context" : "graph"  }
+            , { "keys" : "Ctrl+Z"        , "command" : "zoom"    , "context" : "graph"  }
+            ]"#).unwrap();
+        let keyboard    = Keyboard::default();
+        let mut actions = KeymapActions::new(&keyboard,keymap);
+        let undo        = run_counter(&mut actions,"undo");
+        let redo        = run_counter(&mut actions,"redo");
+        let comment     = run_counter(&mut actions,"comment");
+        let collapse    = run_counter(&mut actions,"collapse");
+        let zoom        = run_counter(&mut actions,"zoom");
+        let counts      = || (undo.get(),redo.get(),comment.get(),collapse.get(),zoom.get());
+
+        press(&keyboard,&[character("z")]);
+        assert_eq!((0,0,0,0,0), counts());
+        press(&keyboard,&[Control,character("z")]);
+        assert_eq!((1,0,0,0,0), counts());
+        press(&keyboard,&[Control,Shift,character("Z")]);
+        assert_eq!((1,1,0,0,0), counts());
+
+        // Chords are ignored outside of their contexts.
+        press(&keyboard,&[Control,character("k")]);
+        press(&keyboard,&[Control,character("c")]);
+        assert_eq!((1,1,0,0,0), counts());
+
+        actions.set_context_active("editor",true);
+        keyboard.on_pressed.event.emit(Control);
+        keyboard.on_pressed.event.emit(character("k"));
+        keyboard.on_released.event.emit(character("k"));
+        assert_eq!(1, actions.pending_chord().len());
+        keyboard.on_pressed.event.emit(character("c"));
+        keyboard.on_released.event.emit(character("c"));
+        keyboard.on_released.event.emit(Control);
+        assert_eq!((1,1,1,0,0), counts());
+        assert!(actions.pending_chord().is_empty());
+
+        // The abandoned chord does not swallow the combination breaking it.
+        press(&keyboard,&[Control,character("k")]);
+        press(&keyboard,&[Control,character("z")]);
+        assert_eq!((2,1,1,0,0), counts());
+        assert!(actions.pending_chord().is_empty());
+
+        // The context-specific bindings take precedence over global ones.
+        actions.set_context_active("editor",false);
+        actions.set_context_active("graph",true);
+        press(&keyboard,&[Control,character("k")]);
+        press(&keyboard,&[Control,character("z")]);
+        assert_eq!((2,1,1,1,1), counts());
+
+        actions.unset_handler(&"zoom".into());
+        press(&keyboard,&[Control,character("z")]);
+        assert_eq!((2,1,1,1,1), counts());
+    }
+}
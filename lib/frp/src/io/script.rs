@@ -0,0 +1,516 @@
+//! Input scripts: timestamped sequences of keyboard and mouse events, which may be recorded from
+//! live sessions and replayed into the `Keyboard` and `Mouse` FRP graphs, e.g. to run UI
+//! regression tests headless.
+//!
+//! The script is described in JSON, as a list of events with time in milliseconds since the
+//! script's start:
+//!
+//! ```text
+//! [ { "time" :   0.0, "type" : "text"       , "text" : "foo = 1" }
+//! , { "time" : 120.0, "type" : "key_down"   , "key"  : "Control" }
+//! , { "time" : 150.0, "type" : "key_down"   , "key"  : "s"       }
+//! , { "time" : 170.0, "type" : "key_up"     , "key"  : "s"       }
+//! , { "time" : 180.0, "type" : "key_up"     , "key"  : "Control" }
+//! , { "time" : 300.0, "type" : "mouse_move" , "x"    : 10, "y" : 20 }
+//! , { "time" : 310.0, "type" : "mouse_down" }
+//! , { "time" : 380.0, "type" : "mouse_up"   }
+//! ]
+//! ```
+//!
+//! The key names are the same as in keymap chords (see `keymap::parse_chord`). The `text` event
+//! is a shorthand for pressing and releasing the key of each character in turn, where the line
+//! breaks are typed with the `Enter` key.
+
+use crate::prelude::*;
+
+use crate::*;
+use crate::io::keymap::parse_key;
+
+use serde::Deserialize;
+use serde::Serialize;
+use std::cmp::Ordering;
+
+
+
+// ==================
+// === InputEvent ===
+// ==================
+
+/// A single keyboard or mouse event.
+#[derive(Clone,Debug,PartialEq)]
+pub enum InputEvent {
+    /// The key was pressed.
+    KeyDown(Key),
+    /// The key was released.
+    KeyUp(Key),
+    /// The mouse was moved to the position.
+    MouseMove(Position),
+    /// The mouse button was pressed.
+    MouseDown,
+    /// The mouse button was released.
+    MouseUp,
+    /// The text was typed, each character pressed and released in turn. The line breaks are typed
+    /// with the `Enter` key.
+    Text(String),
+}
+
+/// The event with the time it happened, in milliseconds since the script's start.
+#[derive(Clone,Debug,PartialEq)]
+pub struct TimedEvent {
+    /// The time in milliseconds.
+    pub time  : f64,
+    /// The event.
+    pub event : InputEvent,
+}
+
+/// Errors which may occur when reading the script.
+#[derive(Clone,Debug,Fail)]
+pub enum ScriptError {
+    /// The script is not a valid JSON list of events.
+    #[fail(display = "Invalid input script: {}.", _0)]
+    InvalidDefinition(String),
+    /// The key name was not recognized.
+    #[fail(display = "Unknown key \"{}\" in input script.", _0)]
+    UnknownKey(String),
+}
+
+/// An event as it is written in JSON script.
+#[derive(Deserialize,Serialize)]
+struct EventDefinition {
+    time : f64,
+    #[serde(flatten)]
+    kind : EventKindDefinition,
+}
+
+#[derive(Deserialize,Serialize)]
+#[serde(tag="type", rename_all="snake_case")]
+enum EventKindDefinition {
+    KeyDown   {key:String},
+    KeyUp     {key:String},
+    MouseMove {x:i32, y:i32},
+    MouseDown,
+    MouseUp,
+    Text      {text:String},
+}
+
+impl TimedEvent {
+    fn from_definition(definition:EventDefinition) -> Result<Self,ScriptError> {
+        use EventKindDefinition as Kind;
+        let key   = |name:String| {
+            parse_key(&name,&name).map_err(|_| ScriptError::UnknownKey(name))
+        };
+        let time  = definition.time;
+        let event = match definition.kind {
+            Kind::KeyDown   {key:name} => InputEvent::KeyDown(key(name)?),
+            Kind::KeyUp     {key:name} => InputEvent::KeyUp(key(name)?),
+            Kind::MouseMove {x,y}      => InputEvent::MouseMove(Position::new(x,y)),
+            Kind::MouseDown            => InputEvent::MouseDown,
+            Kind::MouseUp              => InputEvent::MouseUp,
+            Kind::Text      {text}     => InputEvent::Text(text),
+        };
+        Ok(TimedEvent {time,event})
+    }
+
+    fn to_definition(&self) -> EventDefinition {
+        use EventKindDefinition as Kind;
+        let time = self.time;
+        let kind = match &self.event {
+            InputEvent::KeyDown(key)        => Kind::KeyDown   {key:key.to_string()},
+            InputEvent::KeyUp(key)          => Kind::KeyUp     {key:key.to_string()},
+            InputEvent::MouseMove(position) => Kind::MouseMove {x:position.x, y:position.y},
+            InputEvent::MouseDown           => Kind::MouseDown,
+            InputEvent::MouseUp             => Kind::MouseUp,
+            InputEvent::Text(text)          => Kind::Text      {text:text.clone()},
+        };
+        EventDefinition {time,kind}
+    }
+}
+
+
+
+// ===================
+// === InputScript ===
+// ===================
+
+/// A sequence of events ordered by their time. See the module docs for the JSON format.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct InputScript {
+    /// The events.
+    pub events : Vec<TimedEvent>,
+}
+
+impl InputScript {
+    /// Create an empty script.
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Create script from the JSON definition.
+    pub fn from_json(json:&str) -> Result<Self,ScriptError> {
+        let to_error    = |err:serde_json::Error| ScriptError::InvalidDefinition(err.to_string());
+        let definitions = serde_json::from_str::<Vec<EventDefinition>>(json).map_err(to_error)?;
+        let events      = definitions.into_iter().map(TimedEvent::from_definition);
+        let mut script  = Self {events:events.collect::<Result<_,_>>()?};
+        script.sort();
+        Ok(script)
+    }
+
+    /// Describe the script in JSON.
+    pub fn to_json(&self) -> String {
+        let definitions = self.events.iter().map(TimedEvent::to_definition).collect_vec();
+        serde_json::to_string_pretty(&definitions).expect("Input script should be serializable.")
+    }
+
+    /// Add the event at given time. The events added with the same time keep their order.
+    ///
+    /// The event is inserted after all the events which are not later, searching from the end, so
+    /// adding the events in order of their time (e.g. when recording) does not move any event.
+    pub fn push(&mut self, time:f64, event:InputEvent) {
+        let index = self.events.iter().rposition(|e| e.time <= time).map_or(0, |i| i + 1);
+        self.events.insert(index,TimedEvent {time,event});
+    }
+
+    /// The time of the last event, or 0 if the script is empty.
+    pub fn duration(&self) -> f64 {
+        self.events.last().map_or(0.0, |event| event.time)
+    }
+
+    /// Append the pressing of key combination (e.g. `Ctrl+S`) and releasing its keys in reverse
+    /// order, all at the end of script. Convenient for writing scripts in tests.
+    pub fn press(mut self, keys:&str) -> Result<Self,ScriptError> {
+        let names = keys.split('+').map(|name| parse_key(name,keys).map_err(|_| name));
+        let keys  = names.collect::<Result<Vec<_>,_>>().map_err(|name| {
+            ScriptError::UnknownKey(name.to_string())
+        })?;
+        let time  = self.duration();
+        for key in &keys {
+            self.events.push(TimedEvent {time, event:InputEvent::KeyDown(key.clone())});
+        }
+        for key in keys.into_iter().rev() {
+            self.events.push(TimedEvent {time, event:InputEvent::KeyUp(key)});
+        }
+        Ok(self)
+    }
+
+    /// Append the typing of text at the end of script. Convenient for writing scripts in tests.
+    pub fn type_text(mut self, text:impl Str) -> Self {
+        let time = self.duration();
+        self.events.push(TimedEvent {time, event:InputEvent::Text(text.into())});
+        self
+    }
+
+    fn sort(&mut self) {
+        self.events.sort_by(|a,b| a.time.partial_cmp(&b.time).unwrap_or(Ordering::Equal));
+    }
+}
+
+
+
+// ====================
+// === InputSources ===
+// ====================
+
+/// The FRP graphs fed with the events by `InputPlayer`. The events of a missing graph are
+/// skipped.
+#[derive(Clone,Copy,Debug,Default)]
+pub struct InputSources<'a> {
+    /// The keyboard graph.
+    pub keyboard : Option<&'a Keyboard>,
+    /// The mouse graph.
+    pub mouse    : Option<&'a Mouse>,
+}
+
+impl<'a> InputSources<'a> {
+    /// Sources feeding the keyboard graph only.
+    pub fn keyboard(keyboard:&'a Keyboard) -> Self {
+        let keyboard = Some(keyboard);
+        let mouse    = None;
+        Self {keyboard,mouse}
+    }
+
+    /// Sources feeding both keyboard and mouse graphs.
+    pub fn new(keyboard:&'a Keyboard, mouse:&'a Mouse) -> Self {
+        let keyboard = Some(keyboard);
+        let mouse    = Some(mouse);
+        Self {keyboard,mouse}
+    }
+
+    /// Emit the event in the proper FRP source.
+    pub fn emit(&self, event:&InputEvent) {
+        match event {
+            InputEvent::KeyDown(key) => self.keyboard.for_each(|kb| kb.on_pressed.event.emit(key)),
+            InputEvent::KeyUp(key)   => self.keyboard.for_each(|kb| kb.on_released.event.emit(key)),
+            InputEvent::MouseMove(position) => {
+                self.mouse.for_each(|mouse| mouse.position.event.emit(position))
+            }
+            InputEvent::MouseDown => self.mouse.for_each(|mouse| mouse.on_down.event.emit(())),
+            InputEvent::MouseUp   => self.mouse.for_each(|mouse| mouse.on_up.event.emit(())),
+            InputEvent::Text(text) => for character in text.chars() {
+                let key = match character {
+                    '\n' => Key::Enter,
+                    _    => Key::Character(character.to_string()),
+                };
+                self.emit(&InputEvent::KeyDown(key.clone()));
+                self.emit(&InputEvent::KeyUp(key));
+            }
+        }
+    }
+}
+
+
+
+// ===================
+// === InputPlayer ===
+// ===================
+
+/// Replays the script, emitting its events in the FRP sources.
+///
+/// The player does not measure time by itself: it is advanced explicitly, e.g. on each animation
+/// frame in the browser, or at once with `play_all` in headless tests.
+#[derive(Clone,Debug)]
+pub struct InputPlayer {
+    script : InputScript,
+    next   : usize,
+}
+
+impl InputPlayer {
+    /// Create player at the script's start.
+    pub fn new(script:InputScript) -> Self {
+        let next = 0;
+        Self {script,next}
+    }
+
+    /// Emit all not yet played events which happened until given time since the script's start.
+    pub fn advance_to(&mut self, time:f64, sources:InputSources) {
+        while let Some(timed) = self.script.events.get(self.next).filter(|e| e.time <= time) {
+            self.next += 1;
+            sources.emit(&timed.event);
+        }
+    }
+
+    /// Emit all remaining events at once, ignoring their time.
+    pub fn play_all(&mut self, sources:InputSources) {
+        self.advance_to(std::f64::INFINITY,sources);
+    }
+
+    /// Check if all events were played.
+    pub fn is_finished(&self) -> bool {
+        self.next >= self.script.events.len()
+    }
+}
+
+
+
+// =====================
+// === InputRecorder ===
+// =====================
+
+/// A clock giving the current time in milliseconds, e.g. from `window.performance.now()`.
+pub trait Clock = Fn() -> f64 + 'static;
+
+/// A structure bound to Keyboard and Mouse FRP graphs, which records all their events into the
+/// script, as long as it lives. The event time is measured since the recorder's creation.
+pub struct InputRecorder {
+    script : Rc<RefCell<InputScript>>,
+    _nodes : Vec<Dynamic<()>>,
+}
+
+impl InputRecorder {
+    /// Start recording events of given sources.
+    pub fn new<C:Clock>(sources:InputSources, clock:C) -> Self {
+        let script    = Rc::new(RefCell::new(InputScript::new()));
+        let start     = clock();
+        let clock     = Rc::new(move || clock() - start) as Rc<dyn Fn() -> f64>;
+        let mut nodes = Vec::new();
+        if let Some(keyboard) = sources.keyboard {
+            let key_down = Self::record_lambda(&script,&clock,|key:&Key| {
+                InputEvent::KeyDown(key.clone())
+            });
+            let key_up = Self::record_lambda(&script,&clock,|key:&Key| {
+                InputEvent::KeyUp(key.clone())
+            });
+            frp! {
+                keyboard.record_key_down = keyboard.on_pressed.map(key_down);
+                keyboard.record_key_up   = keyboard.on_released.map(key_up);
+            }
+            nodes.push(record_key_down);
+            nodes.push(record_key_up);
+        }
+        if let Some(mouse) = sources.mouse {
+            let move_to = Self::record_lambda(&script,&clock,|position:&Position| {
+                InputEvent::MouseMove(*position)
+            });
+            let down = Self::record_lambda(&script,&clock,|_:&()| InputEvent::MouseDown);
+            let up   = Self::record_lambda(&script,&clock,|_:&()| InputEvent::MouseUp);
+            frp! {
+                mouse.record_move = mouse.position.map(move_to);
+                mouse.record_down = mouse.on_down.map(down);
+                mouse.record_up   = mouse.on_up.map(up);
+            }
+            nodes.push(record_move);
+            nodes.push(record_down);
+            nodes.push(record_up);
+        }
+        InputRecorder {script, _nodes:nodes}
+    }
+
+    /// Start recording events of given sources, with time measured by `window.performance`.
+    pub fn new_with_performance_clock(sources:InputSources) -> Self {
+        let performance = web::performance();
+        Self::new(sources, move || performance.now())
+    }
+
+    /// The events recorded so far.
+    pub fn script(&self) -> InputScript {
+        self.script.borrow().clone()
+    }
+
+    fn record_lambda<T,F>(script:&Rc<RefCell<InputScript>>, clock:&Rc<dyn Fn() -> f64>, f:F)
+    -> impl Fn(&T)
+    where T : 'static,
+          F : Fn(&T) -> InputEvent + 'static {
+        let script = script.clone();
+        let clock  = clock.clone();
+        move |value| script.borrow_mut().push(clock(),f(value))
+    }
+}
+
+impl Debug for InputRecorder {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "<InputRecorder>")
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use keyboard_types::Key::*;
+
+    fn character(character:&str) -> Key {
+        Character(character.to_string())
+    }
+
+    #[test]
+    fn reading_and_writing_json() {
+        let json = r#"
+            [ { "time" : 10.0, "type" : "key_down"   , "key"  : "Ctrl"   }
+            , { "time" :  0.0, "type" : "text"       , "text" : "ab"     }
+            , { "time" : 20.0, "type" : "key_up"     , "key"  : "Ctrl"   }
+            , { "time" : 30.0, "type" : "mouse_move" , "x" : 1, "y" : -2 }
+            , { "time" : 40.0, "type" : "mouse_down" }
+            , { "time" : 50.0, "type" : "mouse_up"   }
+            ]"#;
+        let script   = InputScript::from_json(json).unwrap();
+        let expected = vec!
+            [ TimedEvent {time: 0.0, event:InputEvent::Text("ab".to_string())}
+            , TimedEvent {time:10.0, event:InputEvent::KeyDown(Control)}
+            , TimedEvent {time:20.0, event:InputEvent::KeyUp(Control)}
+            , TimedEvent {time:30.0, event:InputEvent::MouseMove(Position::new(1,-2))}
+            , TimedEvent {time:40.0, event:InputEvent::MouseDown}
+            , TimedEvent {time:50.0, event:InputEvent::MouseUp}
+            ];
+        assert_eq!(script.events, expected);
+        assert_eq!(InputScript::from_json(&script.to_json()).unwrap(), script);
+
+        let unknown_key = r#"[ { "time" : 0.0, "type" : "key_down", "key" : "Foo" } ]"#;
+        assert!(InputScript::from_json(unknown_key).is_err());
+        assert!(InputScript::from_json(r#"[ { "type" : "mouse_up" } ]"#).is_err());
+    }
+
+    #[test]
+    fn playing_script_into_keymap() {
+        let keyboard    = Keyboard::default();
+        let keymap      = Keymap::from_json(r#"[{"keys":"Ctrl+S", "command":"save"}]"#).unwrap();
+        let mut actions = KeymapActions::new(&keyboard,keymap);
+        let typed       = Rc::new(RefCell::new(String::new()));
+        let saved       = Rc::new(RefCell::new(None));
+        let typed_clone = typed.clone();
+        let saved_clone = saved.clone();
+        actions.set_handler("save", move || {
+            *saved_clone.borrow_mut() = Some(typed_clone.borrow().clone())
+        });
+        let typed_clone = typed.clone();
+        frp! {
+            test._text_input = keyboard.on_pressed.map2(&keyboard.key_mask,move |key,mask| {
+                if let Character(string) = key {
+                    if !mask.has_key(&Control) { typed_clone.borrow_mut().push_str(string) }
+                }
+            });
+        }
+
+        let script     = InputScript::new().type_text("foo = 1").press("Ctrl+s").unwrap();
+        let mut player = InputPlayer::new(script);
+        player.play_all(InputSources::keyboard(&keyboard));
+        assert!(player.is_finished());
+        assert_eq!(*saved.borrow(), Some("foo = 1".to_string()));
+        assert_eq!(keyboard.key_mask.behavior.current_value(), KeyMask::default());
+    }
+
+    #[test]
+    fn pushing_events_and_typing_line_breaks() {
+        let mut script = InputScript::new();
+        script.push(10.0,InputEvent::MouseDown);
+        script.push( 0.0,InputEvent::Text("a\nb".to_string()));
+        script.push(10.0,InputEvent::MouseUp);
+        let expected = vec!
+            [ TimedEvent {time: 0.0, event:InputEvent::Text("a\nb".to_string())}
+            , TimedEvent {time:10.0, event:InputEvent::MouseDown}
+            , TimedEvent {time:10.0, event:InputEvent::MouseUp}
+            ];
+        assert_eq!(script.events, expected);
+
+        let keyboard      = Keyboard::default();
+        let pressed       = Rc::new(RefCell::new(Vec::new()));
+        let pressed_clone = pressed.clone();
+        frp! {
+            test._pressed = keyboard.on_pressed.map(move |key:&Key| {
+                pressed_clone.borrow_mut().push(key.clone())
+            });
+        }
+        InputPlayer::new(script).play_all(InputSources::keyboard(&keyboard));
+        assert_eq!(*pressed.borrow(), vec![character("a"),Enter,character("b")]);
+    }
+
+    #[test]
+    fn recording_and_replaying() {
+        let keyboard = Keyboard::default();
+        let mouse    = Mouse::new();
+        let time     = Rc::new(Cell::new(100.0));
+        let time_ref = time.clone();
+        let clock    = move || time_ref.get();
+        let recorder = InputRecorder::new(InputSources::new(&keyboard,&mouse),clock);
+        keyboard.on_pressed.event.emit(character("x"));
+        time.set(150.0);
+        keyboard.on_released.event.emit(character("x"));
+        mouse.position.event.emit(Position::new(3,4));
+        mouse.on_down.event.emit(());
+        let expected = vec!
+            [ TimedEvent {time: 0.0, event:InputEvent::KeyDown(character("x"))}
+            , TimedEvent {time:50.0, event:InputEvent::KeyUp(character("x"))}
+            , TimedEvent {time:50.0, event:InputEvent::MouseMove(Position::new(3,4))}
+            , TimedEvent {time:50.0, event:InputEvent::MouseDown}
+            ];
+        assert_eq!(recorder.script().events, expected);
+
+        let replayed_keyboard = Keyboard::default();
+        let replayed_mouse    = Mouse::new();
+        let sources           = InputSources::new(&replayed_keyboard,&replayed_mouse);
+        let mut player        = InputPlayer::new(recorder.script());
+        player.advance_to(10.0,sources);
+        let pressed = KeyMask::from_vec(vec![character("x")]);
+        assert_eq!(replayed_keyboard.key_mask.behavior.current_value(), pressed);
+        assert!(!player.is_finished());
+        player.advance_to(50.0,sources);
+        assert!(player.is_finished());
+        assert_eq!(replayed_keyboard.key_mask.behavior.current_value(), KeyMask::default());
+        assert_eq!(replayed_mouse.position.behavior.current_value(), Position::new(3,4));
+        assert!(replayed_mouse.is_down.behavior.current_value());
+    }
+}
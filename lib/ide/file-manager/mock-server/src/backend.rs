@@ -0,0 +1,85 @@
+//! The `Backend` trait abstracting over the filesystem served by the server.
+
+pub mod memory;
+pub mod native;
+
+use enso_prelude::*;
+
+use crate::error::Error;
+use crate::error::Result;
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+
+// ===============
+// === Backend ===
+// ===============
+
+/// Filesystem operations used to implement the File Manager API.
+///
+/// All paths are relative to the served root and normalized with `sandbox::resolve`, so they
+/// never point outside of the root. The root itself is an empty path. The checks required by
+/// the API (e.g. that the copy target does not exist yet) are done by the server, backends just
+/// perform the operations.
+pub trait Backend : Debug {
+    /// Get the file attributes.
+    fn status(&self, path:&Path) -> Result<fmc::Attributes>;
+
+    /// List paths of the directory entries.
+    fn list(&self, path:&Path) -> Result<Vec<PathBuf>>;
+
+    /// Read the whole file contents.
+    fn read(&self, path:&Path) -> Result<Vec<u8>>;
+
+    /// Write the file contents, creating the file if it does not exist.
+    fn write(&mut self, path:&Path, contents:&[u8]) -> Result<()>;
+
+    /// Overwrite the file contents starting at `offset`, extending the file if needed.
+    fn write_range(&mut self, path:&Path, offset:u64, contents:&[u8]) -> Result<()>;
+
+    /// Create an empty file if it does not exist.
+    fn touch(&mut self, path:&Path) -> Result<()>;
+
+    /// Create the directory and all its missing parents.
+    fn create_directory(&mut self, path:&Path) -> Result<()>;
+
+    /// Copy the regular file.
+    fn copy_file(&mut self, from:&Path, to:&Path) -> Result<()>;
+
+    /// Move the file or directory.
+    fn rename(&mut self, from:&Path, to:&Path) -> Result<()>;
+
+    /// Delete the file or the directory with all its contents.
+    fn delete(&mut self, path:&Path) -> Result<()>;
+
+    /// Check if the file exists.
+    fn exists(&self, path:&Path) -> bool {
+        self.status(path).is_ok()
+    }
+
+    /// Read at most `length` bytes of the file, starting at `offset`.
+    fn read_range(&self, path:&Path, offset:u64, length:u64) -> Result<Vec<u8>> {
+        let contents = self.read(path)?;
+        let start    = (offset as usize).min(contents.len());
+        let end      = start.saturating_add(length as usize).min(contents.len());
+        Ok(contents[start..end].to_vec())
+    }
+
+    /// Copy the directory with all its contents.
+    fn copy_directory(&mut self, from:&Path, to:&Path) -> Result<()> {
+        self.create_directory(to)?;
+        for entry in self.list(from)? {
+            let missing = || Error::NotFound(sandbox::client_path(&entry));
+            let target  = to.join(entry.file_name().ok_or_else(missing)?);
+            match self.status(&entry)?.file_kind {
+                fmc::FileKind::Directory => self.copy_directory(&entry,&target)?,
+                _                        => self.copy_file(&entry,&target)?,
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,290 @@
+//! Backend keeping the whole filesystem in memory, for hermetic tests.
+
+use enso_prelude::*;
+
+use crate::backend::Backend;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+
+// =============
+// === Entry ===
+// =============
+
+/// Contents of a single file.
+#[derive(Clone,Debug,PartialEq)]
+enum Node {
+    Directory,
+    File(Vec<u8>),
+}
+
+/// A single file with its attributes.
+#[derive(Clone,Debug)]
+struct Entry {
+    node     : Node,
+    created  : fmc::FileTime,
+    modified : fmc::FileTime,
+}
+
+impl Entry {
+    fn new(node:Node) -> Self {
+        let created  = now();
+        let modified = created;
+        Entry {node,created,modified}
+    }
+}
+
+/// The largest file that can be kept in memory, in bytes. Writes that would make a file larger
+/// are rejected.
+pub const MAX_FILE_SIZE:usize = 256 * 1024 * 1024;
+
+/// The current time as `FileTime`.
+fn now() -> fmc::FileTime {
+    chrono::Utc::now().into()
+}
+
+
+
+// ========================
+// === MemoryFilesystem ===
+// ========================
+
+/// Backend keeping all files in memory. Initially it contains only the empty root directory.
+#[derive(Clone,Debug)]
+pub struct MemoryFilesystem {
+    entries : BTreeMap<PathBuf,Entry>,
+}
+
+impl Default for MemoryFilesystem {
+    fn default() -> Self {
+        let mut entries = BTreeMap::new();
+        entries.insert(PathBuf::new(),Entry::new(Node::Directory));
+        MemoryFilesystem {entries}
+    }
+}
+
+impl MemoryFilesystem {
+    /// Creates a filesystem with the empty root directory.
+    pub fn new() -> Self {
+        default()
+    }
+
+    /// Adds the file with given contents, creating all the missing parent directories. Meant for
+    /// preparing the test data.
+    pub fn insert_file(&mut self, path:impl AsRef<Path>, contents:impl Into<Vec<u8>>) {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            self.create_directory(parent).expect("Cannot create the parent directory.");
+        }
+        self.write(path,&contents.into()).expect("Cannot write the file.");
+    }
+
+    fn entry(&self, path:&Path) -> Result<&Entry> {
+        self.entries.get(path).ok_or_else(|| Error::NotFound(sandbox::client_path(path)))
+    }
+
+    fn file_contents(&self, path:&Path) -> Result<&Vec<u8>> {
+        match &self.entry(path)?.node {
+            Node::File(contents) => Ok(contents),
+            Node::Directory      => Err(Error::IsDirectory(sandbox::client_path(path))),
+        }
+    }
+
+    /// Checks that the directory where the file would be created exists.
+    fn check_parent(&self, path:&Path) -> Result<()> {
+        let is_root = || Error::IsDirectory(sandbox::client_path(path));
+        let parent  = path.parent().ok_or_else(is_root)?;
+        match self.entry(parent)?.node {
+            Node::Directory => Ok(()),
+            Node::File(_)   => Err(Error::NotDirectory(sandbox::client_path(parent))),
+        }
+    }
+
+    /// Paths of the given file and all files below it.
+    fn subtree(&self, path:&Path) -> Vec<PathBuf> {
+        self.entries.keys().filter(|key| key.starts_with(path)).cloned().collect()
+    }
+}
+
+impl Backend for MemoryFilesystem {
+    fn status(&self, path:&Path) -> Result<fmc::Attributes> {
+        let entry = self.entry(path)?;
+        let (file_kind,byte_size) = match &entry.node {
+            Node::Directory      => (fmc::FileKind::Directory,0),
+            Node::File(contents) => (fmc::FileKind::RegularFile,contents.len() as u64),
+        };
+        Ok(fmc::Attributes {
+            creation_time      : entry.created,
+            last_access_time   : entry.modified,
+            last_modified_time : entry.modified,
+            file_kind,
+            byte_size,
+        })
+    }
+
+    fn list(&self, path:&Path) -> Result<Vec<PathBuf>> {
+        match self.entry(path)?.node {
+            Node::File(_)   => Err(Error::NotDirectory(sandbox::client_path(path))),
+            Node::Directory => {
+                let children = self.entries.keys().filter(|key| key.parent() == Some(path));
+                Ok(children.cloned().collect())
+            }
+        }
+    }
+
+    fn read(&self, path:&Path) -> Result<Vec<u8>> {
+        Ok(self.file_contents(path)?.clone())
+    }
+
+    fn write(&mut self, path:&Path, contents:&[u8]) -> Result<()> {
+        self.check_parent(path)?;
+        match self.entries.get_mut(path) {
+            Some(Entry{node:Node::Directory,..}) =>
+                Err(Error::IsDirectory(sandbox::client_path(path))),
+            Some(entry) => {
+                entry.node     = Node::File(contents.to_vec());
+                entry.modified = now();
+                Ok(())
+            },
+            None => {
+                let entry = Entry::new(Node::File(contents.to_vec()));
+                self.entries.insert(path.to_path_buf(),entry);
+                Ok(())
+            },
+        }
+    }
+
+    fn write_range(&mut self, path:&Path, offset:u64, contents:&[u8]) -> Result<()> {
+        let too_large = || {
+            let path = sandbox::client_path(path);
+            Error::Other(format!("File {} cannot exceed {} bytes.",path,MAX_FILE_SIZE))
+        };
+        let start    = usize::try_from(offset).map_err(|_| too_large())?;
+        let end      = start.checked_add(contents.len()).filter(|end| *end <= MAX_FILE_SIZE);
+        let end      = end.ok_or_else(too_large)?;
+        let mut data = if self.exists(path) { self.read(path)? } else { default() };
+        if data.len() < end {
+            data.resize(end,0);
+        }
+        data[start..end].copy_from_slice(contents);
+        self.write(path,&data)
+    }
+
+    fn touch(&mut self, path:&Path) -> Result<()> {
+        if self.exists(path) { Ok(()) } else { self.write(path,&[]) }
+    }
+
+    fn create_directory(&mut self, path:&Path) -> Result<()> {
+        let ancestors = path.ancestors().collect_vec();
+        for directory in ancestors.into_iter().rev() {
+            match self.entries.get(directory).map(|entry| &entry.node) {
+                Some(Node::Directory) => {},
+                Some(Node::File(_))   =>
+                    return Err(Error::NotDirectory(sandbox::client_path(directory))),
+                None => {
+                    let entry = Entry::new(Node::Directory);
+                    self.entries.insert(directory.to_path_buf(),entry);
+                },
+            }
+        }
+        Ok(())
+    }
+
+    fn copy_file(&mut self, from:&Path, to:&Path) -> Result<()> {
+        let contents = self.file_contents(from)?.clone();
+        self.write(to,&contents)
+    }
+
+    fn rename(&mut self, from:&Path, to:&Path) -> Result<()> {
+        self.entry(from)?;
+        self.check_parent(to)?;
+        if to.starts_with(from) {
+            let message = format!("Cannot move {} into itself.",sandbox::client_path(from));
+            return Err(Error::Other(message))
+        }
+        for path in self.subtree(from) {
+            if let Some(entry) = self.entries.remove(&path) {
+                let relative = path.strip_prefix(from).unwrap_or(path.as_path());
+                self.entries.insert(to.join(relative),entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, path:&Path) -> Result<()> {
+        self.entry(path)?;
+        for path in self.subtree(path) {
+            self.entries.remove(&path);
+        }
+        Ok(())
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(paths:&[&str]) -> Vec<PathBuf> {
+        paths.iter().map(PathBuf::from).collect()
+    }
+
+    #[test]
+    fn writing_and_listing_files() {
+        let mut fs = MemoryFilesystem::new();
+        fs.insert_file("src/Main.enso","main = 2 + 2");
+        fs.insert_file("src/Lib/Util.enso","");
+        assert_eq!(fs.list(Path::new("")).unwrap(), paths(&["src"]));
+        assert_eq!(fs.list(Path::new("src")).unwrap(), paths(&["src/Lib","src/Main.enso"]));
+        assert_eq!(fs.read(Path::new("src/Main.enso")).unwrap(), b"main = 2 + 2");
+        let status = fs.status(Path::new("src/Main.enso")).unwrap();
+        assert_eq!(status.file_kind, fmc::FileKind::RegularFile);
+        assert_eq!(status.byte_size, 12);
+
+        let error = fs.write(Path::new("missing/Main.enso"),b"");
+        assert_eq!(error, Err(Error::NotFound(fmc::Path::new("./missing"))));
+        let error = fs.read(Path::new("src"));
+        assert_eq!(error, Err(Error::IsDirectory(fmc::Path::new("./src"))));
+    }
+
+    #[test]
+    fn writing_ranges() {
+        let mut fs   = MemoryFilesystem::new();
+        let     path = Path::new("data.bin");
+        fs.write_range(path,2,&[1,2]).unwrap();
+        fs.write_range(path,0,&[3]).unwrap();
+        assert_eq!(fs.read(path).unwrap(), vec![3,0,1,2]);
+        assert_eq!(fs.read_range(path,1,2).unwrap(), vec![0,1]);
+        assert_eq!(fs.read_range(path,3,10).unwrap(), vec![2]);
+        assert!(fs.read_range(path,10,10).unwrap().is_empty());
+
+        let huge = MAX_FILE_SIZE as u64;
+        assert!(fs.write_range(path,u64::max_value(),&[1]).is_err());
+        assert!(fs.write_range(path,huge,&[1]).is_err());
+        assert_eq!(fs.read(path).unwrap(), vec![3,0,1,2]);
+    }
+
+    #[test]
+    fn moving_copying_and_deleting_directories() {
+        let mut fs = MemoryFilesystem::new();
+        fs.insert_file("a/b/c.txt","c");
+        fs.copy_directory(Path::new("a"),Path::new("copy")).unwrap();
+        fs.rename(Path::new("a"),Path::new("moved")).unwrap();
+        assert!(!fs.exists(Path::new("a")));
+        assert_eq!(fs.read(Path::new("moved/b/c.txt")).unwrap(), b"c");
+        assert_eq!(fs.read(Path::new("copy/b/c.txt")).unwrap(), b"c");
+        fs.delete(Path::new("moved")).unwrap();
+        assert_eq!(fs.list(Path::new("")).unwrap(), paths(&["copy"]));
+    }
+}
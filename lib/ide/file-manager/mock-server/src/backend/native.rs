@@ -0,0 +1,227 @@
+//! Backend serving a directory of the real filesystem.
+
+use crate::backend::Backend;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use std::fs;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+
+
+// ========================
+// === NativeFilesystem ===
+// ========================
+
+/// Backend serving the given root directory of the real filesystem.
+///
+/// Symbolic links are not followed when listing directories, though the files they point to can
+/// still be read and written, as long as they are inside the root.
+#[derive(Clone,Debug)]
+pub struct NativeFilesystem {
+    root : PathBuf,
+}
+
+impl NativeFilesystem {
+    /// Creates a backend serving the given directory.
+    pub fn new(root:impl Into<PathBuf>) -> Self {
+        let root = root.into();
+        NativeFilesystem {root}
+    }
+
+    /// The served root directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Path in the real filesystem for the path relative to the root. Fails if symbolic links
+    /// make it point outside of the root.
+    fn absolute(&self, path:&Path) -> Result<PathBuf> {
+        sandbox::confine(&self.root,path)
+    }
+
+    /// Runs the IO operation, reporting its failure as an error about the given path.
+    fn io<T>(path:&Path, operation:impl FnOnce() -> std::io::Result<T>) -> Result<T> {
+        operation().map_err(|error| Error::from_io(path,error))
+    }
+}
+
+fn file_time(time:SystemTime) -> fmc::FileTime {
+    chrono::DateTime::<chrono::Utc>::from(time).into()
+}
+
+fn file_kind(file_type:fs::FileType) -> fmc::FileKind {
+    if      file_type.is_symlink() { fmc::FileKind::SymbolicLink }
+    else if file_type.is_dir()     { fmc::FileKind::Directory    }
+    else if file_type.is_file()    { fmc::FileKind::RegularFile  }
+    else                           { fmc::FileKind::Other        }
+}
+
+impl Backend for NativeFilesystem {
+    fn status(&self, path:&Path) -> Result<fmc::Attributes> {
+        let absolute = self.absolute(path)?;
+        let metadata = Self::io(path, || fs::symlink_metadata(absolute))?;
+        let modified = Self::io(path, || metadata.modified())?;
+        // Not all platforms track the creation and access times.
+        let created  = metadata.created().unwrap_or(modified);
+        let accessed = metadata.accessed().unwrap_or(modified);
+        Ok(fmc::Attributes {
+            creation_time      : file_time(created),
+            last_access_time   : file_time(accessed),
+            last_modified_time : file_time(modified),
+            file_kind          : file_kind(metadata.file_type()),
+            byte_size          : metadata.len(),
+        })
+    }
+
+    fn list(&self, path:&Path) -> Result<Vec<PathBuf>> {
+        let absolute    = self.absolute(path)?;
+        let entries     = Self::io(path, || fs::read_dir(absolute))?;
+        let mut entries = Self::io(path, || entries.collect::<std::io::Result<Vec<_>>>())?;
+        entries.sort_by_key(|entry| entry.file_name());
+        Ok(entries.iter().map(|entry| path.join(entry.file_name())).collect())
+    }
+
+    fn read(&self, path:&Path) -> Result<Vec<u8>> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || fs::read(absolute))
+    }
+
+    fn write(&mut self, path:&Path, contents:&[u8]) -> Result<()> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || fs::write(absolute,contents))
+    }
+
+    fn write_range(&mut self, path:&Path, offset:u64, contents:&[u8]) -> Result<()> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || {
+            let mut opts = fs::OpenOptions::new();
+            let mut file = opts.create(true).write(true).open(absolute)?;
+            file.seek(SeekFrom::Start(offset))?;
+            file.write_all(contents)
+        })
+    }
+
+    fn touch(&mut self, path:&Path) -> Result<()> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || {
+            let mut opts = fs::OpenOptions::new();
+            opts.create(true).append(true).open(absolute).map(|_| ())
+        })
+    }
+
+    fn create_directory(&mut self, path:&Path) -> Result<()> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || fs::create_dir_all(absolute))
+    }
+
+    fn copy_file(&mut self, from:&Path, to:&Path) -> Result<()> {
+        let (source,target) = (self.absolute(from)?,self.absolute(to)?);
+        Self::io(from, || fs::copy(source,target).map(|_| ()))
+    }
+
+    fn rename(&mut self, from:&Path, to:&Path) -> Result<()> {
+        let (source,target) = (self.absolute(from)?,self.absolute(to)?);
+        Self::io(from, || fs::rename(source,target))
+    }
+
+    fn delete(&mut self, path:&Path) -> Result<()> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || {
+            if fs::symlink_metadata(&absolute)?.is_dir() {
+                fs::remove_dir_all(&absolute)
+            } else {
+                fs::remove_file(&absolute)
+            }
+        })
+    }
+
+    fn read_range(&self, path:&Path, offset:u64, length:u64) -> Result<Vec<u8>> {
+        let absolute = self.absolute(path)?;
+        Self::io(path, || {
+            let mut file     = fs::File::open(absolute)?;
+            let mut contents = Vec::new();
+            file.seek(SeekFrom::Start(offset))?;
+            file.take(length).read_to_end(&mut contents)?;
+            Ok(contents)
+        })
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+
+    use uuid::Uuid;
+
+    /// A backend serving a fresh, empty directory.
+    pub(crate) fn temp_filesystem() -> NativeFilesystem {
+        let root = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        fs::create_dir_all(&root).unwrap();
+        NativeFilesystem::new(root)
+    }
+
+    #[test]
+    fn status_of_files() {
+        let mut backend = temp_filesystem();
+        let     file    = Path::new("src/Main.enso");
+        backend.create_directory(Path::new("src")).unwrap();
+        backend.write(file,b"main = 2 + 2").unwrap();
+        let status = backend.status(file).unwrap();
+        assert_eq!(status.file_kind, fmc::FileKind::RegularFile);
+        assert_eq!(status.byte_size, 12);
+        let status = backend.status(Path::new("src")).unwrap();
+        assert_eq!(status.file_kind, fmc::FileKind::Directory);
+        assert_eq!(backend.list(Path::new("")).unwrap(), vec![PathBuf::from("src")]);
+        let missing = Path::new("src/Missing.enso");
+        let error   = Error::NotFound(fmc::Path::new("./src/Missing.enso"));
+        assert_eq!(backend.status(missing), Err(error));
+        std::fs::remove_dir_all(backend.root()).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_outside_root_are_rejected() {
+        use std::os::unix::fs::symlink;
+        let mut backend = temp_filesystem();
+        let     outside = temp_filesystem();
+        let     root    = backend.root().to_path_buf();
+        fs::write(outside.root().join("secret.txt"),"secret").unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("src/Main.enso"),"main = 2 + 2").unwrap();
+        symlink(outside.root(),root.join("escape")).unwrap();
+        symlink(outside.root().join("secret.txt"),root.join("secret.txt")).unwrap();
+        symlink(outside.root().join("new.txt"),root.join("dangling.txt")).unwrap();
+        symlink(root.join("src"),root.join("inner")).unwrap();
+
+        let outside_root = |path:&str| Error::OutsideRoot(fmc::Path::new(path));
+        let read_error   = backend.read(Path::new("escape/secret.txt")).unwrap_err();
+        assert_eq!(read_error, outside_root("./escape/secret.txt"));
+        let read_error   = backend.read(Path::new("secret.txt")).unwrap_err();
+        assert_eq!(read_error, outside_root("./secret.txt"));
+        let write_error  = backend.write(Path::new("escape/new.txt"),b"").unwrap_err();
+        assert_eq!(write_error, outside_root("./escape/new.txt"));
+        let write_error  = backend.write(Path::new("dangling.txt"),b"").unwrap_err();
+        assert_eq!(write_error, outside_root("./dangling.txt"));
+        assert!(!outside.root().join("new.txt").exists());
+        assert_eq!(backend.read(Path::new("inner/Main.enso")).unwrap(), b"main = 2 + 2");
+        backend.write(Path::new("inner/New.enso"),b"").unwrap();
+        assert!(root.join("src/New.enso").exists());
+        std::fs::remove_dir_all(backend.root()).unwrap();
+        std::fs::remove_dir_all(outside.root()).unwrap();
+    }
+}
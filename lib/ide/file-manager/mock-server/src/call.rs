@@ -0,0 +1,299 @@
+//! Implementation of the File Manager API methods over the `Backend`.
+//!
+//! The call types are generated in the client library from the File Manager API declaration.
+
+use crate::backend::Backend;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use serde::Serialize;
+use std::path::Path;
+
+
+
+// ==============
+// === IsCall ===
+// ==============
+
+/// All methods supported by the server should implement the trait.
+pub trait IsCall {
+    /// Type of value returned on a successful call.
+    type Returned : Serialize;
+
+    /// Perform the "actual" work for this call.
+    fn realize(&self, backend:&mut dyn Backend) -> Result<Self::Returned>;
+}
+
+
+
+// ===============
+// === Helpers ===
+// ===============
+
+/// Fails unless the path points to a directory.
+fn expect_directory(backend:&dyn Backend, path:&Path) -> Result<()> {
+    match backend.status(path)?.file_kind {
+        fmc::FileKind::Directory => Ok(()),
+        _                        => Err(Error::NotDirectory(sandbox::client_path(path))),
+    }
+}
+
+/// Fails unless the path points to a file that is not a directory.
+fn expect_file(backend:&dyn Backend, path:&Path) -> Result<()> {
+    match backend.status(path)?.file_kind {
+        fmc::FileKind::Directory => Err(Error::IsDirectory(sandbox::client_path(path))),
+        _                        => Ok(()),
+    }
+}
+
+/// Fails if the file under the path already exists.
+fn expect_absent(backend:&dyn Backend, path:&Path) -> Result<()> {
+    if backend.exists(path) {
+        Err(Error::AlreadyExists(sandbox::client_path(path)))
+    } else {
+        Ok(())
+    }
+}
+
+/// Fails if the path is the served root, which cannot be moved or deleted.
+fn expect_not_root(path:&Path) -> Result<()> {
+    if path.as_os_str().is_empty() {
+        Err(Error::PermissionDenied(sandbox::client_path(path)))
+    } else {
+        Ok(())
+    }
+}
+
+
+
+// ===============
+// === Methods ===
+// ===============
+
+impl IsCall for fmc::ChecksumInput {
+    type Returned = fmc::Checksum;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<fmc::Checksum> {
+        let contents = backend.read(&sandbox::resolve(&self.path)?)?;
+        Ok(fmc::Checksum::of(&contents))
+    }
+}
+
+impl IsCall for fmc::CopyDirectoryInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let from = sandbox::resolve(&self.from)?;
+        let to   = sandbox::resolve(&self.to)?;
+        expect_directory(backend,&from)?;
+        expect_absent(backend,&to)?;
+        backend.copy_directory(&from,&to)
+    }
+}
+
+impl IsCall for fmc::CopyFileInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let from = sandbox::resolve(&self.from)?;
+        let to   = sandbox::resolve(&self.to)?;
+        expect_file(backend,&from)?;
+        expect_absent(backend,&to)?;
+        backend.copy_file(&from,&to)
+    }
+}
+
+impl IsCall for fmc::CreateDirectoryInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let path = sandbox::resolve(&self.path)?;
+        match backend.status(&path).map(|attributes| attributes.file_kind) {
+            Ok(fmc::FileKind::Directory) => Ok(()),
+            Ok(_)                        => Err(Error::AlreadyExists(self.path.clone())),
+            Err(_)                       => backend.create_directory(&path),
+        }
+    }
+}
+
+impl IsCall for fmc::DeleteFileInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let path = sandbox::resolve(&self.path)?;
+        expect_not_root(&path)?;
+        backend.delete(&path)
+    }
+}
+
+impl IsCall for fmc::ExistsInput {
+    type Returned = bool;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<bool> {
+        Ok(backend.exists(&sandbox::resolve(&self.path)?))
+    }
+}
+
+impl IsCall for fmc::ListInput {
+    type Returned = Vec<fmc::Path>;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<Vec<fmc::Path>> {
+        let path = sandbox::resolve(&self.path)?;
+        expect_directory(backend,&path)?;
+        let entries = backend.list(&path)?;
+        Ok(entries.iter().map(|entry| sandbox::client_path(entry)).collect())
+    }
+}
+
+impl IsCall for fmc::MoveDirectoryInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let from = sandbox::resolve(&self.from)?;
+        let to   = sandbox::resolve(&self.to)?;
+        expect_not_root(&from)?;
+        expect_directory(backend,&from)?;
+        expect_absent(backend,&to)?;
+        backend.rename(&from,&to)
+    }
+}
+
+impl IsCall for fmc::MoveFileInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let from = sandbox::resolve(&self.from)?;
+        let to   = sandbox::resolve(&self.to)?;
+        expect_file(backend,&from)?;
+        expect_absent(backend,&to)?;
+        backend.rename(&from,&to)
+    }
+}
+
+impl IsCall for fmc::ReadInput {
+    type Returned = String;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<String> {
+        let contents = backend.read(&sandbox::resolve(&self.path)?)?;
+        String::from_utf8(contents).map_err(|_| {
+            Error::Other(format!("File {} is not a valid UTF-8 text.",self.path))
+        })
+    }
+}
+
+impl IsCall for fmc::ReadBytesInput {
+    type Returned = fmc::Bytes;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<fmc::Bytes> {
+        Ok(fmc::Bytes::new(backend.read(&sandbox::resolve(&self.path)?)?))
+    }
+}
+
+impl IsCall for fmc::ReadRangeInput {
+    type Returned = fmc::Bytes;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<fmc::Bytes> {
+        let path     = sandbox::resolve(&self.path)?;
+        let contents = backend.read_range(&path,self.offset,self.length)?;
+        Ok(fmc::Bytes::new(contents))
+    }
+}
+
+impl IsCall for fmc::StatusInput {
+    type Returned = fmc::Attributes;
+    fn realize(&self, backend:&mut dyn Backend) -> Result<fmc::Attributes> {
+        backend.status(&sandbox::resolve(&self.path)?)
+    }
+}
+
+impl IsCall for fmc::TouchInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        backend.touch(&sandbox::resolve(&self.path)?)
+    }
+}
+
+impl IsCall for fmc::WriteInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let path = sandbox::resolve(&self.path)?;
+        if let Some(expected) = &self.expected_checksum {
+            if fmc::Checksum::of(backend.read(&path)?) != *expected {
+                return Err(Error::ChecksumMismatch(self.path.clone()))
+            }
+        }
+        backend.write(&path,self.contents.as_bytes())
+    }
+}
+
+impl IsCall for fmc::WriteBytesInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        backend.write(&sandbox::resolve(&self.path)?,&self.contents)
+    }
+}
+
+impl IsCall for fmc::WriteRangeInput {
+    type Returned = ();
+    fn realize(&self, backend:&mut dyn Backend) -> Result<()> {
+        let path = sandbox::resolve(&self.path)?;
+        backend.write_range(&path,self.offset,&self.contents)
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::backend::native::tests::temp_filesystem;
+
+    /// A small PNG image used to check that binary contents survive the round trip.
+    const PNG_FIXTURE:&[u8] = include_bytes!("../fixtures/pixel.png");
+
+    #[test]
+    fn binary_round_trip() {
+        let mut backend  = temp_filesystem();
+        let     path     = fmc::Path::new("./pixel.png");
+        let     contents = fmc::Bytes::new(PNG_FIXTURE);
+        fmc::WriteBytesInput {path:path.clone(),contents}.realize(&mut backend).unwrap();
+        let read = fmc::ReadBytesInput {path:path.clone()}.realize(&mut backend).unwrap();
+        assert_eq!(read.0, PNG_FIXTURE);
+        let header = fmc::ReadRangeInput {path,offset:1,length:3}.realize(&mut backend).unwrap();
+        assert_eq!(header.0, b"PNG");
+        std::fs::remove_dir_all(backend.root()).unwrap();
+    }
+
+    #[test]
+    fn chunked_transfer_of_large_csv() {
+        let mut backend = temp_filesystem();
+        let     path    = fmc::Path::new("./data.csv");
+        let     lines   = (0..200_000).map(|i| format!("{},{},{}\n",i,i*i,i%7));
+        let     csv     = lines.collect::<String>().into_bytes();
+        let     chunk   = 1024 * 1024;
+        assert!(csv.len() > 2 * chunk);
+        for (index,part) in csv.chunks(chunk).enumerate() {
+            let offset   = (index * chunk) as u64;
+            let contents = fmc::Bytes::new(part);
+            let path     = path.clone();
+            fmc::WriteRangeInput {path,offset,contents}.realize(&mut backend).unwrap();
+        }
+        let mut read = Vec::new();
+        loop {
+            let offset = read.len() as u64;
+            let length = chunk as u64;
+            let path   = path.clone();
+            let part   = fmc::ReadRangeInput {path,offset,length}.realize(&mut backend).unwrap();
+            if part.is_empty() { break }
+            read.extend_from_slice(&part);
+        }
+        assert_eq!(read, csv);
+        std::fs::remove_dir_all(backend.root()).unwrap();
+    }
+
+    #[test]
+    fn paths_outside_root_are_rejected() {
+        let mut backend           = temp_filesystem();
+        let     path              = fmc::Path::new("../outside.txt");
+        let     contents          = "".to_string();
+        let     expected_checksum = None;
+        let     write             = fmc::WriteInput {path:path.clone(),contents,expected_checksum};
+        assert_eq!(write.realize(&mut backend), Err(Error::OutsideRoot(path)));
+        std::fs::remove_dir_all(backend.root()).unwrap();
+    }
+}
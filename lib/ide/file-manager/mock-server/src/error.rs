@@ -0,0 +1,99 @@
+//! Errors that calls to the server may fail with, and their JSON-RPC error codes.
+
+use enso_prelude::*;
+
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use std::path::Path;
+
+
+
+// =============
+// === Codes ===
+// =============
+
+/// Error codes sent to the client in replies to failed calls.
+pub use fmc::error_code as code;
+
+
+
+// =============
+// === Error ===
+// =============
+
+/// Result of the server's operations.
+pub type Result<T> = std::result::Result<T,Error>;
+
+/// Reasons for which a call can fail. Paths are given as seen by the client.
+#[derive(Clone,Debug,Fail,PartialEq)]
+pub enum Error {
+    /// The file does not exist.
+    #[fail(display = "No such file: {}.", _0)]
+    NotFound(fmc::Path),
+    /// The file already exists, while the call requires it not to.
+    #[fail(display = "File already exists: {}.", _0)]
+    AlreadyExists(fmc::Path),
+    /// The server is not allowed to access the file.
+    #[fail(display = "Access denied: {}.", _0)]
+    PermissionDenied(fmc::Path),
+    /// The path points outside of the served root directory.
+    #[fail(display = "Path {} points outside of the served directory.", _0)]
+    OutsideRoot(fmc::Path),
+    /// The call requires a directory, while the path points to another kind of file.
+    #[fail(display = "Not a directory: {}.", _0)]
+    NotDirectory(fmc::Path),
+    /// The call requires a regular file, while the path points to a directory.
+    #[fail(display = "Is a directory: {}.", _0)]
+    IsDirectory(fmc::Path),
+    /// The file contents do not have the checksum expected by the call.
+    #[fail(display = "File {} was modified, its checksum is different than expected.", _0)]
+    ChecksumMismatch(fmc::Path),
+    /// Any other failure.
+    #[fail(display = "{}", _0)]
+    Other(String),
+}
+
+impl Error {
+    /// Creates an error for the path relative to the served root, describing the IO failure.
+    pub fn from_io(path:&Path, error:std::io::Error) -> Self {
+        use std::io::ErrorKind::*;
+        let path = sandbox::client_path(path);
+        match error.kind() {
+            NotFound         => Error::NotFound(path),
+            AlreadyExists    => Error::AlreadyExists(path),
+            PermissionDenied => Error::PermissionDenied(path),
+            _                => Error::Other(format!("{}: {}",path,error)),
+        }
+    }
+
+    /// The JSON-RPC error code used to report this error to the client.
+    pub fn code(&self) -> i64 {
+        match self {
+            Error::NotFound(_)         => code::NOT_FOUND,
+            Error::AlreadyExists(_)    => code::ALREADY_EXISTS,
+            Error::PermissionDenied(_) => code::ACCESS_DENIED,
+            Error::OutsideRoot(_)      => code::ACCESS_DENIED,
+            Error::NotDirectory(_)     => code::NOT_DIRECTORY,
+            Error::IsDirectory(_)      => code::FAILED_CALL,
+            Error::ChecksumMismatch(_) => code::CHECKSUM_MISMATCH,
+            Error::Other(_)            => code::FAILED_CALL,
+        }
+    }
+
+    /// Additional data sent with the error, identifying the file that caused the failure.
+    pub fn data(&self) -> Option<serde_json::Value> {
+        let path = match self {
+            Error::NotFound(path)         => path,
+            Error::AlreadyExists(path)    => path,
+            Error::PermissionDenied(path) => path,
+            Error::OutsideRoot(path)      => path,
+            Error::NotDirectory(path)     => path,
+            Error::IsDirectory(path)      => path,
+            Error::ChecksumMismatch(path) => path,
+            Error::Other(_)               => return None,
+        };
+        let data = fmc::ErrorData {path:path.clone()};
+        Some(serde_json::to_value(data).expect("error data must be serializable"))
+    }
+}
@@ -0,0 +1,33 @@
+//! WARNING: PROVISIONAL CODE [mwu]
+//!
+//! This is a provisional mock implementation for the File Manager Server.
+//! Its purpose is to enable development and testing of the IDE code, until the
+//! proper server is delivered by the cloud team.
+//!
+//! The library can be used directly in tests: `Server` implements the whole File Manager API
+//! over a `Backend`, which is either a sandboxed directory of the real filesystem
+//! (`NativeFilesystem`) or an in-memory filesystem for hermetic tests (`MemoryFilesystem`).
+//! `websocket::serve` makes the server available over WebSocket connections.
+
+#![warn(missing_docs)]
+#![warn(trivial_casts)]
+#![warn(trivial_numeric_casts)]
+#![warn(unused_import_braces)]
+#![warn(unused_qualifications)]
+#![warn(unsafe_code)]
+#![warn(missing_copy_implementations)]
+#![warn(missing_debug_implementations)]
+
+pub mod backend;
+pub mod call;
+pub mod error;
+pub mod sandbox;
+pub mod server;
+pub mod watch;
+pub mod websocket;
+
+pub use backend::Backend;
+pub use backend::memory::MemoryFilesystem;
+pub use backend::native::NativeFilesystem;
+pub use error::Error;
+pub use server::Server;
@@ -0,0 +1,99 @@
+//! Mapping between paths given by the client and paths relative to the served root directory.
+//!
+//! The client's paths are always interpreted relative to the root, e.g. `./src/Main.enso`. Paths
+//! that could point outside of the root are rejected. Backends serving the real filesystem must
+//! additionally `confine` the paths, as symbolic links can lead outside of the root.
+
+use crate::error::Error;
+use crate::error::Result;
+
+use file_manager_client as fmc;
+use std::fs;
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+
+// ===============
+// === Sandbox ===
+// ===============
+
+/// Converts the client's path into a normalized path relative to the served root.
+///
+/// The `.` components are dropped. Absolute paths and paths with `..` components are rejected
+/// with `Error::OutsideRoot`. The root itself is represented by an empty path.
+pub fn resolve(path:&fmc::Path) -> Result<PathBuf> {
+    let mut resolved = PathBuf::new();
+    for component in Path::new(&path.0).components() {
+        match component {
+            Component::CurDir       => {},
+            Component::Normal(name) => resolved.push(name),
+            _                       => return Err(Error::OutsideRoot(path.clone())),
+        }
+    }
+    Ok(resolved)
+}
+
+/// Converts the path relative to the served root into the path in the real filesystem, checking
+/// that symbolic links do not lead outside of the `root` directory.
+///
+/// The path is canonicalized and compared with the canonical root. If the file does not exist yet,
+/// its deepest existing ancestor is checked instead. Dangling symbolic links are rejected, as
+/// writing through them could create a file anywhere.
+pub fn confine(root:&Path, path:&Path) -> Result<PathBuf> {
+    let outside   = || Error::OutsideRoot(client_path(path));
+    let absolute  = root.join(path);
+    let root      = fs::canonicalize(root).map_err(|error| Error::from_io(Path::new(""),error))?;
+    let existing  = absolute.ancestors().find(|ancestor| fs::symlink_metadata(ancestor).is_ok());
+    let existing  = existing.ok_or_else(outside)?;
+    let canonical = fs::canonicalize(existing).map_err(|_| outside())?;
+    if canonical.starts_with(&root) { Ok(absolute) } else { Err(outside()) }
+}
+
+/// Converts the path relative to the served root into the path reported to the client.
+pub fn client_path(path:&Path) -> fmc::Path {
+    let current_dir = Path::new(".");
+    let path        = if path.as_os_str().is_empty() {
+        current_dir.to_path_buf()
+    } else {
+        current_dir.join(path)
+    };
+    fmc::Path::new(path.to_string_lossy())
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolve_str(path:&str) -> Result<PathBuf> {
+        resolve(&fmc::Path::new(path))
+    }
+
+    #[test]
+    fn resolving_paths() {
+        assert_eq!(resolve_str("./src/Main.enso"), Ok(PathBuf::from("src/Main.enso")));
+        assert_eq!(resolve_str("src/./Main.enso"), Ok(PathBuf::from("src/Main.enso")));
+        assert_eq!(resolve_str("."), Ok(PathBuf::new()));
+    }
+
+    #[test]
+    fn rejecting_paths_outside_root() {
+        for path in &["..", "./src/../../etc", "/etc/passwd"] {
+            let expected = Error::OutsideRoot(fmc::Path::new(*path));
+            assert_eq!(resolve_str(path), Err(expected));
+        }
+    }
+
+    #[test]
+    fn client_paths() {
+        assert_eq!(client_path(Path::new("")), fmc::Path::new("."));
+        assert_eq!(client_path(Path::new("src/Main.enso")), fmc::Path::new("./src/Main.enso"));
+    }
+}
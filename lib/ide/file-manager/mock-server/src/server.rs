@@ -0,0 +1,275 @@
+//! The File Manager protocol implementation, independent of the transport used to exchange the
+//! messages.
+
+use enso_prelude::*;
+
+use crate::backend::Backend;
+use crate::call::IsCall;
+use crate::error::code;
+use crate::error::Error;
+use crate::error::Result;
+use crate::sandbox;
+use crate::watch::Watch;
+
+use file_manager_client as fmc;
+use fmc::FileManagerApiCall as Call;
+use json_rpc::messages;
+use serde::Serialize;
+use uuid::Uuid;
+
+
+
+// ==================
+// == Useful types ==
+// ==================
+
+/// Partially decoded Request - its `id` can be read but the call parameters
+/// remain encoded in JSON.
+pub type SomeRequest = messages::Message<messages::Request<serde_json::Value>>;
+
+/// Wraps the call result (or error) into a reply.
+fn reply<T:Serialize>(result:Result<T>) -> messages::Result<serde_json::Value> {
+    match result {
+        Ok(value)  => messages::Result::new_success(serde_json::to_value(&value).unwrap()),
+        Err(error) => messages::Result::new_error(error.code(),error.to_string(),error.data()),
+    }
+}
+
+
+
+// ==============
+// === Server ===
+// ==============
+
+/// Server of the File Manager protocol over the given filesystem `Backend`.
+///
+/// The server handles messages of a single connection. It is not bound to any transport: the
+/// requests are passed to `handle_message` as text and the notifications are obtained by calling
+/// `poll_watches` periodically.
+#[derive(Debug)]
+pub struct Server {
+    backend : Box<dyn Backend>,
+    /// Active watches, created by the client with `createWatch`.
+    watches : HashMap<Uuid,Watch>,
+}
+
+impl Server {
+    /// Creates a server over the given backend.
+    pub fn new(backend:impl Backend + 'static) -> Self {
+        let backend = Box::new(backend);
+        let watches = default();
+        Server {backend,watches}
+    }
+
+    /// The served filesystem.
+    pub fn backend(&self) -> &dyn Backend {
+        self.backend.as_ref()
+    }
+
+    /// The served filesystem, e.g. for preparing files in tests.
+    pub fn backend_mut(&mut self) -> &mut dyn Backend {
+        self.backend.as_mut()
+    }
+
+    /// Make the call and wrap the result (or error) into a reply message.
+    fn realize_call<C:IsCall>(&mut self, call:&C) -> messages::Result<serde_json::Value> {
+        reply(call.realize(self.backend.as_mut()))
+    }
+
+    /// Recognize what kind of call has been requested and delegate it to appropriate
+    /// handling code.
+    pub fn handle_request(&mut self, request:SomeRequest) -> messages::Result<serde_json::Value> {
+        let value = request.payload.call;
+        match serde_json::from_value::<Call>(value) {
+            Ok(call) => match call {
+                Call::Checksum       (call) => self.realize_call(&call),
+                Call::CopyDirectory  (call) => self.realize_call(&call),
+                Call::CopyFile       (call) => self.realize_call(&call),
+                Call::CreateDirectory(call) => self.realize_call(&call),
+                Call::CreateWatch    (call) => reply(self.create_watch(call)),
+                Call::DeleteFile     (call) => self.realize_call(&call),
+                Call::DeleteWatch    (call) => reply(self.delete_watch(call)),
+                Call::Exists         (call) => self.realize_call(&call),
+                Call::List           (call) => self.realize_call(&call),
+                Call::MoveDirectory  (call) => self.realize_call(&call),
+                Call::MoveFile       (call) => self.realize_call(&call),
+                Call::Read           (call) => self.realize_call(&call),
+                Call::ReadBytes      (call) => self.realize_call(&call),
+                Call::ReadRange      (call) => self.realize_call(&call),
+                Call::Status         (call) => self.realize_call(&call),
+                Call::Touch          (call) => self.realize_call(&call),
+                Call::Write          (call) => self.realize_call(&call),
+                Call::WriteBytes     (call) => self.realize_call(&call),
+                Call::WriteRange     (call) => self.realize_call(&call),
+            }
+            Err(e) => {
+                messages::Result::new_error_simple(code::FAILED_CALL,e.to_string())
+            }
+        }
+    }
+
+    /// Takes JSON-encoded message and returns the response.
+    pub fn handle_message(&mut self, request_text:String) -> serde_json::Value {
+        let request = serde_json::from_str::<SomeRequest>(&request_text);
+        match request {
+            Ok(request) => {
+                let id       = request.id;
+                let result   = self.handle_request(request);
+                let response = messages::Response{id,result};
+                let reply    = messages::Message::new(response);
+                serde_json::to_value(reply).unwrap()
+            },
+            Err(_e) => {
+                // TODO: [mwu] here should go proper error handling that builds
+                //       an error that has `id` set to `null`
+                default()
+            }
+        }
+    }
+
+    /// Starts a new watch and returns its id.
+    fn create_watch(&mut self, call:fmc::CreateWatchInput) -> Result<Uuid> {
+        let path = sandbox::resolve(&call.path)?;
+        self.backend.status(&path)?;
+        let id = Uuid::new_v4();
+        self.watches.insert(id,Watch::new(self.backend.as_ref(),path));
+        Ok(id)
+    }
+
+    /// Removes the watch.
+    fn delete_watch(&mut self, call:fmc::DeleteWatchInput) -> Result<()> {
+        match self.watches.remove(&call.watch_id) {
+            Some(_) => Ok(()),
+            None    => Err(Error::Other(format!("There is no watch {}.",call.watch_id))),
+        }
+    }
+
+    /// Checks all active watches and returns notifications about filesystem events that occurred
+    /// since the last call.
+    pub fn poll_watches(&mut self) -> Vec<fmc::Notification> {
+        let backend = self.backend.as_ref();
+        let events  = self.watches.values_mut().flat_map(|watch| watch.poll(backend));
+        events.map(fmc::Notification::FilesystemEvent).collect()
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::backend::memory::MemoryFilesystem;
+
+    use json_rpc::messages::Id;
+    use serde_json::json;
+    use serde_json::Value;
+
+    /// Calls the method on the server and returns the result put in the reply.
+    fn call(server:&mut Server, method:&str, params:Value) -> messages::Result<Value> {
+        let call    = json!({"method":method,"params":params});
+        let request = messages::Message::new(messages::Request::new(Id(0),call));
+        server.handle_request(request)
+    }
+
+    fn expect_success(result:messages::Result<Value>) -> Value {
+        match result {
+            messages::Result::Success(success) => success.result,
+            messages::Result::Error(error)     => panic!("Unexpected error: {:?}.", error),
+        }
+    }
+
+    fn expect_error_code(result:messages::Result<Value>) -> i64 {
+        match result {
+            messages::Result::Success(success) => panic!("Unexpected success: {:?}.", success),
+            messages::Result::Error(error)     => error.code,
+        }
+    }
+
+    fn server_with_files() -> Server {
+        let mut backend = MemoryFilesystem::new();
+        backend.insert_file("src/Main.enso","main = 2 + 2");
+        backend.insert_file("src/Lib/Util.enso","util = 1");
+        Server::new(backend)
+    }
+
+    #[test]
+    fn listing_and_reading() {
+        let mut server = server_with_files();
+        let listing    = call(&mut server,"list",json!({"path":"./src"}));
+        assert_eq!(expect_success(listing), json!(["./src/Lib","./src/Main.enso"]));
+        let contents   = call(&mut server,"read",json!({"path":"./src/Main.enso"}));
+        assert_eq!(expect_success(contents), json!("main = 2 + 2"));
+        let status     = call(&mut server,"status",json!({"path":"./src/Main.enso"}));
+        let status     = serde_json::from_value::<fmc::Attributes>(expect_success(status));
+        assert_eq!(status.unwrap().file_kind, fmc::FileKind::RegularFile);
+    }
+
+    #[test]
+    fn moving_copying_and_deleting() {
+        let mut server = server_with_files();
+        let copy       = json!({"from":"./src","to":"./backup"});
+        expect_success(call(&mut server,"copyDirectory",copy));
+        let move_file  = json!({"from":"./backup/Main.enso","to":"./backup/Old.enso"});
+        expect_success(call(&mut server,"moveFile",move_file));
+        let move_dir   = json!({"from":"./backup/Lib","to":"./Lib"});
+        expect_success(call(&mut server,"moveDirectory",move_dir));
+        expect_success(call(&mut server,"deleteFile",json!({"path":"./src"})));
+        let listing    = call(&mut server,"list",json!({"path":"."}));
+        assert_eq!(expect_success(listing), json!(["./Lib","./backup"]));
+        let listing    = call(&mut server,"list",json!({"path":"./backup"}));
+        assert_eq!(expect_success(listing), json!(["./backup/Old.enso"]));
+    }
+
+    #[test]
+    fn error_codes() {
+        let mut server = server_with_files();
+        let missing    = call(&mut server,"read",json!({"path":"./Missing.enso"}));
+        assert_eq!(expect_error_code(missing), code::NOT_FOUND);
+        let copy       = json!({"from":"./src/Main.enso","to":"./src/Lib/Util.enso"});
+        let existing   = call(&mut server,"copyFile",copy);
+        assert_eq!(expect_error_code(existing), code::ALREADY_EXISTS);
+        let escaping   = call(&mut server,"read",json!({"path":"../secret.txt"}));
+        assert_eq!(expect_error_code(escaping), code::ACCESS_DENIED);
+        let root       = call(&mut server,"deleteFile",json!({"path":"."}));
+        assert_eq!(expect_error_code(root), code::ACCESS_DENIED);
+        let not_dir    = call(&mut server,"list",json!({"path":"./src/Main.enso"}));
+        assert_eq!(expect_error_code(not_dir), code::NOT_DIRECTORY);
+        let unknown    = call(&mut server,"format",json!({"path":"./src/Main.enso"}));
+        assert_eq!(expect_error_code(unknown), code::FAILED_CALL);
+    }
+
+    #[test]
+    fn checked_writes() {
+        let mut server   = server_with_files();
+        let     checksum = fmc::Checksum::of("main = 2 + 2");
+        let     write    = json!({
+            "path"             : "./src/Main.enso",
+            "contents"         : "main = 3 + 3",
+            "expectedChecksum" : checksum
+        });
+        expect_success(call(&mut server,"write",write.clone()));
+        // The file has changed since the checksum was computed.
+        let conflict = call(&mut server,"write",write);
+        assert_eq!(expect_error_code(conflict), code::CHECKSUM_MISMATCH);
+        let contents = call(&mut server,"read",json!({"path":"./src/Main.enso"}));
+        assert_eq!(expect_success(contents), json!("main = 3 + 3"));
+    }
+
+    #[test]
+    fn watch_notifications() {
+        let mut server = server_with_files();
+        expect_success(call(&mut server,"createWatch",json!({"path":"./src"})));
+        assert!(server.poll_watches().is_empty());
+        let write = json!({"path":"./src/New.enso","contents":""});
+        expect_success(call(&mut server,"write",write));
+        let path  = fmc::Path::new("./src/New.enso");
+        let kind  = fmc::FilesystemEventKind::Created;
+        let event = fmc::Notification::FilesystemEvent(fmc::FilesystemEvent {path,kind});
+        assert_eq!(server.poll_watches(), vec![event]);
+    }
+}
@@ -0,0 +1,168 @@
+//! File watches implemented by polling the filesystem.
+//!
+//! Each watch remembers a snapshot of the watched tree. Polling takes a new snapshot and reports
+//! the differences as `FilesystemEvent`s.
+
+use enso_prelude::*;
+
+use crate::backend::Backend;
+use crate::sandbox;
+
+use file_manager_client as fmc;
+use fmc::FilesystemEvent;
+use fmc::FilesystemEventKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+
+
+// ================
+// === Snapshot ===
+// ================
+
+/// State of a single file that is used to detect its modifications.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct FileState {
+    /// The last modification time.
+    pub modified : fmc::FileTime,
+    /// Size of the file in bytes.
+    pub size     : u64,
+}
+
+/// State of all files in the watched tree.
+pub type Snapshot = BTreeMap<PathBuf,FileState>;
+
+/// Takes a snapshot of the file under given path. If it is a directory, the whole subtree is
+/// included. Entries that cannot be read are silently skipped, as they might be removed during
+/// the traversal.
+pub fn take_snapshot(backend:&dyn Backend, root:&Path) -> Snapshot {
+    let mut snapshot = Snapshot::new();
+    add_to_snapshot(backend,&mut snapshot,root);
+    snapshot
+}
+
+fn add_to_snapshot(backend:&dyn Backend, snapshot:&mut Snapshot, path:&Path) {
+    if let Ok(attributes) = backend.status(path) {
+        let modified = attributes.last_modified_time;
+        let size     = attributes.byte_size;
+        snapshot.insert(path.to_path_buf(),FileState {modified,size});
+        if attributes.file_kind == fmc::FileKind::Directory {
+            for entry in backend.list(path).into_iter().flatten() {
+                add_to_snapshot(backend,snapshot,&entry);
+            }
+        }
+    }
+}
+
+/// Lists events describing how the `old` snapshot turned into the `new` one.
+pub fn compare_snapshots(old:&Snapshot, new:&Snapshot) -> Vec<FilesystemEvent> {
+    let event = |path:&PathBuf, kind| {
+        let path = sandbox::client_path(path);
+        FilesystemEvent {path,kind}
+    };
+    let mut events = Vec::new();
+    for (path,state) in new {
+        match old.get(path) {
+            None                      => events.push(event(path,FilesystemEventKind::Created)),
+            Some(old) if old != state => events.push(event(path,FilesystemEventKind::Modified)),
+            Some(_)                   => {},
+        }
+    }
+    for path in old.keys().filter(|path| !new.contains_key(*path)) {
+        events.push(event(path,FilesystemEventKind::Deleted));
+    }
+    events
+}
+
+
+
+// =============
+// === Watch ===
+// =============
+
+/// An active watch over a file or directory tree. Paths are relative to the served root.
+#[derive(Clone,Debug)]
+pub struct Watch {
+    root     : PathBuf,
+    snapshot : Snapshot,
+}
+
+impl Watch {
+    /// Starts watching the given path.
+    pub fn new(backend:&dyn Backend, root:PathBuf) -> Self {
+        let snapshot = take_snapshot(backend,&root);
+        Watch {root,snapshot}
+    }
+
+    /// Checks the watched tree and returns the events that occurred since the last poll.
+    pub fn poll(&mut self, backend:&dyn Backend) -> Vec<FilesystemEvent> {
+        let snapshot = take_snapshot(backend,&self.root);
+        let events   = compare_snapshots(&self.snapshot,&snapshot);
+        self.snapshot = snapshot;
+        events
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::backend::memory::MemoryFilesystem;
+
+    use chrono::TimeZone;
+
+    fn state(seconds:i64, size:u64) -> FileState {
+        let modified = chrono::FixedOffset::east(0).timestamp(seconds,0);
+        FileState {modified,size}
+    }
+
+    fn snapshot(files:&[(&str,FileState)]) -> Snapshot {
+        files.iter().map(|(path,state)| (PathBuf::from(path),*state)).collect()
+    }
+
+    fn event(path:&str, kind:FilesystemEventKind) -> FilesystemEvent {
+        FilesystemEvent {path:fmc::Path::new(path),kind}
+    }
+
+    #[test]
+    fn comparing_snapshots() {
+        let old = snapshot(&[("a",state(1,10)),("b",state(1,10)),("c",state(1,10))]);
+        let new = snapshot(&[("a",state(1,10)),("b",state(2,12)),("d",state(2,1))]);
+        let events = compare_snapshots(&old,&new);
+        let expected = vec!
+            [ event("./b",FilesystemEventKind::Modified)
+            , event("./d",FilesystemEventKind::Created)
+            , event("./c",FilesystemEventKind::Deleted)
+            ];
+        assert_eq!(events, expected);
+    }
+
+    #[test]
+    fn no_events_for_unchanged_tree() {
+        let old = snapshot(&[("a",state(1,10)),("a/b",state(1,10))]);
+        assert!(compare_snapshots(&old,&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn watching_directory() {
+        let mut backend = MemoryFilesystem::new();
+        backend.insert_file("src/Main.enso","main = 2 + 2");
+        let mut watch = Watch::new(&backend,PathBuf::from("src"));
+        assert!(watch.poll(&backend).is_empty());
+
+        backend.insert_file("src/Lib.enso","");
+        backend.delete(Path::new("src/Main.enso")).unwrap();
+        let expected = vec!
+            [ event("./src/Lib.enso",FilesystemEventKind::Created)
+            , event("./src/Main.enso",FilesystemEventKind::Deleted)
+            ];
+        assert_eq!(watch.poll(&backend), expected);
+        assert!(watch.poll(&backend).is_empty());
+    }
+}
@@ -0,0 +1,115 @@
+//! Serving the File Manager protocol over WebSocket connections.
+
+use crate::backend::Backend;
+use crate::server::Server;
+
+use json_rpc::messages;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::thread::spawn;
+use std::time::Duration;
+use tungstenite::accept_hdr;
+use tungstenite::WebSocket;
+use tungstenite::Message;
+use tungstenite::handshake::server::Request;
+use tungstenite::handshake::server::Response;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// How often the active watches check the filesystem for changes.
+pub const WATCH_POLL_INTERVAL:Duration = Duration::from_millis(500);
+
+
+
+// ==================
+// === Connection ===
+// ==================
+
+/// Handler for the established, websocket connection.
+struct Connection {
+    socket : WebSocket<TcpStream>,
+    server : Server,
+}
+
+impl Connection {
+    /// Sends notifications about filesystem events noticed by the active watches.
+    ///
+    /// Returns error if the notification could not be sent.
+    fn send_notifications(&mut self) -> tungstenite::Result<()> {
+        for notification in self.server.poll_watches() {
+            let text = serde_json::to_string(&messages::Message::new(notification));
+            let text = text.unwrap();
+            self.socket.write_message(Message::text(text))?;
+        }
+        Ok(())
+    }
+
+    /// Reads text messages and replies to them. Between the messages, polls active watches.
+    /// If the connection is lost, silently returns.
+    fn run(&mut self) {
+        // Reading times out periodically, so the watches can be polled.
+        let timeout = self.socket.get_ref().set_read_timeout(Some(WATCH_POLL_INTERVAL));
+        timeout.expect("Failed to set the socket read timeout.");
+        loop {
+            match self.socket.read_message() {
+                Ok(request_msg) => {
+                    if request_msg.is_text() {
+                        let request_text = request_msg.to_string();
+                        let reply_json   = self.server.handle_message(request_text);
+                        let reply_msg    = Message::text(reply_json.to_string());
+                        if self.socket.write_message(reply_msg).is_err() {
+                            break;
+                        }
+                    }
+                    // ignore non-text messages.
+                },
+                Err(tungstenite::Error::Io(error)) if is_timeout(&error) => {},
+                Err(_) => break,
+            }
+            if self.send_notifications().is_err() {
+                break;
+            }
+        }
+        println!("Finished handling connection");
+    }
+}
+
+/// Checks if the IO error was caused by the read timeout.
+fn is_timeout(error:&std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    match error.kind() {
+        WouldBlock | TimedOut => true,
+        _                     => false,
+    }
+}
+
+
+
+// ===============
+// === Serving ===
+// ===============
+
+/// Accepts WebSocket connections on the listener and serves each one in a separate thread.
+///
+/// Every connection gets its own `Server` with the backend created by `make_backend`. Never
+/// returns, unless the listener fails.
+pub fn serve<F,B>(listener:TcpListener, make_backend:F)
+where F : Fn() -> B + Clone + Send + 'static,
+      B : Backend + 'static {
+    for stream in listener.incoming() {
+        let make_backend = make_backend.clone();
+        spawn(move || {
+            println!("Got a new connection");
+            let callback = |_req: &Request, response: Response| {
+                Ok(response)
+            };
+            let socket = accept_hdr(stream.unwrap(), callback).unwrap();
+            let server = Server::new(make_backend());
+            Connection {socket,server}.run();
+        });
+    }
+}
@@ -0,0 +1,100 @@
+//! Sessions of the File Manager client with the server, recorded for the IDE tests which replay
+//! them with `ReplayTransport`.
+//!
+//! The tests check that the recordings in `lib/ide/tests/recordings` are what the client and
+//! the server exchange now. When the protocol changes, they should be regenerated by running
+//! the tests with `ENSO_REGENERATE_RECORDINGS` environment variable set.
+
+use file_manager_client as fmc;
+use file_manager_server::MemoryFilesystem;
+use file_manager_server::Server;
+use futures::channel::mpsc::UnboundedSender;
+use futures::executor::LocalPool;
+use futures::task::LocalSpawnExt;
+use json_rpc::Transport;
+use json_rpc::TransportEvent;
+use json_rpc::test_util::transport::recording::Recording;
+use json_rpc::test_util::transport::recording::RecordingTransport;
+use std::path::PathBuf;
+
+
+
+// =======================
+// === ServerTransport ===
+// =======================
+
+/// Transport passing the messages directly to the server, which replies immediately.
+#[derive(Debug)]
+struct ServerTransport {
+    server      : Server,
+    transmitter : Option<UnboundedSender<TransportEvent>>,
+}
+
+impl ServerTransport {
+    fn new(server:Server) -> Self {
+        let transmitter = None;
+        ServerTransport {server,transmitter}
+    }
+}
+
+impl Transport for ServerTransport {
+    fn send_text(&mut self, message:String) -> Result<(),failure::Error> {
+        let reply = self.server.handle_message(message).to_string();
+        if let Some(transmitter) = self.transmitter.as_ref() {
+            transmitter.unbounded_send(TransportEvent::TextMessage(reply))?;
+        }
+        Ok(())
+    }
+
+    fn set_event_transmitter(&mut self, transmitter:UnboundedSender<TransportEvent>) {
+        self.transmitter = Some(transmitter);
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+/// Name of the environment variable which makes the tests regenerate the recordings.
+const REGENERATE_VARIABLE:&str = "ENSO_REGENERATE_RECORDINGS";
+
+/// Path of the recording with given name.
+fn recording_path(name:&str) -> PathBuf {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    manifest_dir.join("../../tests/recordings").join(name)
+}
+
+/// Saves the recording, if the recordings are being regenerated, or checks that it matches the
+/// saved one. The timestamps are not compared, as they depend on the machine's speed.
+fn check_recording(name:&str, recording:Recording) {
+    let path = recording_path(name);
+    if std::env::var_os(REGENERATE_VARIABLE).is_some() {
+        recording.save(path).unwrap();
+    } else {
+        let saved   = Recording::load(&path).unwrap();
+        let content = |recording:&Recording| recording.messages.iter().map(|message| {
+            (message.direction,message.text.clone())
+        }).collect::<Vec<_>>();
+        assert_eq!(content(&saved), content(&recording),
+            "The recording {} is outdated. Run the tests with {} set to regenerate it.",
+            path.display(), REGENERATE_VARIABLE);
+    }
+}
+
+#[test]
+fn load_module() {
+    let mut backend = MemoryFilesystem::new();
+    backend.insert_file("TestLocation.enso","2 + 2");
+    let mut executor = LocalPool::new();
+    let transport    = ServerTransport::new(Server::new(backend));
+    let recorder     = RecordingTransport::new(transport,executor.spawner());
+    let recording    = recorder.handle();
+    let mut client   = fmc::Client::new(recorder);
+    executor.spawner().spawn_local(client.runner()).unwrap();
+
+    let contents = executor.run_until(client.read(fmc::Path::new("./TestLocation.enso")));
+    assert_eq!(contents.unwrap(), "2 + 2");
+    check_recording("load_module.jsonl",recording.recording());
+}
@@ -0,0 +1,138 @@
+//! End-to-end tests of the server running over WebSocket, used through the File Manager client.
+
+use file_manager_client as fmc;
+use file_manager_server::NativeFilesystem;
+use file_manager_server::websocket;
+use futures::channel::mpsc::UnboundedSender;
+use futures::executor::LocalPool;
+use futures::StreamExt;
+use futures::task::LocalSpawnExt;
+use json_rpc::Transport;
+use json_rpc::TransportEvent;
+use std::net::TcpListener;
+use std::net::TcpStream;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::mpsc;
+use std::time::Duration;
+use tungstenite::Message;
+use tungstenite::WebSocket;
+use uuid::Uuid;
+
+
+
+// ==========================
+// === WebSocketTransport ===
+// ==========================
+
+/// How long the connection thread waits for incoming messages before sending the queued ones.
+const READ_TIMEOUT:Duration = Duration::from_millis(10);
+
+/// Transmitter of the received events, available once set by the client.
+type EventTransmitter = Arc<Mutex<Option<UnboundedSender<TransportEvent>>>>;
+
+/// Client's end of the WebSocket connection. The socket is handled by a separate thread, which
+/// sends the queued messages and passes the received ones to the client.
+#[derive(Debug)]
+struct WebSocketTransport {
+    outgoing    : mpsc::Sender<String>,
+    transmitter : EventTransmitter,
+}
+
+impl WebSocketTransport {
+    /// Connects to the server listening on the given address.
+    fn connect(address:std::net::SocketAddr) -> Self {
+        let stream            = TcpStream::connect(address).unwrap();
+        let url               = format!("ws://{}",address);
+        let (socket,_)        = tungstenite::client(url.as_str(),stream).unwrap();
+        socket.get_ref().set_read_timeout(Some(READ_TIMEOUT)).unwrap();
+        let (outgoing,queued) = mpsc::channel();
+        let transmitter       = EventTransmitter::default();
+        let received          = transmitter.clone();
+        std::thread::spawn(move || Self::run(socket,queued,received));
+        WebSocketTransport {outgoing,transmitter}
+    }
+
+    /// Handles the socket until the connection is lost or the transport is dropped.
+    fn run
+    (mut socket:WebSocket<TcpStream>, queued:mpsc::Receiver<String>, transmitter:EventTransmitter) {
+        loop {
+            loop {
+                match queued.try_recv() {
+                    Ok(text) => if socket.write_message(Message::text(text)).is_err() { return },
+                    Err(mpsc::TryRecvError::Empty)        => break,
+                    Err(mpsc::TryRecvError::Disconnected) => return,
+                }
+            }
+            match socket.read_message() {
+                Ok(Message::Text(text)) => {
+                    if let Some(transmitter) = transmitter.lock().unwrap().as_ref() {
+                        transmitter.unbounded_send(TransportEvent::TextMessage(text)).ok();
+                    }
+                },
+                Ok(_)                                                    => {},
+                Err(tungstenite::Error::Io(error)) if is_timeout(&error) => {},
+                Err(_)                                                   => return,
+            }
+        }
+    }
+}
+
+/// Checks if the IO error was caused by the read timeout.
+fn is_timeout(error:&std::io::Error) -> bool {
+    use std::io::ErrorKind::*;
+    match error.kind() {
+        WouldBlock | TimedOut => true,
+        _                     => false,
+    }
+}
+
+impl Transport for WebSocketTransport {
+    fn send_text(&mut self, message:String) -> Result<(),failure::Error> {
+        Ok(self.outgoing.send(message)?)
+    }
+
+    fn set_event_transmitter(&mut self, transmitter:UnboundedSender<TransportEvent>) {
+        *self.transmitter.lock().unwrap() = Some(transmitter);
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+/// Serves a fresh, empty directory on a free port. Returns the directory and the address.
+fn serve_temp_directory() -> (PathBuf,std::net::SocketAddr) {
+    let root     = std::env::temp_dir().join(Uuid::new_v4().to_string());
+    std::fs::create_dir_all(&root).unwrap();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address  = listener.local_addr().unwrap();
+    let served   = root.clone();
+    std::thread::spawn(move || {
+        websocket::serve(listener, move || NativeFilesystem::new(served.clone()))
+    });
+    (root,address)
+}
+
+#[test]
+fn watch_notifications_are_received_by_client() {
+    let (root,address) = serve_temp_directory();
+    std::fs::create_dir(root.join("src")).unwrap();
+    let mut client   = fmc::Client::new(WebSocketTransport::connect(address));
+    let mut executor = LocalPool::new();
+    executor.spawner().spawn_local(client.runner()).unwrap();
+    let mut events   = Box::pin(client.filesystem_events());
+
+    executor.run_until(client.create_watch(fmc::Path::new("./src"))).unwrap();
+    std::fs::write(root.join("src/New.enso"),"main = 2 + 2").unwrap();
+
+    let expected = fmc::FilesystemEvent {
+        path : fmc::Path::new("./src/New.enso"),
+        kind : fmc::FilesystemEventKind::Created,
+    };
+    assert_eq!(executor.run_until(events.next()), Some(expected));
+    std::fs::remove_dir_all(root).unwrap();
+}
@@ -13,8 +13,11 @@ use crate::prelude::*;
 
 pub use enso_prelude as prelude;
 use json_rpc::api::Result;
+use json_rpc::error::RpcError;
+use json_rpc::messages;
 use json_rpc::Handler;
 use futures::Stream;
+use futures::StreamExt;
 use serde::Serialize;
 use serde::Deserialize;
 use shapely::shared;
@@ -51,10 +54,73 @@ impl Path {
 
 
 
+// =============
+// === Bytes ===
+// =============
+
+/// Binary data. Serialized as a base64-encoded string.
+#[derive(Clone,Debug,Default,Eq,Hash,PartialEq)]
+#[derive(Shrinkwrap)]
+pub struct Bytes(pub Vec<u8>);
+
+impl Bytes {
+    /// Wraps a byte vector-like entity into a new `Bytes`.
+    pub fn new<B>(bytes:B) -> Bytes where B:Into<Vec<u8>> {
+        Bytes(bytes.into())
+    }
+}
+
+impl Serialize for Bytes {
+    fn serialize<S>(&self, serializer:S) -> std::result::Result<S::Ok,S::Error>
+    where S:serde::Serializer {
+        serializer.serialize_str(&base64::encode(&self.0))
+    }
+}
+
+impl<'de> Deserialize<'de> for Bytes {
+    fn deserialize<D>(deserializer:D) -> std::result::Result<Self,D::Error>
+    where D:serde::Deserializer<'de> {
+        let encoded = String::deserialize(deserializer)?;
+        let decoded = base64::decode(&encoded).map_err(serde::de::Error::custom)?;
+        Ok(Bytes(decoded))
+    }
+}
+
+
+
+// ================
+// === Checksum ===
+// ================
+
+/// Checksum of the file contents: SHA-256 digest written with lowercase hexadecimal digits.
+#[derive(Clone,Debug,Display,Eq,Hash,PartialEq)]
+#[derive(Serialize, Deserialize)]
+#[derive(Shrinkwrap)]
+pub struct Checksum(pub String);
+
+impl Checksum {
+    /// Wraps a `String`-like entity into a new `Checksum`.
+    pub fn new<S>(s:S) -> Checksum where S:Into<String> {
+        Checksum(s.into())
+    }
+
+    /// Computes the checksum of the given file contents, the same way as the server does.
+    pub fn of(contents:impl AsRef<[u8]>) -> Checksum {
+        use sha2::Digest;
+        let digest = sha2::Sha256::digest(contents.as_ref());
+        Checksum(format!("{:x}",digest))
+    }
+}
+
+
+
 // ====================
 // === Notification ===
 // ====================
 
+/// Name of the method used by `FilesystemEvent` notifications.
+pub const FILESYSTEM_EVENT_METHOD:&str = "filesystemEvent";
+
 /// Notification generated by the File Manager.
 #[derive(Clone,Debug,PartialEq)]
 #[derive(Serialize, Deserialize)]
@@ -138,6 +204,106 @@ pub enum FileKind {
 
 
 
+// ==============
+// === Errors ===
+// ==============
+
+/// Error codes used by the File Manager server to report failed calls.
+pub mod error_code {
+    /// The call failed for a reason not covered by a more specific code.
+    pub const FAILED_CALL       : i64 = -32000;
+    /// The file does not exist.
+    pub const NOT_FOUND         : i64 = -32001;
+    /// The file already exists.
+    pub const ALREADY_EXISTS    : i64 = -32002;
+    /// The access to the file is not allowed.
+    pub const ACCESS_DENIED     : i64 = -32003;
+    /// The file is not a directory.
+    pub const NOT_DIRECTORY     : i64 = -32004;
+    /// The file contents do not have the checksum expected by the call.
+    pub const CHECKSUM_MISMATCH : i64 = -32005;
+}
+
+/// Additional data of the error reply, identifying the file that caused the failure.
+#[derive(Clone,Debug,PartialEq)]
+#[derive(Serialize, Deserialize)]
+pub struct ErrorData {
+    /// Path of the file that caused the failure.
+    pub path : Path,
+}
+
+/// Reason of the failed File Manager call, decoded from the error reported by the server.
+#[derive(Clone,Debug,Fail,PartialEq)]
+pub enum FileManagerError {
+    /// The file does not exist.
+    #[fail(display = "File not found: {}.", _0)]
+    NotFound(Path),
+    /// The file already exists.
+    #[fail(display = "File already exists: {}.", _0)]
+    AlreadyExists(Path),
+    /// The access to the file is not allowed.
+    #[fail(display = "Access denied: {}.", _0)]
+    AccessDenied(Path),
+    /// The call requires a directory, while the path points to another kind of file.
+    #[fail(display = "Not a directory: {}.", _0)]
+    NotDirectory(Path),
+    /// The file contents do not have the expected checksum, e.g. because the file was modified by
+    /// someone else.
+    #[fail(display = "Checksum mismatch: {}.", _0)]
+    ChecksumMismatch(Path),
+    /// Any other failure reported by the server.
+    #[fail(display = "File Manager call failed with code {}: {}", code, message)]
+    Other {
+        /// The error code sent by the server.
+        code    : i64,
+        /// The error message sent by the server.
+        message : String,
+    },
+}
+
+impl FileManagerError {
+    /// Decodes the error reply. Errors with unknown codes or without the path in their data are
+    /// decoded as `Other`.
+    pub fn from_remote(error:&messages::Error) -> Self {
+        use FileManagerError::*;
+        let data = error.data.clone().and_then(|data| serde_json::from_value(data).ok());
+        let path = data.map(|data:ErrorData| data.path);
+        match (error.code,path) {
+            (error_code::NOT_FOUND        ,Some(path)) => NotFound(path),
+            (error_code::ALREADY_EXISTS   ,Some(path)) => AlreadyExists(path),
+            (error_code::ACCESS_DENIED    ,Some(path)) => AccessDenied(path),
+            (error_code::NOT_DIRECTORY    ,Some(path)) => NotDirectory(path),
+            (error_code::CHECKSUM_MISMATCH,Some(path)) => ChecksumMismatch(path),
+            _ => {
+                let code    = error.code;
+                let message = error.message.clone();
+                Other {code,message}
+            }
+        }
+    }
+
+    /// Decodes the error of the remote call. Returns `None` if the call was not rejected by the
+    /// server, e.g. when the connection was lost.
+    pub fn from_rpc_error(error:&RpcError) -> Option<Self> {
+        match error {
+            RpcError::RemoteError(error) => Some(Self::from_remote(error)),
+            _                            => None,
+        }
+    }
+}
+
+/// Converts the error of the File Manager call into `failure::Error`, so it can be propagated
+/// with other errors. The errors reported by the server are decoded into `FileManagerError`, which
+/// can be recognized with `downcast_ref`.
+pub fn decode_error(error:RpcError) -> failure::Error {
+    match FileManagerError::from_rpc_error(&error) {
+        Some(decoded) => decoded.into(),
+        None          => error.into(),
+    }
+}
+
+
+
 // ==============
 // === Client ===
 // ==============
@@ -162,11 +328,22 @@ shared! { Handle
 
         /// Asynchronous event stream with notification and errors.
         ///
-        /// On a repeated call, previous stream is closed.
+        /// Each call creates a new, independent stream. Dropping the stream unsubscribes it.
         pub fn events(&mut self) -> impl Stream<Item = Event> {
             self.handler.handler_event_stream()
         }
 
+        /// Asynchronous stream of filesystem events reported by the active watches.
+        ///
+        /// Each call creates a new, independent stream. Dropping the stream unsubscribes it.
+        pub fn filesystem_events(&mut self) -> impl Stream<Item = FilesystemEvent> {
+            let events = self.handler.subscribe_method(FILESYSTEM_EVENT_METHOD);
+            events.filter_map(|event| futures::future::ready(match event {
+                Event::Notification(Notification::FilesystemEvent(event)) => Some(event),
+                _                                                         => None,
+            }))
+        }
+
         /// Returns a future that performs any background, asynchronous work needed
         /// for this Client to correctly work. Should be continually run while the
         /// `Client` is used. Will end once `Client` is dropped.
@@ -183,74 +360,112 @@ shared! { Handle
 // === RPC Methods ===
 // ===================
 
+/// Remote API of the File Manager Server.
+///
+/// Each method gets its counterpart in `Client` and `Handle`, making the remote call and
+/// returning `Future` with its result. See `json_rpc::api` for details.
+#[json_rpc::api(client="Client", handle="Handle")]
+trait FileManagerApi {
+    /// Copy the directory with all its contents.
+    fn copy_directory(&self, from:Path, to:Path) -> ();
+
+    /// Copy the file.
+    fn copy_file(&self, from:Path, to:Path) -> ();
 
-// === Helper macro ===
+    /// Create the directory, including all its missing parent directories.
+    fn create_directory(&self, path:Path) -> ();
 
-/// Macro that generates a asynchronous method making relevant RPC call to the
-/// server. First three args is the name appropriately in CamelCase,
-/// snake_case, camelCase. Then goes the function signature, in form of
-/// `(arg:Arg) -> Ret`.
-///
-/// Macro generates:
-/// * a method in Client named `snake_case` that takes `(arg:Arg)` and returns
-/// `Future<Ret>`.
-/// * a structure named `CamelCase` that stores function arguments as fields and
-///   its JSON serialization conforms to JSON-RPC (yielding `method` and
-///   `params` fields).
-/// * `snakeCase` is the name of the remote method.
-macro_rules! make_rpc_method {
-    ( $name_typename:ident
-      $name:ident
-      $name_ext:ident
-      ($($arg:ident : $type:ty),* $(,)?) -> $out:ty   ) => {
-    paste::item! {
-        impl Client {
-            /// Remote call to the method on the File Manager Server.
-            pub fn $name
-            (&mut self, $($arg:$type),*) -> impl Future<Output=Result<$out>> {
-                let input = [<$name_typename Input>] { $($arg:$arg),* };
-                self.handler.open_request(input)
-            }
-        }
+    /// Delete the file.
+    fn delete_file(&self, path:Path) -> ();
 
-        impl Handle {
-            /// Remote call to the method on the File Manager Server.
-            pub fn $name
-            (&mut self, $($arg:$type),*) -> impl Future<Output=Result<$out>> {
-                self.with_borrowed(|client| client.$name  ($($arg),*))
-            }
-        }
+    /// Check if the file exists.
+    fn exists(&self, path:Path) -> bool;
 
-        /// Structure transporting method arguments.
-        #[derive(Serialize,Deserialize,Debug,PartialEq)]
-        #[serde(rename_all = "camelCase")]
-        struct [<$name_typename Input>] {
-            $($arg : $type),*
-        }
+    /// List the directory contents.
+    fn list(&self, path:Path) -> Vec<Path>;
 
-        impl json_rpc::RemoteMethodCall for [<$name_typename Input>] {
-            const NAME:&'static str = stringify!($name_ext);
-            type Returned = $out;
-        }
-    }}
+    /// Move the directory with all its contents.
+    fn move_directory(&self, from:Path, to:Path) -> ();
+
+    /// Move the file.
+    fn move_file(&self, from:Path, to:Path) -> ();
+
+    /// Read the file contents.
+    fn read(&self, path:Path) -> String;
+
+    /// Read the file contents as binary data.
+    fn read_bytes(&self, path:Path) -> Bytes;
+
+    /// Read at most `length` bytes of the file, starting at `offset`. Fewer bytes are returned
+    /// if the file ends earlier.
+    fn read_range(&self, path:Path, offset:u64, length:u64) -> Bytes;
+
+    /// Get the file attributes.
+    fn status(&self, path:Path) -> Attributes;
+
+    /// Create the file if it does not exist.
+    fn touch(&self, path:Path) -> ();
+
+    /// Write the file contents, replacing the previous ones.
+    ///
+    /// If `expected_checksum` is given, the file is written only if its current contents have
+    /// this checksum. Otherwise the call fails with `CHECKSUM_MISMATCH` error. The server checks
+    /// and writes the file in one step, so no modification made in the meantime is lost.
+    fn write(&self, path:Path, contents:String, expected_checksum:Option<Checksum>) -> ();
+
+    /// Write binary data as the file contents, replacing the previous ones.
+    fn write_bytes(&self, path:Path, contents:Bytes) -> ();
+
+    /// Write binary data to the file, starting at `offset`. The rest of the file is kept intact,
+    /// the file is extended if needed.
+    fn write_range(&self, path:Path, offset:u64, contents:Bytes) -> ();
+
+    /// Start watching the path. Filesystem events will be sent as `FilesystemEvent`
+    /// notifications.
+    fn create_watch(&self, path:Path) -> Uuid;
+
+    /// Stop the watch with the given id.
+    fn delete_watch(&self, watch_id:Uuid) -> ();
+
+    /// Compute the checksum of the file contents.
+    fn checksum(&self, path:Path) -> Checksum;
 }
 
 
-// === Remote API definition ===
 
-make_rpc_method!(CopyDirectory copy_directory copyDirectory (from:Path, to:Path)         -> ()        );
-make_rpc_method!(CopyFile      copy_file      copyFile      (from:Path, to:Path)         -> ()        );
-make_rpc_method!(DeleteFile    delete_file    deleteFile    (path:Path)                  -> ()        );
-make_rpc_method!(Exists        exists         exists        (path:Path)                  -> bool      );
-make_rpc_method!(List          list           list          (path:Path)                  -> Vec<Path> );
-make_rpc_method!(MoveDirectory move_directory moveDirectory (from:Path, to:Path)         -> ()        );
-make_rpc_method!(MoveFile      move_file      moveFile      (from:Path, to:Path)         -> ()        );
-make_rpc_method!(Read          read           read          (path:Path)                  -> String    );
-make_rpc_method!(Status        status         status        (path:Path)                  -> Attributes);
-make_rpc_method!(Touch         touch          touch         (path:Path)                  -> ()        );
-make_rpc_method!(Write         write          write         (path:Path, contents:String) -> ()        );
-make_rpc_method!(CreateWatch   create_watch   createWatch   (path:Path)                  -> Uuid      );
-make_rpc_method!(DeleteWatch   delete_watch   deleteWatch   (watch_id:Uuid)              -> ()        );
+// =====================
+// === Chunked Reads ===
+// =====================
+
+/// Default size of chunks yielded by `Handle::read_stream`.
+pub const DEFAULT_CHUNK_SIZE:u64 = 1024 * 1024;
+
+impl Handle {
+    /// Reads the file in chunks of given size, using subsequent `read_range` calls.
+    ///
+    /// The stream ends after the last chunk or after the first error.
+    pub fn read_stream(&self, path:Path, chunk_size:u64) -> impl Stream<Item=Result<Bytes>> {
+        let handle = self.clone_ref();
+        // The offset of the next chunk, `None` once the stream is finished.
+        let offset = Some(0);
+        futures::stream::unfold(offset, move |offset| {
+            let mut handle = handle.clone_ref();
+            let path       = path.clone();
+            async move {
+                let offset = offset?;
+                match handle.read_range(path,offset,chunk_size).await {
+                    Ok(chunk) if chunk.is_empty() => None,
+                    Ok(chunk) => {
+                        let length = chunk.len() as u64;
+                        let next   = (length == chunk_size).as_some(offset + length);
+                        Some((Ok(chunk),next))
+                    },
+                    Err(error) => Some((Err(error),None)),
+                }
+            }
+        })
+    }
+}
 
 
 
@@ -287,6 +502,29 @@ mod tests {
         Fixture {transport,client,executor}
     }
 
+    #[test]
+    fn test_independent_filesystem_event_streams() {
+        let mut fixture = setup_fm();
+        let mut first   = Box::pin(fixture.client.filesystem_events());
+        let mut second  = Box::pin(fixture.client.filesystem_events());
+        let notification_text = r#"{
+            "jsonrpc": "2.0",
+            "method": "filesystemEvent",
+            "params": {"path" : "./Main.luna", "kind" : "Created"}
+        }"#;
+        fixture.transport.mock_peer_message_text(notification_text);
+        fixture.executor.run_until_stalled();
+
+        let expected_event = FilesystemEvent {
+            path : Path::new("./Main.luna"),
+            kind : FilesystemEventKind::Created,
+        };
+        assert_eq!(poll_stream_output(&mut first) , Some(expected_event.clone()));
+        assert_eq!(poll_stream_output(&mut second), Some(expected_event));
+        assert!(poll_stream_output(&mut first).is_none());
+        assert!(poll_stream_output(&mut second).is_none());
+    }
+
     #[test]
     fn test_notification() {
         let mut fixture = setup_fm();
@@ -344,6 +582,85 @@ mod tests {
         assert_eq!(output, expected_output);
     }
 
+    #[test]
+    fn test_read_stream() {
+        let mut transport = MockTransport::new();
+        let     handle    = Handle::new(transport.clone());
+        let mut executor  = futures::executor::LocalPool::new();
+        executor.spawner().spawn_local(handle.runner()).unwrap();
+        let     path      = Path::new("./data.csv");
+        let mut stream    = Box::pin(handle.read_stream(path,4));
+        let mut reply     = |expected_offset:u64, chunk:&[u8]| {
+            let request = transport.expect_message::<RequestMessage<Value>>();
+            assert_eq!(request.method, "readRange");
+            assert_eq!(request.params["offset"], json!(expected_offset));
+            assert_eq!(request.params["length"], json!(4));
+            let result = serde_json::to_value(Bytes::new(chunk)).unwrap();
+            transport.mock_peer_message(Message::new_success(request.id,result));
+        };
+
+        // Polling the stream makes the request for the next chunk.
+        assert!(poll_stream_output(&mut stream).is_none());
+        reply(0,b"a,b\n");
+        executor.run_until_stalled();
+        let chunk = poll_stream_output(&mut stream).unwrap().unwrap();
+        assert_eq!(chunk, Bytes::new(&b"a,b\n"[..]));
+
+        assert!(poll_stream_output(&mut stream).is_none());
+        reply(4,b"1,");
+        executor.run_until_stalled();
+        let chunk = poll_stream_output(&mut stream).unwrap().unwrap();
+        assert_eq!(chunk, Bytes::new(&b"1,"[..]));
+
+        // The last chunk was shorter than requested, so the stream has ended.
+        assert!(poll_stream_output(&mut stream).is_none());
+        assert!(transport.with_mut_data(|data| data.sent_msgs.is_empty()));
+    }
+
+    #[test]
+    fn test_error_decoding() {
+        let error = |code, data| messages::Error {code,message:"Failed.".into(),data};
+        let path  = Path::new("./Main.luna");
+        let data  = Some(json!({"path" : "./Main.luna"}));
+
+        let not_found = error(error_code::NOT_FOUND,data.clone());
+        let expected  = FileManagerError::NotFound(path.clone());
+        assert_eq!(FileManagerError::from_remote(&not_found), expected);
+        let not_dir   = error(error_code::NOT_DIRECTORY,data.clone());
+        let expected  = FileManagerError::NotDirectory(path);
+        assert_eq!(FileManagerError::from_remote(&not_dir), expected);
+        let mismatch  = error(error_code::CHECKSUM_MISMATCH,data.clone());
+        let expected  = FileManagerError::ChecksumMismatch(path.clone());
+        assert_eq!(FileManagerError::from_remote(&mismatch), expected);
+        let unknown   = error(error_code::FAILED_CALL,None);
+        let expected  = FileManagerError::Other {code:-32000, message:"Failed.".into()};
+        assert_eq!(FileManagerError::from_remote(&unknown), expected);
+
+        let decoded = decode_error(RpcError::RemoteError(not_found));
+        assert!(decoded.downcast_ref::<FileManagerError>().is_some());
+        let decoded = decode_error(RpcError::LostConnection);
+        assert!(decoded.downcast_ref::<FileManagerError>().is_none());
+    }
+
+    #[test]
+    fn test_checksum_of() {
+        let empty = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(Checksum::of(""), Checksum::new(empty));
+        assert_eq!(Checksum::of("main = 2 + 2"), Checksum::of(b"main = 2 + 2"));
+    }
+
+    #[test]
+    fn test_call_decoding() {
+        let watch_id = uuid::Uuid::parse_str("02723954-fbb0-4641-af53-cec0883f260a").unwrap();
+        let request  = json!({
+            "method" : "deleteWatch",
+            "params" : {"watchId" : "02723954-fbb0-4641-af53-cec0883f260a"}
+        });
+        let call     = serde_json::from_value::<FileManagerApiCall>(request).unwrap();
+        assert_eq!(call, FileManagerApiCall::DeleteWatch(DeleteWatchInput {watch_id}));
+        assert_eq!(call.method_name(), "deleteWatch");
+    }
+
     #[test]
     fn test_requests() {
         let main                = Path::new("./Main.luna");
@@ -368,6 +685,12 @@ mod tests {
             from_main_to_target.clone(),
             unit_json.clone(),
             ());
+        test_request(
+            |client| client.create_directory(main.clone()),
+            "createDirectory",
+            path_main.clone(),
+            unit_json.clone(),
+            ());
         test_request(
             |client| client.delete_file(main.clone()),
             "deleteFile",
@@ -408,6 +731,33 @@ mod tests {
             json!("Hello world!"),
             "Hello world!".into());
 
+        let binary_data = Bytes::new(vec![0x89,b'P',b'N',b'G',0x00,0xFF]);
+        let binary_json = json!("iVBORwD/");
+        test_request(
+            |client| client.read_bytes(main.clone()),
+            "readBytes",
+            path_main.clone(),
+            binary_json.clone(),
+            binary_data.clone());
+        test_request(
+            |client| client.read_range(main.clone(),4,2),
+            "readRange",
+            json!({"path" : "./Main.luna", "offset" : 4, "length" : 2}),
+            json!("AP8="),
+            Bytes::new(vec![0x00,0xFF]));
+        test_request(
+            |client| client.write_bytes(main.clone(),binary_data.clone()),
+            "writeBytes",
+            json!({"path" : "./Main.luna", "contents" : binary_json.clone()}),
+            unit_json.clone(),
+            ());
+        test_request(
+            |client| client.write_range(main.clone(),4,Bytes::new(vec![0x00,0xFF])),
+            "writeRange",
+            json!({"path" : "./Main.luna", "offset" : 4, "contents" : "AP8="}),
+            unit_json.clone(),
+            ());
+
         let parse_rfc3339 = |s| {
             chrono::DateTime::parse_from_rfc3339(s).unwrap()
         };
@@ -438,9 +788,20 @@ mod tests {
             unit_json.clone(),
             ());
         test_request(
-            |client| client.write(main.clone(), "Hello world!".into()),
+            |client| client.write(main.clone(), "Hello world!".into(), None),
+            "write",
+            json!({"path" : "./Main.luna", "contents" : "Hello world!", "expectedChecksum" : null}),
+            unit_json.clone(),
+            ());
+        let checksum = Checksum::of("Hello");
+        test_request(
+            |client| client.write(main.clone(), "Hello world!".into(), Some(checksum.clone())),
             "write",
-            json!({"path" : "./Main.luna", "contents" : "Hello world!"}),
+            json!({
+                "path"             : "./Main.luna",
+                "contents"         : "Hello world!",
+                "expectedChecksum" : checksum.0.clone()
+            }),
             unit_json.clone(),
             ());
 
@@ -461,5 +822,13 @@ mod tests {
             watch_id.clone(),
             unit_json.clone(),
             ());
+
+        let checksum_hex = "9a0364b9e99bb480dd25e1f0284c8555ce9ec5d4ab5b2c14c8a8bdb5d8e7d5f4";
+        test_request(
+            |client| client.checksum(main.clone()),
+            "checksum",
+            path_main.clone(),
+            json!(checksum_hex),
+            Checksum::new(checksum_hex));
     }
 }
@@ -0,0 +1,296 @@
+//! Procedural macros used to declare remote APIs of JSON-RPC services.
+
+#![warn(missing_docs)]
+
+extern crate proc_macro;
+
+use crate::prelude::*;
+
+use enso_prelude as prelude;
+use inflector::cases::camelcase::to_camel_case;
+use inflector::cases::pascalcase::to_pascal_case;
+use proc_macro2::Ident;
+use proc_macro2::Span;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn;
+
+
+
+// ===============
+// === ApiArgs ===
+// ===============
+
+/// Arguments given to the `api` attribute, e.g. `#[api(client="Client", handle="Handle")]`.
+struct ApiArgs {
+    /// Client type that gets the methods making remote calls. It must have a `handler` field
+    /// with the `json_rpc::Handler`.
+    client : Ident,
+    /// Optional shared handle over the client, as defined by the `shared!` macro.
+    handle : Option<Ident>,
+    /// Name of the generated server-side dispatch enum. Defaults to trait's name with `Call`
+    /// suffix.
+    call   : Option<Ident>,
+}
+
+impl ApiArgs {
+    fn parse(args:syn::AttributeArgs) -> syn::Result<Self> {
+        let mut client = None;
+        let mut handle = None;
+        let mut call   = None;
+        for arg in args {
+            let (name,value) = match &arg {
+                syn::NestedMeta::Meta(syn::Meta::NameValue(name_value)) => {
+                    match &name_value.lit {
+                        syn::Lit::Str(value) => (&name_value.path,value.parse::<Ident>()?),
+                        lit => return Err(syn::Error::new_spanned(lit,"Expected a string.")),
+                    }
+                },
+                _ => return Err(syn::Error::new_spanned(&arg,"Expected `name = \"value\"`.")),
+            };
+            if      name.is_ident("client") { client = Some(value) }
+            else if name.is_ident("handle") { handle = Some(value) }
+            else if name.is_ident("call")   { call   = Some(value) }
+            else {
+                let message = "Unknown argument, expected `client`, `handle` or `call`.";
+                return Err(syn::Error::new_spanned(name,message))
+            }
+        }
+        let missing_client = || syn::Error::new(Span::call_site(),"Missing `client` argument.");
+        let client         = client.ok_or_else(missing_client)?;
+        Ok(ApiArgs {client,handle,call})
+    }
+}
+
+
+
+// ====================
+// === RemoteMethod ===
+// ====================
+
+/// Name of the attribute that overrides the remote method name.
+const RPC_NAME_ATTRIBUTE:&str = "rpc_name";
+
+/// Description of a single remote method, obtained from the API trait's method signature.
+struct RemoteMethod {
+    /// Documentation of the method.
+    docs     : Vec<syn::Attribute>,
+    /// Name of the generated client method, e.g. `copy_file`.
+    name     : Ident,
+    /// Name of the method on the remote side, e.g. `copyFile`.
+    rpc_name : String,
+    /// Names of the method's arguments.
+    args     : Vec<Ident>,
+    /// Types of the method's arguments.
+    types    : Vec<syn::Type>,
+    /// Type of the value returned by a successful call.
+    returned : syn::Type,
+}
+
+impl RemoteMethod {
+    fn new(method:&syn::TraitItemMethod) -> syn::Result<Self> {
+        let sig      = &method.sig;
+        let name     = sig.ident.clone();
+        let docs     = method.attrs.iter().filter(|a| a.path.is_ident("doc")).cloned().collect();
+        let rpc_name = Self::rpc_name_override(&method.attrs)?;
+        let rpc_name = rpc_name.unwrap_or_else(|| to_camel_case(&name.to_string()));
+        let returned = match &sig.output {
+            syn::ReturnType::Default    => syn::parse_quote!(()),
+            syn::ReturnType::Type(_,ty) => ty.as_ref().clone(),
+        };
+        let mut args  = Vec::new();
+        let mut types = Vec::new();
+        for input in &sig.inputs {
+            if let syn::FnArg::Typed(arg) = input {
+                match arg.pat.as_ref() {
+                    syn::Pat::Ident(pat) => args.push(pat.ident.clone()),
+                    pat => return Err(syn::Error::new_spanned(pat,"Expected a named argument.")),
+                }
+                types.push(arg.ty.as_ref().clone());
+            }
+        }
+        Ok(RemoteMethod {docs,name,rpc_name,args,types,returned})
+    }
+
+    /// Reads the `#[rpc_name="..."]` attribute, if present.
+    fn rpc_name_override(attrs:&[syn::Attribute]) -> syn::Result<Option<String>> {
+        let attr = attrs.iter().find(|a| a.path.is_ident(RPC_NAME_ATTRIBUTE));
+        match attr.map(|a| a.parse_meta()).transpose()? {
+            Some(syn::Meta::NameValue(syn::MetaNameValue{lit:syn::Lit::Str(name),..})) =>
+                Ok(Some(name.value())),
+            Some(meta) =>
+                Err(syn::Error::new_spanned(meta,"Expected `#[rpc_name=\"name\"]`.")),
+            None =>
+                Ok(None),
+        }
+    }
+
+    /// Identifier of the call variant, e.g. `CopyFile`.
+    fn variant_ident(&self) -> Ident {
+        let name = to_pascal_case(&self.name.to_string());
+        Ident::new(&name,self.name.span())
+    }
+
+    /// Identifier of the structure with arguments, e.g. `CopyFileInput`.
+    fn input_ident(&self) -> Ident {
+        let name = format!("{}Input",self.variant_ident());
+        Ident::new(&name,self.name.span())
+    }
+
+    /// Structure transporting method arguments and its `RemoteMethodCall` implementation.
+    fn input_struct(&self) -> TokenStream {
+        let input     = self.input_ident();
+        let rpc_name  = &self.rpc_name;
+        let args      = &self.args;
+        let types     = &self.types;
+        let returned  = &self.returned;
+        let doc       = format!("Structure transporting arguments of the `{}` method.",rpc_name);
+        let field_doc = args.iter().map(|arg| format!("Argument `{}`.",arg));
+        quote! {
+            #[doc = #doc]
+            #[derive(Clone,Debug,PartialEq,::serde::Serialize,::serde::Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            pub struct #input {
+                #(#[doc = #field_doc] pub #args : #types),*
+            }
+
+            impl ::json_rpc::RemoteMethodCall for #input {
+                const NAME:&'static str = #rpc_name;
+                type Returned = #returned;
+            }
+        }
+    }
+
+    /// Client's method making the remote call.
+    fn client_method(&self) -> TokenStream {
+        let docs     = &self.docs;
+        let name     = &self.name;
+        let input    = self.input_ident();
+        let args     = &self.args;
+        let types    = &self.types;
+        let returned = &self.returned;
+        quote! {
+            #(#docs)*
+            pub fn #name(&mut self, #(#args:#types),*)
+            -> impl ::std::future::Future<Output=::json_rpc::api::Result<#returned>> {
+                let input = #input { #(#args),* };
+                self.handler.open_request(input)
+            }
+        }
+    }
+
+    /// Handle's method forwarding the remote call to the borrowed client.
+    fn handle_method(&self) -> TokenStream {
+        let docs     = &self.docs;
+        let name     = &self.name;
+        let args     = &self.args;
+        let types    = &self.types;
+        let returned = &self.returned;
+        quote! {
+            #(#docs)*
+            pub fn #name(&mut self, #(#args:#types),*)
+            -> impl ::std::future::Future<Output=::json_rpc::api::Result<#returned>> {
+                self.with_borrowed(|client| client.#name(#(#args),*))
+            }
+        }
+    }
+}
+
+
+
+// ===========
+// === api ===
+// ===========
+
+/// Declares a remote API of a JSON-RPC service using a plain trait.
+///
+/// For the following input:
+/// ```compile_fail
+/// #[json_rpc::api(client="Client", handle="Handle")]
+/// trait FileManagerApi {
+///     /// Copy the file.
+///     fn copy_file(&self, from:Path, to:Path) -> ();
+/// }
+/// ```
+///
+/// the trait is consumed and the macro generates:
+/// * `CopyFileInput` structure with `from` and `to` fields, implementing `RemoteMethodCall` for
+///   the remote method `copyFile`. The remote name is the method's name in camelCase, unless
+///   overridden with the `#[rpc_name="..."]` attribute on the method.
+/// * `Client::copy_file` method, making the remote call and returning its `Future` result.
+///   `Client` must have a `handler` field with the `json_rpc::Handler`.
+/// * `Handle::copy_file` method forwarding to the client, if `handle` was given. The handle is
+///   expected to be defined with the `shared!` macro.
+/// * `FileManagerApiCall` enum with `CopyFile(CopyFileInput)` variant, which deserializes from a
+///   request's `method` and `params`, so the server can dispatch the call. The enum's name can
+///   be customized with the `call` argument.
+#[proc_macro_attribute]
+pub fn api
+( args  : proc_macro::TokenStream
+, input : proc_macro::TokenStream
+) -> proc_macro::TokenStream {
+    let args = syn::parse_macro_input!(args as syn::AttributeArgs);
+    let decl = syn::parse_macro_input!(input as syn::ItemTrait);
+    match generate_api(args,decl) {
+        Ok(output) => output.into(),
+        Err(error) => error.to_compile_error().into(),
+    }
+}
+
+fn generate_api(args:syn::AttributeArgs, decl:syn::ItemTrait) -> syn::Result<TokenStream> {
+    let args    = ApiArgs::parse(args)?;
+    let methods = decl.items.iter().filter_map(|item| match item {
+        syn::TraitItem::Method(method) => Some(RemoteMethod::new(method)),
+        _                              => None,
+    });
+    let methods = methods.collect::<syn::Result<Vec<_>>>()?;
+
+    let client         = &args.client;
+    let call_name      = format!("{}Call",decl.ident);
+    let default_call   = || Ident::new(&call_name,decl.ident.span());
+    let call           = args.call.clone().unwrap_or_else(default_call);
+    let call_doc       = format!("Any call to the `{}` API, decoded from a request.",decl.ident);
+    let input_structs  = methods.iter().map(RemoteMethod::input_struct);
+    let client_methods = methods.iter().map(RemoteMethod::client_method);
+    let variants       = methods.iter().map(RemoteMethod::variant_ident).collect_vec();
+    let inputs         = methods.iter().map(RemoteMethod::input_ident);
+    let rpc_names      = methods.iter().map(|method| method.rpc_name.clone()).collect_vec();
+    let variant_docs   = rpc_names.iter().map(|name| format!("Call to the `{}` method.",name));
+    let handle_impl    = args.handle.as_ref().map(|handle| {
+        let handle_methods = methods.iter().map(RemoteMethod::handle_method);
+        quote! {
+            impl #handle {
+                #(#handle_methods)*
+            }
+        }
+    });
+    Ok(quote! {
+        #(#input_structs)*
+
+        impl #client {
+            #(#client_methods)*
+        }
+
+        #handle_impl
+
+        #[doc = #call_doc]
+        #[derive(Clone,Debug,PartialEq,::serde::Serialize,::serde::Deserialize)]
+        #[serde(tag = "method", content = "params")]
+        pub enum #call {
+            #(
+                #[doc = #variant_docs]
+                #[serde(rename = #rpc_names)]
+                #variants(#inputs)
+            ),*
+        }
+
+        impl #call {
+            /// Name of the called remote method.
+            pub fn method_name(&self) -> &'static str {
+                match self {
+                    #(#call::#variants(_) => #rpc_names),*
+                }
+            }
+        }
+    })
+}
@@ -8,6 +8,8 @@ use crate::error::HandlingError;
 use crate::error::RpcError;
 use crate::messages;
 use crate::messages::Id;
+use crate::subscription::Filter;
+use crate::subscription::Subscribers;
 use crate::transport::Transport;
 use crate::transport::TransportEvent;
 
@@ -15,11 +17,9 @@ use futures::FutureExt;
 use futures::StreamExt;
 use futures::Stream;
 use futures::channel::mpsc::unbounded;
-use futures::channel::mpsc::UnboundedSender;
 use futures::channel::oneshot;
 use serde::de::DeserializeOwned;
 use std::future::Future;
-use utils::channel;
 
 
 
@@ -84,12 +84,14 @@ impl IdGenerator {
 // =============
 
 /// Event emitted by the `Handler<N>`.
-#[derive(Debug)]
+///
+/// Events are broadcast to all subscribers, so the error is shared between them.
+#[derive(Clone,Debug)]
 pub enum Event<N> {
     /// Transport has been closed.
     Closed,
     /// Error occurred.
-    Error(HandlingError),
+    Error(Rc<HandlingError>),
     /// Notification received.
     Notification(N),
 }
@@ -100,6 +102,9 @@ pub enum Event<N> {
 // === HandlerData ===
 // ===================
 
+/// Name of the JSON field that holds the notification's method name.
+const NOTIFICATION_METHOD_FIELD:&str = "method";
+
 /// Container that stores Sender's for ongoing calls. Each call identified by
 /// id has its own sender. After reply is received, the call is removed
 /// from this container.
@@ -118,7 +123,8 @@ pub type OngoingCalls = HashMap<Id,oneshot::Sender<ReplyMessage>>;
 /// It allows making request, where method calls are described by values
 /// implementing `RemoteMethodCall`. The response is returned as a `Future`.
 ///
-/// Notifications and internal messages are emitted using the `events` stream.
+/// Notifications and internal messages are broadcast to any number of event streams, obtained
+/// by `subscribe`. Each stream may be narrowed to chosen notifications only.
 ///
 /// `Notification` is a type for notifications. It should implement
 /// `DeserializeOwned` and deserialize from JSON maps with `method` and `params`
@@ -133,8 +139,8 @@ shared! { Handler
 pub struct HandlerData<Notification> {
     /// Ongoing calls.
     ongoing_calls   : OngoingCalls,
-    /// Handle to send outgoing events.
-    outgoing_events : Option<UnboundedSender<Event<Notification>>>,
+    /// Subscribers of the outgoing events.
+    subscribers     : Subscribers<Notification>,
     /// Provides identifiers for requests.
     id_generator    : IdGenerator,
     /// Transports text messages between this handler and the peer.
@@ -142,7 +148,7 @@ pub struct HandlerData<Notification> {
 }
 
 
-impl<Notification> {
+impl<Notification:Clone> {
     /// Inserts a new entry for an ongoing request awaiting reply.
     pub fn insert_ongoing_request(&mut self, id:Id, sender:oneshot::Sender<ReplyMessage>) {
         self.ongoing_calls.insert(id,sender);
@@ -174,21 +180,30 @@ impl<Notification> {
         self.transport.send_text(text)
     }
 
-    /// Creates a new stream with events from this handler.
+    /// Creates a new stream with events from this handler, narrowed by the given filter.
     ///
-    /// If such stream was already existing, it will be finished (and
-    /// continuations should be able to process any remaining events).
+    /// Any number of streams may be alive at once, each with its own buffer. Dropping the stream
+    /// cancels the subscription.
+    pub fn subscribe
+    (&mut self, filter:Filter<Notification>) -> impl Stream<Item = Event<Notification>> {
+        self.subscribers.subscribe(filter)
+    }
+
+    /// Creates a new stream with all events from this handler.
+    ///
+    /// Previously created streams remain functional.
     pub fn handler_event_stream(&mut self) -> impl Stream<Item = Event<Notification>> {
-        let (transmitter,receiver) = unbounded();
-        self.outgoing_events = Some(transmitter);
-        receiver
+        self.subscribe(Filter::All)
     }
 
-    /// Sends a handler event to the event stream.
-    pub fn emit_event(&self, event:Event<Notification>) {
-        if let Some(event_transmitter) = self.outgoing_events.as_ref() {
-            channel::emit(event_transmitter,event)
-        }
+    /// Sends a handler event to the event streams.
+    pub fn emit_event(&mut self, event:Event<Notification>) {
+        self.subscribers.emit(event)
+    }
+
+    /// Sends a notification to the event streams that are interested in it.
+    pub fn emit_notification(&mut self, method:&str, notification:Notification) {
+        self.subscribers.notify(method,&notification)
     }
 }
 } // shared!
@@ -196,7 +211,7 @@ impl<Notification> {
 
 // === Handler methods ===
 
-impl<Notification> Handler<Notification> {
+impl<Notification:Clone> Handler<Notification> {
     /// Obtains stream of events from our transport layer.
     ///
     /// Calling this function invalidates (closes) any previous stream obtained
@@ -212,6 +227,18 @@ impl<Notification> Handler<Notification> {
         event_receiver
     }
 
+    /// Creates a new stream with notifications having the given method name.
+    pub fn subscribe_method(&self, method:impl Into<String>)
+    -> impl Stream<Item = Event<Notification>> {
+        self.subscribe(Filter::method(method))
+    }
+
+    /// Creates a new stream with notifications satisfying the given predicate.
+    pub fn subscribe_filtered(&self, predicate:impl Fn(&Notification) -> bool + 'static)
+    -> impl Stream<Item = Event<Notification>> {
+        self.subscribe(Filter::predicate(predicate))
+    }
+
     /// Creates a new handler working on a given `Transport`.
     ///
     /// `Transport` must be functional (e.g. not in the process of opening).
@@ -220,7 +247,7 @@ impl<Notification> Handler<Notification> {
             ongoing_calls   : default(),
             id_generator    : IdGenerator::new(),
             transport       : Box::new(transport),
-            outgoing_events : None,
+            subscribers     : default(),
         };
         Handler {rc: Rc::new(RefCell::new(data))}
     }
@@ -268,11 +295,10 @@ impl<Notification> Handler<Notification> {
     pub fn process_notification
     (&self, message:messages::Notification<serde_json::Value>)
     where Notification: DeserializeOwned {
+        let method = message.0.get(NOTIFICATION_METHOD_FIELD).and_then(|m| m.as_str());
+        let method = method.map(ToString::to_string).unwrap_or_default();
         match serde_json::from_value(message.0) {
-            Ok(notification) => {
-                let event = Event::Notification(notification);
-                self.emit_event(event);
-            },
+            Ok(notification) => self.emit_notification(&method,notification),
             Err(e) => {
                 let err = HandlingError::InvalidNotification(e);
                 self.error_occurred(err);
@@ -299,7 +325,7 @@ impl<Notification> Handler<Notification> {
     /// With with a handling error. Uses `on_error` callback to notify the
     /// owner.
     pub fn error_occurred(&self, error: HandlingError) {
-        self.emit_event(Event::Error(error))
+        self.emit_event(Event::Error(Rc::new(error)))
     }
 
     /// Processes a single transport event.
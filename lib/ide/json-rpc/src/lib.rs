@@ -16,13 +16,16 @@ pub mod api;
 pub mod error;
 pub mod handler;
 pub mod messages;
+pub mod subscription;
 pub mod test_util;
 pub mod transport;
 
 pub use api::RemoteMethodCall;
 pub use api::Result;
 pub use enso_prelude as prelude;
+pub use json_rpc_macros::api;
 pub use transport::Transport;
 pub use transport::TransportEvent;
 pub use handler::Event;
 pub use handler::Handler;
+pub use subscription::Filter;
@@ -0,0 +1,254 @@
+//! Module providing `Subscribers` — a broadcasting hub that distributes `Handler` events between
+//! any number of independent consumers.
+
+use crate::prelude::*;
+
+use crate::handler::Event;
+
+use futures::channel::mpsc::unbounded;
+use futures::channel::mpsc::UnboundedReceiver;
+use futures::channel::mpsc::UnboundedSender;
+use utils::channel;
+
+
+
+// ==============
+// === Filter ===
+// ==============
+
+/// Decides which notifications are delivered to a given subscriber.
+///
+/// Filters apply only to notifications. `Closed` events are delivered to every subscriber, while
+/// `Error` events are delivered only to subscribers using `Filter::All`.
+pub enum Filter<Notification> {
+    /// Subscriber receives all events.
+    All,
+    /// Subscriber receives only notifications with the given method name.
+    Method(String),
+    /// Subscriber receives only notifications for which the predicate returns `true`.
+    Predicate(Rc<dyn Fn(&Notification) -> bool>),
+}
+
+impl<Notification> Filter<Notification> {
+    /// Creates a filter accepting only notifications with given method name.
+    pub fn method(name:impl Into<String>) -> Self {
+        Filter::Method(name.into())
+    }
+
+    /// Creates a filter accepting only notifications that satisfy the predicate.
+    pub fn predicate(f:impl Fn(&Notification) -> bool + 'static) -> Self {
+        Filter::Predicate(Rc::new(f))
+    }
+
+    /// Checks if the notification with given method name should pass through this filter.
+    pub fn accepts(&self, method:&str, notification:&Notification) -> bool {
+        match self {
+            Filter::All          => true,
+            Filter::Method(name) => name == method,
+            Filter::Predicate(f) => f(notification),
+        }
+    }
+
+    /// Checks if this filter lets through events that are not notifications, like errors.
+    pub fn accepts_non_notifications(&self) -> bool {
+        match self {
+            Filter::All => true,
+            _           => false,
+        }
+    }
+}
+
+impl<Notification> Clone for Filter<Notification> {
+    fn clone(&self) -> Self {
+        match self {
+            Filter::All          => Filter::All,
+            Filter::Method(name) => Filter::Method(name.clone()),
+            Filter::Predicate(f) => Filter::Predicate(f.clone_ref()),
+        }
+    }
+}
+
+impl<Notification> Debug for Filter<Notification> {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::All          => write!(f,"Filter::All"),
+            Filter::Method(name) => write!(f,"Filter::Method({:?})",name),
+            Filter::Predicate(_) => write!(f,"Filter::Predicate(<closure>)"),
+        }
+    }
+}
+
+
+
+// ==================
+// === Subscriber ===
+// ==================
+
+/// A single subscription: the filter and the sink of the subscriber's own event buffer.
+#[derive(Debug)]
+struct Subscriber<Notification> {
+    filter : Filter<Notification>,
+    sink   : UnboundedSender<Event<Notification>>,
+}
+
+impl<Notification> Subscriber<Notification> {
+    /// Checks if the receiving side of this subscription was dropped.
+    fn is_unsubscribed(&self) -> bool {
+        self.sink.is_closed()
+    }
+}
+
+
+
+// ===================
+// === Subscribers ===
+// ===================
+
+/// Collection of all active subscriptions for events.
+///
+/// Every subscriber has its own buffer, so a slow consumer does not affect the others. When the
+/// receiving stream is dropped, the subscription is removed on the next broadcast.
+#[derive(Debug)]
+pub struct Subscribers<Notification> {
+    subscribers : Vec<Subscriber<Notification>>,
+}
+
+impl<Notification> Default for Subscribers<Notification> {
+    fn default() -> Self {
+        let subscribers = default();
+        Subscribers {subscribers}
+    }
+}
+
+impl<Notification:Clone> Subscribers<Notification> {
+    /// Creates a new subscription. Events are available through the returned stream until it is
+    /// dropped.
+    pub fn subscribe
+    (&mut self, filter:Filter<Notification>) -> UnboundedReceiver<Event<Notification>> {
+        let (sink,stream) = unbounded();
+        self.subscribers.push(Subscriber {filter,sink});
+        stream
+    }
+
+    /// Number of subscriptions that are still alive.
+    pub fn count(&self) -> usize {
+        self.subscribers.iter().filter(|s| !s.is_unsubscribed()).count()
+    }
+
+    /// Sends the notification to every subscriber whose filter accepts it.
+    pub fn notify(&mut self, method:&str, notification:&Notification) {
+        self.broadcast(|filter| filter.accepts(method,notification), || {
+            Event::Notification(notification.clone())
+        })
+    }
+
+    /// Sends the event that is not a notification (e.g. an error) to the subscribers.
+    pub fn emit(&mut self, event:Event<Notification>) {
+        let is_closed = if let Event::Closed = event {true} else {false};
+        self.broadcast(|filter| is_closed || filter.accepts_non_notifications(), || event.clone())
+    }
+
+    fn broadcast<P,E>(&mut self, predicate:P, make_event:E)
+    where P : Fn(&Filter<Notification>) -> bool,
+          E : Fn() -> Event<Notification> {
+        self.subscribers.retain(|s| !s.is_unsubscribed());
+        for subscriber in &self.subscribers {
+            if predicate(&subscriber.filter) {
+                channel::emit(&subscriber.sink,make_event());
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use futures::Stream;
+    use std::pin::Pin;
+    use utils::test::poll_stream_output;
+
+    #[derive(Clone,Debug,PartialEq)]
+    enum MockNotification {
+        Meow(i32),
+        Bark(i32),
+    }
+
+    fn method_of(notification:&MockNotification) -> &'static str {
+        match notification {
+            MockNotification::Meow(_) => "meow",
+            MockNotification::Bark(_) => "bark",
+        }
+    }
+
+    fn notify(subscribers:&mut Subscribers<MockNotification>, notification:MockNotification) {
+        subscribers.notify(method_of(&notification),&notification)
+    }
+
+    fn expect_notification<S>(stream:&mut Pin<Box<S>>) -> MockNotification
+    where S : Stream<Item=Event<MockNotification>> + ?Sized {
+        match poll_stream_output(stream) {
+            Some(Event::Notification(n)) => n,
+            other                        => panic!("Expected notification, got {:?}.", other),
+        }
+    }
+
+    #[test]
+    fn each_subscriber_receives_events() {
+        let mut subscribers = Subscribers::default();
+        let mut first       = Box::pin(subscribers.subscribe(Filter::All));
+        let mut second      = Box::pin(subscribers.subscribe(Filter::All));
+        notify(&mut subscribers,MockNotification::Meow(1));
+        assert_eq!(expect_notification(&mut first) , MockNotification::Meow(1));
+        assert_eq!(expect_notification(&mut second), MockNotification::Meow(1));
+        assert!(poll_stream_output(&mut first).is_none());
+        assert!(poll_stream_output(&mut second).is_none());
+    }
+
+    #[test]
+    fn filtering_notifications() {
+        let mut subscribers = Subscribers::default();
+        let     is_big      = |n:&MockNotification| match n {
+            MockNotification::Meow(i) | MockNotification::Bark(i) => *i > 10
+        };
+        let mut meows = Box::pin(subscribers.subscribe(Filter::method("meow")));
+        let mut bigs  = Box::pin(subscribers.subscribe(Filter::predicate(is_big)));
+        notify(&mut subscribers,MockNotification::Meow(1));
+        notify(&mut subscribers,MockNotification::Bark(2));
+        notify(&mut subscribers,MockNotification::Bark(20));
+        assert_eq!(expect_notification(&mut meows), MockNotification::Meow(1));
+        assert!(poll_stream_output(&mut meows).is_none());
+        assert_eq!(expect_notification(&mut bigs), MockNotification::Bark(20));
+        assert!(poll_stream_output(&mut bigs).is_none());
+    }
+
+    #[test]
+    fn closed_event_reaches_filtered_subscribers() {
+        let mut subscribers = Subscribers::<MockNotification>::default();
+        let mut meows       = Box::pin(subscribers.subscribe(Filter::method("meow")));
+        subscribers.emit(Event::Closed);
+        match poll_stream_output(&mut meows) {
+            Some(Event::Closed) => {},
+            other               => panic!("Expected closed event, got {:?}.", other),
+        }
+    }
+
+    #[test]
+    fn dropping_stream_unsubscribes() {
+        let mut subscribers = Subscribers::default();
+        let     first       = subscribers.subscribe(Filter::All);
+        let mut second      = Box::pin(subscribers.subscribe(Filter::All));
+        assert_eq!(subscribers.count(), 2);
+        drop(first);
+        assert_eq!(subscribers.count(), 1);
+        notify(&mut subscribers,MockNotification::Bark(3));
+        assert_eq!(subscribers.subscribers.len(), 1);
+        assert_eq!(expect_notification(&mut second), MockNotification::Bark(3));
+    }
+}
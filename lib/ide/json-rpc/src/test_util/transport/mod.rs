@@ -1,3 +1,5 @@
 //! Test utilities for the Transport.
 
 pub mod mock;
+pub mod recording;
+pub mod replay;
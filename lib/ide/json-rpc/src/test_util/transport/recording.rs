@@ -0,0 +1,267 @@
+//! Module provides `RecordingTransport`, a `Transport` decorator that records the whole traffic
+//! passing through it, so it can be later replayed with `ReplayTransport`.
+//!
+//! The recording is serialized as JSON-lines: each line is a single JSON-serialized
+//! `RecordedMessage`.
+//!
+//! Neither the system clock nor the filesystem is available in the browser, so there the
+//! recording is kept only in memory and timestamped by the clock given to
+//! `RecordingTransport::new_with_clock`.
+
+use crate::prelude::*;
+
+use crate::transport::Transport;
+use crate::transport::TransportEvent;
+
+use failure::Error;
+use futures::channel::mpsc::unbounded;
+use futures::channel::mpsc::UnboundedSender;
+use futures::StreamExt;
+use futures::task::LocalSpawn;
+use futures::task::LocalSpawnExt;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io::Write;
+use utils::channel;
+
+
+
+// =============
+// === Clock ===
+// =============
+
+/// A clock giving the current time in milliseconds.
+pub trait Clock = Fn() -> u64 + 'static;
+
+/// The clock measuring time elapsed since its creation by the system clock.
+#[cfg(not(target_arch="wasm32"))]
+fn system_clock() -> impl Clock {
+    let start = std::time::Instant::now();
+    move || start.elapsed().as_millis() as u64
+}
+
+
+
+// =================
+// === Direction ===
+// =================
+
+/// Describes whether the message was sent to the peer or received from it.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+#[derive(Serialize,Deserialize)]
+pub enum Direction {
+    /// Message sent by the recorded client.
+    Sent,
+    /// Message received by the recorded client from its peer.
+    Received,
+}
+
+
+
+// =======================
+// === RecordedMessage ===
+// =======================
+
+/// A single message that went through the recorded transport.
+#[derive(Clone,Debug,PartialEq)]
+#[derive(Serialize,Deserialize)]
+pub struct RecordedMessage {
+    /// Milliseconds elapsed since the recording has started.
+    pub timestamp : u64,
+    /// Was the message sent or received.
+    pub direction : Direction,
+    /// The message text.
+    pub text      : String,
+}
+
+
+
+// =================
+// === Recording ===
+// =================
+
+/// Sequence of messages recorded from a session.
+#[derive(Clone,Debug,Default,PartialEq)]
+pub struct Recording {
+    /// Recorded messages in the order of their appearance.
+    pub messages : Vec<RecordedMessage>,
+}
+
+impl Recording {
+    /// Serializes the recording into the JSON-lines format.
+    pub fn to_json_lines(&self) -> String {
+        let lines = self.messages.iter().map(|message| {
+            serde_json::to_string(message).expect("recorded message must be serializable")
+        });
+        lines.map(|line| line + "\n").collect()
+    }
+
+    /// Deserializes the recording from JSON-lines. Empty lines are ignored.
+    pub fn from_json_lines(text:&str) -> serde_json::Result<Self> {
+        let lines    = text.lines().filter(|line| !line.trim().is_empty());
+        let messages = lines.map(serde_json::from_str).collect::<serde_json::Result<_>>()?;
+        Ok(Recording {messages})
+    }
+
+    /// Writes the recording into a JSON-lines file.
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn save(&self, path:impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        std::fs::write(path,self.to_json_lines())
+    }
+
+    /// Reads the recording from a JSON-lines file.
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn load(path:impl AsRef<std::path::Path>) -> Result<Self,Error> {
+        let text = std::fs::read_to_string(path)?;
+        Ok(Self::from_json_lines(&text)?)
+    }
+}
+
+
+
+// =====================
+// === RecordingData ===
+// =====================
+
+/// The state of the ongoing recording.
+#[derive(Derivative)]
+#[derivative(Debug)]
+struct RecordingData {
+    /// Clock giving the timestamps of the messages.
+    #[derivative(Debug="ignore")]
+    clock     : Box<dyn Clock>,
+    /// The clock's time when the recording has started.
+    start     : u64,
+    /// Messages recorded so far.
+    recording : Recording,
+    /// File where each recorded message is appended as soon as it is recorded.
+    output    : Option<std::fs::File>,
+}
+
+impl RecordingData {
+    fn new(clock:impl Clock, output:Option<std::fs::File>) -> Self {
+        let start     = clock();
+        let clock     = Box::new(clock);
+        let recording = default();
+        RecordingData {clock,start,recording,output}
+    }
+
+    fn record(&mut self, direction:Direction, text:String) {
+        let timestamp = (self.clock)() - self.start;
+        let message   = RecordedMessage {timestamp,direction,text};
+        if let Some(output) = self.output.as_mut() {
+            let line = serde_json::to_string(&message);
+            let line = line.expect("recorded message must be serializable");
+            // Failing to write the log should not affect the recorded session.
+            writeln!(output,"{}",line).ok();
+        }
+        self.recording.messages.push(message);
+    }
+}
+
+
+
+// =======================
+// === RecordingHandle ===
+// =======================
+
+/// Shared handle to the recording made by a `RecordingTransport`.
+#[derive(Clone,Debug)]
+pub struct RecordingHandle(Rc<RefCell<RecordingData>>);
+
+impl RecordingHandle {
+    /// Returns all messages recorded so far.
+    pub fn recording(&self) -> Recording {
+        self.0.borrow().recording.clone()
+    }
+}
+
+impl CloneRef for RecordingHandle {}
+
+
+
+// ==========================
+// === RecordingTransport ===
+// ==========================
+
+/// A `Transport` that wraps another transport and records all text messages sent and received
+/// through it.
+///
+/// The received events must be forwarded from the inner transport, so `RecordingTransport`
+/// requires a spawner of an executor that will run while the transport is in use.
+pub struct RecordingTransport<T> {
+    inner   : T,
+    data    : Rc<RefCell<RecordingData>>,
+    spawner : Box<dyn LocalSpawn>,
+}
+
+impl<T:Transport> RecordingTransport<T> {
+    /// Wraps the given transport. The recording is kept only in memory.
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn new(inner:T, spawner:impl LocalSpawn + 'static) -> Self {
+        Self::new_with_clock(inner,spawner,system_clock())
+    }
+
+    /// Wraps the given transport. The recording is kept only in memory and the messages are
+    /// timestamped by the given clock, so it can be used in the browser.
+    pub fn new_with_clock(inner:T, spawner:impl LocalSpawn + 'static, clock:impl Clock) -> Self {
+        Self::new_with_output(inner,spawner,clock,None)
+    }
+
+    /// Wraps the given transport. Every recorded message is additionally appended to the
+    /// JSON-lines file under given path.
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn new_with_file
+    (inner:T, spawner:impl LocalSpawn + 'static, path:impl AsRef<std::path::Path>)
+    -> std::io::Result<Self> {
+        let output = std::fs::File::create(path)?;
+        Ok(Self::new_with_output(inner,spawner,system_clock(),Some(output)))
+    }
+
+    fn new_with_output
+    (inner:T, spawner:impl LocalSpawn + 'static, clock:impl Clock, output:Option<std::fs::File>)
+    -> Self {
+        let data    = Rc::new(RefCell::new(RecordingData::new(clock,output)));
+        let spawner = Box::new(spawner);
+        RecordingTransport {inner,data,spawner}
+    }
+
+    /// Returns all messages recorded so far.
+    pub fn recording(&self) -> Recording {
+        self.data.borrow().recording.clone()
+    }
+
+    /// Returns a handle allowing to access the recording after this transport has been moved
+    /// into the client.
+    pub fn handle(&self) -> RecordingHandle {
+        RecordingHandle(self.data.clone_ref())
+    }
+}
+
+impl<T:Transport> Transport for RecordingTransport<T> {
+    fn send_text(&mut self, message:String) -> Result<(),Error> {
+        self.data.borrow_mut().record(Direction::Sent,message.clone());
+        self.inner.send_text(message)
+    }
+
+    fn set_event_transmitter(&mut self, transmitter:UnboundedSender<TransportEvent>) {
+        let (inner_transmitter,inner_receiver) = unbounded();
+        self.inner.set_event_transmitter(inner_transmitter);
+        let data       = self.data.clone_ref();
+        let forwarding = inner_receiver.for_each(move |event| {
+            if let TransportEvent::TextMessage(text) = &event {
+                data.borrow_mut().record(Direction::Received,text.clone());
+            }
+            channel::emit(&transmitter,event);
+            futures::future::ready(())
+        });
+        let error_msg = "Failed to spawn the recorded events forwarding.";
+        self.spawner.spawn_local(forwarding).expect(error_msg);
+    }
+}
+
+impl<T:Debug> Debug for RecordingTransport<T> {
+    fn fmt(&self, f:&mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f,"RecordingTransport({:?})",self.inner)
+    }
+}
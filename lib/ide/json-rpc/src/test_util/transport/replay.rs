@@ -0,0 +1,173 @@
+//! Module provides `ReplayTransport`, a `Transport` that plays the peer's part of a session
+//! recorded with `RecordingTransport`.
+//!
+//! It is meant to be used in tests.
+
+use crate::prelude::*;
+
+use crate::messages::Id;
+use crate::test_util::transport::recording::Direction;
+use crate::test_util::transport::recording::RecordedMessage;
+use crate::test_util::transport::recording::Recording;
+use crate::transport::Transport;
+use crate::transport::TransportEvent;
+
+use failure::Error;
+use futures::channel::mpsc::UnboundedSender;
+use serde_json::Value;
+use std::collections::VecDeque;
+use utils::channel;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// Names of the JSON-RPC message fields that the replay needs to inspect.
+mod field {
+    pub const ID     : &str = "id";
+    pub const METHOD : &str = "method";
+    pub const PARAMS : &str = "params";
+}
+
+
+
+// ==================
+// === ReplayData ===
+// ==================
+
+/// Mutable state of the `ReplayTransport`.
+#[derive(Debug,Default)]
+pub struct ReplayData {
+    /// Messages from the recording that were not replayed yet.
+    remaining         : VecDeque<RecordedMessage>,
+    /// Maps request ids used in the recording to the ids used by the replayed client.
+    id_map            : HashMap<Id,Id>,
+    /// Events sink.
+    event_transmitter : Option<UnboundedSender<TransportEvent>>,
+}
+
+impl ReplayData {
+    /// Checks that the sent message matches the next message expected by the recording.
+    ///
+    /// Messages are compared by their method and params, request ids are ignored. The ids are
+    /// remembered, so replies can be given with the ids used by the replayed client.
+    fn expect_sent(&mut self, text:&str) {
+        let expected = self.remaining.pop_front();
+        let expected = expected.unwrap_or_else(|| {
+            panic!("Client sent a message not present in the recording: {}", text)
+        });
+        assert_eq!(expected.direction, Direction::Sent,
+            "Client sent {} while the recording expected receiving {}.", text, expected.text);
+        let sent     = parse(text);
+        let recorded = parse(&expected.text);
+        assert_eq!(sent.get(field::METHOD), recorded.get(field::METHOD),
+            "Sent message method does not match the recording.");
+        assert_eq!(sent.get(field::PARAMS), recorded.get(field::PARAMS),
+            "Sent message params do not match the recording.");
+        if let (Some(sent_id),Some(recorded_id)) = (get_id(&sent),get_id(&recorded)) {
+            self.id_map.insert(recorded_id,sent_id);
+        }
+    }
+
+    /// Emits all received messages that in the recording precede the next sent message.
+    fn emit_pending_replies(&mut self) {
+        if let Some(transmitter) = self.event_transmitter.clone() {
+            while self.next_is_received() {
+                let message = self.remaining.pop_front().unwrap();
+                let text    = self.remap_id(message.text);
+                channel::emit(&transmitter,TransportEvent::TextMessage(text));
+            }
+        }
+    }
+
+    fn next_is_received(&self) -> bool {
+        let direction = self.remaining.front().map(|message| message.direction);
+        direction == Some(Direction::Received)
+    }
+
+    /// Replaces the id in the recorded reply with the id used by the replayed request.
+    fn remap_id(&self, text:String) -> String {
+        let mut json    = parse(&text);
+        let recorded_id = get_id(&json);
+        match recorded_id.and_then(|id| self.id_map.get(&id)) {
+            Some(id) => {
+                json[field::ID] = Value::from(id.0);
+                json.to_string()
+            },
+            None => text,
+        }
+    }
+}
+
+fn parse(text:&str) -> Value {
+    serde_json::from_str(text).expect("Replayed message is not a valid JSON.")
+}
+
+fn get_id(json:&Value) -> Option<Id> {
+    json.get(field::ID).and_then(Value::as_i64).map(Id)
+}
+
+
+
+// =======================
+// === ReplayTransport ===
+// =======================
+
+/// A `Transport` replaying the peer's side of the recorded session.
+///
+/// Every message sent by the client is checked against the next recorded sent message and the
+/// test panics on mismatch. Then all messages that the recorded client received before sending
+/// its next message are emitted.
+#[derive(Clone,Debug,Default)]
+pub struct ReplayTransport(Rc<RefCell<ReplayData>>);
+
+impl ReplayTransport {
+    /// Creates a transport replaying given recording.
+    pub fn new(recording:Recording) -> Self {
+        let remaining = recording.messages.into();
+        let data      = ReplayData {remaining,..default()};
+        ReplayTransport(Rc::new(RefCell::new(data)))
+    }
+
+    /// Creates a transport replaying recording in the JSON-lines format.
+    pub fn from_json_lines(text:&str) -> serde_json::Result<Self> {
+        Ok(Self::new(Recording::from_json_lines(text)?))
+    }
+
+    /// Creates a transport replaying the recording from a JSON-lines file.
+    #[cfg(not(target_arch="wasm32"))]
+    pub fn load(path:impl AsRef<std::path::Path>) -> Result<Self,Error> {
+        Ok(Self::new(Recording::load(path)?))
+    }
+
+    /// Checks if the whole recording has been replayed.
+    pub fn is_finished(&self) -> bool {
+        self.0.borrow().remaining.is_empty()
+    }
+
+    /// Panics if there are messages in the recording that were not replayed.
+    pub fn assert_finished(&self) {
+        let remaining = &self.0.borrow().remaining;
+        assert!(remaining.is_empty(), "Recording has not been fully replayed, remaining: {:?}",
+            remaining);
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send_text(&mut self, text:String) -> Result<(),Error> {
+        let mut data = self.0.borrow_mut();
+        data.expect_sent(&text);
+        data.emit_pending_replies();
+        Ok(())
+    }
+
+    fn set_event_transmitter(&mut self, transmitter:UnboundedSender<TransportEvent>) {
+        let mut data = self.0.borrow_mut();
+        data.event_transmitter = Some(transmitter);
+        data.emit_pending_replies();
+    }
+}
+
+impl CloneRef for ReplayTransport {}
@@ -11,6 +11,8 @@ use json_rpc::messages::Id;
 use json_rpc::messages::Message;
 use json_rpc::messages::Version;
 use json_rpc::test_util::transport::mock::MockTransport;
+use json_rpc::test_util::transport::recording::RecordingTransport;
+use json_rpc::test_util::transport::replay::ReplayTransport;
 use serde::Deserialize;
 use serde::Serialize;
 use std::future::Future;
@@ -110,7 +112,7 @@ impl Client {
         self.try_get_notification().expect("expected notification event")
     }
 
-    pub fn expect_handling_error(&mut self) -> HandlingError {
+    pub fn expect_handling_error(&mut self) -> Rc<HandlingError> {
         let event = self.try_get_event().expect("no events, while expected error event");
         if let json_rpc::handler::Event::Error(err) = event {
             err
@@ -220,7 +222,7 @@ fn test_garbage_reply_error() {
 
     assert!(poll_future_output(&mut fut).is_none()); // no valid reply
     let internal_error = fixture.client.expect_handling_error();
-    if let HandlingError::InvalidMessage(_) = internal_error {
+    if let HandlingError::InvalidMessage(_) = *internal_error {
     } else {
         panic!("Expected an error to be InvalidMessage");
     }
@@ -292,8 +294,76 @@ fn test_handling_invalid_notification() {
     fixture.pool.run_until_stalled();
 
     let internal_error = fixture.client.expect_handling_error();
-    if let HandlingError::InvalidNotification(_) = internal_error {}
+    if let HandlingError::InvalidNotification(_) = *internal_error {}
     else {
         panic!("expected InvalidNotification error");
     }
 }
+
+#[test]
+fn test_independent_subscribers() {
+    type EventStream = Pin<Box<dyn Stream<Item=MockEvent>>>;
+    let     is_bark = |n:&MockNotification| if let MockNotification::Bark{..} = n {true} else {false};
+    let mut fixture = Fixture::new();
+    let mut meows   : EventStream = Box::pin(fixture.client.handler.subscribe_method("Meow"));
+    let mut barks   : EventStream = Box::pin(fixture.client.handler.subscribe_filtered(is_bark));
+    let     meow    = MockNotification::Meow {text:"meow!".into()};
+    let     bark    = MockNotification::Bark {text:"woof!".into()};
+    fixture.transport.mock_peer_message(Message::new(meow.clone()));
+    fixture.transport.mock_peer_message(Message::new(bark.clone()));
+
+    fixture.pool.run_until_stalled();
+
+    let expect_only = |stream:&mut EventStream, expected| {
+        match poll_stream_output(stream) {
+            Some(MockEvent::Notification(n)) => assert_eq!(n, expected),
+            other => panic!("expected notification event, encountered: {:?}", other),
+        }
+        assert!(poll_stream_output(stream).is_none());
+    };
+    expect_only(&mut meows,meow.clone());
+    expect_only(&mut barks,bark.clone());
+    // The unfiltered stream obtained by the client is still functional.
+    assert_eq!(fixture.client.expect_notification(), meow);
+    assert_eq!(fixture.client.expect_notification(), bark);
+}
+
+#[test]
+fn test_recording_and_replay() {
+    // Record a session with a manually mocked peer.
+    let mut pool      = futures::executor::LocalPool::new();
+    let mut transport = MockTransport::new();
+    let     recorder  = RecordingTransport::new(transport.clone(),pool.spawner());
+    let     recording = recorder.handle();
+    let mut client    = Client::new(recorder);
+    pool.spawner().spawn_local(client.events_processor()).unwrap();
+    let mut fut = Box::pin(client.pow(8));
+    let request = transport.expect_message::<MockRequestMessage>();
+    transport.mock_peer_message(pow_impl(request));
+    pool.run_until_stalled();
+    assert_eq!(poll_future_output(&mut fut).unwrap().unwrap(), 8*8);
+    let recording = recording.recording();
+    assert_eq!(recording.messages.len(), 2);
+
+    // Replay the session with a fresh client.
+    let mut pool   = futures::executor::LocalPool::new();
+    let     replay = ReplayTransport::from_json_lines(&recording.to_json_lines()).unwrap();
+    let mut client = Client::new(replay.clone());
+    pool.spawner().spawn_local(client.events_processor()).unwrap();
+    let mut fut = Box::pin(client.pow(8));
+    pool.run_until_stalled();
+    assert_eq!(poll_future_output(&mut fut).unwrap().unwrap(), 8*8);
+    replay.assert_finished();
+}
+
+#[test]
+#[should_panic]
+fn test_replay_mismatch() {
+    let recording = r#"
+        {"timestamp":0,"direction":"Sent","text":"{\"jsonrpc\":\"2.0\",\"id\":0,\"method\":\"pow\",\"params\":{\"i\":8}}"}
+        {"timestamp":1,"direction":"Received","text":"{\"jsonrpc\":\"2.0\",\"id\":0,\"result\":{\"result\":64}}"}
+    "#;
+    let     replay = ReplayTransport::from_json_lines(recording).unwrap();
+    let mut client = Client::new(replay);
+    let _          = client.pow(9);
+}
@@ -18,6 +18,8 @@
 pub mod text;
 pub mod module;
 pub mod project;
+pub mod save;
+pub mod searcher;
 
 /// General-purpose `Result` supporting any `Error`-compatible failures.
 pub type FallibleResult<T> = Result<T,failure::Error>;
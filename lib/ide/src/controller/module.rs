@@ -8,6 +8,7 @@
 use crate::prelude::*;
 
 use crate::controller::FallibleResult;
+use crate::controller::save::SavedVersion;
 use crate::double_representation::text::apply_code_change_to_id_map;
 
 use ast::Ast;
@@ -18,7 +19,6 @@ use data::text::Size;
 use data::text::Span;
 use data::text::TextChangedNotification;
 use file_manager_client as fmc;
-use json_rpc::error::RpcError;
 use parser::api::IsParser;
 use parser::Parser;
 use shapely::shared;
@@ -74,6 +74,8 @@ shared! { Handle
         // TODO: written for test purposes, should be removed once generating id_map from AST will
         // be implemented.
         id_map: IdMap,
+        /// The module file contents as last read or written by this controller.
+        saved: SavedVersion,
         /// The File Manager Client handle.
         file_manager: fmc::Handle,
         /// The Parser handle
@@ -87,6 +89,11 @@ shared! { Handle
             self.location.clone()
         }
 
+        /// Get the File Manager handle used by this controller.
+        pub fn file_manager(&self) -> fmc::Handle {
+            self.file_manager.clone_ref()
+        }
+
         /// Updates AST after code change.
         pub fn apply_code_change(&mut self,change:&TextChangedNotification) -> FallibleResult<()> {
             let mut code        = self.code();
@@ -102,6 +109,11 @@ shared! { Handle
             Ok(())
         }
 
+        /// Obtain clone of the current module AST.
+        pub fn ast(&self) -> Ast {
+            self.ast.clone()
+        }
+
         /// Read module code.
         pub fn code(&self) -> String {
             self.ast.repr()
@@ -131,33 +143,65 @@ impl Handle {
         let logger  = Logger::new(format!("Module Controller {}", location));
         logger.info(|| "Loading module file");
         let path    = location.to_path();
-        file_manager.touch(path.clone()).await?;
-        let content = file_manager.read(path).await?;
+        let content = Self::read_or_create(&logger,&mut file_manager,path).await?;
+        let saved   = SavedVersion::new(content.clone());
         logger.info(|| "Parsing code");
         let ast     = parser.parse(content,default())?;
         logger.info(|| "Code parsed");
         logger.trace(|| format!("The parsed ast is {:?}", ast));
         let id_map  = default();
-        let data    = Controller {location,ast,file_manager,parser,id_map,logger};
+        let data    = Controller {location,ast,saved,file_manager,parser,id_map,logger};
         Ok(Handle::new_from_data(data))
     }
 
+    /// Read the module file. If it does not exist, an empty module file is created.
+    async fn read_or_create(logger:&Logger, file_manager:&mut fmc::Handle, path:fmc::Path)
+    -> FallibleResult<String> {
+        match file_manager.read(path.clone()).await {
+            Ok(content) => Ok(content),
+            Err(error)  => match fmc::FileManagerError::from_rpc_error(&error) {
+                Some(fmc::FileManagerError::NotFound(_)) => {
+                    logger.info(|| "Module file does not exist, creating an empty one");
+                    file_manager.touch(path).await.map_err(fmc::decode_error)?;
+                    Ok(default())
+                },
+                _ => Err(fmc::decode_error(error)),
+            }
+        }
+    }
+
     /// Save the module to file.
-    pub fn save_file(&self) -> impl Future<Output=Result<(),RpcError>> {
-        let (path,mut fm) = self.with_borrowed(|data| {
-            (data.location.to_path(),data.file_manager.clone_ref())
+    ///
+    /// Fails with `SaveConflict` if the file was modified by someone else since it was loaded.
+    pub async fn save_file(&self) -> FallibleResult<()> {
+        let (path,mut fm,saved) = self.with_borrowed(|data| {
+            (data.location.to_path(),data.file_manager.clone_ref(),data.saved.clone())
         });
-        fm.write(path,self.code())
+        let code  = self.code();
+        let saved = controller::save::write_checked(&mut fm,path,&saved,code).await?;
+        self.with_borrowed(|data| data.saved = saved);
+        Ok(())
         // TODO [ao] here save also the id_map and metadata.
     }
 
+    /// Check if the module file differs from the version last read or written by this controller,
+    /// i.e. if someone else has modified it.
+    pub async fn is_modified_externally(&self) -> FallibleResult<bool> {
+        let (path,mut fm,saved) = self.with_borrowed(|data| {
+            (data.location.to_path(),data.file_manager.clone_ref(),data.saved.clone())
+        });
+        let content = fm.read(path).await.map_err(fmc::decode_error)?;
+        Ok(content != saved.contents)
+    }
+
     #[cfg(test)]
     fn new_mock
     (location:Location, code:&str, id_map:IdMap, file_manager:fmc::Handle, mut parser:Parser)
     -> FallibleResult<Self> {
         let logger   = Logger::new("Mocked Module Controller");
         let ast      = parser.parse(code.to_string(),id_map.clone())?;
-        let data     = Controller {location,ast,file_manager,parser,id_map,logger};
+        let saved    = SavedVersion::new(code.to_string());
+        let data     = Controller {location,ast,saved,file_manager,parser,id_map,logger};
         Ok(Handle::new_from_data(data))
     }
 
@@ -169,6 +213,8 @@ impl Handle {
 mod test {
     use super::*;
 
+    use crate::controller::save::SaveConflict;
+
     use ast;
     use ast::BlockLine;
     use data::text::Index;
@@ -176,8 +222,15 @@ mod test {
     use data::text::Size;
     use data::text::TextChange;
     use data::text::TextLocation;
+    use futures::executor::LocalPool;
+    use futures::task::LocalSpawnExt;
+    use json_rpc::messages::Message;
+    use json_rpc::messages::RequestMessage;
     use json_rpc::test_util::transport::mock::MockTransport;
     use parser::Parser;
+    use serde_json::json;
+    use serde_json::Value;
+    use utils::test::poll_future_output;
     use uuid::Uuid;
     use wasm_bindgen_test::wasm_bindgen_test;
     use file_manager_client::Path;
@@ -231,4 +284,50 @@ mod test {
         }, None);
         assert_eq!(expected_ast, controller.with_borrowed(|data| data.ast.clone()));
     }
+
+    #[wasm_bindgen_test]
+    fn detect_save_conflict() {
+        let mut transport = MockTransport::new();
+        let file_manager  = file_manager_client::Handle::new(transport.clone_ref());
+        let mut executor  = LocalPool::new();
+        executor.spawner().spawn_local(file_manager.runner()).unwrap();
+        let parser        = Parser::new().unwrap();
+        let location      = Location("Test".to_string());
+        let controller    = Handle::new_mock(location,"2+2",default(),file_manager,parser);
+        let controller    = controller.unwrap();
+        let mut reply     = |method:&str, result:Result<Value,i64>| {
+            let request = transport.expect_message::<RequestMessage<Value>>();
+            assert_eq!(request.method, method);
+            let reply   = match result {
+                Ok(result) => Message::new_success(request.id,result),
+                Err(code)  => {
+                    let data = Some(json!({"path" : "./Test.enso"}));
+                    Message::new_error(request.id,code,"Failed.".into(),data)
+                }
+            };
+            transport.mock_peer_message(reply);
+            request.params
+        };
+
+        // The file has not been modified, so it is written.
+        let mut save = Box::pin(controller.save_file());
+        assert!(poll_future_output(&mut save).is_none());
+        let written  = reply("write",Ok(Value::Null));
+        assert_eq!(written["expectedChecksum"], json!(fmc::Checksum::of("2+2")));
+        executor.run_until_stalled();
+        poll_future_output(&mut save).unwrap().unwrap();
+
+        // The file has been modified by someone else, so the server rejects the write.
+        let mut save = Box::pin(controller.save_file());
+        assert!(poll_future_output(&mut save).is_none());
+        reply("write",Err(fmc::error_code::CHECKSUM_MISMATCH));
+        executor.run_until_stalled();
+        assert!(poll_future_output(&mut save).is_none());
+        reply("read",Ok(json!("3+3")));
+        executor.run_until_stalled();
+        let error    = poll_future_output(&mut save).unwrap().unwrap_err();
+        let conflict = error.downcast::<SaveConflict>().unwrap();
+        assert_eq!(conflict.base  , "2+2");
+        assert_eq!(conflict.theirs, "3+3");
+    }
 }
@@ -2,6 +2,10 @@
 //!
 //! Responsible for owning any remote connection clients, and providing controllers for specific
 //! files and modules. Expected to live as long as the project remains open in the IDE.
+//!
+//! The project files are watched by the File Manager. The events about specific files are handled
+//! by their text controllers, the project controller checks all opened files if some events were
+//! lost.
 
 use crate::prelude::*;
 
@@ -37,6 +41,7 @@ shared! { Handle
         parser: Parser,
         /// Id which will be given to next unsaved file.
         next_unsaved_id: usize,
+        logger: Logger,
     }
 
     impl {
@@ -50,6 +55,7 @@ shared! { Handle
                 text_cache      : default(),
                 parser          : Parser::new_or_panic(),
                 next_unsaved_id : default(),
+                logger          : Logger::new("Project Controller"),
             }
         }
 
@@ -75,7 +81,18 @@ impl Handle {
     /// the global executor.
     pub fn new_running(file_manager_transport:impl Transport + 'static) -> Self {
         let data = Controller::new_running(file_manager_transport);
-        Self::new_from_data(data)
+        let this = Self::new_from_data(data);
+        this.listen_to_filesystem_events();
+        this
+    }
+
+    /// Start watching the project files. The File Manager will report their modifications, so the
+    /// opened files can be reloaded.
+    pub async fn watch_project_files(&self) -> FallibleResult<()> {
+        let mut file_manager = self.file_manager();
+        let root             = fmc::Path::new(".");
+        file_manager.create_watch(root).await.map_err(fmc::decode_error)?;
+        Ok(())
     }
 
     /// Returns a text controller for given file path.
@@ -143,6 +160,35 @@ impl Handle {
         let (fm,parser) = self.with_borrowed(|d| (d.file_manager.clone_ref(),d.parser.clone_ref()));
         controller::module::Handle::new(location,fm,parser).await
     }
+
+    /// Check all opened files for external modifications whenever the File Manager reports that
+    /// some filesystem events were lost. Works for as long as the controller lives.
+    fn listen_to_filesystem_events(&self) {
+        let is_overflow = |event:&fmc::FilesystemEvent| {
+            futures::future::ready(event.kind == fmc::FilesystemEventKind::Overflow)
+        };
+        let events = self.file_manager().filesystem_events().filter(is_overflow);
+        let weak   = self.downgrade();
+        executor::global::spawn(async move {
+            let mut events = Box::pin(events);
+            while events.next().await.is_some() {
+                let this = match weak.upgrade() {
+                    Some(this) => this,
+                    None       => break,
+                };
+                let (opened,logger) = this.with_borrowed(|data| {
+                    (data.text_cache.values().collect_vec(),data.logger.clone())
+                });
+                for text_ctrl in opened {
+                    let kind = fmc::FilesystemEventKind::Modified;
+                    if let Err(error) = text_ctrl.handle_filesystem_event(kind).await {
+                        let path = text_ctrl.file_path();
+                        logger.error(|| format!("Failed to check {}: {}",path,error));
+                    }
+                }
+            }
+        });
+    }
 }
 
 
@@ -156,7 +202,10 @@ mod test {
 
     use file_manager_client::Path;
     use json_rpc::test_util::transport::mock::MockTransport;
+    use json_rpc::test_util::transport::replay::ReplayTransport;
     use futures::executor::LocalPool;
+    use json_rpc::messages::RequestMessage;
+    use serde_json::Value;
     use wasm_bindgen_test::wasm_bindgen_test;
     use wasm_bindgen_test::wasm_bindgen_test_configure;
 
@@ -187,37 +236,90 @@ mod test {
             assert!(module_ctrl.identity_equals(&same_module_ctrl));
             *finished_clone.borrow_mut() = true;
         });
-        // Load module (touch + read content)
+        // Load module (read content)
         executor.run_until_stalled();
         transport.mock_peer_message_text(r#"{
             "jsonrpc" : "2.0",
             "id"      : 0,
-            "result"  : null
+            "result"  :"2 + 2"
         }"#);
+        // Load Another Module (read content)
         executor.run_until_stalled();
         transport.mock_peer_message_text(r#"{
             "jsonrpc" : "2.0",
             "id"      : 1,
-            "result"  :"2 + 2"
+            "result"  :"3+3"
         }"#);
-        // Load Another Module (touch + read content)
+        // Check test reach its end
         executor.run_until_stalled();
+        assert!(*finished.borrow());
+    }
+
+    #[wasm_bindgen_test]
+    fn create_missing_module() {
+        let mut executor    = LocalPool::new();
+        let finished        = Rc::new(RefCell::new(false));
+        let finished_clone  = finished.clone_ref();
+        let mut transport   = MockTransport::new();
+        let transport_clone = transport.clone_ref();
+        set_spawner(executor.spawner());
+
+        spawn(async move {
+            let project_ctrl = controller::project::Handle::new_running(transport_clone);
+            let location     = controller::module::Location("Missing".to_string());
+            let module_ctrl  = project_ctrl.get_module_controller(location).await.unwrap();
+            assert_eq!("", module_ctrl.code());
+            *finished_clone.borrow_mut() = true;
+        });
+        // Reading fails, as the module file does not exist.
+        executor.run_until_stalled();
+        let read = transport.expect_message::<RequestMessage<Value>>();
+        assert_eq!(read.method, "read");
         transport.mock_peer_message_text(r#"{
             "jsonrpc" : "2.0",
-            "id"      : 2,
-            "result"  : null
+            "id"      : 0,
+            "error"   : {
+                "code"    : -32001,
+                "message" : "No such file: ./Missing.enso.",
+                "data"    : {"path" : "./Missing.enso"}
+            }
         }"#);
+        // The file is created.
         executor.run_until_stalled();
+        let touch = transport.expect_message::<RequestMessage<Value>>();
+        assert_eq!(touch.method, "touch");
         transport.mock_peer_message_text(r#"{
             "jsonrpc" : "2.0",
-            "id"      : 3,
-            "result"  :"3+3"
+            "id"      : 1,
+            "result"  : null
         }"#);
-        // Check test reach its end
         executor.run_until_stalled();
         assert!(*finished.borrow());
     }
 
+    #[wasm_bindgen_test]
+    fn obtain_module_controller_from_recorded_session() {
+        // Recorded against the mock server, see `file-manager-server`'s `recordings` test.
+        let recording      = include_str!("../../tests/recordings/load_module.jsonl");
+        let transport      = ReplayTransport::from_json_lines(recording).unwrap();
+        let mut executor   = LocalPool::new();
+        let finished       = Rc::new(RefCell::new(false));
+        let finished_clone = finished.clone_ref();
+        let replay         = transport.clone_ref();
+        set_spawner(executor.spawner());
+
+        spawn(async move {
+            let project_ctrl = controller::project::Handle::new_running(replay);
+            let location     = controller::module::Location("TestLocation".to_string());
+            let module_ctrl  = project_ctrl.get_module_controller(location).await.unwrap();
+            assert_eq!("2 + 2", module_ctrl.code());
+            *finished_clone.borrow_mut() = true;
+        });
+        executor.run_until_stalled();
+        assert!(*finished.borrow());
+        transport.assert_finished();
+    }
+
     #[wasm_bindgen_test]
     fn obtain_plain_text_controller() {
         let mut executor    = LocalPool::new();
@@ -268,12 +370,6 @@ mod test {
         transport.mock_peer_message_text(r#"{
             "jsonrpc" : "2.0",
             "id"      : 0,
-            "result"  : null
-        }"#);
-        executor.run_until_stalled();
-        transport.mock_peer_message_text(r#"{
-            "jsonrpc" : "2.0",
-            "id"      : 1,
             "result"  :"2 + 2"
         }"#);
         executor.run_until_stalled();
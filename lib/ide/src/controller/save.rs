@@ -0,0 +1,129 @@
+//! Saving files with detection of changes made to them by someone else in the meantime.
+//!
+//! Controllers remember the version of the file they loaded. Its checksum is sent along with the
+//! write, and the File Manager writes the file only if its current checksum is the same. Otherwise
+//! the file is not written and `SaveConflict` is returned instead, allowing the user (or the
+//! `SaveConflict::merge` helper) to reconcile both versions.
+
+use crate::prelude::*;
+
+use crate::controller::FallibleResult;
+
+use data::text::MergeConflict;
+use data::text::TextChange;
+use file_manager_client as fmc;
+
+
+
+// ====================
+// === SaveConflict ===
+// ====================
+
+/// Error returned when saving a file that was modified by someone else since we loaded it.
+#[derive(Clone,Debug,Fail)]
+#[fail(display = "File {} was modified by someone else since it was loaded.", path)]
+pub struct SaveConflict {
+    /// The saved file.
+    pub path   : fmc::Path,
+    /// The file contents when it was loaded or last saved by us.
+    pub base   : String,
+    /// The contents we tried to save.
+    pub ours   : String,
+    /// The current contents of the file.
+    pub theirs : String,
+}
+
+impl SaveConflict {
+    /// Merges both versions of the file, if they changed different lines of the base text.
+    pub fn merge(&self) -> Result<String,MergeConflict> {
+        let ours   = TextChange::from_line_diff(&self.base,&self.ours);
+        let theirs = TextChange::from_line_diff(&self.base,&self.theirs);
+        data::text::merge(&self.base,&ours,&theirs)
+    }
+}
+
+
+
+// ====================
+// === SavedVersion ===
+// ====================
+
+/// The version of the file last read from or written to the server by us.
+#[derive(Clone,Debug)]
+pub struct SavedVersion {
+    /// The file contents.
+    pub contents : String,
+    /// Checksum of the `contents`, as computed by the File Manager.
+    pub checksum : fmc::Checksum,
+}
+
+impl SavedVersion {
+    /// Creates the version of the file with given contents.
+    pub fn new(contents:String) -> Self {
+        let checksum = fmc::Checksum::of(&contents);
+        SavedVersion {contents,checksum}
+    }
+}
+
+/// Writes `contents` to the file, unless the file was modified since the `saved` version.
+///
+/// The File Manager compares the file with the checksum of the `saved` version and writes it in
+/// one step. Returns the version which has been written. If the file was modified, fails with
+/// `SaveConflict`.
+pub async fn write_checked
+(file_manager:&mut fmc::Handle, path:fmc::Path, saved:&SavedVersion, contents:String)
+-> FallibleResult<SavedVersion> {
+    let expected = Some(saved.checksum.clone());
+    match file_manager.write(path.clone(),contents.clone(),expected).await {
+        Ok(())     => Ok(SavedVersion::new(contents)),
+        Err(error) => match fmc::FileManagerError::from_rpc_error(&error) {
+            Some(fmc::FileManagerError::ChecksumMismatch(_)) => {
+                let theirs = file_manager.read(path.clone()).await.map_err(fmc::decode_error)?;
+                if theirs == contents {
+                    Ok(SavedVersion::new(contents))
+                } else {
+                    let base = saved.contents.clone();
+                    Err(SaveConflict {path,base,ours:contents,theirs}.into())
+                }
+            },
+            _ => Err(fmc::decode_error(error)),
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn conflict(base:&str, ours:&str, theirs:&str) -> SaveConflict {
+        let path   = fmc::Path::new("./Main.enso");
+        let base   = base.to_string();
+        let ours   = ours.to_string();
+        let theirs = theirs.to_string();
+        SaveConflict {path,base,ours,theirs}
+    }
+
+    #[test]
+    fn merging_conflicting_versions() {
+        let base     = "main =\n    a = 1\n    a + 1";
+        let disjoint = conflict(base,"main =\n    a = 2\n    a + 1","main =\n    a = 1\n    a * 2");
+        assert_eq!(disjoint.merge().unwrap(), "main =\n    a = 2\n    a * 2");
+        let same     = conflict(base,"main =\n    a = 2\n    a + 1","main =\n    a = 3\n    a + 1");
+        assert!(same.merge().is_err());
+    }
+
+    #[test]
+    fn merging_versions_with_several_edits() {
+        let base     = "main =\n    a = 1\n    b = 2\n    a + b";
+        let ours     = "main =\n    a = 3\n    b = 2\n    a * b";
+        let theirs   = "main =\n    a = 1\n    b = 4\n    a + b\n    c = 5";
+        let conflict = conflict(base,ours,theirs);
+        assert_eq!(conflict.merge().unwrap(), "main =\n    a = 3\n    b = 4\n    a * b\n    c = 5");
+    }
+}
@@ -0,0 +1,195 @@
+//! Searcher Controller.
+//!
+//! The searcher is the code completion: it suggests the definitions of the current module and
+//! the modules it imports, ranked by how well they match the typed pattern (see `ranking`).
+//! The picked suggestion is inserted as an `Ast` calling the definition, with a placeholder for
+//! each argument.
+
+pub mod ranking;
+
+use crate::prelude::*;
+
+use crate::controller::module::Location;
+use crate::controller::searcher::ranking::History;
+use crate::controller::searcher::ranking::Ranked;
+use crate::controller::searcher::ranking::rank;
+use crate::double_representation::definition::DefinitionInfo;
+use crate::double_representation::definition::DefinitionName;
+use crate::double_representation::definition::DefinitionProvider;
+use crate::double_representation::import::ImportInfo;
+use crate::double_representation::import::list_imports;
+
+use ast::Ast;
+use ast::Blank;
+use ast::Infix;
+use ast::known;
+use ast::opr;
+
+
+
+// ==================
+// === Suggestion ===
+// ==================
+
+/// The definition suggested by the searcher.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Suggestion {
+    /// The definition's name.
+    pub name   : DefinitionName,
+    /// The number of the definition's explicit arguments.
+    pub arity  : usize,
+    /// The module containing the definition.
+    pub module : Location,
+}
+
+impl Suggestion {
+    /// Creates a suggestion of the definition. Returns `None` for operators, as they are not
+    /// typed by name.
+    pub fn from_definition(definition:&DefinitionInfo, module:Location) -> Option<Suggestion> {
+        let first_char  = definition.name.name.chars().next()?;
+        let is_operator = !first_char.is_alphanumeric() && first_char != '_';
+        let name        = definition.name.clone();
+        let arity       = definition.args.len();
+        (!is_operator).as_some(Suggestion {name,arity,module})
+    }
+
+    /// The name matched against the typed pattern, e.g. `Foo.Bar.baz`.
+    pub fn label(&self) -> String {
+        self.name.to_string()
+    }
+
+    /// The code inserted when the suggestion is picked: the name followed by the `_` placeholder
+    /// for each argument, e.g. `Foo.Bar.baz _ _`.
+    pub fn to_ast(&self) -> Ast {
+        let segments = self.name.extended_target.iter().chain(std::iter::once(&self.name.name));
+        let segments = segments.map(|segment| identifier_ast(segment));
+        let name     = segments.fold1(|larg,rarg| {
+            let opr = Ast::opr(opr::predefined::ACCESS);
+            Ast::from(Infix {larg, loff:0, opr, roff:0, rarg})
+        });
+        let name = name.expect("Definition name should not be empty.");
+        (0..self.arity).fold(name, |func,_| Ast::prefix(func,Ast::from(Blank{})))
+    }
+}
+
+/// Creates `Cons` for capitalized identifiers (types) and `Var` for other ones.
+fn identifier_ast(name:&str) -> Ast {
+    let is_capitalized = name.chars().next().map_or(false, |first| first.is_uppercase());
+    if is_capitalized { Ast::cons(name) } else { Ast::var(name) }
+}
+
+/// Lists the suggestions of all definitions in the module.
+pub fn module_suggestions(location:&Location, module:&known::Module) -> Vec<Suggestion> {
+    let definitions = module.list_definitions();
+    let suggestions = definitions.iter().filter_map(|definition| {
+        Suggestion::from_definition(definition,location.clone())
+    });
+    suggestions.collect()
+}
+
+/// Obtains the location of the imported module.
+///
+/// TODO [ao] Extremely provisional, just as `Location::to_path`: the module path segments are
+///      treated as directories in the project.
+pub fn import_location(import:&ImportInfo) -> Location {
+    Location(import.target.join("/"))
+}
+
+/// Loads the suggestions of the module and all modules it imports. The imported modules which
+/// cannot be loaded are skipped.
+pub async fn load_suggestions
+(project:controller::project::Handle, location:Location, module:known::Module, logger:Logger)
+-> Vec<Suggestion> {
+    let mut suggestions = module_suggestions(&location,&module);
+    for import in list_imports(&module) {
+        let imported = import_location(&import);
+        match project.get_module_controller(imported.clone()).await {
+            Ok(controller) => match known::Module::try_new(controller.ast()) {
+                Ok(module) => suggestions.extend(module_suggestions(&imported,&module)),
+                Err(_)     => logger.warning(|| format!("{} is not a module.",imported)),
+            },
+            Err(error) => logger.warning(|| {
+                format!("Failed to load imported module {}: {}",imported,error)
+            }),
+        }
+    }
+    suggestions
+}
+
+
+
+// ================
+// === Searcher ===
+// ================
+
+/// The state of searcher: the available suggestions and the history of picked ones.
+#[derive(Clone,Debug,Default)]
+pub struct Searcher {
+    suggestions : Vec<Suggestion>,
+    history     : History,
+}
+
+impl Searcher {
+    /// Replaces the available suggestions. The history of picks is kept.
+    pub fn set_suggestions(&mut self, suggestions:Vec<Suggestion>) {
+        self.suggestions = suggestions;
+    }
+
+    /// Lists the suggestions matching the pattern, the best ranked first.
+    pub fn search(&self, pattern:&str) -> Vec<Ranked<Suggestion>> {
+        rank(pattern,self.suggestions.iter().cloned(),Suggestion::label,&self.history)
+    }
+
+    /// Picks the suggestion, returning the code to be inserted. The picked suggestions are
+    /// ranked higher in the next searches.
+    pub fn pick(&mut self, suggestion:&Suggestion) -> Ast {
+        self.history.record(suggestion.label());
+        suggestion.to_ast()
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use ast::HasRepr;
+
+    fn suggestion(extended_target:&[&str], name:&str, arity:usize) -> Suggestion {
+        let extended_target = extended_target.iter().map(|s| s.to_string()).collect();
+        let name            = DefinitionName {extended_target, name:name.to_string()};
+        let module          = Location("Main".to_string());
+        Suggestion {name,arity,module}
+    }
+
+    #[test]
+    fn suggestion_to_ast() {
+        assert_eq!(suggestion(&[],"main",0).to_ast().repr()             , "main");
+        assert_eq!(suggestion(&[],"add",2).to_ast().repr()              , "add _ _");
+        assert_eq!(suggestion(&["Foo","Bar"],"baz",1).to_ast().repr()   , "Foo.Bar.baz _");
+    }
+
+    #[test]
+    fn searching_and_picking() {
+        let mut searcher = Searcher::default();
+        searcher.set_suggestions(vec!
+            [ suggestion(&[],"my_append",2)
+            , suggestion(&[],"map",2)
+            , suggestion(&["Foo"],"print",1)
+            ]);
+        let labels = |searcher:&Searcher, pattern| {
+            searcher.search(pattern).into_iter().map(|ranked| ranked.item.label()).collect_vec()
+        };
+        assert_eq!(labels(&searcher,"map")  , vec!["map","my_append"]);
+        assert_eq!(labels(&searcher,"Fo.p") , vec!["Foo.print"]);
+
+        let my_append = suggestion(&[],"my_append",2);
+        assert_eq!(searcher.pick(&my_append).repr(), "my_append _ _");
+        assert_eq!(labels(&searcher,"map"), vec!["my_append","map"]);
+    }
+}
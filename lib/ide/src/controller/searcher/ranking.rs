@@ -0,0 +1,250 @@
+//! Fuzzy matching and ranking of searcher suggestions.
+//!
+//! The typed pattern _matches_ the candidate name if all its characters appear in the name in the
+//! same order, ignoring case. The match is scored: the characters matched at word starts (e.g.
+//! `t` and `s` in `to_string`) or directly one after another score more, while the skipped
+//! characters lower the score. Of all possible matches the best scored one is chosen.
+//!
+//! The suggestions are ranked by their match score, increased by the bonus for being picked
+//! recently (see `History`).
+
+use crate::prelude::*;
+
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The score of each matched character.
+const MATCH_SCORE:i64 = 16;
+
+/// The bonus for the character matched right after the previous matched one.
+const CONSECUTIVE_BONUS:i64 = 12;
+
+/// The bonus for the character matched at the word start.
+const WORD_START_BONUS:i64 = 12;
+
+/// The bonus for matching the character of the same case.
+const SAME_CASE_BONUS:i64 = 1;
+
+/// The penalty for each character skipped between or before the matched ones.
+const GAP_PENALTY:i64 = 2;
+
+/// The maximum number of remembered picks.
+pub const HISTORY_SIZE:usize = 32;
+
+/// The bonus for the most recently picked suggestion, decreasing by `RECENCY_STEP` with each
+/// suggestion picked later.
+const RECENCY_BONUS:i64 = 64;
+
+/// See `RECENCY_BONUS`.
+const RECENCY_STEP:i64 = RECENCY_BONUS / HISTORY_SIZE as i64;
+
+
+
+// =============
+// === Match ===
+// =============
+
+/// The result of matching the pattern against the candidate name.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct Match {
+    /// The score of the match, the higher the better.
+    pub score   : i64,
+    /// The indices of the matched characters in the candidate name.
+    pub indices : Vec<usize>,
+}
+
+/// Check if the character at given index starts a word: it is the first character, follows
+/// the `_` or `.` separator, or is an upper-case letter after a lower-case one.
+fn is_word_start(chars:&[char], index:usize) -> bool {
+    let previous = index.checked_sub(1).map(|previous| chars[previous]);
+    previous.map_or(true, |previous| {
+        let after_separator = previous == '_' || previous == '.';
+        let camel_case      = previous.is_lowercase() && chars[index].is_uppercase();
+        after_separator || camel_case
+    })
+}
+
+fn chars_match(pattern:char, candidate:char) -> bool {
+    pattern.to_lowercase().eq(candidate.to_lowercase())
+}
+
+/// Match the pattern against the candidate name, returning the best scored match or `None` if the
+/// candidate does not contain all the pattern's characters in order. The empty pattern matches
+/// everything with zero score.
+pub fn fuzzy_match(pattern:&str, candidate:&str) -> Option<Match> {
+    let pattern   = pattern.chars().collect_vec();
+    let candidate = candidate.chars().collect_vec();
+    if pattern.is_empty() {
+        return Some(Match {score:0, indices:default()});
+    }
+    // `best[i][j]` is the best score of matching `pattern[..=i]` with `pattern[i]` matched at
+    // `candidate[j]`, and `previous[i][j]` is the index where `pattern[i-1]` was then matched.
+    let mut best     = vec![vec![None;candidate.len()];pattern.len()];
+    let mut previous = vec![vec![0;candidate.len()];pattern.len()];
+    for (i,pattern_char) in pattern.iter().enumerate() {
+        for (j,candidate_char) in candidate.iter().enumerate() {
+            if !chars_match(*pattern_char,*candidate_char) {
+                continue;
+            }
+            let word_start = is_word_start(&candidate,j);
+            let same_case  = pattern_char == candidate_char;
+            let char_score = MATCH_SCORE + word_start.as_some(WORD_START_BONUS).unwrap_or(0)
+                + same_case.as_some(SAME_CASE_BONUS).unwrap_or(0);
+            if i == 0 {
+                best[i][j] = Some(char_score - GAP_PENALTY * j as i64);
+            } else {
+                let candidates = (0..j).filter_map(|k| best[i-1][k].map(|score| (k,score)));
+                let scored     = candidates.map(|(k,score)| {
+                    let gap   = (j - k - 1) as i64;
+                    let bonus = if gap == 0 { CONSECUTIVE_BONUS } else { -GAP_PENALTY * gap };
+                    (k,score + bonus)
+                });
+                if let Some((k,score)) = scored.max_by_key(|(k,score)| (*score,*k)) {
+                    best[i][j]     = Some(score + char_score);
+                    previous[i][j] = k;
+                }
+            }
+        }
+    }
+    let last        = pattern.len() - 1;
+    let ends        = best[last].iter().enumerate();
+    let scored_ends = ends.filter_map(|(j,score)| score.map(|score| (j,score)));
+    let (end,score) = scored_ends.max_by_key(|(j,score)| (*score,Reverse(*j)))?;
+    let mut indices = vec![end];
+    for i in (1..pattern.len()).rev() {
+        let next = previous[i][indices[indices.len()-1]];
+        indices.push(next);
+    }
+    indices.reverse();
+    Some(Match {score,indices})
+}
+
+
+
+// ===============
+// === History ===
+// ===============
+
+/// The names of the recently picked suggestions, the most recent first.
+#[derive(Clone,Debug,Default)]
+pub struct History {
+    picked : VecDeque<String>,
+}
+
+impl History {
+    /// Remember the suggestion being picked. The oldest pick is forgotten once there are more
+    /// than `HISTORY_SIZE` of them.
+    pub fn record(&mut self, name:impl Str) {
+        let name = name.into();
+        self.picked.retain(|picked| picked != &name);
+        self.picked.push_front(name);
+        self.picked.truncate(HISTORY_SIZE);
+    }
+
+    /// The ranking bonus of the suggestion with given name: the more recently it was picked, the
+    /// higher. The suggestions which were not picked get no bonus.
+    pub fn recency_bonus(&self, name:&str) -> i64 {
+        let position = self.picked.iter().position(|picked| picked == name);
+        position.map_or(0, |position| RECENCY_BONUS - RECENCY_STEP * position as i64)
+    }
+}
+
+
+
+// ===============
+// === Ranking ===
+// ===============
+
+/// The suggestion matching the pattern, with its rank.
+#[derive(Clone,Debug)]
+pub struct Ranked<T> {
+    /// The suggestion.
+    pub item    : T,
+    /// The match score increased by the recency bonus.
+    pub score   : i64,
+    /// The indices of the characters matching the pattern, e.g. for highlighting them.
+    pub indices : Vec<usize>,
+}
+
+/// Rank the items whose names match the pattern, the best first. The items with equal score are
+/// ordered by the shorter name, and then keep their order.
+pub fn rank<T,F>(pattern:&str, items:impl IntoIterator<Item=T>, name:F, history:&History)
+-> Vec<Ranked<T>>
+where F : Fn(&T) -> String {
+    let ranked = items.into_iter().filter_map(|item| {
+        let item_name = name(&item);
+        let matched   = fuzzy_match(pattern,&item_name)?;
+        let score     = matched.score + history.recency_bonus(&item_name);
+        let indices   = matched.indices;
+        Some((item_name.chars().count(),Ranked {item,score,indices}))
+    });
+    let mut ranked = ranked.collect_vec();
+    ranked.sort_by_key(|(length,ranked)| (Reverse(ranked.score),*length));
+    ranked.into_iter().map(|(_,ranked)| ranked).collect()
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn indices(pattern:&str, candidate:&str) -> Option<Vec<usize>> {
+        fuzzy_match(pattern,candidate).map(|matched| matched.indices)
+    }
+
+    fn score(pattern:&str, candidate:&str) -> i64 {
+        fuzzy_match(pattern,candidate).expect("pattern should match").score
+    }
+
+    fn ranked_names(pattern:&str, names:&[&str], history:&History) -> Vec<String> {
+        let ranked = rank(pattern,names.iter(),|name| name.to_string(),history);
+        ranked.into_iter().map(|ranked| ranked.item.to_string()).collect()
+    }
+
+    #[test]
+    fn fuzzy_matching() {
+        assert_eq!(indices(""   ,"foo")       , Some(vec![]));
+        assert_eq!(indices("fb" ,"foo_bar")   , Some(vec![0,4]));
+        assert_eq!(indices("FB" ,"foo_bar")   , Some(vec![0,4]));
+        assert_eq!(indices("ts" ,"toString")  , Some(vec![0,2]));
+        assert_eq!(indices("bar","foo_bar")   , Some(vec![4,5,6]));
+        assert_eq!(indices("sb" ,"sub_bar")   , Some(vec![0,4]));
+        assert_eq!(indices("baz","foo_bar")   , None);
+        assert_eq!(indices("oof","foo")       , None);
+
+        assert!(score("map","mapper") > score("map","remap"));
+        assert!(score("map","mapper") > score("map","my_append"));
+        assert!(score("fb","foo_bar") > score("fb","fooxbar"));
+        assert!(score("Foo","Foo")    > score("Foo","foo"));
+    }
+
+    #[test]
+    fn ranking_by_score_and_recency() {
+        let names       = ["my_append","mapper","map","print"];
+        let mut history = History::default();
+        assert_eq!(ranked_names("map",&names,&history), vec!["map","mapper","my_append"]);
+        assert_eq!(ranked_names("",&names,&history)   , vec!["map","print","mapper","my_append"]);
+
+        history.record("my_append");
+        assert_eq!(ranked_names("map",&names,&history), vec!["my_append","map","mapper"]);
+        history.record("mapper");
+        assert!(history.recency_bonus("mapper") > history.recency_bonus("my_append"));
+        assert_eq!(history.recency_bonus("print"), 0);
+
+        for index in 0..HISTORY_SIZE {
+            history.record(format!("other{}",index));
+        }
+        assert_eq!(history.recency_bonus("mapper"), 0);
+    }
+}
@@ -3,10 +3,22 @@
 //! Facade over filesystem API or module text API for text editor. Does discerning between Luna
 //! module file and plain text file. In case of luna module idmap and metadata are hidden for the
 //! user.
+//!
+//! The plain text files are kept by the controller in an in-memory buffer, so all views of the
+//! file can see the unsaved edits. Each edit is sent to the views other than the one which made it
+//! as `TextChanged` notification. The controller tracks whether the file has unsaved changes and
+//! saves it automatically, according to its `AutosavePolicy`.
+//!
+//! The controller listens to the File Manager's filesystem events about its file. When someone
+//! else modifies the file, the plain text file without unsaved changes is reloaded. Otherwise the
+//! views are notified with `ChangedExternally`.
 
 use crate::prelude::*;
 use crate::controller::FallibleResult;
+use crate::controller::save::SavedVersion;
 
+use ast::Ast;
+use data::text::TextChange;
 use data::text::TextChangedNotification;
 use failure::_core::fmt::Formatter;
 use failure::_core::fmt::Error;
@@ -14,8 +26,9 @@ use file_manager_client as fmc;
 use flo_stream::MessagePublisher;
 use flo_stream::Publisher;
 use flo_stream::Subscriber;
-use json_rpc::error::RpcError;
+use futures::future::LocalBoxFuture;
 use shapely::shared;
+use std::time::Duration;
 
 
 // ====================
@@ -29,11 +42,81 @@ use shapely::shared;
 /// therefore there is no need for setting big buffers.
 const NOTIFICATION_BUFFER_SIZE : usize = 36;
 
+/// Tells if the file has changes which were not saved yet.
+#[derive(Clone,Copy,Debug,Eq,PartialEq)]
+pub enum SavedState {
+    /// All changes are saved.
+    Saved,
+    /// Some changes were not saved yet.
+    Modified,
+}
+
+/// Identifies a view of the file, so it is not notified about its own edits.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+pub struct ViewId(pub usize);
+
 /// A notification from TextController.
-#[derive(Clone,Debug)]
+#[derive(Clone,Debug,PartialEq)]
 pub enum Notification {
-    /// File contents needs to be set to the following due to synchronization with external state.
+    /// File contents needs to be set to the following due to synchronization with external state,
+    /// e.g. the file was reloaded.
     SetNewContent(String),
+    /// The change was made in the view `origin`. Other views should apply it to stay in sync.
+    TextChanged {
+        /// The applied change.
+        change : TextChange,
+        /// The view which made the change, and has it applied already.
+        origin : ViewId,
+    },
+    /// The file became saved or modified.
+    SavedStateChanged(SavedState),
+    /// The file was modified or deleted by someone else and could not be reloaded, because it has
+    /// unsaved changes or is a module. Saving it will fail with `SaveConflict`.
+    ChangedExternally,
+}
+
+
+
+// ================
+// === Autosave ===
+// ================
+
+/// Default time since the last edit, after which the file is saved.
+pub const DEFAULT_AUTOSAVE_DELAY:Duration = Duration::from_secs(1);
+
+/// Creates a future which completes after the given time. Used for delaying autosaves.
+pub type Timer = Rc<dyn Fn(Duration) -> LocalBoxFuture<'static,()>>;
+
+/// Describes when the controller saves the modified file by itself.
+#[derive(Clone,Copy,Debug,PartialEq)]
+pub struct AutosavePolicy {
+    /// Save the file when no edits were made for the given time.
+    pub after_idle : Option<Duration>,
+    /// Save the file when the editor loses focus.
+    pub on_focus_lost : bool,
+}
+
+impl AutosavePolicy {
+    /// The policy never saving the file automatically.
+    pub fn disabled() -> Self {
+        AutosavePolicy {after_idle:None, on_focus_lost:false}
+    }
+}
+
+impl Default for AutosavePolicy {
+    fn default() -> Self {
+        AutosavePolicy {after_idle:Some(DEFAULT_AUTOSAVE_DELAY), on_focus_lost:true}
+    }
+}
+
+/// Error raised when editing a plain text file which contents were not read yet.
+#[derive(Clone,Debug,Fail)]
+#[fail(display = "The contents of file {} were not read yet.", _0)]
+pub struct ContentNotRead(pub fmc::Path);
+
+/// Timer measuring time with the browser's `setTimeout`.
+fn web_timer() -> Timer {
+    Rc::new(|duration| executor::web::timeout(duration).boxed_local())
 }
 
 
@@ -52,14 +135,47 @@ enum FileHandle {
     Module    {controller:controller::module::Handle},
 }
 
+impl FileHandle {
+    /// Path of the handled file.
+    fn path(&self) -> fmc::Path {
+        match self {
+            FileHandle::PlainText{path,..} => path.clone(),
+            FileHandle::Module{controller} => controller.location().to_path(),
+        }
+    }
+
+    /// The File Manager handle used to access the file.
+    fn file_manager(&self) -> fmc::Handle {
+        match self {
+            FileHandle::PlainText{file_manager,..} => file_manager.clone_ref(),
+            FileHandle::Module{controller}         => controller.file_manager(),
+        }
+    }
+}
+
 
 shared! { Handle
 
     /// Data stored by the text controller.
     pub struct Controller {
         file: FileHandle,
+        /// The plain text file contents with all the applied edits. `None` until the file is read.
+        /// Modules keep their contents by themselves.
+        content: Option<String>,
+        /// The plain text file contents as last read or written by this controller. Modules keep
+        /// track of their saved version by themselves.
+        saved: Option<SavedVersion>,
+        saved_state: SavedState,
+        /// Number of edits applied so far. Allows checking if the file was edited during some
+        /// asynchronous operation.
+        edit_count: usize,
+        /// Id which will be given to the next view.
+        next_view_id: usize,
+        autosave: AutosavePolicy,
+        timer: Timer,
         /// Sink where we put events to be consumed by the view.
         notification_publisher: Publisher<Notification>,
+        logger: Logger,
     }
 
     impl {
@@ -68,48 +184,150 @@ shared! { Handle
             self.notification_publisher.subscribe()
         }
 
+        /// Get a new id identifying the view in `apply_text_change` calls.
+        pub fn new_view_id(&mut self) -> ViewId {
+            let id = ViewId(self.next_view_id);
+            self.next_view_id += 1;
+            id
+        }
+
         /// Get clone of file path handled by this controller.
         pub fn file_path(&self) -> fmc::Path {
-            match &self.file {
-                FileHandle::PlainText{path,..} => path.clone(),
-                FileHandle::Module{controller} => controller.location().to_path(),
-            }
+            self.file.path()
+        }
+
+        /// Check if the file has unsaved changes.
+        pub fn saved_state(&self) -> SavedState {
+            self.saved_state
+        }
+
+        /// Set the autosave policy. It applies to the edits made from now on.
+        pub fn set_autosave_policy(&mut self, policy:AutosavePolicy) {
+            self.autosave = policy;
+        }
+
+        /// Set the timer used for delaying autosaves.
+        pub fn set_timer(&mut self, timer:Timer) {
+            self.timer = timer;
         }
     }
 }
 
 impl Handle {
     /// Create controller managing plain text file (which is not a module).
+    ///
+    /// The controller starts listening to the filesystem events at once, so the global executor's
+    /// spawner must be set (see `executor::global::set_spawner`).
     pub fn new_for_plain_text(path:fmc::Path, file_manager:fmc::Handle) -> Self {
         Self::new(FileHandle::PlainText {path,file_manager})
     }
+
     /// Create controller managing Luna module file.
+    ///
+    /// As `new_for_plain_text`, requires the global executor's spawner to be set.
     pub fn new_for_module(controller:controller::module::Handle) -> Self {
         Self::new(FileHandle::Module {controller})
     }
 
-    /// Read file's content.
-    pub async fn read_content(&self) -> Result<String,RpcError> {
+    /// Read file's content, including the edits which were not saved yet.
+    ///
+    /// The plain text file is read from the server only once, then its contents are kept by the
+    /// controller.
+    pub async fn read_content(&self) -> FallibleResult<String> {
         use FileHandle::*;
         match self.file_handle() {
-            PlainText {path,mut file_manager} => file_manager.read(path).await,
-            Module    {controller}            => Ok(controller.code())
+            PlainText {path,mut file_manager} => {
+                if let Some(content) = self.with_borrowed(|state| state.content.clone()) {
+                    return Ok(content);
+                }
+                let read = file_manager.read(path).await.map_err(fmc::decode_error)?;
+                // Another read may have finished in the meantime, and its result could be edited
+                // already.
+                Ok(self.with_borrowed(|state| {
+                    if state.content.is_none() {
+                        state.content = Some(read.clone());
+                        state.saved   = Some(SavedVersion::new(read));
+                    }
+                    state.content.clone().unwrap_or_default()
+                }))
+            },
+            Module {controller} => Ok(controller.code())
+        }
+    }
+
+    /// Read the plain text file from the server again, discarding the unsaved edits. All views
+    /// are notified about the new contents.
+    pub async fn reload(&self) -> FallibleResult<()> {
+        // TODO: Reloading modules requires support in the Module Controller.
+        if let FileHandle::PlainText {path,mut file_manager} = self.file_handle() {
+            let content = file_manager.read(path).await.map_err(fmc::decode_error)?;
+            self.with_borrowed(|state| state.set_reloaded_content(content));
         }
+        Ok(())
+    }
+
+    /// Handle the filesystem event about the file, reported by the File Manager's watch.
+    ///
+    /// If someone else has modified the plain text file without unsaved changes, it is reloaded.
+    /// Otherwise the views are notified with `ChangedExternally`. Events caused by our own writes
+    /// and events about plain text files not read yet are ignored.
+    pub async fn handle_filesystem_event(&self, kind:fmc::FilesystemEventKind)
+    -> FallibleResult<()> {
+        if kind == fmc::FilesystemEventKind::Deleted {
+            self.with_borrowed(|state| {
+                let is_read = match state.file {
+                    FileHandle::PlainText {..} => state.content.is_some(),
+                    FileHandle::Module    {..} => true,
+                };
+                if is_read {
+                    state.notify(Notification::ChangedExternally);
+                }
+            });
+            return Ok(())
+        }
+        match self.file_handle() {
+            FileHandle::PlainText {path,mut file_manager} => {
+                let content = file_manager.read(path).await.map_err(fmc::decode_error)?;
+                self.with_borrowed(|state| state.external_content_read(content));
+            },
+            FileHandle::Module {controller} => {
+                if controller.is_modified_externally().await? {
+                    self.with_borrowed(|state| state.notify(Notification::ChangedExternally));
+                }
+            },
+        }
+        Ok(())
     }
 
     /// Store the given content to file.
+    ///
+    /// Fails with `SaveConflict` if the file was modified by someone else since it was read. The
+    /// plain text file which was not read yet is not written, as it could overwrite unknown
+    /// contents: `ContentNotRead` is returned instead. The file becomes `Saved`, unless it was
+    /// edited while being stored.
     pub fn store_content(&self, content:String) -> impl Future<Output=FallibleResult<()>> {
         let file_handle = self.file_handle();
+        let this        = self.clone_ref();
+        let edit_count  = self.with_borrowed(|state| state.edit_count);
         async move {
             match file_handle {
                 FileHandle::PlainText {path,mut file_manager} => {
-                    file_manager.write(path,content).await?
+                    let saved = this.with_borrowed(|state| state.saved.clone());
+                    let saved = saved.ok_or_else(|| ContentNotRead(path.clone()))?;
+                    let write = controller::save::write_checked;
+                    let saved = write(&mut file_manager,path,&saved,content).await?;
+                    this.with_borrowed(|state| state.saved = Some(saved));
                 },
                 FileHandle::Module {controller} => {
                     controller.check_code_sync(content)?;
                     controller.save_file().await?
                 }
             }
+            this.with_borrowed(|state| {
+                if state.edit_count == edit_count {
+                    state.set_saved_state(SavedState::Saved);
+                }
+            });
             Ok(())
         }
     }
@@ -118,12 +336,47 @@ impl Handle {
     ///
     /// This function should be called by view on every user interaction changing the text content
     /// of file. It will e.g. update the Module Controller state and notify other views about
-    /// update in case of module files.
-    pub fn apply_text_change(&self, change:&TextChangedNotification) -> FallibleResult<()> {
-        if let FileHandle::Module {controller} =  self.file_handle() {
-            controller.apply_code_change(change)
-        } else {
-            Ok(())
+    /// update in case of module files. For plain text files the change is applied to the kept
+    /// contents and sent to the other views with `TextChanged` notification. The `origin` is the
+    /// view which made the change. The file becomes `Modified` and the autosave is scheduled.
+    pub fn apply_text_change(&self, change:&TextChangedNotification, origin:ViewId)
+    -> FallibleResult<()> {
+        match self.file_handle() {
+            FileHandle::Module {controller} => controller.apply_code_change(change)?,
+            FileHandle::PlainText {path,..} => {
+                let applied = self.with_borrowed(|state| {
+                    state.content.as_mut().map(|content| change.apply(content))
+                });
+                applied.ok_or(ContentNotRead(path))?;
+                let change = change.change.clone();
+                self.with_borrowed(|state| state.notify(Notification::TextChanged {change,origin}));
+            },
+        }
+        let (edit_count,delay) = self.with_borrowed(|state| {
+            state.edit_count += 1;
+            state.set_saved_state(SavedState::Modified);
+            (state.edit_count,state.autosave.after_idle)
+        });
+        if let Some(delay) = delay {
+            self.schedule_autosave(edit_count,delay);
+        }
+        Ok(())
+    }
+
+    /// Notify the controller that the editor lost focus. Saves the file if required by the
+    /// autosave policy.
+    pub fn focus_lost(&self) {
+        if self.with_borrowed(|state| state.autosave.on_focus_lost) {
+            let this = self.clone_ref();
+            executor::global::spawn(async move { this.autosave().await });
+        }
+    }
+
+    /// The current AST of the module, or `None` if the handled file is a plain text file.
+    pub fn module_ast(&self) -> Option<Ast> {
+        match self.file_handle() {
+            FileHandle::Module {controller} => Some(controller.ast()),
+            FileHandle::PlainText {..}      => None,
         }
     }
 }
@@ -132,18 +385,121 @@ impl Handle {
 // === Private functions ===
 
 impl Handle {
-    /// Create controller managing plain text file.
+    /// Create controller managing given file. Spawns the filesystem events handling on the global
+    /// executor, which panics if its spawner was not set (see `executor::global::set_spawner`).
     fn new(file_handle:FileHandle) -> Self {
-        let state = Controller {
+        let logger = Logger::new(format!("Text Controller {}", file_handle.path()));
+        let state  = Controller {
             file                   : file_handle,
+            content                : None,
+            saved                  : None,
+            saved_state            : SavedState::Saved,
+            edit_count             : 0,
+            next_view_id           : 0,
+            autosave               : default(),
+            timer                  : web_timer(),
             notification_publisher : Publisher::new(NOTIFICATION_BUFFER_SIZE),
+            logger,
         };
-        Self {rc:Rc::new(RefCell::new(state))}
+        let this = Self {rc:Rc::new(RefCell::new(state))};
+        this.listen_to_filesystem_events();
+        this
+    }
+
+    /// Handle the File Manager's filesystem events about the file for as long as the controller
+    /// lives.
+    fn listen_to_filesystem_events(&self) {
+        let file_handle = self.file_handle();
+        let path        = file_handle.path();
+        let events      = file_handle.file_manager().filesystem_events();
+        let events      = events.filter(move |event| futures::future::ready(event.path == path));
+        let weak        = self.downgrade();
+        executor::global::spawn(async move {
+            let mut events = Box::pin(events);
+            while let Some(event) = events.next().await {
+                let this = match weak.upgrade() {
+                    Some(this) => this,
+                    None       => break,
+                };
+                if let Err(error) = this.handle_filesystem_event(event.kind).await {
+                    let logger = this.with_borrowed(|state| state.logger.clone());
+                    logger.error(|| format!("Failed to handle filesystem event: {}", error));
+                }
+            }
+        });
     }
 
     fn file_handle(&self) -> FileHandle {
         self.with_borrowed(|state| state.file.clone())
     }
+
+    /// Save the file after `delay`, unless it is edited again in the meantime.
+    fn schedule_autosave(&self, edit_count:usize, delay:Duration) {
+        let timer = self.with_borrowed(|state| state.timer.clone());
+        let weak  = self.downgrade();
+        executor::global::spawn(async move {
+            timer(delay).await;
+            if let Some(this) = weak.upgrade() {
+                if this.with_borrowed(|state| state.edit_count == edit_count) {
+                    this.autosave().await;
+                }
+            }
+        });
+    }
+
+    /// Save the file, if it has unsaved changes. Failures are logged.
+    async fn autosave(&self) {
+        let (saved_state,logger) = self.with_borrowed(|s| (s.saved_state,s.logger.clone()));
+        if saved_state == SavedState::Modified {
+            let content = match self.file_handle() {
+                FileHandle::Module    {controller} => Some(controller.code()),
+                FileHandle::PlainText {..}         => self.with_borrowed(|s| s.content.clone()),
+            };
+            if let Some(content) = content {
+                match self.store_content(content).await {
+                    Ok(())     => logger.info("File autosaved"),
+                    Err(error) => logger.error(|| format!("Autosave failed: {}", error)),
+                }
+            }
+        }
+    }
+}
+
+impl Controller {
+    /// Replace the plain text file contents with the ones read from the server, discarding the
+    /// unsaved edits.
+    fn set_reloaded_content(&mut self, content:String) {
+        self.content    = Some(content.clone());
+        self.saved      = Some(SavedVersion::new(content.clone()));
+        self.edit_count += 1;
+        self.set_saved_state(SavedState::Saved);
+        self.notify(Notification::SetNewContent(content));
+    }
+
+    /// Handle the plain text file contents read after the file was modified. The file is reloaded
+    /// if it has no unsaved changes. Contents written by us and files not read yet are ignored.
+    fn external_content_read(&mut self, content:String) {
+        let saved = self.saved.as_ref().map(|saved| &saved.contents);
+        if self.content.is_some() && saved != Some(&content) {
+            match self.saved_state {
+                SavedState::Saved    => self.set_reloaded_content(content),
+                SavedState::Modified => self.notify(Notification::ChangedExternally),
+            }
+        }
+    }
+
+    /// Send the notification to all subscribers.
+    fn notify(&mut self, notification:Notification) {
+        executor::global::spawn(self.notification_publisher.publish(notification));
+    }
+
+    /// Set the saved state, notifying subscribers if it has changed.
+    fn set_saved_state(&mut self, saved_state:SavedState) {
+        if self.saved_state != saved_state {
+            self.saved_state = saved_state;
+            self.notify(Notification::SavedStateChanged(saved_state));
+        }
+    }
 }
 
 
@@ -177,3 +533,214 @@ impl Handle {
         })
     }
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::executor::global::set_spawner;
+
+    use data::text::TextLocation;
+    use futures::executor::LocalPool;
+    use json_rpc::messages::Message;
+    use json_rpc::messages::RequestMessage;
+    use json_rpc::test_util::transport::mock::MockTransport;
+    use serde_json::json;
+    use serde_json::Value;
+    use utils::test::poll_future_output;
+    use utils::test::poll_stream_output;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Replies to the oldest request sent to the transport and returns its parameters.
+    fn reply(transport:&mut MockTransport, method:&str, result:Value) -> Value {
+        let request = transport.expect_message::<RequestMessage<Value>>();
+        assert_eq!(request.method, method);
+        transport.mock_peer_message(Message::new_success(request.id,result));
+        request.params
+    }
+
+    #[wasm_bindgen_test]
+    fn rapid_edits_are_saved_once() {
+        let mut executor  = LocalPool::new();
+        let mut transport = MockTransport::new();
+        set_spawner(executor.spawner());
+
+        let project       = controller::project::Handle::new_running(transport.clone_ref());
+        let path          = controller::module::Location("Test".to_string()).to_path();
+        let mut text_ctrl = Box::pin(project.get_text_controller(path));
+        assert!(poll_future_output(&mut text_ctrl).is_none());
+        reply(&mut transport,"read",json!("2 + 2"));
+        executor.run_until_stalled();
+        let text_ctrl     = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+        let notifications = text_ctrl.subscribe();
+        let instant_timer = |_:Duration| futures::future::ready(()).boxed_local();
+        text_ctrl.set_timer(Rc::new(instant_timer));
+
+        let view = text_ctrl.new_view_id();
+        for column in 1..4 {
+            let change = TextChangedNotification {
+                change         : TextChange::insert(TextLocation{line:0,column},"2"),
+                replaced_chars : column..column,
+            };
+            text_ctrl.apply_text_change(&change,view).unwrap();
+        }
+        assert_eq!(text_ctrl.saved_state(), SavedState::Modified);
+
+        // Only the autosave scheduled by the last edit writes the file.
+        executor.run_until_stalled();
+        let written = reply(&mut transport,"write",Value::Null);
+        assert_eq!(written["contents"], json!("2222 + 2"));
+        assert_eq!(written["expectedChecksum"], json!(fmc::Checksum::of("2 + 2")));
+        executor.run_until_stalled();
+        assert!(transport.with_mut_data(|data| data.sent_msgs.is_empty()));
+        assert_eq!(text_ctrl.saved_state(), SavedState::Saved);
+
+        let mut notifications = Box::pin(notifications);
+        let modified          = Notification::SavedStateChanged(SavedState::Modified);
+        let saved             = Notification::SavedStateChanged(SavedState::Saved);
+        assert_eq!(poll_stream_output(&mut notifications), Some(modified));
+        assert_eq!(poll_stream_output(&mut notifications), Some(saved));
+        assert_eq!(poll_stream_output(&mut notifications), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn plain_text_edits_are_seen_by_all_views() {
+        let mut executor  = LocalPool::new();
+        let mut transport = MockTransport::new();
+        set_spawner(executor.spawner());
+
+        let project       = controller::project::Handle::new_running(transport.clone_ref());
+        let path          = fmc::Path::new("./data.csv");
+        let mut text_ctrl = Box::pin(project.get_text_controller(path));
+        let text_ctrl     = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+        text_ctrl.set_autosave_policy(AutosavePolicy::disabled());
+        let mut first     = Box::pin(text_ctrl.subscribe());
+        let mut second    = Box::pin(text_ctrl.subscribe());
+
+        let mut content = Box::pin(text_ctrl.read_content());
+        assert!(poll_future_output(&mut content).is_none());
+        reply(&mut transport,"read",json!("a,b\n1,2"));
+        executor.run_until_stalled();
+        assert_eq!(poll_future_output(&mut content).unwrap().unwrap(), "a,b\n1,2");
+
+        let change = TextChangedNotification {
+            change         : TextChange::insert(TextLocation{line:1,column:3},",3"),
+            replaced_chars : 7..7,
+        };
+        let view = text_ctrl.new_view_id();
+        text_ctrl.apply_text_change(&change,view).unwrap();
+        executor.run_until_stalled();
+        let changed  = Notification::TextChanged {change:change.change.clone(), origin:view};
+        let modified = Notification::SavedStateChanged(SavedState::Modified);
+        for subscriber in vec![&mut first, &mut second] {
+            assert_eq!(poll_stream_output(subscriber), Some(changed.clone()));
+            assert_eq!(poll_stream_output(subscriber), Some(modified.clone()));
+        }
+
+        // The contents with the unsaved edit are kept by the controller.
+        let mut content = Box::pin(text_ctrl.read_content());
+        assert_eq!(poll_future_output(&mut content).unwrap().unwrap(), "a,b\n1,2,3");
+        assert!(transport.with_mut_data(|data| data.sent_msgs.is_empty()));
+    }
+
+    #[wasm_bindgen_test]
+    fn storing_unread_file_fails() {
+        let executor  = LocalPool::new();
+        let transport = MockTransport::new();
+        set_spawner(executor.spawner());
+
+        let project       = controller::project::Handle::new_running(transport.clone_ref());
+        let path          = fmc::Path::new("./data.csv");
+        let mut text_ctrl = Box::pin(project.get_text_controller(path));
+        let text_ctrl     = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+        let mut stored    = Box::pin(text_ctrl.store_content("a,b".to_string()));
+        let error         = poll_future_output(&mut stored).unwrap().unwrap_err();
+        assert!(error.downcast_ref::<ContentNotRead>().is_some());
+        assert!(transport.with_mut_data(|data| data.sent_msgs.is_empty()));
+    }
+
+    #[wasm_bindgen_test]
+    fn deleting_unread_file_is_ignored() {
+        let mut executor  = LocalPool::new();
+        let mut transport = MockTransport::new();
+        set_spawner(executor.spawner());
+
+        let project           = controller::project::Handle::new_running(transport.clone_ref());
+        let path              = fmc::Path::new("./data.csv");
+        let mut text_ctrl     = Box::pin(project.get_text_controller(path));
+        let text_ctrl         = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+        let mut notifications = Box::pin(text_ctrl.subscribe());
+        transport.mock_peer_message_text(r#"{
+            "jsonrpc" : "2.0",
+            "method"  : "filesystemEvent",
+            "params"  : {"path" : "./data.csv", "kind" : "Deleted"}
+        }"#);
+        executor.run_until_stalled();
+        assert_eq!(poll_stream_output(&mut notifications), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn external_modifications_reload_or_flag_file() {
+        let mut executor  = LocalPool::new();
+        let mut transport = MockTransport::new();
+        set_spawner(executor.spawner());
+
+        let project       = controller::project::Handle::new_running(transport.clone_ref());
+        let path          = fmc::Path::new("./data.csv");
+        let mut text_ctrl = Box::pin(project.get_text_controller(path));
+        let text_ctrl     = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+        text_ctrl.set_autosave_policy(AutosavePolicy::disabled());
+        let mut content   = Box::pin(text_ctrl.read_content());
+        assert!(poll_future_output(&mut content).is_none());
+        reply(&mut transport,"read",json!("a,b"));
+        executor.run_until_stalled();
+        poll_future_output(&mut content).unwrap().unwrap();
+        let mut notifications = Box::pin(text_ctrl.subscribe());
+        let file_modified     = |transport:&mut MockTransport, executor:&mut LocalPool, new:&str| {
+            transport.mock_peer_message_text(r#"{
+                "jsonrpc" : "2.0",
+                "method"  : "filesystemEvent",
+                "params"  : {"path" : "./data.csv", "kind" : "Modified"}
+            }"#);
+            executor.run_until_stalled();
+            reply(transport,"read",json!(new));
+            executor.run_until_stalled();
+        };
+
+        // The file without unsaved changes is reloaded.
+        file_modified(&mut transport,&mut executor,"a,b\n1,2");
+        let reloaded = Notification::SetNewContent("a,b\n1,2".to_string());
+        assert_eq!(poll_stream_output(&mut notifications), Some(reloaded));
+        assert_eq!(poll_stream_output(&mut notifications), None);
+
+        // The event about contents we already have is ignored.
+        file_modified(&mut transport,&mut executor,"a,b\n1,2");
+        assert_eq!(poll_stream_output(&mut notifications), None);
+
+        // The file with unsaved changes is only flagged.
+        let change = TextChangedNotification {
+            change         : TextChange::insert(TextLocation{line:1,column:3},",3"),
+            replaced_chars : 7..7,
+        };
+        let view = text_ctrl.new_view_id();
+        text_ctrl.apply_text_change(&change,view).unwrap();
+        executor.run_until_stalled();
+        file_modified(&mut transport,&mut executor,"a,b,c\n1,2,3");
+        let edited   = Notification::TextChanged {change:change.change.clone(), origin:view};
+        let modified = Notification::SavedStateChanged(SavedState::Modified);
+        assert_eq!(poll_stream_output(&mut notifications), Some(edited));
+        assert_eq!(poll_stream_output(&mut notifications), Some(modified));
+        assert_eq!(poll_stream_output(&mut notifications), Some(Notification::ChangedExternally));
+        let mut content = Box::pin(text_ctrl.read_content());
+        assert_eq!(poll_future_output(&mut content).unwrap().unwrap(), "a,b\n1,2,3");
+    }
+}
@@ -3,5 +3,6 @@
 
 pub mod definition;
 pub mod graph;
+pub mod import;
 pub mod node;
 pub mod text;
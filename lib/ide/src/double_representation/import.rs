@@ -0,0 +1,66 @@
+//! Code for import discovery in the module.
+
+use crate::prelude::*;
+
+use crate::double_representation::definition::identifier_name;
+
+use ast::Ast;
+use ast::Shape;
+use ast::known;
+
+
+
+// ==================
+// === ImportInfo ===
+// ==================
+
+/// Information about the import of module, e.g. `import Foo.Bar`.
+#[derive(Clone,Debug,Eq,Hash,PartialEq)]
+pub struct ImportInfo {
+    /// The segments of the imported module's path, e.g. `["Foo","Bar"]`.
+    pub target : Vec<String>,
+}
+
+impl ImportInfo {
+    /// Tries to interpret the line's `Ast` as an import.
+    ///
+    /// The imports are macros, so the parser gives them as a `Match` whose resolved Ast is
+    /// the `Import`.
+    pub fn from_line_ast(ast:&Ast) -> Option<ImportInfo> {
+        match ast.shape() {
+            Shape::Match  (matched) => Self::from_line_ast(&matched.resolved),
+            Shape::Import (import)  => {
+                let target = import.path.iter().map(identifier_name).collect::<Option<_>>()?;
+                Some(ImportInfo {target})
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Lists all the imports in the module.
+pub fn list_imports(module:&known::Module) -> Vec<ImportInfo> {
+    module.iter().filter_map(ImportInfo::from_line_ast).collect()
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use ast::Import;
+
+    #[test]
+    fn import_from_ast() {
+        let path   = vec![Ast::cons("Foo"),Ast::cons("Bar")];
+        let import = Ast::from(Import {path});
+        let info   = ImportInfo::from_line_ast(&import).expect("failed to read import");
+        assert_eq!(info.target, vec!["Foo".to_string(),"Bar".to_string()]);
+        assert_eq!(ImportInfo::from_line_ast(&Ast::var("foo")), None);
+    }
+}
@@ -11,6 +11,7 @@ use futures::task::LocalFutureObj;
 use futures::task::SpawnError;
 use futures::executor::LocalPool;
 use futures::executor::LocalSpawner;
+use wasm_bindgen::JsCast;
 
 /// Executor. Uses a single-threaded `LocalPool` underneath, relying on basegl's
 /// `EventLoop` to do as much progress as possible on every animation frame.
@@ -99,3 +100,24 @@ impl LocalSpawn for EventLoopExecutor {
         self.spawner.status_local()
     }
 }
+
+
+
+// ===============
+// === Timeout ===
+// ===============
+
+/// Returns a future that completes after the given time, measured by the browser's `setTimeout`.
+///
+/// If the timeout cannot be scheduled, the future completes immediately.
+pub fn timeout(duration:std::time::Duration) -> impl Future<Output=()> {
+    let (sender,receiver) = futures::channel::oneshot::channel();
+    let callback          = Closure::once_into_js(move || { let _ = sender.send(()); });
+    let millis            = duration.as_millis() as i32;
+    let window            = basegl::system::web::window();
+    let callback          = callback.unchecked_ref();
+    match window.set_timeout_with_callback_and_timeout_and_arguments_0(callback,millis) {
+        Ok(_)  => receiver.map(|_| ()).left_future(),
+        Err(_) => futures::future::ready(()).right_future(),
+    }
+}
@@ -113,6 +113,9 @@ pub async fn setup_project_view(logger:&Logger,config:SetupConfig)
 -> Result<ProjectView,failure::Error> {
     let fm_transport = connect_to_file_manager(config).await?;
     let controller   = controller::project::Handle::new_running(fm_transport);
+    if let Err(error) = controller.watch_project_files().await {
+        logger.warning(|| format!("Project files are not watched: {}", error));
+    }
     let project_view = ProjectView::new(logger,controller).await?;
     Ok(project_view)
 }
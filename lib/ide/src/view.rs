@@ -9,7 +9,10 @@
 #![warn(unused_import_braces)]
 #![warn(unused_qualifications)]
 
+pub mod folding;
+pub mod highlighting;
 pub mod temporary_panel;
 pub mod project;
+pub mod searcher;
 pub mod layout;
 pub mod text_editor;
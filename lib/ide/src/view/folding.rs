@@ -0,0 +1,95 @@
+//! Code folding of Enso modules, driven by the module's AST.
+//!
+//! Each block of code is a folding region, with the line where the block starts as its header.
+//! The regions are given in the form expected by `TextField::set_folding_regions`.
+
+use crate::prelude::*;
+
+use ast::Ast;
+use ast::HasTokens;
+use ast::Shape;
+use ast::Token;
+use ast::TokenConsumer;
+use basegl::display::shape::text::text_field::folding::FoldingRegion;
+
+
+
+// =======================
+// === Folding Regions ===
+// =======================
+
+/// Computes the folding regions of code represented by given AST, ordered by their headers. The
+/// blank lines at the end of block are not included in its region.
+pub fn folding_regions(ast:&Ast) -> Vec<FoldingRegion> {
+    let mut collector = RegionsCollector::default();
+    ast.feed_to(&mut collector);
+    let mut regions = collector.regions;
+    regions.sort_by_key(|region| region.header);
+    regions
+}
+
+
+// === RegionsCollector ===
+
+/// Consumes AST tokens, tracking the current line and collecting the regions of fed blocks.
+#[derive(Debug,Default)]
+struct RegionsCollector {
+    regions        : Vec<FoldingRegion>,
+    line           : usize,
+    last_non_blank : usize,
+}
+
+impl RegionsCollector {
+    fn push_char(&mut self, character:char) {
+        if character == ast::repr::NEWLINE {
+            self.line += 1;
+        } else if !character.is_whitespace() {
+            self.last_non_blank = self.line;
+        }
+    }
+}
+
+impl TokenConsumer for RegionsCollector {
+    fn feed(&mut self, token:Token) {
+        match token {
+            Token::Off(_)   => {},
+            Token::Chr(val) => self.push_char(val),
+            Token::Str(val) => val.chars().for_each(|c| self.push_char(c)),
+            Token::Ast(val) => {
+                let header = self.line;
+                val.shape().feed_to(self);
+                if let Shape::Block(_) = val.shape() {
+                    let end = self.last_non_blank + 1;
+                    if end > header + 1 {
+                        self.regions.push(FoldingRegion::new(header,end));
+                    }
+                }
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use parser::api::IsParser;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn computing_regions_of_blocks() {
+        let program    = "main =\n    foo x ->\n        x\n    bar\n\nbaz = 1";
+        let mut parser = parser::Parser::new_or_panic();
+        let ast        = parser.parse(program.into(),default()).unwrap();
+        let expected   = vec![FoldingRegion::new(0,4), FoldingRegion::new(1,3)];
+        assert_eq!(folding_regions(&ast), expected);
+    }
+}
@@ -0,0 +1,266 @@
+//! Syntax highlighting of Enso modules, driven by the module's AST.
+//!
+//! The highlighting is done in two steps: first the `classify` function walks over the tokens of
+//! the AST and assigns a `StyleClass` to each character fragment, then the classes are mapped to
+//! colors by `Theme`. The resulting spans are given per line, as expected by
+//! `TextField::set_highlighting`.
+
+use crate::prelude::*;
+
+use ast::Ast;
+use ast::HasTokens;
+use ast::Shape;
+use ast::Token;
+use ast::TokenConsumer;
+use basegl::display::shape::text::text_field::content::line::ColorSpan;
+use nalgebra::Vector4;
+use std::ops::Range;
+
+
+
+// ==================
+// === StyleClass ===
+// ==================
+
+/// The kind of code fragment, deciding how it is displayed.
+#[derive(Clone,Copy,Debug,Eq,Hash,PartialEq)]
+#[allow(missing_docs)]
+pub enum StyleClass {
+    Variable, Constructor, Operator, Number, Text, Comment, Invalid
+}
+
+impl StyleClass {
+    /// The class of the code represented by given shape, if it has any by itself.
+    pub fn of_shape(shape:&Shape<Ast>) -> Option<Self> {
+        match shape {
+            Shape::Var          (_) => Some(StyleClass::Variable),
+            Shape::Cons         (_) => Some(StyleClass::Constructor),
+            Shape::Opr          (_) => Some(StyleClass::Operator),
+            Shape::Mod          (_) => Some(StyleClass::Operator),
+            Shape::Number       (_) => Some(StyleClass::Number),
+            Shape::TextLineRaw  (_) => Some(StyleClass::Text),
+            Shape::TextLineFmt  (_) => Some(StyleClass::Text),
+            Shape::TextBlockRaw (_) => Some(StyleClass::Text),
+            Shape::TextBlockFmt (_) => Some(StyleClass::Text),
+            Shape::Unrecognized (_) => Some(StyleClass::Invalid),
+            Shape::InvalidQuote (_) => Some(StyleClass::Invalid),
+            Shape::InlineBlock  (_) => Some(StyleClass::Invalid),
+            Shape::InvalidSuffix(_) => Some(StyleClass::Invalid),
+            Shape::DanglingBase (_) => Some(StyleClass::Invalid),
+            Shape::TextUnclosed (_) => Some(StyleClass::Invalid),
+            Shape::Ambiguous    (_) => Some(StyleClass::Invalid),
+            Shape::Match        (m) => match m.resolved.shape() {
+                Shape::Comment(_) => Some(StyleClass::Comment),
+                _                 => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Checks if all the code inside the fragment of this class should have this class, even if
+    /// it would have another one by itself.
+    fn covers_children(self) -> bool {
+        self == StyleClass::Comment || self == StyleClass::Invalid
+    }
+}
+
+/// A fragment of line with assigned `StyleClass`.
+#[derive(Clone,Debug,Eq,PartialEq)]
+pub struct ClassSpan {
+    /// The range of columns in the line.
+    pub columns : Range<usize>,
+    /// The class of the fragment.
+    pub class   : StyleClass,
+}
+
+/// Assigns style classes to the fragments of code represented by given AST.
+///
+/// Returns the list of spans for each line of code, in order of their appearance. The parts of
+/// code without any class (e.g. spaces between tokens) are not covered by any span.
+pub fn classify(ast:&Ast) -> Vec<Vec<ClassSpan>> {
+    let mut classifier = Classifier::default();
+    ast.feed_to(&mut classifier);
+    classifier.lines
+}
+
+
+// === Classifier ===
+
+/// Consumes AST tokens, tracking the position in code and the class of currently fed node.
+#[derive(Debug)]
+struct Classifier {
+    lines  : Vec<Vec<ClassSpan>>,
+    column : usize,
+    class  : Option<StyleClass>,
+}
+
+impl Default for Classifier {
+    fn default() -> Self {
+        let lines  = vec![default()];
+        let column = 0;
+        let class  = None;
+        Classifier {lines,column,class}
+    }
+}
+
+impl Classifier {
+    fn push_columns(&mut self, count:usize) {
+        let start = self.column;
+        self.column += count;
+        if let Some(class) = self.class {
+            let line = self.lines.last_mut().unwrap();
+            match line.last_mut() {
+                Some(span) if span.class == class && span.columns.end == start =>
+                    span.columns.end = self.column,
+                _ => line.push(ClassSpan {columns:start..self.column, class}),
+            }
+        }
+    }
+
+    fn push_char(&mut self, character:char) {
+        if character == ast::repr::NEWLINE {
+            self.lines.push(default());
+            self.column = 0;
+        } else {
+            self.push_columns(1);
+        }
+    }
+}
+
+impl TokenConsumer for Classifier {
+    fn feed(&mut self, token:Token) {
+        match token {
+            Token::Off(val) => self.push_columns(val),
+            Token::Chr(val) => self.push_char(val),
+            Token::Str(val) => val.chars().for_each(|c| self.push_char(c)),
+            Token::Ast(val) => {
+                let parent = self.class;
+                let cover  = parent.filter(|class| class.covers_children());
+                self.class = cover.or_else(|| StyleClass::of_shape(val.shape())).or(parent);
+                val.shape().feed_to(self);
+                self.class = parent;
+            }
+        }
+    }
+}
+
+
+
+// =============
+// === Theme ===
+// =============
+
+/// Colors assigned to each style class.
+#[derive(Clone,Copy,Debug)]
+#[allow(missing_docs)]
+pub struct Theme {
+    pub variable    : Vector4<f32>,
+    pub constructor : Vector4<f32>,
+    pub operator    : Vector4<f32>,
+    pub number      : Vector4<f32>,
+    pub text        : Vector4<f32>,
+    pub comment     : Vector4<f32>,
+    pub invalid     : Vector4<f32>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            variable    : Vector4::new(0.0 ,0.0 ,0.0 ,1.0),
+            constructor : Vector4::new(0.0 ,0.35,0.6 ,1.0),
+            operator    : Vector4::new(0.5 ,0.0 ,0.5 ,1.0),
+            number      : Vector4::new(0.0 ,0.5 ,0.5 ,1.0),
+            text        : Vector4::new(0.0 ,0.5 ,0.0 ,1.0),
+            comment     : Vector4::new(0.5 ,0.5 ,0.5 ,1.0),
+            invalid     : Vector4::new(0.85,0.0 ,0.0 ,1.0),
+        }
+    }
+}
+
+impl Theme {
+    /// The color of given class.
+    pub fn color(&self, class:StyleClass) -> Vector4<f32> {
+        match class {
+            StyleClass::Variable    => self.variable,
+            StyleClass::Constructor => self.constructor,
+            StyleClass::Operator    => self.operator,
+            StyleClass::Number      => self.number,
+            StyleClass::Text        => self.text,
+            StyleClass::Comment     => self.comment,
+            StyleClass::Invalid     => self.invalid,
+        }
+    }
+
+    /// Computes the colors of code represented by given AST, for each line of code.
+    pub fn highlight(&self, ast:&Ast) -> Vec<Vec<ColorSpan>> {
+        let to_color_span = |span:ClassSpan| {
+            let color = self.color(span.class);
+            ColorSpan {columns:span.columns,color}
+        };
+        let to_color_spans = |line:Vec<ClassSpan>| -> Vec<ColorSpan> {
+            line.into_iter().map(to_color_span).collect()
+        };
+        classify(ast).into_iter().map(to_color_spans).collect()
+    }
+}
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use parser::api::IsParser;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    wasm_bindgen_test::wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Classifies the program and returns the classified fragments of each line.
+    fn classified_fragments(program:&str) -> Vec<Vec<(String,StyleClass)>> {
+        let mut parser = parser::Parser::new_or_panic();
+        let ast        = parser.parse(program.into(),default()).unwrap();
+        let lines      = program.split('\n').collect_vec();
+        classify(&ast).into_iter().zip(lines).map(|(spans,line)| {
+            let chars = line.chars().collect_vec();
+            spans.into_iter().map(|span| {
+                let fragment = String::from_iter(&chars[span.columns]);
+                (fragment,span.class)
+            }).collect()
+        }).collect()
+    }
+
+    #[test]
+    fn classifying_constructed_ast() {
+        let ast      = Ast::infix_var("foo","+","bar");
+        let expected = vec![vec!
+            [ ClassSpan {columns:0..3, class:StyleClass::Variable}
+            , ClassSpan {columns:4..5, class:StyleClass::Operator}
+            , ClassSpan {columns:6..9, class:StyleClass::Variable}
+            ]];
+        assert_eq!(classify(&ast), expected);
+    }
+
+    #[wasm_bindgen_test]
+    fn classifying_parsed_code() {
+        use StyleClass::*;
+        let program  = "main = foo 'ą b' 12 + Bar\n\n# a comment";
+        let expected = vec!
+            [ vec!
+                [ ("main".to_string(),Variable)
+                , ("=".to_string()   ,Operator)
+                , ("foo".to_string() ,Variable)
+                , ("'ą b'".to_string(),Text)
+                , ("12".to_string()  ,Number)
+                , ("+".to_string()   ,Operator)
+                , ("Bar".to_string() ,Constructor)
+                ]
+            , vec![]
+            , vec![("# a comment".to_string(),Comment)]
+            ];
+        assert_eq!(classified_fragments(program), expected);
+    }
+}
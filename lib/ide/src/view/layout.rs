@@ -8,8 +8,8 @@ use crate::view::temporary_panel::TemporaryPanel;
 use crate::view::text_editor::TextEditor;
 
 use basegl::display::world::World;
-use enso_frp::io::KeyboardActions;
-use enso_frp::io::KeyMask;
+use enso_frp::io::Keymap;
+use enso_frp::io::KeymapActions;
 use nalgebra::zero;
 use nalgebra::Vector2;
 use std::rc::Rc;
@@ -110,12 +110,15 @@ impl ViewLayout {
     /// Creates a new ViewLayout with a single TextEditor.
     pub fn new
     ( logger     : &Logger
-    , kb_actions : &mut KeyboardActions
+    , kb_actions : &mut KeymapActions
     , world      : &World
     , controller : controller::text::Handle
+    , project    : controller::project::Handle
+    , overrides  : &Keymap
     ) -> Self {
         let logger       = logger.sub("ViewLayout");
-        let text_editor  = TextEditor::new(&logger,&world,controller,kb_actions);
+        let text_editor  = TextEditor::new
+            (&logger,&world,controller,project,kb_actions,overrides);
         let layout_mode  = default();
         let size         = zero();
         let data         = ViewLayoutData {text_editor,layout_mode,size,logger};
@@ -123,19 +126,18 @@ impl ViewLayout {
         Self {rc}.init(world,kb_actions)
     }
 
-    fn init_keyboard(self, keyboard_actions:&mut KeyboardActions) -> Self {
-        let switch_mode_keys = KeyMask::new_control_character('f');
+    fn init_keyboard(self, keymap_actions:&mut KeymapActions) -> Self {
         let view_layout = self.clone();
-        keyboard_actions.set_action(switch_mode_keys,move |_| {
+        keymap_actions.set_handler("layout.switch_mode",move || {
             view_layout.switch_layout_mode();
         });
         self
     }
 
-    fn init(self, world:&World, keyboard_actions:&mut KeyboardActions) -> Self {
+    fn init(self, world:&World, keymap_actions:&mut KeymapActions) -> Self {
         let screen = world.scene().camera().screen();
         let size   = Vector2::new(screen.width,screen.height);
         self.set_size(size);
-        self.init_keyboard(keyboard_actions)
+        self.init_keyboard(keymap_actions)
     }
 }
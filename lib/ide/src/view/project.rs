@@ -4,6 +4,7 @@
 use crate::prelude::*;
 
 use crate::view::layout::ViewLayout;
+use crate::view::text_editor::is_text_field_command;
 use crate::controller::FallibleResult;
 
 use basegl::control::callback::CallbackHandle;
@@ -12,7 +13,9 @@ use basegl::display::world::WorldData;
 use basegl::display::world::World;
 use basegl::system::web;
 use enso_frp::Keyboard;
-use enso_frp::KeyboardActions;
+use enso_frp::Keymap;
+use enso_frp::KeymapActions;
+use file_manager_client as fmc;
 use file_manager_client::Path;
 use nalgebra::Vector2;
 use shapely::shared;
@@ -32,6 +35,42 @@ use shapely::shared;
 ///      default initial layout for the project.
 const INITIAL_FILE_PATH:&str = "Main.enso";
 
+/// The default keymap of project view commands. See `enso_frp::io::keymap` for the format
+/// description.
+pub const DEFAULT_KEYMAP:&str = r#"
+    [ { "keys" : "Ctrl+F"     , "command" : "layout.switch_mode"                                }
+    , { "keys" : "Ctrl+S"     , "command" : "text_editor.save"         , "context" : "text_editor" }
+    , { "keys" : "Ctrl+Space" , "command" : "text_editor.open_searcher", "context" : "text_editor" }
+    ]"#;
+
+/// Path of the optional project file with the user's keymap, overriding the bindings of the
+/// default keymaps. See `enso_frp::io::keymap` for the format description.
+pub const USER_KEYMAP_PATH:&str = "keymap.json";
+
+
+
+// ==================
+// === UserKeymap ===
+// ==================
+
+/// Reads the user's keymap from the project's `USER_KEYMAP_PATH` file. If the file does not exist
+/// or is not a valid keymap, an empty keymap is returned, so the defaults are used.
+async fn load_user_keymap(logger:&Logger, project:&controller::project::Handle) -> Keymap {
+    let mut file_manager = project.file_manager();
+    let path             = Path::new(USER_KEYMAP_PATH);
+    let exists           = file_manager.exists(path.clone()).await.map_err(fmc::decode_error);
+    let json             = match exists {
+        Ok(true)   => file_manager.read(path).await.map_err(fmc::decode_error),
+        Ok(false)  => return Keymap::new(),
+        Err(error) => Err(error),
+    };
+    let keymap = json.and_then(|json| Ok(Keymap::from_json(&json)?));
+    keymap.unwrap_or_else(|error| {
+        logger.warning(|| format!("Cannot load the user keymap {}: {}",USER_KEYMAP_PATH,error));
+        Keymap::new()
+    })
+}
+
 
 
 // ===================
@@ -50,7 +89,7 @@ shared! { ProjectView
         controller        : controller::project::Handle,
         keyboard          : Keyboard,
         keyboard_bindings : KeyboardFrpBindings,
-        keyboard_actions  : KeyboardActions
+        keymap_actions    : KeymapActions
     }
 
     impl {
@@ -71,12 +110,16 @@ impl ProjectView {
         let logger               = logger.sub("ProjectView");
         let keyboard             = Keyboard::default();
         let keyboard_bindings    = KeyboardFrpBindings::new(&logger,&keyboard);
-        let mut keyboard_actions = KeyboardActions::new(&keyboard);
+        let user_keymap          = load_user_keymap(&logger,&controller).await;
+        let mut keymap           = Keymap::from_json(DEFAULT_KEYMAP)?;
+        keymap.override_with(user_keymap.filtered(|b| !is_text_field_command(&b.command)));
+        let mut keymap_actions   = KeymapActions::new(&keyboard,keymap);
         let resize_callback      = None;
+        let project              = controller.clone_ref();
         let layout               = ViewLayout::new
-            (&logger,&mut keyboard_actions,&world,text_controller);
+            (&logger,&mut keymap_actions,&world,text_controller,project,&user_keymap);
         let data = ProjectViewData
-            {world,layout,resize_callback,controller,keyboard,keyboard_bindings,keyboard_actions};
+            {world,layout,resize_callback,controller,keyboard,keyboard_bindings,keymap_actions};
         Ok(Self::new_from_data(data).init())
     }
 
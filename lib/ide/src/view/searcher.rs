@@ -0,0 +1,158 @@
+//! This module contains SearcherView, a popup listing the code completion suggestions.
+
+use crate::prelude::*;
+
+use crate::controller::searcher::Suggestion;
+use crate::controller::searcher::ranking::Ranked;
+
+use ast::HasRepr;
+use basegl::display;
+use basegl::display::shape::text::glyph::font::FontHandle;
+use basegl::display::shape::text::glyph::system::GlyphSystem;
+use basegl::display::shape::text::glyph::system::Line as GlyphLine;
+use basegl::display::world::World;
+use nalgebra::Vector2;
+use nalgebra::Vector3;
+use nalgebra::Vector4;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The maximum number of suggestions displayed at once.
+pub const MAX_VISIBLE_SUGGESTIONS:usize = 10;
+
+/// The maximum number of characters displayed in the suggestion's line.
+const MAX_LINE_LENGTH:usize = 64;
+
+/// The marker displayed before the selected suggestion.
+const SELECTION_MARKER:&str = "> ";
+
+
+
+// ====================
+// === SearcherView ===
+// ====================
+
+/// The popup listing the searcher's suggestions, one per line, with the characters matching
+/// the typed pattern highlighted. One of the suggestions is _selected_, being the one inserted
+/// when the user accepts the searcher.
+#[derive(Debug)]
+pub struct SearcherView {
+    glyph_system   : GlyphSystem,
+    glyph_lines    : Vec<GlyphLine>,
+    suggestions    : Vec<Ranked<Suggestion>>,
+    selected       : usize,
+    line_height    : f32,
+    display_object : display::object::Node,
+}
+
+impl SearcherView {
+    /// Creates an empty SearcherView.
+    pub fn new(logger:&Logger, world:&World, font:FontHandle, line_height:f32) -> Self {
+        let mut glyph_system = GlyphSystem::new(world,font);
+        let display_object   = display::object::Node::new(logger.sub("SearcherView"));
+        display_object.add_child(&glyph_system);
+        let color       = Self::base_color();
+        let glyph_lines = (0..MAX_VISIBLE_SUGGESTIONS).map(|index| {
+            let baseline_start = Vector2::new(0.0,(-(index as f32) - 0.85) * line_height);
+            glyph_system.new_empty_line(baseline_start,line_height,MAX_LINE_LENGTH,color)
+        }).collect();
+        let suggestions = default();
+        let selected    = 0;
+        Self {glyph_system,glyph_lines,suggestions,selected,line_height,display_object}
+    }
+
+    /// Sets the position of popup's top-left corner.
+    pub fn set_position(&mut self, position:Vector2<f32>) {
+        self.display_object.set_position(Vector3::new(position.x,position.y,0.0));
+        self.display_object.update();
+    }
+
+    /// The height of a single suggestion's line.
+    pub fn line_height(&self) -> f32 {
+        self.line_height
+    }
+
+    /// Replaces the displayed suggestions, selecting the first one. At most
+    /// `MAX_VISIBLE_SUGGESTIONS` of them are kept.
+    pub fn set_suggestions(&mut self, mut suggestions:Vec<Ranked<Suggestion>>) {
+        suggestions.truncate(MAX_VISIBLE_SUGGESTIONS);
+        self.suggestions = suggestions;
+        self.selected    = 0;
+        self.redraw();
+    }
+
+    /// Hides the popup, removing all suggestions.
+    pub fn clear(&mut self) {
+        self.set_suggestions(default());
+    }
+
+    /// Selects the next suggestion, or the first one if the last is selected.
+    pub fn select_next(&mut self) {
+        if !self.suggestions.is_empty() {
+            self.selected = (self.selected + 1) % self.suggestions.len();
+            self.redraw();
+        }
+    }
+
+    /// Selects the previous suggestion, or the last one if the first is selected.
+    pub fn select_previous(&mut self) {
+        if !self.suggestions.is_empty() {
+            let count     = self.suggestions.len();
+            self.selected = (self.selected + count - 1) % count;
+            self.redraw();
+        }
+    }
+
+    /// The selected suggestion, if there is any displayed.
+    pub fn selected(&self) -> Option<&Suggestion> {
+        self.suggestions.get(self.selected).map(|ranked| &ranked.item)
+    }
+
+    fn base_color() -> Vector4<f32> {
+        Vector4::new(0.4,0.4,0.4,1.0)
+    }
+
+    fn selected_color() -> Vector4<f32> {
+        Vector4::new(0.0,0.0,0.0,1.0)
+    }
+
+    fn matched_color() -> Vector4<f32> {
+        Vector4::new(0.0,0.3,0.8,1.0)
+    }
+
+    fn redraw(&mut self) {
+        for (index,glyph_line) in self.glyph_lines.iter_mut().enumerate() {
+            match self.suggestions.get(index) {
+                Some(ranked) => {
+                    let is_selected = index == self.selected;
+                    let marker      = if is_selected { SELECTION_MARKER } else { "  " };
+                    let text        = format!("{}{}",marker,ranked.item.to_ast().repr());
+                    glyph_line.replace_text(text.chars().take(MAX_LINE_LENGTH));
+                    if is_selected {
+                        glyph_line.set_color(0..MAX_LINE_LENGTH,Self::selected_color());
+                    }
+                    let offset = SELECTION_MARKER.len();
+                    for index in &ranked.indices {
+                        let column = offset + index;
+                        glyph_line.set_color(column..column + 1,Self::matched_color());
+                    }
+                }
+                None => glyph_line.replace_text("".chars()),
+            }
+        }
+        self.display_object.update();
+    }
+}
+
+
+// === DisplayObject ===
+
+impl From<&SearcherView> for display::object::Node {
+    fn from(searcher:&SearcherView) -> Self {
+        searcher.display_object.clone_ref()
+    }
+}
@@ -3,17 +3,114 @@
 use basegl::traits::*;
 use crate::prelude::*;
 
+use crate::controller::module::Location;
+use crate::controller::searcher::Searcher;
+use crate::controller::searcher::Suggestion;
+use crate::controller::searcher::load_suggestions;
+use crate::controller::text::Notification;
+use crate::controller::text::ViewId;
+use crate::view::folding::folding_regions;
+use crate::view::highlighting::Theme;
+use crate::view::searcher::SearcherView;
 use crate::view::temporary_panel::TemporaryPadding;
 use crate::view::temporary_panel::TemporaryPanel;
 
+use ast::HasRepr;
+use ast::known;
+use ast::repr;
 use basegl::display::shape::text::glyph::font::FontRegistry;
+use basegl::display::shape::text::text_field::editing::DEFAULT_INDENT_UNIT;
+use basegl::display::shape::text::text_field::editing::IndentationRules;
+use basegl::display::shape::text::text_field::frp::keyboard::TextFieldKeyboardFrp;
 use basegl::display::shape::text::text_field::TextField;
 use basegl::display::shape::text::text_field::TextFieldProperties;
 use basegl::display::world::*;
-use enso_frp::io::KeyboardActions;
-use enso_frp::io::KeyMask;
+use data::text::TextLocation;
+use enso_frp::io::Command;
+use enso_frp::io::Keymap;
+use enso_frp::io::KeymapActions;
 use nalgebra::Vector2;
 use nalgebra::zero;
+use std::ops::Range;
+
+
+
+// =================
+// === Constants ===
+// =================
+
+/// The keymap context in which the text editor commands are active.
+pub const KEYMAP_CONTEXT:&str = "text_editor";
+
+/// The line endings after which a new code block is indented in Enso modules: the lambda arrow
+/// and the assignment or definition.
+const ENSO_BLOCK_OPENERS:&[&str] = &["->","="];
+
+/// The quotes of Enso text literals, matched and auto-closed like brackets.
+const ENSO_QUOTES:&[char] = &[repr::FMT_QUOTE,repr::RAW_QUOTE,repr::EXPR_QUOTE];
+
+/// The context of TextField's keymap in which the searcher commands are active, i.e. the context
+/// active while the searcher is open.
+const SEARCHER_KEYMAP_CONTEXT:&str = "searcher";
+
+/// The prefixes of commands bound in the TextField's keymap rather than in the project view's one.
+const TEXT_FIELD_COMMAND_PREFIXES:&[&str] = &["text_field.","searcher."];
+
+/// The bindings of searcher commands added to the TextField's keymap. While the searcher is open,
+/// they take precedence over the TextField's bindings of the same keys.
+const SEARCHER_BINDINGS:&[(&str,&str)] =
+    &[ ("Up"    , "searcher.select_previous")
+     , ("Down"  , "searcher.select_next")
+     , ("Enter" , "searcher.accept")
+     , ("Tab"   , "searcher.accept")
+     , ("Esc"   , "searcher.close")
+     ];
+
+
+
+// ==============================
+// === Highlighting & Folding ===
+// ==============================
+
+/// Colors the code in the text field and sets its folding regions according to the module's AST.
+/// Does nothing for plain text files, which are folded by indentation.
+fn update_from_ast(text_field:&TextField, controller:&controller::text::Handle) {
+    if let Some(ast) = controller.module_ast() {
+        text_field.set_highlighting(Theme::default().highlight(&ast));
+        text_field.set_folding_regions(folding_regions(&ast));
+    }
+}
+
+
+
+// ================
+// === Searcher ===
+// ================
+
+/// The TextField's keymap with the searcher commands bound in `SEARCHER_KEYMAP_CONTEXT`.
+fn keymap_with_searcher_bindings() -> Keymap {
+    let mut keymap = TextFieldKeyboardFrp::default_keymap();
+    for (keys,command) in SEARCHER_BINDINGS {
+        let context = Some(SEARCHER_KEYMAP_CONTEXT.into());
+        keymap.bind(keys,*command,context).expect("The searcher bindings should be valid.");
+    }
+    keymap
+}
+
+/// Check if the command is bound in the TextField's keymap rather than in the project view's one.
+pub fn is_text_field_command(command:&Command) -> bool {
+    TEXT_FIELD_COMMAND_PREFIXES.iter().any(|prefix| command.starts_with(prefix))
+}
+
+/// The fragment of line typed right before the cursor, which is the searcher's pattern: the
+/// identifier, possibly preceded by other ones joined with `.` (e.g. `Foo.ba`).
+fn pattern_range(line:&str, cursor:TextLocation) -> Range<TextLocation> {
+    let is_pattern_char = |ch:&char| ch.is_alphanumeric() || *ch == '_' || *ch == '.';
+    let before_cursor   = line.chars().take(cursor.column).collect_vec();
+    let length          = before_cursor.iter().rev().take_while(|ch| is_pattern_char(ch)).count();
+    let start           = TextLocation {line:cursor.line, column:before_cursor.len() - length};
+    start..cursor
+}
 
 
 
@@ -23,16 +120,25 @@ use nalgebra::zero;
 
 shared! { TextEditor
 
-/// TextEditor allows us to edit text files or Enso Modules. Extensible code highlighting is
-/// planned to be implemented for it.
+/// TextEditor allows us to edit text files or Enso Modules. The modules' code is highlighted
+/// according to their AST.
+///
+/// In modules, the searcher may be opened at the cursor to complete the typed name with
+/// the definitions of the module and the modules it imports (see `controller::searcher`). It is
+/// closed when the text field loses focus or the cursor is moved elsewhere.
 #[derive(Debug)]
 pub struct TextEditorData {
     text_field : TextField,
     padding    : TemporaryPadding,
     position   : Vector2<f32>,
     size       : Vector2<f32>,
-    controller : controller::text::Handle,
-    logger     : Logger
+    controller    : controller::text::Handle,
+    view_id       : ViewId,
+    project       : controller::project::Handle,
+    searcher      : Searcher,
+    searcher_view : SearcherView,
+    searcher_open : bool,
+    logger        : Logger
 }
 
 impl {
@@ -43,8 +149,8 @@ impl {
         let text       = self.text_field.get_content();
         let logger     = self.logger.clone();
         executor::global::spawn(async move {
-            if controller.store_content(text).await.is_err() {
-                let message:&str = &format!("Failed to save file: {}", file_path);
+            if let Err(error) = controller.store_content(text).await {
+                let message:&str = &format!("Failed to save file {}: {}", file_path, error);
                 logger.error(message);
             } else {
                 logger.info("File saved");
@@ -56,15 +162,79 @@ impl {
     pub fn select_next_word_occurrence(&mut self) {
         self.text_field.select_next_word_occurrence();
     }
+
+    /// Opens the searcher at the cursor with given suggestions.
+    pub fn show_searcher(&mut self, suggestions:Vec<Suggestion>) {
+        self.searcher.set_suggestions(suggestions);
+        self.searcher_open = true;
+        self.text_field.set_keymap_context_active(SEARCHER_KEYMAP_CONTEXT,true);
+        self.refresh_searcher();
+    }
+
+    /// Updates the displayed suggestions and the searcher's position after the pattern typed
+    /// before the cursor changed. Does nothing if the searcher is closed.
+    pub fn refresh_searcher(&mut self) {
+        if self.searcher_open {
+            let (_,pattern)  = self.searcher_pattern();
+            let cursor_point = self.text_field.active_cursor_point();
+            let position     = self.text_field.position().xy() + cursor_point;
+            let line_height  = self.searcher_view.line_height();
+            self.searcher_view.set_suggestions(self.searcher.search(&pattern));
+            self.searcher_view.set_position(position - Vector2::new(0.0,line_height / 2.0));
+        }
+    }
+
+    /// Closes the searcher without inserting anything.
+    pub fn close_searcher(&mut self) {
+        self.searcher_open = false;
+        self.text_field.set_keymap_context_active(SEARCHER_KEYMAP_CONTEXT,false);
+        self.searcher_view.clear();
+    }
+
+    /// Selects the next suggestion in searcher.
+    pub fn select_next_suggestion(&mut self) {
+        self.searcher_view.select_next();
+    }
+
+    /// Selects the previous suggestion in searcher.
+    pub fn select_previous_suggestion(&mut self) {
+        self.searcher_view.select_previous();
+    }
 }}
 
+impl TextEditorData {
+    /// The searcher's pattern typed before the cursor and its range.
+    fn searcher_pattern(&self) -> (Range<TextLocation>,String) {
+        let cursor  = self.text_field.active_cursor_location();
+        let content = self.text_field.get_content();
+        let line    = content.lines().nth(cursor.line).unwrap_or_default();
+        let range   = pattern_range(line,cursor);
+        let length  = cursor.column - range.start.column;
+        let pattern = line.chars().skip(range.start.column).take(length).collect();
+        (range,pattern)
+    }
+
+    /// Picks the selected suggestion, if there is any, and closes the searcher. Returns the code
+    /// to be inserted and the range of the pattern it replaces.
+    fn pick_suggestion(&mut self) -> Option<(Range<TextLocation>,String)> {
+        let suggestion = self.searcher_view.selected().cloned();
+        let (range,_)  = self.searcher_pattern();
+        let code       = suggestion.map(|suggestion| self.searcher.pick(&suggestion).repr());
+        self.close_searcher();
+        code.map(|code| (range,code))
+    }
+}
+
 impl TextEditor {
-    /// Creates a new TextEditor.
+    /// Creates a new TextEditor. The bindings of TextField's commands in `keymap_overrides`
+    /// override the default TextField's keymap.
     pub fn new
     ( logger           : &Logger
     , world            : &World
     , controller       : controller::text::Handle
-    , keyboard_actions : &mut KeyboardActions) -> Self {
+    , project          : controller::project::Handle
+    , keymap_actions   : &mut KeymapActions
+    , keymap_overrides : &Keymap) -> Self {
         let logger     = logger.sub("TextEditor");
         let scene      = world.scene();
         let camera     = scene.camera();
@@ -77,8 +247,17 @@ impl TextEditor {
         let black      = Vector4::new(0.0,0.0,0.0,1.0);
         let base_color = black;
         let text_size  = 16.0;
+        let searcher_view = SearcherView::new(&logger,world,font.clone_ref(),text_size);
         let properties = TextFieldProperties {font,text_size,base_color,size};
         let text_field = TextField::new(&world,properties);
+        let mut keymap = keymap_with_searcher_bindings();
+        keymap.override_with(keymap_overrides.filtered(|b| is_text_field_command(&b.command)));
+        text_field.set_keymap(keymap);
+        if controller.module_ast().is_some() {
+            let rules = IndentationRules::new(DEFAULT_INDENT_UNIT,ENSO_BLOCK_OPENERS);
+            let rules = rules.with_quotes(ENSO_QUOTES);
+            text_field.set_editing_rules(rules);
+        }
 
         let text_field_weak   = text_field.downgrade();
         let controller_clone = controller.clone_ref();
@@ -87,41 +266,163 @@ impl TextEditor {
             if let Ok(content) = controller_clone.read_content().await {
                 if let Some(text_field) = text_field_weak.upgrade() {
                     text_field.set_content(&content);
+                    update_from_ast(&text_field,&controller_clone);
                     logger_ref.info("File loaded");
                 }
             }
         });
 
         world.add_child(&text_field);
+        world.add_child(&searcher_view);
 
-        let data = TextEditorData {controller,text_field,padding,position,size,logger};
-        Self::new_from_data(data).initialize(keyboard_actions)
+        let view_id          = controller.new_view_id();
+        let text_field_weak  = text_field.downgrade();
+        let controller_clone = controller.clone_ref();
+        let logger_ref       = logger.clone();
+        let notifications    = controller.subscribe();
+        executor::global::spawn(notifications.for_each(move |notification| {
+            match notification {
+                Notification::SetNewContent(content) => {
+                    if let Some(text_field) = text_field_weak.upgrade() {
+                        text_field.set_content(&content);
+                        update_from_ast(&text_field,&controller_clone);
+                    }
+                },
+                // The change made in this view is already applied, and the user may have typed
+                // further since it was sent.
+                Notification::TextChanged {change,origin} => if origin != view_id {
+                    if let Some(text_field) = text_field_weak.upgrade() {
+                        text_field.apply_change(change);
+                        update_from_ast(&text_field,&controller_clone);
+                    }
+                },
+                Notification::SavedStateChanged(state) => {
+                    logger_ref.info(|| format!("File is now {:?}", state));
+                },
+                Notification::ChangedExternally => {
+                    logger_ref.warning("File was changed by someone else since it was loaded.");
+                },
+            }
+            futures::future::ready(())
+        }));
+
+        let searcher      = default();
+        let searcher_open = false;
+        let data          = TextEditorData {controller,view_id,project,text_field,padding,position,
+            size,searcher,searcher_view,searcher_open,logger};
+        Self::new_from_data(data).initialize(keymap_actions)
     }
 
-    fn initialize(self, keyboard_actions:&mut KeyboardActions) -> Self {
-        let save_keys   = KeyMask::new_control_character('s');
+    fn initialize(self, keymap_actions:&mut KeymapActions) -> Self {
+        // The text editor is currently the only view, so its context is always active.
+        keymap_actions.set_context_active(KEYMAP_CONTEXT,true);
         let text_editor = Rc::downgrade(&self.rc);
-        keyboard_actions.set_action(save_keys,move |_| {
+        keymap_actions.set_handler("text_editor.save",move || {
             if let Some(text_editor) = text_editor.upgrade() {
                 text_editor.borrow().save();
             }
         });
+        let text_editor = self.downgrade();
+        keymap_actions.set_handler("text_editor.open_searcher",move || {
+            if let Some(text_editor) = text_editor.upgrade() {
+                text_editor.open_searcher();
+            }
+        });
+        self.set_searcher_command("searcher.select_next"    ,Self::select_next_suggestion);
+        self.set_searcher_command("searcher.select_previous",Self::select_previous_suggestion);
+        self.set_searcher_command("searcher.accept"         ,Self::accept_searcher);
+        self.set_searcher_command("searcher.close"          ,Self::close_searcher);
 
+        let text_editor = self.downgrade();
         self.with_borrowed(move |data| {
+            let controller_clone = data.controller.clone_ref();
+            let text_editor_weak = text_editor.clone_ref();
+            data.text_field.set_focus_lost_callback(move || {
+                controller_clone.focus_lost();
+                if let Some(text_editor) = text_editor_weak.upgrade() {
+                    text_editor.close_searcher();
+                }
+            });
+
+            let text_editor_weak = text_editor.clone_ref();
+            data.text_field.set_cursors_moved_callback(move || {
+                if let Some(text_editor) = text_editor_weak.upgrade() {
+                    text_editor.close_searcher();
+                }
+            });
+
             let logger           = data.logger.clone();
             let controller_clone = data.controller.clone_ref();
+            let view_id          = data.view_id;
             data.text_field.set_text_edit_callback(move |change| {
-                let result = controller_clone.apply_text_change(change);
+                let result = controller_clone.apply_text_change(change,view_id);
                 if result.is_err() {
                     logger.error(|| "Error while notifying controllers about text change");
                     logger.error(|| format!("{:?}", result));
                 }
             });
+
+            // The highlighting and the searcher are updated once the whole edit is applied, not
+            // after each of its changes.
+            let controller_clone = data.controller.clone_ref();
+            let text_field_weak  = data.text_field.downgrade();
+            data.text_field.set_edit_finished_callback(move || {
+                if let Some(text_field) = text_field_weak.upgrade() {
+                    update_from_ast(&text_field,&controller_clone);
+                }
+                if let Some(text_editor) = text_editor.upgrade() {
+                    text_editor.refresh_searcher();
+                }
+            });
         });
         self.update();
         self
     }
 
+    /// Opens the searcher at the cursor once the suggestions are loaded. The searcher is available
+    /// only in modules.
+    pub fn open_searcher(&self) {
+        let (controller,project,logger) = self.with_borrowed(|data| {
+            (data.controller.clone_ref(),data.project.clone_ref(),data.logger.clone())
+        });
+        let module   = controller.module_ast().and_then(|ast| known::Module::try_new(ast).ok());
+        let location = Location::from_path(&controller.file_path());
+        match (module,location) {
+            (Some(module),Some(location)) => {
+                let text_editor = self.downgrade();
+                executor::global::spawn(async move {
+                    let suggestions = load_suggestions(project,location,module,logger).await;
+                    if let Some(text_editor) = text_editor.upgrade() {
+                        text_editor.show_searcher(suggestions);
+                    }
+                });
+            }
+            _ => logger.info("The searcher is available only in modules."),
+        }
+    }
+
+    /// Inserts the selected suggestion in place of the pattern typed before the cursor, and
+    /// closes the searcher.
+    pub fn accept_searcher(&self) {
+        let picked = self.with_borrowed(|data| data.pick_suggestion());
+        if let Some((range,code)) = picked {
+            let text_field = self.with_borrowed(|data| data.text_field.clone_ref());
+            text_field.select_range(range);
+            text_field.write(&code);
+        }
+    }
+
+    /// Sets the handler of searcher command, bound in the TextField's keymap.
+    fn set_searcher_command<F:Fn(&Self) + 'static>(&self, command:&str, f:F) {
+        let text_editor = self.downgrade();
+        let text_field  = self.with_borrowed(|data| data.text_field.clone_ref());
+        text_field.set_command_handler(command,move || {
+            if let Some(text_editor) = text_editor.upgrade() {
+                f(&text_editor);
+            }
+        });
+    }
+
     /// Modify the underlying TextEditorData.
     pub fn modify_data<F:FnMut(&mut TextEditorData)>(&mut self, mut f:F) {
         f(&mut self.rc.borrow_mut());
@@ -170,3 +471,137 @@ impl TemporaryPanel for TextEditor {
         Vector2::new(position.x, position.y)
     }
 }
+
+
+
+// =============
+// === Tests ===
+// =============
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::executor::global::set_spawner;
+    use crate::view::project::DEFAULT_KEYMAP;
+
+    use basegl::display::world::WorldData;
+    use basegl::system::web;
+    use basegl_core_msdf_sys as msdf_sys;
+    use enso_frp::io::InputPlayer;
+    use enso_frp::io::InputScript;
+    use enso_frp::io::InputSources;
+    use enso_frp::io::Keyboard;
+    use file_manager_client as fmc;
+    use futures::executor::LocalPool;
+    use json_rpc::messages::Message;
+    use json_rpc::messages::RequestMessage;
+    use json_rpc::test_util::transport::mock::MockTransport;
+    use serde_json::json;
+    use serde_json::Value;
+    use utils::test::poll_future_output;
+    use wasm_bindgen_test::wasm_bindgen_test;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// Reply to the next request sent to the mock File Manager, returning its parameters.
+    fn reply(transport:&mut MockTransport, method:&str, result:Value) -> Value {
+        let request = transport.expect_message::<RequestMessage<Value>>();
+        assert_eq!(request.method, method);
+        transport.mock_peer_message(Message::new_success(request.id,result));
+        request.params
+    }
+
+    /// The plain text file opened in a project connected to the mock File Manager.
+    struct Fixture {
+        executor  : LocalPool,
+        transport : MockTransport,
+        project   : controller::project::Handle,
+        text_ctrl : controller::text::Handle,
+        world     : World,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            let executor      = LocalPool::new();
+            let transport     = MockTransport::new();
+            set_spawner(executor.spawner());
+            let project       = controller::project::Handle::new_running(transport.clone_ref());
+            let path          = fmc::Path::new("./data.csv");
+            let mut text_ctrl = Box::pin(project.get_text_controller(path));
+            let text_ctrl     = poll_future_output(&mut text_ctrl).unwrap().unwrap();
+            text_ctrl.set_autosave_policy(controller::text::AutosavePolicy::disabled());
+            let root          = web::create_div();
+            web::body().append_child(&root).unwrap();
+            let world         = WorldData::new(&root);
+            Fixture {executor,transport,project,text_ctrl,world}
+        }
+
+        /// Open the file in a new TextEditor, with the keyboard of project view. The keymap
+        /// actions must be kept as long as the keyboard is used.
+        fn open_editor(&mut self, content:&str) -> (TextEditor,Keyboard,KeymapActions) {
+            let logger      = Logger::new("Test");
+            let keyboard    = Keyboard::default();
+            let keymap      = Keymap::from_json(DEFAULT_KEYMAP).unwrap();
+            let mut actions = KeymapActions::new(&keyboard,keymap);
+            let text_ctrl   = self.text_ctrl.clone_ref();
+            let project     = self.project.clone_ref();
+            let overrides   = Keymap::new();
+            let text_editor = TextEditor::new
+                (&logger,&self.world,text_ctrl,project,&mut actions,&overrides);
+            self.executor.run_until_stalled();
+            reply(&mut self.transport,"read",json!(content));
+            self.executor.run_until_stalled();
+            (text_editor,keyboard,actions)
+        }
+    }
+
+    fn text_field(text_editor:&TextEditor) -> TextField {
+        text_editor.with_borrowed(|data| data.text_field.clone_ref())
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn typing_text_and_saving_headless() {
+        msdf_sys::initialized().await;
+        let mut fixture                     = Fixture::new();
+        let (text_editor,keyboard,_actions) = fixture.open_editor("a,b\n1,2");
+
+        // In the browser, each key event is received by both the project view's keyboard and the
+        // TextField's one.
+        let script = InputScript::new().press("Ctrl+End").unwrap().type_text(",3");
+        let script = script.press("Ctrl+s").unwrap();
+        for keyboard in vec![text_field(&text_editor).keyboard().unwrap(),keyboard] {
+            let mut player = InputPlayer::new(script.clone());
+            player.play_all(InputSources::keyboard(&keyboard));
+            assert!(player.is_finished());
+        }
+        fixture.executor.run_until_stalled();
+        let written = reply(&mut fixture.transport,"write",Value::Null);
+        assert_eq!(written["path"]    , json!("./data.csv"));
+        assert_eq!(written["contents"], json!("a,b\n1,2,3"));
+        fixture.executor.run_until_stalled();
+        assert!(fixture.transport.with_mut_data(|data| data.sent_msgs.is_empty()));
+    }
+
+    #[wasm_bindgen_test(async)]
+    async fn editing_file_in_two_editors() {
+        msdf_sys::initialized().await;
+        let mut fixture    = Fixture::new();
+        let (first,_,_)    = fixture.open_editor("a,b\n1,2\n3,4");
+        let (second,_,_)   = fixture.open_editor("a,b\n1,2\n3,4");
+        let (first,second) = (text_field(&first),text_field(&second));
+        let at             = |line,column| TextLocation {line,column};
+        second.select_range(at(2,3)..at(2,3));
+
+        // The second editor's cursor is moved to the end of the remaining text.
+        first.select_range(at(0,3)..at(2,3));
+        first.remove_selection();
+        fixture.executor.run_until_stalled();
+        assert_eq!(second.get_content(), "a,b");
+        second.new_line();
+        fixture.executor.run_until_stalled();
+        assert_eq!(second.get_content(), "a,b\n");
+        assert_eq!(first.get_content() , "a,b\n");
+    }
+}